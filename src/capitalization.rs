@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+/// The longest word-initial prefix after which we look for internal capitals ("Mac").
+const MAX_PREFIX_LEN: usize = 3;
+
+/// Learns where capital letters appear in the training data, so that the same patterns can be
+/// reapplied to generated names even though the model itself is trained on lowercased text.
+///
+/// Three patterns are tracked: capitalized first letters, capitals after a space, apostrophe
+/// or hyphen ("O'Brien", "Ara-Nel"), and internal capitals after a short word-initial prefix
+/// ("McCoy", "MacDonald").  Each is reapplied if it held for the majority of the training
+/// samples in which it could have occurred.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CapitalizationModel {
+    initial: Tally,
+    after_separator: Tally,
+    after_prefix: HashMap<String, Tally>,
+}
+
+/// How many times a letter was seen in some position, and how many of those were capitals.
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    seen: usize,
+    capitalized: usize,
+}
+
+impl Tally {
+    fn record(&mut self, c: char) {
+        self.seen += 1;
+        if c.is_uppercase() {
+            self.capitalized += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Tally) {
+        self.seen += other.seen;
+        self.capitalized += other.capitalized;
+    }
+
+    fn is_majority(&self) -> bool {
+        self.capitalized * 2 > self.seen
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == '-' || c == '\''
+}
+
+impl CapitalizationModel {
+    /// Adds the observations of another model, learned from other training data.
+    pub(crate) fn merge(&mut self, other: CapitalizationModel) {
+        self.initial.merge(other.initial);
+        self.after_separator.merge(other.after_separator);
+        for (prefix, tally) in other.after_prefix {
+            self.after_prefix.entry(prefix).or_default().merge(tally);
+        }
+    }
+
+    /// Records the capitalization of one line of (not yet lowercased) training data.
+    pub(crate) fn observe(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut word_start = 0;
+        for (i, &c) in chars.iter().enumerate() {
+            if is_separator(c) {
+                word_start = i + 1;
+                continue;
+            }
+            if i == 0 {
+                self.initial.record(c);
+            } else if i == word_start {
+                self.after_separator.record(c);
+            } else if i - word_start <= MAX_PREFIX_LEN {
+                let prefix: String = chars[word_start..i].iter().collect();
+                self.after_prefix
+                    .entry(prefix.to_lowercase())
+                    .or_default()
+                    .record(c);
+            }
+        }
+    }
+
+    /// Reapplies the learned capitalization patterns to a generated name.
+    pub(crate) fn apply(&self, name: &str) -> String {
+        let chars: Vec<char> = name.chars().collect();
+        let mut result = String::with_capacity(name.len());
+        let mut word_start = 0;
+        for (i, &c) in chars.iter().enumerate() {
+            if is_separator(c) {
+                word_start = i + 1;
+                result.push(c);
+                continue;
+            }
+            let capitalize = if i == 0 {
+                self.initial.is_majority()
+            } else if i == word_start {
+                self.after_separator.is_majority()
+            } else if i - word_start <= MAX_PREFIX_LEN && i + 1 < chars.len() {
+                let prefix: String = chars[word_start..i].iter().collect();
+                self.after_prefix
+                    .get(&prefix.to_lowercase())
+                    .is_some_and(Tally::is_majority)
+            } else {
+                false
+            };
+            if capitalize {
+                result.extend(c.to_uppercase());
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learns_capitalization_patterns() {
+        let mut model = CapitalizationModel::default();
+        for line in [
+            "McCoy", "McKinley", "Macey", "O'Brien", "Jean-Luc", "Marcus",
+        ] {
+            model.observe(line);
+        }
+        assert_eq!(model.apply("mcdonald"), "McDonald");
+        assert_eq!(model.apply("o'neil"), "O'Neil");
+        assert_eq!(model.apply("marius-paul"), "Marius-Paul");
+        assert_eq!(model.apply("mace"), "Mace");
+    }
+
+    #[test]
+    fn test_lowercase_corpus_stays_lowercase() {
+        let mut model = CapitalizationModel::default();
+        model.observe("bulbasaur");
+        model.observe("pikachu");
+        assert_eq!(model.apply("pikasaur"), "pikasaur");
+    }
+}
@@ -1,44 +1,578 @@
+use crate::capitalization::CapitalizationModel;
 use crate::characterchain::generator::CharacterChainGenerator;
+use crate::checkpoint;
+use crate::confusable::ConfusablePolicy;
+use crate::error::NamegenError;
+use crate::hooks::{Hooks, Rejection};
+use crate::identifier::IdentifierStyle;
+use crate::memory::{BloomFilter, NameMemory, PersistentMemory};
+use crate::model::{tuned_order_and_prior, untrain, Chain, Endings, MarkovModel, Refinement};
+use crate::nickname::Diminutives;
+use crate::parallel::{train_shards, Shard};
+use crate::phonetic::PhoneticBlocklist;
+use crate::phonotactics::Phonotactics;
+use crate::positional::count_finals;
+use crate::postprocess::{slugify, Affixes, Postprocessor, SpellingVariants, Stylizer};
+use crate::preprocess::{
+    CaseFolding, MultiWordPolicy, Preprocessor, PunctuationPolicy, ReservedCharPolicy, SENTINEL,
+};
+use crate::prior::PriorPolicy;
+use crate::state::GeneratorRng;
+use crate::storage::ContextStorage;
+use crate::training::{Trained, Untrained};
+use crate::transliterate::Transliteration;
+use is_vowel::IsRomanceVowel;
+use log::debug;
 use multimarkov::builder::MultiMarkovBuilder;
 use multimarkov::MultiMarkov;
 use rand::RngCore;
-use regex::Regex;
-use std::ops::Deref;
+use regex::RegexBuilder;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::ops::{ControlFlow, Deref};
+use std::path::Path;
+use std::sync::Arc;
 
 /// A Builder pattern for CharacterChainGenerator.
-pub struct CharacterChainGeneratorBuilder<'a> {
+pub struct CharacterChainGeneratorBuilder<'a, State = Untrained> {
+    name: Option<String>,
     model: MultiMarkovBuilder<char>,
+    order: usize,
+    prior: Option<PriorPolicy>,
+    storage: ContextStorage,
+    decay: f64,
+    interpolation: Option<Vec<f64>>,
+    min_occurrences: usize,
+    strict: bool,
+    auto_tune: bool,
+    resume_after: usize,
+    precompiled: Option<MarkovModel<char>>,
     pattern: Option<&'a str>,
+    pattern_ignores_case: bool,
+    pattern_is_full_match: bool,
+    required: Option<String>,
+    phonotactics: Option<Phonotactics>,
+    max_attempts: Option<usize>,
+    hooks: Hooks,
+    memory: Option<NameMemory>,
+    avoid: HashSet<String>,
+    phonetic_blocklist: Option<PhoneticBlocklist>,
+    confusable_policy: Option<ConfusablePolicy>,
+    identifier_style: Option<IdentifierStyle>,
+    forbidden: Vec<(char, char)>,
+    negative: Vec<String>,
+    postprocessors: Vec<Postprocessor>,
+    spelling_variants: Option<SpellingVariants>,
+    pronunciation: Option<Transliteration>,
+    diminutives: Option<Diminutives>,
+    rng: Option<GeneratorRng>,
+    preprocessor: Preprocessor,
+    capitalization: Option<CapitalizationModel>,
+    finals: Option<Chain<char>>,
+    error: Option<NamegenError>,
+    state: PhantomData<State>,
 }
 
-impl<'a> CharacterChainGeneratorBuilder<'a> {
+impl<'a> Default for CharacterChainGeneratorBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> CharacterChainGeneratorBuilder<'a, Untrained> {
     /// Instantiate a new builder with default values.
     pub fn new() -> Self {
         Self {
+            name: None,
             model: MultiMarkov::<char>::builder()
-                .with_order(CharacterChainGenerator::DEFAULT_ORDER)
-                .with_prior(CharacterChainGenerator::DEFAULT_PRIOR),
+                .with_order(CharacterChainGenerator::DEFAULT_ORDER as i32)
+                .without_prior(), // we add our own priors in try_build()
+            order: CharacterChainGenerator::DEFAULT_ORDER,
+            prior: Some(PriorPolicy::Constant(
+                CharacterChainGenerator::DEFAULT_PRIOR,
+            )),
+            storage: ContextStorage::default(),
+            decay: 0.0,
+            interpolation: None,
+            min_occurrences: 0,
+            strict: false,
+            auto_tune: false,
+            resume_after: 0,
+            precompiled: None,
             pattern: None,
+            pattern_ignores_case: false,
+            pattern_is_full_match: false,
+            required: None,
+            phonotactics: None,
+            max_attempts: None,
+            hooks: Hooks::default(),
+            memory: None,
+            avoid: HashSet::new(),
+            phonetic_blocklist: None,
+            confusable_policy: None,
+            identifier_style: None,
+            forbidden: Vec::new(),
+            negative: Vec::new(),
+            postprocessors: Vec::new(),
+            spelling_variants: None,
+            pronunciation: None,
+            diminutives: None,
+            rng: None,
+            preprocessor: Preprocessor::default(),
+            capitalization: None,
+            finals: None,
+            error: None,
+            state: PhantomData,
+        }
+    }
+    /// Sets a custom value for order of the Markov model.
+    /// Must be from 1 to `CharacterChainGenerator::MAX_ORDER`; values from 1 to 3 are recommended.
+    /// Higher-order models will make procedurally generated text more like the training data,
+    /// and less random, and will be slower and require more memory.
+    ///
+    /// If the order is out of range, `try_build()` returns a `NamegenError::InvalidConfig`
+    /// error.
+    pub fn with_order(mut self, order: usize) -> Self {
+        if !(1..=CharacterChainGenerator::MAX_ORDER).contains(&order) {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "order must be from 1 to {}, not {}",
+                    CharacterChainGenerator::MAX_ORDER,
+                    order
+                )));
+            return self;
+        }
+        self.order = order;
+        // update model now, so it'll affect training
+        self.model = self.model.with_order(order as i32);
+        self
+    }
+    /// Picks the order and prior for you when the model is first trained, from the size of the
+    /// corpus and of its alphabet of characters, overriding `.with_order()` and `.with_prior()`.
+    /// The order is ln(corpus size) / ln(alphabet size), rounded and kept between 1 and 5: the
+    /// longest context the corpus is big enough to see about once.  The prior is 0.1 / alphabet
+    /// size, which keeps unseen transitions rare however large the alphabet is.
+    pub fn auto_tune(mut self) -> Self {
+        self.auto_tune = true;
+        self
+    }
+    /// Sets what to do with training samples that contain `'#'` (reserved internally to mark the
+    /// beginning and end of words) or control characters.  By default they are stripped out.
+    pub fn with_reserved_char_policy(mut self, policy: ReservedCharPolicy) -> Self {
+        self.preprocessor.reserved_char_policy = policy;
+        self
+    }
+    /// Sets how training data is lowercased.  The default, `CaseFolding::Unicode`, is right for
+    /// most languages, but mangles the dotted and dotless i of Turkish and Azerbaijani; use
+    /// `CaseFolding::Turkic` for corpora in those languages.
+    pub fn with_case_folding(mut self, case_folding: CaseFolding) -> Self {
+        self.preprocessor.case_folding = case_folding;
+        self
+    }
+    /// Sets whether training data is lowercased (the default is `true`).  Turn this off if the
+    /// case of your corpus is meaningful, as in "McKinley" or all-caps inscriptions, and the
+    /// generated text will reproduce it.
+    pub fn lowercase_input(mut self, lowercase: bool) -> Self {
+        if !lowercase {
+            self.preprocessor.case_folding = CaseFolding::None;
+        } else if self.preprocessor.case_folding == CaseFolding::None {
+            self.preprocessor.case_folding = CaseFolding::Unicode;
         }
+        self
+    }
+    /// Learns separate statistics for the letters (or clusters) that end words, and decides
+    /// whether to end a generated name as it draws its last letter, from those statistics,
+    /// rather than after.  (The first letters of words already have statistics of their own,
+    /// from the contexts that begin at the start-of-word marker.)  This makes names end more
+    /// like the training data does, for languages whose final letters differ strongly from the
+    /// rest, like the "-us" and "-a" of Latin names; the default is `false`.  It takes more
+    /// memory, and isn't part of a precompiled model.
+    pub fn with_positional_tables(mut self, positional: bool) -> Self {
+        self.finals = positional.then(Chain::new);
+        self
+    }
+    /// Learns where capital letters appear in the training data (initial letters, after spaces,
+    /// apostrophes and hyphens, and internal capitals like "McCoy") and reapplies those patterns
+    /// to generated names, before any other postprocessors.  The model itself is still trained
+    /// on lowercased text, so it learns as much from "McCoy" as from "mccoy".
+    pub fn learn_capitalization(mut self, learn: bool) -> Self {
+        self.capitalization = learn.then(CapitalizationModel::default);
+        self
+    }
+    /// Sets what to do with training lines containing more than one word, like
+    /// "Gaius Julius Caesar".  By default the whole line is trained, spaces included; you can
+    /// instead train on each word separately, or only on the first or last word of each line.
+    pub fn with_multi_word_policy(mut self, policy: MultiWordPolicy) -> Self {
+        self.preprocessor.multi_word_policy = policy;
+        self
+    }
+    /// Sets whether whitespace, including the carriage returns of Windows line endings, is
+    /// trimmed from both ends of each training line, and blank lines skipped (the default is
+    /// `false`).
+    pub fn trim_input(mut self, trim: bool) -> Self {
+        self.preprocessor.trim = trim;
+        self
+    }
+    /// Skips training lines that start with the given comment marker, like `'#'` or `';'`,
+    /// after any leading whitespace.
+    pub fn skip_comments(mut self, marker: char) -> Self {
+        self.preprocessor.comment_marker = Some(marker);
+        self
+    }
+    /// Sets what to do with punctuation in training data.  By default it's trained on like any
+    /// other character; you can instead strip it all, or all but the apostrophes and hyphens
+    /// inside words.  Lines left empty are skipped.
+    pub fn with_punctuation_policy(mut self, policy: PunctuationPolicy) -> Self {
+        self.preprocessor.punctuation_policy = policy;
+        self
+    }
+    /// Transliterates the training data before it is trained on, e.g. to romanize a Cyrillic
+    /// corpus with `Transliteration::iso9()`, or to normalize one that mixes spellings with a
+    /// table of your own.  The generated names are then romanized too.  See `Transliteration`
+    /// for details.
+    pub fn with_training_romanization(mut self, table: Transliteration) -> Self {
+        self.preprocessor.romanization = Some(Arc::new(table));
+        self
+    }
+    /// Resumes training from a checkpoint written by `.train_with_checkpoints()`: the model
+    /// starts with what was trained before the checkpoint, in the order it was trained with,
+    /// and the next call to `.train_with_checkpoints()` skips the samples it was trained on.
+    /// If there is no checkpoint at `path` yet, this does nothing, so the same code can start
+    /// a training job and resume it.  Other settings, such as the prior, aren't part of the
+    /// checkpoint, so set them as before.
+    ///
+    /// If the checkpoint can't be read, `try_build()` returns a `NamegenError::InvalidConfig`
+    /// error.
+    pub fn resume_from_checkpoint(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match checkpoint::read(path) {
+            Ok(Some(checkpoint)) => {
+                self.auto_tune = false;
+                self.order = checkpoint.order;
+                self.model = self.model.with_order(checkpoint.order as i32);
+                self.model.markov_chain = checkpoint.chain;
+                self.model.known_states = checkpoint.known_states;
+                self.resume_after = checkpoint.trained;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(format!(
+                        "could not read the checkpoint {}: {}",
+                        path.display(),
+                        error
+                    )));
+            }
+        }
+        self
+    }
+    /// Uses a model precompiled from `CharacterChainGenerator::model_bytes()`, e.g. by a build
+    /// script (see the `precompile` module), instead of training one, so that the generator is
+    /// ready instantly.  Training data and training settings such as `.with_order()` are
+    /// ignored, as is capitalization learned with `.learn_capitalization()`, since it isn't part
+    /// of the model.  If the bytes aren't a valid model for this kind of generator,
+    /// `try_build()` returns a `NamegenError::InvalidConfig` error.
+    pub fn with_precompiled_model(
+        mut self,
+        bytes: &[u8],
+    ) -> CharacterChainGeneratorBuilder<'a, Trained> {
+        match MarkovModel::decode(bytes) {
+            Some(model) => self.precompiled = Some(model),
+            None => {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(String::from(
+                        "not a valid precompiled model",
+                    )));
+            }
+        }
+        self.into_state()
+    }
+}
+
+impl<'a, State> CharacterChainGeneratorBuilder<'a, State> {
+    /// Gives the generator a name, returned by `RandomTextGenerator::name()`, for registries,
+    /// CLIs and debugging UIs to show.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
     }
     /// Sets a custom regex pattern for pattern matching (filtering) of output.
     /// The generator will generate names repeatedly until it finds one that matches your pattern.
     /// Be warned that if you define an impossible-to-match pattern (e.g. one that includes letters
     /// not found in the training dataset), you could end up with an infinite loop when you try
     /// to generate a name.
+    ///
+    /// The pattern is matched against the name before postprocessing, so before any
+    /// capitalization or stylization is applied.  Like any regex, it matches if it matches
+    /// *anywhere* in the name: `"[a-z]{4,8}"` accepts a name of any length with four letters in
+    /// a row, so anchor it as `"^[a-z]{4,8}$"`, or use `.with_full_match_pattern()`.
     pub fn with_pattern(mut self, pattern: &'a str) -> Self {
         self.pattern = Some(pattern);
+        self.pattern_is_full_match = false;
         self
     }
-    /// Sets a custom value for order of the Markov model.
-    /// Must be an integer greater than zero.  Values from 1 to 3 are recommended.
-    /// Higher-order models will make procedurally generated text more like the training data,
-    /// and less random, and will be slower and require more memory.
+    /// Sets a pattern, like `.with_pattern()`, that the whole name must match: the pattern is
+    /// anchored at both ends for you, so `"[a-z]{4,8}"` accepts only names of four to eight
+    /// letters.
+    pub fn with_full_match_pattern(mut self, pattern: &'a str) -> Self {
+        self.pattern = Some(pattern);
+        self.pattern_is_full_match = true;
+        self
+    }
+    /// Matches `.with_pattern()` without regard to case, so that a pattern like
+    /// `^[a-z]{4,8}$` keeps working whether or not case is kept with `.with_case_folding()`.
+    pub fn ignore_pattern_case(mut self, ignore: bool) -> Self {
+        self.pattern_ignores_case = ignore;
+        self
+    }
+    /// Makes every generated name contain the given substring.  Rather than re-rolling names
+    /// until one happens to contain it, as a pattern would, the generator plans a path through
+    /// the model: it draws the start of the name as usual until it reaches a point where the
+    /// model can go on to produce the substring, splices it in there, and finishes the name as
+    /// usual.  This is fast even for substrings the model would rarely produce by itself.  The
+    /// substring is matched against the characters of the model, before postprocessing, so it
+    /// should be in lowercase unless case is kept with `.with_case_folding()`.
+    ///
+    /// If the model doesn't know all of its characters, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error; if the model can't produce them in that order,
+    /// generating a name returns a `NamegenError::DeadEnd` error.
+    pub fn with_required_substring(mut self, substring: &str) -> Self {
+        self.required = Some(substring.to_string());
+        self
+    }
+    /// Rejects generated names that break the given phonotactic rules (too many consonants in
+    /// a row, disallowed onsets or codas, banned clusters), just like names that don't match
+    /// `.with_pattern()`.  See `phonotactics::Phonotactics` for details.
+    pub fn with_phonotactics(mut self, rules: Phonotactics) -> Self {
+        self.phonotactics = Some(rules);
+        self
+    }
+    /// Caps the number of candidates the generator will try, per name, to find one that matches
+    /// the pattern.  When the cap is reached, `try_generate_one()` returns a
+    /// `NamegenError::MaxAttemptsExceeded` error (and `generate_one()` panics), so a bad pattern
+    /// fails loudly instead of hanging the calling thread forever.  By default there is no cap.
+    ///
+    /// If the cap is zero, `try_build()` returns a `NamegenError::InvalidConfig` error.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        if max_attempts == 0 {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(String::from(
+                    "max attempts must be greater than zero",
+                )));
+            return self;
+        }
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+    /// Calls `hook` with every candidate the generator comes up with, before it's checked
+    /// against the pattern and other filters (and before postprocessing), e.g. to log or count
+    /// them.
+    pub fn on_candidate(mut self, hook: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.hooks.on_candidate = Some(Arc::new(hook));
+        self
+    }
+    /// Calls `hook` with every name the generator rejects, the reason it was rejected, and the
+    /// number of attempts made so far to generate this name.  Return `ControlFlow::Break(())`
+    /// to give up, and `try_generate_one()` returns a `NamegenError::Aborted` error (and
+    /// `generate_one()` panics); this lets you cap re-rolls with a policy of your own, e.g. a
+    /// time limit.  See the `hooks` module.
+    pub fn on_reject(
+        mut self,
+        hook: impl Fn(&str, &Rejection, usize) -> ControlFlow<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_reject = Some(Arc::new(hook));
+        self
+    }
+    /// Makes the generator remember every name it returns, and never return the same name twice
+    /// until its `reset_memory()` is called.  Rejected repeats count towards
+    /// `.with_max_attempts()`, so that a generator which has exhausted its possible names can
+    /// fail with an error rather than loop forever.
+    pub fn remember_outputs(mut self, remember: bool) -> Self {
+        self.memory = remember.then(|| NameMemory::Exact(HashSet::new()));
+        self
+    }
+    /// Like `.remember_outputs(true)`, but the names are also kept in a file at `path`, one per
+    /// line, so that a long-running server never repeats a name even across restarts.  Names
+    /// already in the file are loaded now, and each new one is appended as it's returned;
+    /// `reset_memory()` empties the file.  Forks of the generator append to the same file, but
+    /// only see each other's names once it's loaded again.
+    ///
+    /// If the file can't be opened or created, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn remember_outputs_in(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match PersistentMemory::open(path) {
+            Ok(memory) => self.memory = Some(NameMemory::Persistent(memory)),
+            Err(error) => {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(format!(
+                        "could not open {}: {}",
+                        path.display(),
+                        error
+                    )));
+            }
+        }
+        self
+    }
+    /// Like `.remember_outputs(true)`, but for massive batches (tens of millions of names),
+    /// where remembering every name exactly would take too much memory.  Names are remembered
+    /// in a Bloom filter sized for `expected_names` at the given `false_positive_rate`, so
+    /// memory use stays fixed, at the cost of occasionally rejecting a name that is in fact new.
     ///
-    /// NOTE: Order should be set *before* training the model with `.train()`
-    pub fn with_order(mut self, order: i32) -> Self {
-        assert!(order > 0, "Order must be an integer greater than zero.");
-        self.model = self.model.with_order(order); // update model now, so it'll affect training
+    /// If the false-positive rate isn't strictly between 0.0 and 1.0, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn remember_outputs_approximately(
+        mut self,
+        expected_names: usize,
+        false_positive_rate: f64,
+    ) -> Self {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "the false-positive rate must be between 0.0 and 1.0, not {}",
+                    false_positive_rate
+                )));
+            return self;
+        }
+        self.memory = Some(NameMemory::Approximate(BloomFilter::new(
+            expected_names,
+            false_positive_rate,
+        )));
+        self
+    }
+    /// Makes the given transitions impossible, e.g. `[("q", "k")]` to never let 'k' follow
+    /// 'q', however often the training data or the prior would allow it.  Each transition is
+    /// from one character to the next, in lowercase unless case is kept with
+    /// `.with_case_folding()`.  This bans known-ugly combinations outright, where a pattern
+    /// would only reject the names that contain them.  May be called more than once to add
+    /// more transitions.
+    ///
+    /// If either side of a transition isn't a single character, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn forbid_transitions<S: AsRef<str>>(
+        mut self,
+        transitions: impl IntoIterator<Item = (S, S)>,
+    ) -> Self {
+        for (from, to) in transitions {
+            let single = |s: &str| {
+                let mut chars = s.chars();
+                chars.next().filter(|_| chars.next().is_none())
+            };
+            match (single(from.as_ref()), single(to.as_ref())) {
+                (Some(from), Some(to)) => self.forbidden.push((from, to)),
+                _ => {
+                    self.error
+                        .get_or_insert(NamegenError::InvalidConfig(format!(
+                            "'{}' -> '{}' is not a transition between two characters",
+                            from.as_ref(),
+                            to.as_ref()
+                        )));
+                }
+            }
+        }
+        self
+    }
+    /// Trains the model on an "anti-corpus" of names not to resemble, like real trademarks or
+    /// a list of ugly names: every transition in them is made rarer, its weight halved each
+    /// time it appears, so that generation is steered away from their patterns rather than
+    /// re-rolling names that match them.  The names are cleaned up and split into words like
+    /// a training corpus, and applied to the final model, prior included, so it doesn't matter
+    /// whether they're given before or after `.train()`.  May be called more than once.
+    pub fn train_negative(mut self, lines: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        let words = self.preprocessor.prepare_all(lines, None, &mut self.error);
+        self.negative.extend(words);
+        self
+    }
+    /// Makes the generator never return any of the given names, e.g. those already in use in
+    /// your database or game world.  Names are compared exactly to the final output (after
+    /// postprocessing), and rejected ones count towards `.with_max_attempts()`.  May be called
+    /// more than once to add more names.
+    pub fn avoid(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.avoid.extend(names.into_iter().map(Into::into));
+        self
+    }
+    /// Makes the generator never return a name that sounds like an entry of the blocklist,
+    /// e.g. a respelled slur that a substring check would miss.  Names are checked after
+    /// postprocessing, and rejected ones count towards `.with_max_attempts()`.  See
+    /// `phonetic::PhoneticBlocklist` for details.
+    pub fn with_phonetic_blocklist(mut self, blocklist: PhoneticBlocklist) -> Self {
+        self.phonetic_blocklist = Some(blocklist);
+        self
+    }
+    /// Normalizes or rejects names that mix lookalike letters of different scripts, as a
+    /// model trained on a corpus mixing Latin and Cyrillic may generate.  Names are checked
+    /// after postprocessing, and rejected ones count towards `.with_max_attempts()`.  See
+    /// `ConfusablePolicy` for details.
+    pub fn with_confusable_policy(mut self, policy: ConfusablePolicy) -> Self {
+        self.confusable_policy = Some(policy);
+        self
+    }
+    /// Makes every generated name into a programming identifier, in the given case and no
+    /// longer than the given length, re-rolling names that can't be made into one.  This is
+    /// done last, after postprocessing, and re-rolled names count towards
+    /// `.with_max_attempts()`.  See `identifier::IdentifierStyle` for details.
+    pub fn as_identifiers(mut self, style: IdentifierStyle) -> Self {
+        self.identifier_style = Some(style);
+        self
+    }
+    /// Adds a transformation (capitalization, appending a suffix, transliteration...) to be
+    /// applied to every generated name, after pattern filtering.  You can add as many as you
+    /// like; they are applied in the order they were added.
+    pub fn with_postprocessor(
+        mut self,
+        postprocessor: impl Fn(String) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, _rng| postprocessor(name)));
+        self
+    }
+    /// Adds a postprocessor that randomly attaches prefixes and/or suffixes to generated names.
+    /// See `postprocess::Affixes` for details.
+    pub fn with_affixes(mut self, affixes: Affixes) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, rng| affixes.decorate(name, rng)));
+        self
+    }
+    /// Adds a postprocessor that randomly inserts apostrophes and hyphens at natural syllable
+    /// breaks in generated names.  See `postprocess::Stylizer` for details.
+    pub fn with_stylizer(mut self, stylizer: Stylizer) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, rng| stylizer.stylize(name, rng, None)));
+        self
+    }
+    /// Respells some generated names with historical or dialectal variations (c↔k, i↔y...)
+    /// that the model finds plausible.  See `postprocess::SpellingVariants` for details.  The
+    /// respelling is applied before any other postprocessing, wherever this is called.
+    pub fn with_spelling_variants(mut self, variants: SpellingVariants) -> Self {
+        self.spelling_variants = Some(variants);
+        self
+    }
+    /// Adds a postprocessor that transliterates generated names, e.g. to romanize the output of
+    /// a model trained on a Cyrillic or Greek corpus.  See `Transliteration` for details.
+    pub fn with_romanization(mut self, table: Transliteration) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, _rng| table.apply(&name)));
+        self
+    }
+    /// Adds a postprocessor that makes generated names into URL slugs ("Jean-Luc Marais" →
+    /// "jean-luc-marais"), for readable unique URLs.  Postprocessors are applied in the order
+    /// they were added, so add this one after any that add words or punctuation.  See
+    /// `postprocess::slugify` for details.
+    pub fn as_slug(mut self) -> Self {
+        self.postprocessors
+            .push(Arc::new(|name, _rng| slugify(name)));
+        self
+    }
+    /// Sets the grapheme-to-phoneme table used by the generator's `generate_pronounced()` to
+    /// produce an IPA pronunciation of each name.  By default, `Transliteration::ipa()` is used.
+    pub fn with_pronunciation(mut self, table: Transliteration) -> Self {
+        self.pronunciation = Some(table);
+        self
+    }
+    /// Sets the diminutive endings the generator's `nickname()` and `generate_nicknamed()` may
+    /// give nicknames.  By default, `Diminutives::common()` is used.
+    pub fn with_diminutives(mut self, diminutives: Diminutives) -> Self {
+        self.diminutives = Some(diminutives);
         self
     }
     /// Sets a custom value for prior probabilities. The greater the prior, the more likely you'll
@@ -55,48 +589,484 @@ impl<'a> CharacterChainGeneratorBuilder<'a> {
     ///
     /// By default, they are set to `CharacterChainGenerator::DEFAULT_PRIOR`.
     pub fn with_prior(mut self, prior: f64) -> Self {
-        self.model = self.model.with_prior(prior);
+        self.prior = (prior != 0.0).then_some(PriorPolicy::Constant(prior));
+        self
+    }
+    /// Sets how the prior weight of unseen transitions is decided, e.g. scaling it for each
+    /// context by the number of different states observed after it, instead of using the same
+    /// constant everywhere.  See `PriorPolicy` for the options.
+    pub fn with_prior_policy(mut self, policy: PriorPolicy) -> Self {
+        self.prior = Some(policy);
+        self
+    }
+    /// Interpolates between the model's orders (Jelinek-Mercer smoothing) instead of backing
+    /// off: the probabilities after a context are a mix of those after every tail end of it,
+    /// so that a context seen only a few times in a small corpus doesn't decide the next
+    /// letter alone.  `lambdas` weighs each order, from the 1st up to the model's order, and
+    /// a context shorter than the order mixes only the orders up to its own length, with
+    /// their lambdas scaled to add up to one.  E.g. with `vec![0.2, 0.3, 0.5]` for an order-3
+    /// model, the last three letters count for half, the last two for 30% and the last one for
+    /// 20%.  The mix is worked out once, when the generator is built, so generation is no
+    /// slower.
+    ///
+    /// If a lambda is negative or they're all zero, or there are fewer lambdas than the
+    /// model's order, `try_build()` returns a `NamegenError::InvalidConfig` error.
+    ///
+    /// ```
+    /// use markov_namegen::{CharacterChainGenerator, RandomTextGenerator};
+    /// let mut namegen = CharacterChainGenerator::builder()
+    ///     .with_order(2)
+    ///     .with_interpolation(vec![0.3, 0.7])
+    ///     .train(vec!["julius", "marcus", "lucius", "tiberius"].into_iter())
+    ///     .build();
+    /// println!("{}", namegen.generate_one());
+    /// ```
+    pub fn with_interpolation(mut self, lambdas: Vec<f64>) -> Self {
+        if lambdas
+            .iter()
+            .any(|lambda| !(*lambda >= 0.0 && lambda.is_finite()))
+            || lambdas.iter().all(|lambda| *lambda == 0.0)
+        {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "lambdas must not be negative, nor all zero: {:?}",
+                    lambdas
+                )));
+            return self;
+        }
+        self.interpolation = Some(lambdas);
+        self
+    }
+    /// Never generates the letters (or clusters) seen fewer than `min_occurrences` times in
+    /// the training data, which are often typos or stray foreign letters, without having to
+    /// write a pattern that lists every letter allowed.  They're taken out of the model when
+    /// it's built, so they can't follow anything, even by way of the prior; a draw that only
+    /// ever led to one of them backs off to a shorter context.  The default of 0 keeps them
+    /// all.  It doesn't apply to a precompiled model.
+    pub fn restrict_alphabet(mut self, min_occurrences: usize) -> Self {
+        self.min_occurrences = min_occurrences;
+        self
+    }
+    /// Sets how the trained model stores its contexts.  `ContextStorage::Trie` takes much less
+    /// memory for a high-order model; see `ContextStorage` for the options.  It doesn't change
+    /// what the generator produces.
+    pub fn with_context_storage(mut self, storage: ContextStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+    /// Sets how quickly the generator forgets what it has learned, as it learns more with
+    /// `learn()` after it's built: before each name is learned, every weight in the model is
+    /// multiplied by `1 - decay`.  With the default of 0, names learned later count no more than
+    /// the training corpus; with a decay of, say, 0.001, a long-running generator drifts toward
+    /// the names it has seen lately.
+    ///
+    /// If the decay isn't from 0 up to (but not including) 1, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn with_decay(mut self, decay: f64) -> Self {
+        if !(0.0..1.0).contains(&decay) {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "decay must be from 0 up to 1, not {}",
+                    decay
+                )));
+            return self;
+        }
+        self.decay = decay;
         self
     }
     /// Set the priors to None.
     pub fn without_prior(mut self) -> Self {
-        self.model = self.model.without_prior();
+        self.prior = None;
+        self
+    }
+    /// Generates only what the training data has shown, but safely: sets the priors to None,
+    /// as `.without_prior()` does, and makes the generator recover from dead ends instead of
+    /// failing.  Without a prior, a context can be left with nothing to follow it (e.g. by
+    /// `.forbid_transitions()`, `.untrain()` or `.restrict_alphabet()`), or a required
+    /// substring can be out of reach.  In strict mode, the generator then backs off to a
+    /// shorter context at the end of the name so far, and if none can go on, starts the name
+    /// over; only if it has started over 100 times does it give up with a
+    /// `NamegenError::DeadEnd` error.
+    pub fn strict(mut self) -> Self {
+        self.prior = None;
+        self.strict = true;
         self
     }
     /// Sets a custom Random Number Generator (RNG) for the model.
+    /// A seeded RNG makes output reproducible: the same seed and the same training data will
+    /// always yield the same sequence of names.  If no RNG is given, one is seeded from entropy.
     pub fn with_rng(mut self, rng: Box<dyn RngCore + Send + Sync>) -> Self {
-        self.model = self.model.with_rng(rng);
+        self.rng = Some(GeneratorRng::Custom(rng));
+        self
+    }
+    /// Seeds the generator with a portable, pinned RNG algorithm (ChaCha8).
+    /// Unlike `SmallRng`, whose algorithm differs between 32- and 64-bit platforms, this
+    /// guarantees that the same seed and the same training data produce the same names on every
+    /// OS and architecture, e.g. so that networked players can share a world seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some(GeneratorRng::from_seed(seed));
         self
     }
     /// Ingest a training data set to train the model.
     /// The argument 'sequences' is an iterator of either `String` or `&str` values, the words or names
     /// that we want our randomly generated text to resemble.
-    pub fn train(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
-        self.model = self.model.train(
-            sequences
-                .map(|s| s.to_lowercase()) // lowercase the input
-                .map(|mut s| {
-                    s.insert(0, '#');
-                    s.push('#');
-                    s
-                }) // add the beginning-of-character and end-of-character strings
-                .map(|s| s.chars().collect()), // turn the input stream into an iterator of Vec<char>
+    pub fn train(
+        self,
+        sequences: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> CharacterChainGeneratorBuilder<'a, Trained> {
+        self.learn(sequences).into_state()
+    }
+    /// Trains the model like `.train()`, but on several chunks of a corpus at once, each in a
+    /// thread of its own: each thread trains a partial model on its chunk, and the partial
+    /// models are merged by summing their weights.  The model is exactly the same as if the
+    /// chunks had been trained on in turn, so split a big corpus into as many chunks as you
+    /// have cores.
+    ///
+    /// If `.auto_tune()` is set, the first chunk is trained on first, on its own, to tune the
+    /// model.
+    pub fn train_parallel<C, S>(
+        mut self,
+        chunks: impl IntoIterator<Item = C>,
+    ) -> CharacterChainGeneratorBuilder<'a, Trained>
+    where
+        C: IntoIterator<Item = S> + Send,
+        S: Deref<Target = str>,
+    {
+        let mut chunks = chunks.into_iter();
+        if self.auto_tune {
+            if let Some(first) = chunks.next() {
+                self = self.learn(first.into_iter());
+            }
+        }
+        let preprocessor = self.preprocessor.clone();
+        let order = self.order;
+        let learn_capitalization = self.capitalization.is_some();
+        let count_finals = self.finals.is_some();
+        let train = |chunk: C| {
+            let mut capitalization = learn_capitalization.then(CapitalizationModel::default);
+            let mut error = None;
+            let words =
+                preprocessor.prepare_all(chunk.into_iter(), capitalization.as_mut(), &mut error);
+            let samples = words.into_iter().map(|word| {
+                let mut sample: Vec<char> = word.chars().collect();
+                sample.insert(0, '#');
+                sample.push('#');
+                sample
+            });
+            Shard::train(order, samples, capitalization, count_finals, error)
+        };
+        train_shards(
+            chunks,
+            train,
+            &mut self.model,
+            &mut self.capitalization,
+            &mut self.finals,
+            &mut self.error,
+        );
+        self.into_state()
+    }
+    /// Trains the model like `.train()`, but in batches of `every` samples, writing a checkpoint
+    /// of the model trained so far to `path` after each one, so that a long training job that
+    /// is interrupted can pick up where it left off.  To resume, call
+    /// `.resume_from_checkpoint(path)` first, then call this with the same training data: the
+    /// samples the checkpoint was trained on are skipped.
+    ///
+    /// If a checkpoint can't be written, training stops, and `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.  If `every` is zero, the samples are trained on
+    /// without checkpoints, and `try_build()` returns the same error.
+    pub fn train_with_checkpoints<S: Deref<Target = str>>(
+        mut self,
+        sequences: impl Iterator<Item = S>,
+        path: impl AsRef<Path>,
+        every: usize,
+    ) -> CharacterChainGeneratorBuilder<'a, Trained> {
+        if every == 0 {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(String::from(
+                    "checkpoints must be at least one sample apart",
+                )));
+            return self.train(sequences);
+        }
+        let path = path.as_ref();
+        let mut sequences = sequences.fuse();
+        let mut trained = std::mem::take(&mut self.resume_after);
+        // the capitalization model and final counts aren't part of the checkpoint, so learn
+        // them from scratch
+        let words = self.preprocessor.prepare_all(
+            sequences.by_ref().take(trained),
+            self.capitalization.as_mut(),
+            &mut self.error,
         );
+        if self.finals.is_some() {
+            let samples = self.samples(words);
+            self.count_finals(&samples, 1.0);
+        }
+        loop {
+            let batch: Vec<S> = sequences.by_ref().take(every).collect();
+            if batch.is_empty() {
+                break;
+            }
+            trained += batch.len();
+            self = self.learn(batch.into_iter());
+            if let Err(error) = checkpoint::write(path, &self.model, self.order, trained) {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(format!(
+                        "could not write a checkpoint to {}: {}",
+                        path.display(),
+                        error
+                    )));
+                break;
+            }
+        }
+        self.into_state()
+    }
+    /// Trains the model on more data, without changing the state of the builder.
+    fn learn(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        let words: Vec<Vec<char>> = self
+            .preprocessor
+            .prepare_all(sequences, self.capitalization.as_mut(), &mut self.error)
+            .into_iter()
+            .map(|s| s.chars().collect()) // turn each word into a Vec<char>
+            .collect();
+        if self.auto_tune {
+            // tune on the first corpus only, since the order can't change once we've trained
+            self.auto_tune = false;
+            let (order, prior) = tuned_order_and_prior(&words);
+            debug!("auto-tuned to order {} and prior {}", order, prior);
+            self.order = order;
+            self.model = self.model.with_order(order as i32);
+            self.prior = Some(PriorPolicy::Constant(prior));
+        }
+        let samples: Vec<Vec<char>> = words
+            .into_iter()
+            .map(|mut word| {
+                word.insert(0, '#');
+                word.push('#');
+                word
+            })
+            .collect(); // add the beginning-of-character and end-of-character strings
+        self.count_finals(&samples, 1.0);
+        self.model = self.model.train(samples.into_iter());
+        self
+    }
+    /// Turns preprocessed words into training samples, beginning and ending with the
+    /// end-of-word marker.
+    fn samples(&self, words: Vec<String>) -> Vec<Vec<char>> {
+        words
+            .into_iter()
+            .map(|word| {
+                std::iter::once('#')
+                    .chain(word.chars())
+                    .chain(std::iter::once('#'))
+                    .collect()
+            })
+            .collect()
+    }
+    /// Counts the final states of some samples with the given weight, if positional tables are
+    /// being learned.
+    fn count_finals(&mut self, samples: &[Vec<char>], weight: f64) {
+        if let Some(finals) = self.finals.as_mut() {
+            for sample in samples {
+                count_finals(finals, self.order, sample, weight);
+            }
+        }
+    }
+    /// Moves the builder's settings into a builder in another state.
+    fn into_state<Next>(self) -> CharacterChainGeneratorBuilder<'a, Next> {
+        CharacterChainGeneratorBuilder {
+            name: self.name,
+            model: self.model,
+            order: self.order,
+            prior: self.prior,
+            storage: self.storage,
+            decay: self.decay,
+            interpolation: self.interpolation,
+            min_occurrences: self.min_occurrences,
+            strict: self.strict,
+            auto_tune: self.auto_tune,
+            resume_after: self.resume_after,
+            precompiled: self.precompiled,
+            pattern: self.pattern,
+            pattern_ignores_case: self.pattern_ignores_case,
+            pattern_is_full_match: self.pattern_is_full_match,
+            required: self.required,
+            phonotactics: self.phonotactics,
+            max_attempts: self.max_attempts,
+            hooks: self.hooks,
+            memory: self.memory,
+            avoid: self.avoid,
+            phonetic_blocklist: self.phonetic_blocklist,
+            confusable_policy: self.confusable_policy,
+            identifier_style: self.identifier_style,
+            forbidden: self.forbidden,
+            negative: self.negative,
+            postprocessors: self.postprocessors,
+            spelling_variants: self.spelling_variants,
+            pronunciation: self.pronunciation,
+            diminutives: self.diminutives,
+            rng: self.rng,
+            preprocessor: self.preprocessor,
+            capitalization: self.capitalization,
+            finals: self.finals,
+            error: self.error,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<'a> CharacterChainGeneratorBuilder<'a, Trained> {
+    /// Subtracts a corpus the model was trained on, e.g. to back out a dataset ingested by
+    /// mistake without training on everything else again.  The corpus is cleaned up and split
+    /// into words just as it was for training, and its counts taken out of the model, so that
+    /// the result is the same as if it had never been trained on.  Capitalization patterns
+    /// learned from it are kept.
+    ///
+    /// If the model wasn't trained on all of it (or was loaded precompiled), nothing is
+    /// subtracted, and `try_build()` returns a `NamegenError::InvalidTrainingData` error.
+    pub fn untrain(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        let words = self
+            .preprocessor
+            .prepare_all(sequences, None, &mut self.error);
+        let samples = self.samples(words);
+        let model = &mut self.model;
+        let result = untrain(
+            &mut model.markov_chain,
+            &mut model.known_states,
+            self.order,
+            samples.iter().cloned(),
+        );
+        match result {
+            Ok(()) => self.count_finals(&samples, -1.0),
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
         self
     }
     /// Build the CharacterChainGenerator (consuming the "Builder" in the process).
+    ///
+    /// Panics if the pattern is not a valid regex or a training sample was rejected; use
+    /// `try_build()` to handle that as an error.
     pub fn build(self) -> CharacterChainGenerator {
-        let pattern = self.pattern.map(|pat| Regex::new(pat).unwrap());
-        CharacterChainGenerator {
-            model: self.model.build(),
-            pattern,
+        self.try_build()
+            .unwrap_or_else(|error| panic!("could not build CharacterChainGenerator: {}", error))
+    }
+    /// Build the CharacterChainGenerator, or return an error if the pattern is not a valid regex
+    /// or a training sample was rejected.
+    pub fn try_build(mut self) -> Result<CharacterChainGenerator, NamegenError> {
+        if let Some(error) = self.error {
+            return Err(error);
         }
+        if let Some(capitalization) = self.capitalization {
+            self.postprocessors
+                .insert(0, Arc::new(move |name, _rng| capitalization.apply(&name)));
+        }
+        let pattern = self
+            .pattern
+            .map(|pattern| {
+                let pattern = if self.pattern_is_full_match {
+                    format!("^(?:{})$", pattern)
+                } else {
+                    pattern.to_string()
+                };
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(self.pattern_ignores_case)
+                    .build()
+            })
+            .transpose()
+            .map_err(|error| NamegenError::InvalidPattern(error.to_string()))?;
+        if self.precompiled.is_some() {
+            self.finals = None;
+        }
+        let negative = self
+            .negative
+            .iter()
+            .map(|word| {
+                std::iter::once(SENTINEL)
+                    .chain(word.chars())
+                    .chain(std::iter::once(SENTINEL))
+                    .collect()
+            })
+            .collect();
+        let mut refinement = Refinement {
+            start: SENTINEL,
+            min_occurrences: self.min_occurrences,
+            interpolation: self.interpolation,
+            prior: self.prior,
+            is_vowel: Arc::new(|c: &char| (*c != SENTINEL).then(|| c.is_romance_vowel())),
+            negative,
+            forbidden: self.forbidden,
+            storage: self.storage,
+        };
+        let counts = match self.precompiled {
+            Some(model) => {
+                // a precompiled model was refined when it was trained, but for the anti-corpus
+                // and the forbidden transitions
+                refinement.min_occurrences = 0;
+                refinement.interpolation = None;
+                refinement.prior = None;
+                model
+            }
+            None => self.model.build().into(),
+        };
+        let model = refinement.refine(&counts)?;
+        let required = self
+            .required
+            .map(|substring| model.required_states(&substring, substring.chars().collect()))
+            .transpose()?;
+        let model = Arc::new(model);
+        if let Some(variants) = self.spelling_variants {
+            let model = Arc::clone(&model);
+            let log_probability = move |variant: &str| {
+                let tokens: Vec<char> = std::iter::once('#')
+                    .chain(variant.chars())
+                    .chain(std::iter::once('#'))
+                    .collect();
+                model.log_probability(&tokens)
+            };
+            self.postprocessors.insert(
+                0,
+                Arc::new(move |name, rng| variants.vary(name, rng, &log_probability)),
+            );
+        }
+        Ok(CharacterChainGenerator {
+            name: self.name,
+            model,
+            pattern,
+            required,
+            phonotactics: self.phonotactics,
+            max_attempts: self.max_attempts,
+            hooks: self.hooks,
+            memory: self.memory,
+            avoid: Arc::new(self.avoid),
+            phonetic_blocklist: self.phonetic_blocklist.map(Arc::new),
+            confusable_policy: self.confusable_policy,
+            identifier_style: self.identifier_style,
+            counts: Arc::new(counts),
+            refinement: Arc::new(refinement),
+            preprocessor: self.preprocessor,
+            decay: self.decay,
+            strict: self.strict,
+            finals: self.finals.map(Arc::new),
+            endings: Endings::new(),
+            postprocessors: self.postprocessors,
+            pronunciation: self.pronunciation,
+            diminutives: self.diminutives.unwrap_or_default(),
+            bits_generated: 0.0,
+            rng: self.rng.unwrap_or_else(GeneratorRng::from_entropy),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::NamegenError;
+    use crate::interface::RandomTextGenerator;
+    use crate::nickname::Diminutives;
+    use crate::preprocess::{CaseFolding, PunctuationPolicy};
+    use crate::storage::ContextStorage;
     use crate::CharacterChainGenerator;
+    use std::sync::Arc;
 
     #[test]
     fn test_builder_pattern_works() {
@@ -104,13 +1074,25 @@ mod tests {
             .with_order(2)
             .with_prior(0.007)
             .with_pattern("foo")
+            .train(std::iter::empty::<&str>())
             .build();
     }
 
     #[test]
-    #[should_panic(expected = "Order must be an integer greater than zero.")]
-    fn test_order_cannot_be_less_than_one() {
-        let _generator = CharacterChainGenerator::builder().with_order(0).build();
+    fn test_order_must_be_in_range() {
+        use crate::NamegenError;
+        let build = |order| {
+            CharacterChainGenerator::builder()
+                .with_order(order)
+                .train(vec!["julius", "augustus"].into_iter())
+                .try_build()
+        };
+        assert!(matches!(build(0), Err(NamegenError::InvalidConfig(_))));
+        assert!(matches!(
+            build(CharacterChainGenerator::MAX_ORDER + 1),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        assert!(build(CharacterChainGenerator::MAX_ORDER).is_ok());
     }
 
     #[test]
@@ -123,4 +1105,1068 @@ mod tests {
         //let inputs_as_strings = vec![String::from("dopey"),String::from("sneezy"),String::from("bashful"),String::from("sleepy"),String::from("happy"),String::from("grumpy"),String::from("doc")].into_iter();
         let _generator = CharacterChainGenerator::builder().train(inputs).build();
     }
+
+    #[test]
+    fn test_config_builds_the_same_generator_as_the_builder() {
+        use crate::{GeneratorConfig, NamegenError, RandomTextGenerator};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let config = GeneratorConfig {
+            order: Some(2),
+            prior: Some(0.001),
+            pattern: Some(String::from("^[aeiou]")),
+            seed: Some(11),
+            ..Default::default()
+        };
+        let mut configured =
+            CharacterChainGenerator::from_config(config.clone(), corpus.lines()).unwrap();
+        let mut built = CharacterChainGenerator::builder()
+            .with_order(2)
+            .with_prior(0.001)
+            .with_pattern("^[aeiou]")
+            .with_seed(11)
+            .train(corpus.lines())
+            .build();
+        for _ in 0..20 {
+            assert_eq!(configured.generate_one(), built.generate_one());
+        }
+        let invalid = GeneratorConfig {
+            pattern: Some(String::from("[a-z")),
+            ..config.clone()
+        };
+        assert!(matches!(
+            CharacterChainGenerator::from_config(invalid, corpus.lines()),
+            Err(NamegenError::InvalidPattern(_))
+        ));
+        let invalid = GeneratorConfig {
+            max_attempts: Some(0),
+            ..config
+        };
+        assert!(matches!(
+            CharacterChainGenerator::from_config(invalid, corpus.lines()),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_learning_with_decay_drifts_toward_new_names() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let build = |decay| {
+            CharacterChainGenerator::builder()
+                .with_order(1)
+                .without_prior()
+                .with_seed(3)
+                .with_decay(decay)
+                .train(vec!["abe"].into_iter())
+                .build()
+        };
+        let mut remembering = build(0.0);
+        let mut fork = remembering.fork(4);
+        remembering.learn(vec!["Ode"].into_iter()).unwrap();
+        let names: Vec<String> = (0..50).map(|_| remembering.generate_one()).collect();
+        assert!(names.iter().any(|name| name.starts_with('a')));
+        assert!(names.iter().any(|name| name.starts_with('o')));
+        // a fork keeps the model it was made with
+        for _ in 0..50 {
+            assert_eq!(fork.generate_one(), "abe");
+        }
+        let mut forgetting = build(0.999_999);
+        forgetting.learn(vec!["ode"].into_iter()).unwrap();
+        for _ in 0..50 {
+            assert_eq!(forgetting.generate_one(), "ode");
+        }
+        let invalid = CharacterChainGenerator::builder()
+            .with_decay(1.0)
+            .train(vec!["abe"].into_iter())
+            .try_build();
+        assert!(matches!(invalid, Err(NamegenError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_untraining_backs_out_a_corpus() {
+        use crate::{NamegenError, RandomTextGenerator};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mistake = vec!["Xerxes", "Zzyzx 42", "qwerty"];
+        let mut untrained = CharacterChainGenerator::builder()
+            .with_seed(8)
+            .train(corpus.lines())
+            .train(mistake.clone().into_iter())
+            .untrain(mistake.clone().into_iter())
+            .build();
+        let mut clean = CharacterChainGenerator::builder()
+            .with_seed(8)
+            .train(corpus.lines())
+            .build();
+        for _ in 0..20 {
+            assert_eq!(untrained.generate_one(), clean.generate_one());
+        }
+        let never_trained = CharacterChainGenerator::builder()
+            .train(corpus.lines())
+            .untrain(mistake.into_iter())
+            .try_build();
+        assert!(matches!(
+            never_trained,
+            Err(NamegenError::InvalidTrainingData(_))
+        ));
+    }
+
+    #[test]
+    fn test_negative_training_steers_away_from_the_anti_corpus() {
+        use crate::RandomTextGenerator;
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let starting_with_ma = |mut generator: CharacterChainGenerator| {
+            (0..500)
+                .filter(|_| generator.generate_one().starts_with("ma"))
+                .count()
+        };
+        let plain = CharacterChainGenerator::builder()
+            .with_seed(6)
+            .train(corpus.lines())
+            .build();
+        let steered = CharacterChainGenerator::builder()
+            .with_seed(6)
+            .train_negative(vec!["Marcus", "Marius", "Maximus"].into_iter())
+            .train(corpus.lines())
+            .build();
+        let (plain, steered) = (starting_with_ma(plain), starting_with_ma(steered));
+        assert!(steered * 2 < plain, "{} vs {}", steered, plain);
+    }
+
+    #[test]
+    fn test_positional_tables_end_names_like_the_corpus() {
+        use crate::RandomTextGenerator;
+        // "a" follows "t" as often as "k", but only ever ends a word after "k"
+        let corpus = vec!["ka", "ka", "tak", "tat"];
+        let ending_in_ta = |positional| {
+            let mut generator = CharacterChainGenerator::builder()
+                .with_order(1)
+                .with_positional_tables(positional)
+                .with_seed(3)
+                .train(corpus.clone().into_iter())
+                .build();
+            (0..200)
+                .filter(|_| generator.generate_one().ends_with("ta"))
+                .count()
+        };
+        assert!(ending_in_ta(false) > 0);
+        assert_eq!(ending_in_ta(true), 0);
+    }
+
+    #[test]
+    fn test_names_of_an_exact_length() {
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(4)
+            .train(corpus.lines())
+            .build();
+        for length in 1..10 {
+            for _i in 0..10 {
+                let name = generator.generate_with_length(length);
+                assert_eq!(name.chars().count(), length, "{}", name);
+            }
+        }
+        // without a prior, no name can be longer than "abc"
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["ab", "abc"].into_iter())
+            .build();
+        assert!(matches!(
+            generator.try_generate_with_length(4),
+            Err(NamegenError::DeadEnd(_))
+        ));
+    }
+
+    #[test]
+    fn test_strict_mode_recovers_from_dead_ends() {
+        // nothing can follow "b" once it's forbidden to end a name, so "ab" is a dead end
+        let build = |strict: bool| {
+            let builder = CharacterChainGenerator::builder()
+                .without_prior()
+                .forbid_transitions([("b", "#")]);
+            let builder = if strict { builder.strict() } else { builder };
+            builder
+                .with_seed(2)
+                .train(vec!["ab", "ac"].into_iter())
+                .build()
+        };
+        let mut lenient = build(false);
+        assert!(
+            (0..50).any(|_| matches!(lenient.try_generate_one(), Err(NamegenError::DeadEnd(_))))
+        );
+        let mut strict = build(true);
+        for _i in 0..50 {
+            assert_eq!(strict.generate_one(), "ac");
+        }
+    }
+
+    #[test]
+    fn test_strict_mode_records_names_it_backed_off_to_make() {
+        // nothing follows "ab" once "b" can't go on to "c", so strict mode backs off to "b"
+        let mut recorder = CharacterChainGenerator::builder()
+            .with_order(2)
+            .strict()
+            .with_seed(3)
+            .forbid_transitions(vec![("b", "c")])
+            .train(vec!["abc", "bd"].into_iter())
+            .build();
+        let mut replayer = recorder.fork(4);
+        for _i in 0..50 {
+            let recorded = recorder.try_generate_recorded().unwrap();
+            assert_eq!(replayer.replay(&recorded.choices).unwrap(), recorded.name);
+        }
+    }
+
+    #[test]
+    fn test_learned_names_are_refined_like_the_corpus() {
+        let mut generator = CharacterChainGenerator::builder()
+            .with_order(2)
+            .with_prior(0.1)
+            .restrict_alphabet(2)
+            .train(vec!["abe", "abe", "ode"].into_iter())
+            .build();
+        assert!(!generator.model.known_states.contains(&'o'));
+        generator.learn(vec!["eba", "xab"].into_iter()).unwrap();
+        // a context first seen in the learned names gets the prior
+        let (context, transitions) = generator.model.best_context(&['e', 'b']).unwrap();
+        assert_eq!(context, ['e', 'b']);
+        assert!(transitions.get(&'e').is_some());
+        // letters still too rare stay out, and those seen often enough now come in
+        assert!(!generator.model.known_states.contains(&'x'));
+        generator.learn(vec!["od"].into_iter()).unwrap();
+        assert!(generator.model.known_states.contains(&'o'));
+        assert!(generator
+            .model
+            .contexts()
+            .all(|(_, transitions)| transitions.get(&'x').is_none()));
+    }
+
+    #[test]
+    fn test_rare_letters_are_never_generated() {
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = CharacterChainGenerator::builder()
+            .with_prior(0.05)
+            .restrict_alphabet(2)
+            .with_seed(5)
+            .train(corpus.lines().chain(std::iter::once("Bjørn")))
+            .build();
+        for _i in 0..500 {
+            let name = generator.generate_one();
+            assert!(!name.contains('ø'), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_interpolation_smooths_a_small_corpus() {
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let corpus: Vec<String> = corpus.lines().take(60).map(str::to_lowercase).collect();
+        let copies = |lambdas: Option<Vec<f64>>| {
+            let mut builder = CharacterChainGenerator::builder().without_prior();
+            if let Some(lambdas) = lambdas {
+                builder = builder.with_interpolation(lambdas);
+            }
+            let mut generator = builder
+                .with_seed(12)
+                .train(corpus.iter().map(String::as_str))
+                .build();
+            (0..300)
+                .filter(|_| corpus.contains(&generator.generate_one()))
+                .count()
+        };
+        // only the longest contexts count: the same as backing off
+        assert_eq!(copies(Some(vec![0.0, 0.0, 1.0])), copies(None));
+        let smoothed = copies(Some(vec![0.2, 0.3, 0.5]));
+        assert!(smoothed < copies(None), "{} vs {}", smoothed, copies(None));
+        for lambdas in [vec![0.5, -0.5, 1.0], vec![0.0, 0.0, 0.0], vec![0.5, 0.5]] {
+            let result = CharacterChainGenerator::builder()
+                .with_interpolation(lambdas)
+                .train(corpus.iter().map(String::as_str))
+                .try_build();
+            assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn test_seeded_generators_give_the_same_output() {
+        use crate::RandomTextGenerator;
+        use rand::{rngs::SmallRng, SeedableRng};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut gen1 = CharacterChainGenerator::builder()
+            .with_rng(Box::new(SmallRng::seed_from_u64(42)))
+            .train(corpus.lines())
+            .build();
+        let mut gen2 = CharacterChainGenerator::builder()
+            .with_rng(Box::new(SmallRng::seed_from_u64(42)))
+            .train(corpus.lines())
+            .build();
+        for _i in 0..50 {
+            assert_eq!(gen1.generate_one(), gen2.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_seeded_output_is_the_same_on_every_platform() {
+        // These names were recorded once and must never change: if this test fails, a seed
+        // shared between machines (or saved by a previous version) no longer yields the same names.
+        use crate::RandomTextGenerator;
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(2024)
+            .train(corpus.lines())
+            .build();
+        let names: Vec<String> = (0..6).map(|_| generator.generate_one()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "cyrius",
+                "firmus",
+                "piscius",
+                "vitalissus",
+                "poplicjeronian",
+                "maximianus"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untrained_generator_returns_an_error() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .train(std::iter::empty::<&str>())
+            .build();
+        assert_eq!(generator.try_generate_one(), Err(NamegenError::EmptyModel));
+    }
+
+    #[test]
+    fn test_invalid_pattern_returns_an_error() {
+        use crate::NamegenError;
+        let result = CharacterChainGenerator::builder()
+            .with_pattern("[a-z")
+            .train(std::iter::empty::<&str>())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn test_reserved_characters_in_training_data() {
+        use crate::{NamegenError, RandomTextGenerator, ReservedCharPolicy};
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["a#b"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "ab");
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .with_reserved_char_policy(ReservedCharPolicy::Escape)
+            .train(vec!["a#b"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "a#b");
+        let result = CharacterChainGenerator::builder()
+            .with_reserved_char_policy(ReservedCharPolicy::Error)
+            .train(vec!["a#b"].into_iter())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidTrainingData(_))));
+    }
+
+    #[test]
+    fn test_turkic_case_folding() {
+        use crate::{CaseFolding, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .with_case_folding(CaseFolding::Turkic)
+            .train(vec!["IŞIK"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "ışık");
+    }
+
+    #[test]
+    fn test_lowercasing_can_be_turned_off() {
+        use crate::{CaseFolding, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .lowercase_input(false)
+            .train(vec!["McKinley"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "McKinley");
+        let builder = CharacterChainGenerator::builder()
+            .with_case_folding(CaseFolding::Turkic)
+            .lowercase_input(true);
+        assert_eq!(builder.preprocessor.case_folding, CaseFolding::Turkic);
+    }
+
+    #[test]
+    fn test_multi_word_lines_can_be_split() {
+        use crate::{MultiWordPolicy, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .with_multi_word_policy(MultiWordPolicy::LastWord)
+            .train(vec!["Gaius Julius Caesar"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "caesar");
+    }
+
+    #[test]
+    fn test_raw_corpus_is_cleaned_up() {
+        let raw = vec![
+            "# dwarves\r",
+            "  Dopey,\r",
+            "\r",
+            "Sneezy (2)\r",
+            "Bash-ful\r",
+        ];
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .trim_input(true)
+            .skip_comments('#')
+            .with_punctuation_policy(PunctuationPolicy::KeepInWords)
+            .train(raw.into_iter())
+            .build();
+        let clean = CharacterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["dopey", "sneezy 2", "bash-ful"].into_iter())
+            .build();
+        assert_eq!(generator.model.context_count(), clean.model.context_count());
+        for _ in 0..20 {
+            let name = generator.generate_one();
+            assert!(!name.contains(|c: char| c == ',' || c == '(' || c.is_control()));
+        }
+    }
+
+    #[test]
+    fn test_hooks_see_every_candidate_and_rejection() {
+        use crate::hooks::Rejection;
+        use std::ops::ControlFlow;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+        let candidates = Arc::new(AtomicUsize::new(0));
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let mut generator = {
+            let candidates = Arc::clone(&candidates);
+            let rejections = Arc::clone(&rejections);
+            CharacterChainGenerator::builder()
+                .with_seed(2)
+                .with_pattern("^.{5}$")
+                .on_candidate(move |_| {
+                    candidates.fetch_add(1, Ordering::Relaxed);
+                })
+                .on_reject(move |name, rejection, _| {
+                    rejections
+                        .lock()
+                        .unwrap()
+                        .push((name.to_string(), rejection.clone()));
+                    ControlFlow::Continue(())
+                })
+                .train(vec!["dopey", "sneezy", "bashful", "doc"].into_iter())
+                .build()
+        };
+        let records = generator.generate_records(10);
+        let attempts: usize = records.iter().map(|record| record.attempts).sum();
+        assert_eq!(candidates.load(Ordering::Relaxed), attempts);
+        let rejections = rejections.lock().unwrap();
+        assert_eq!(rejections.len(), attempts - 10);
+        assert!(
+            rejections
+                .iter()
+                .all(|(name, rejection)| name.chars().count() != 5
+                    && *rejection == Rejection::Pattern)
+        );
+    }
+
+    #[test]
+    fn test_full_match_pattern_is_anchored() {
+        let corpus = vec![
+            "dopey", "sneezy", "bashful", "doc", "grumpy", "sleepy", "happy",
+        ];
+        let mut generator = CharacterChainGenerator::builder()
+            .with_full_match_pattern("[a-z]{4}|[a-z]{6}")
+            .train(corpus.into_iter())
+            .build();
+        for _ in 0..20 {
+            let length = generator.generate_one().chars().count();
+            assert!(length == 4 || length == 6);
+        }
+    }
+
+    #[test]
+    fn test_generators_collect_from_names() {
+        let names = vec![String::from("dopey"), String::from("sneezy")];
+        let collected: CharacterChainGenerator = names.iter().map(String::as_str).collect();
+        let converted = CharacterChainGenerator::from(names);
+        assert_eq!(
+            collected.model.context_count(),
+            converted.model.context_count()
+        );
+        assert!(collected.model.context_count() > 0);
+    }
+
+    #[test]
+    fn test_pattern_can_ignore_case() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let builder = || {
+            CharacterChainGenerator::builder()
+                .with_pattern("^[a-z]+$")
+                .with_case_folding(CaseFolding::None)
+                .without_prior()
+                .with_max_attempts(20)
+                .train(vec!["Dopey", "Sneezy", "Bashful"].into_iter())
+        };
+        assert_eq!(
+            builder().build().try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(20))
+        );
+        let name = builder().ignore_pattern_case(true).build().generate_one();
+        assert!(name.starts_with(char::is_uppercase));
+    }
+
+    #[test]
+    fn test_impossible_pattern_gives_up_after_max_attempts() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .with_pattern("^zzz$")
+            .with_max_attempts(20)
+            .train(vec!["dopey", "sneezy", "bashful"].into_iter())
+            .build();
+        assert_eq!(
+            generator.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(20))
+        );
+    }
+
+    #[test]
+    fn test_postprocessors_are_applied_in_order() {
+        use crate::RandomTextGenerator;
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .with_pattern("^doc$")
+            .with_postprocessor(|name| name.to_uppercase())
+            .with_postprocessor(|name| format!("{}!", name))
+            .train(vec!["doc"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "DOC!");
+    }
+
+    #[test]
+    fn test_capitalization_is_learned_from_training_data() {
+        use crate::RandomTextGenerator;
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .learn_capitalization(true)
+            .train(vec!["McCoy"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "McCoy");
+    }
+
+    #[test]
+    fn test_remembered_outputs_are_not_repeated() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .remember_outputs(true)
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        let mut names = vec![generator.generate_one(), generator.generate_one()];
+        names.sort();
+        assert_eq!(names, vec!["ab", "ba"]);
+        assert_eq!(
+            generator.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(100))
+        );
+        generator.reset_memory();
+        assert!(generator.try_generate_one().is_ok());
+    }
+
+    #[test]
+    fn test_approximate_memory_does_not_repeat() {
+        use crate::RandomTextGenerator;
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .remember_outputs_approximately(10, 0.001)
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        let mut names = vec![generator.generate_one(), generator.generate_one()];
+        names.sort();
+        assert_eq!(names, vec!["ab", "ba"]);
+        assert!(generator.try_generate_one().is_err());
+    }
+
+    #[test]
+    fn test_false_positive_rate_out_of_range_is_an_error() {
+        use crate::NamegenError;
+        for rate in [0.0, 1.0, f64::NAN] {
+            let result = CharacterChainGenerator::builder()
+                .remember_outputs_approximately(10, rate)
+                .train(vec!["ab", "ba"].into_iter())
+                .try_build();
+            assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn test_avoided_names_are_never_generated() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .avoid(vec!["ab"])
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "ba");
+        }
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .avoid(vec!["ab"])
+            .avoid(vec![String::from("ba")])
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        assert_eq!(
+            generator.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(100))
+        );
+    }
+
+    #[test]
+    fn test_names_that_sound_blocked_are_never_generated() {
+        use crate::phonetic::{PhoneticAlgorithm, PhoneticBlocklist};
+        use crate::RandomTextGenerator;
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .with_phonetic_blocklist(
+                PhoneticBlocklist::new(PhoneticAlgorithm::Metaphone).with_entries(["phuck"]),
+            )
+            .train(vec!["fuk", "tom"].into_iter())
+            .build();
+        assert!(generator.config_summary().ends_with(", blocks 1 sounds"));
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "tom");
+        }
+    }
+
+    #[test]
+    fn test_names_mixing_scripts_are_normalized() {
+        use crate::{ConfusablePolicy, RandomTextGenerator};
+        // the second "kora" has a Cyrillic "о"
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .with_confusable_policy(ConfusablePolicy::Normalize)
+            .train(vec!["kora", "kоra"].into_iter())
+            .build();
+        assert!(generator
+            .config_summary()
+            .ends_with(", normalizes confusables"));
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "kora");
+        }
+    }
+
+    #[test]
+    fn test_names_are_made_into_identifiers() {
+        use crate::identifier::IdentifierStyle;
+        use crate::RandomTextGenerator;
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .as_identifiers(IdentifierStyle::default())
+            .train(vec!["kora vel", "if"].into_iter())
+            .build();
+        assert!(generator.config_summary().ends_with(", identifiers"));
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "kora_vel");
+        }
+    }
+
+    #[test]
+    fn test_restoring_a_snapshot_without_memory_clears_it() {
+        use crate::RandomTextGenerator;
+        let build = |remember| {
+            CharacterChainGenerator::builder()
+                .with_seed(3)
+                .without_prior()
+                .with_max_attempts(5)
+                .remember_outputs(remember)
+                .train(vec!["kora"].into_iter())
+                .build()
+        };
+        let snapshot = build(false).save_state().unwrap();
+        let mut generator = build(true);
+        assert_eq!(generator.generate_one(), "kora");
+        assert!(generator.try_generate_one().is_err());
+        generator.restore_state(&snapshot);
+        assert_eq!(generator.generate_one(), "kora");
+    }
+
+    #[test]
+    fn test_entropy_of_names_and_bits_generated() {
+        use crate::{MultiWordPolicy, RandomTextGenerator};
+        let mut generator = CharacterChainGenerator::builder()
+            .with_order(1)
+            .without_prior()
+            .with_multi_word_policy(MultiWordPolicy::Split)
+            .remember_outputs(true)
+            .train(vec!["kora", "kori", "Kora Vel"].into_iter())
+            .build();
+        // "kora" begins with 'k' three times in four and ends in 'a' two times in three
+        assert!((generator.entropy_of("kora") - 1.0).abs() < 1e-9);
+        assert!((generator.entropy_of("Kora Vel") - 3.0).abs() < 1e-9);
+        assert_eq!(generator.entropy_of("koral"), f64::INFINITY);
+        assert_eq!(generator.entropy_of(""), f64::INFINITY);
+        assert_eq!(generator.bits_generated(), 0.0);
+        // the three names it can make: two choices for "kora" and "kori", one for "vel"
+        for _ in 0..3 {
+            generator.generate_one();
+        }
+        let first = -(0.75f64 * 0.75f64.log2() + 0.25 * 0.25f64.log2());
+        let last = -(2.0f64 / 3.0 * (2.0f64 / 3.0).log2() + 1.0 / 3.0 * (1.0f64 / 3.0).log2());
+        let expected = 3.0 * first + 2.0 * last;
+        assert!((generator.bits_generated() - expected).abs() < 1e-9);
+        assert_eq!(generator.fork(1).bits_generated(), 0.0);
+    }
+
+    #[test]
+    fn test_names_are_made_into_slugs() {
+        use crate::RandomTextGenerator;
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .as_slug()
+            .train(vec!["Jean-Luc Marais"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "jean-luc-marais");
+    }
+
+    #[test]
+    fn test_names_derived_from_keys_are_stable() {
+        use crate::RandomTextGenerator;
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let build = || {
+            CharacterChainGenerator::builder()
+                .with_seed(2024)
+                .train(corpus.lines())
+                .build()
+        };
+        let (mut generator, mut other) = (build(), build());
+        let names: Vec<String> = ["npc:1", "npc:2", "npc:3"]
+            .iter()
+            .map(|key| generator.generate_for_key(99, key))
+            .collect();
+        assert_eq!(names, vec!["tremutilo", "libennius", "memigenius"]);
+        // the generator's own stream is unaffected
+        assert_eq!(generator.generate_one(), other.generate_one());
+        assert_eq!(other.generate_for_key(99, "npc:2"), names[1]);
+        assert_ne!(other.generate_for_key(100, "npc:2"), names[1]);
+    }
+
+    #[test]
+    fn test_records_describe_the_names() {
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(5)
+            .with_pattern("^.{5,}$")
+            .train(vec!["julius", "julia", "junius", "lucius"].into_iter())
+            .build();
+        let records = generator.generate_records(20);
+        assert_eq!(records.len(), 20);
+        for record in records {
+            assert_eq!(record.clusters.concat(), record.name);
+            assert_eq!(record.length, record.name.chars().count());
+            assert!(record.length >= 5);
+            assert!(record.attempts >= 1);
+            assert!(record.log_probability < 0.0 && record.log_probability.is_finite());
+        }
+    }
+
+    #[test]
+    fn test_phonotactics_filter_names() {
+        use crate::{Phonotactics, RandomTextGenerator};
+        let rules = Phonotactics::new().with_max_consonants(1);
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(3)
+            .with_phonotactics(rules.clone())
+            .train(vec!["anstrid", "ola", "brenna", "sigrun", "tove"].into_iter())
+            .build();
+        for _i in 0..20 {
+            assert!(rules.is_valid(&generator.generate_one()));
+        }
+    }
+
+    #[test]
+    fn test_training_romanization_normalizes_the_corpus() {
+        use crate::{RandomTextGenerator, Transliteration};
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(3)
+            .without_prior()
+            .with_training_romanization(Transliteration::iso9())
+            .train(vec!["Иван", "Ivan", "ivan"].into_iter())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "ivan");
+        }
+    }
+
+    #[test]
+    fn test_forks_share_the_model() {
+        use crate::RandomTextGenerator;
+        let generator = CharacterChainGenerator::builder()
+            .with_postprocessor(|name| name.to_uppercase())
+            .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+            .build();
+        let (mut a, mut b) = (generator.fork(8), generator.fork(8));
+        assert!(Arc::ptr_eq(&a.model, &generator.model));
+        for _i in 0..10 {
+            let name = a.generate_one();
+            assert_eq!(name, b.generate_one());
+            assert_eq!(name, name.to_uppercase());
+        }
+    }
+
+    #[test]
+    fn test_generator_describes_itself() {
+        use crate::RandomTextGenerator;
+        let generator = CharacterChainGenerator::builder()
+            .with_name("dwarf")
+            .with_order(2)
+            .with_pattern("^b")
+            .remember_outputs(true)
+            .train(vec!["bofur", "bombur"].into_iter())
+            .build();
+        assert_eq!(generator.name(), Some("dwarf"));
+        assert_eq!(generator.fork(1).name(), Some("dwarf"));
+        assert_eq!(generator.kind(), "characterchain");
+        let summary = generator.config_summary();
+        assert!(summary.starts_with("order 2, "), "{}", summary);
+        assert!(
+            summary.ends_with(", pattern '^b', remembers outputs"),
+            "{}",
+            summary
+        );
+    }
+
+    #[test]
+    fn test_auto_tune_picks_the_order() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let generator = CharacterChainGenerator::builder()
+            .with_order(5)
+            .auto_tune()
+            .train(corpus.lines())
+            .build();
+        // the default order suits this corpus
+        assert_eq!(generator.model.order, 3);
+    }
+
+    #[test]
+    fn test_training_resumes_from_a_checkpoint() {
+        let path = std::env::temp_dir().join(format!("resume-{}.mngc", std::process::id()));
+        let corpus = [
+            "julius", "augustus", "tiberius", "claudius", "nero", "galba",
+        ];
+        let builder = || CharacterChainGenerator::builder().with_seed(4);
+        // the first job stops after five samples, the last checkpoint having all five
+        let interrupted = builder()
+            .resume_from_checkpoint(&path)
+            .train_with_checkpoints(corpus.iter().copied().take(5), &path, 2);
+        drop(interrupted);
+        let mut resumed = builder()
+            .resume_from_checkpoint(&path)
+            .train_with_checkpoints(corpus.iter().copied(), &path, 2)
+            .build();
+        let mut uninterrupted = builder().train(corpus.iter().copied()).build();
+        for _ in 0..20 {
+            assert_eq!(resumed.generate_one(), uninterrupted.generate_one());
+        }
+        std::fs::remove_file(&path).unwrap();
+        let unreadable = builder()
+            .resume_from_checkpoint(std::env::temp_dir())
+            .train(std::iter::empty::<&str>());
+        assert!(matches!(
+            unreadable.try_build(),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_checkpoints_zero_samples_apart_are_an_error() {
+        let path = std::env::temp_dir().join(format!("every-0-{}.mngc", std::process::id()));
+        let builder = CharacterChainGenerator::builder().train_with_checkpoints(
+            ["julius", "nero"].into_iter(),
+            &path,
+            0,
+        );
+        assert!(builder.model.build().known_states.contains(&'j'));
+        assert!(!path.exists());
+        let result = CharacterChainGenerator::builder()
+            .train_with_checkpoints(["julius", "nero"].into_iter(), &path, 0)
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_parallel_training_matches_sequential_training() {
+        let corpus = [
+            "Julius",
+            "Augustus",
+            "Tiberius",
+            "Claudius",
+            "Nero",
+            "Galba",
+            "Otho",
+            "Vitellius",
+        ];
+        let builder = || {
+            CharacterChainGenerator::builder()
+                .with_seed(9)
+                .learn_capitalization(true)
+        };
+        let mut parallel = builder()
+            .train_parallel(corpus.chunks(3).map(|chunk| chunk.iter().copied()))
+            .build();
+        let mut sequential = builder().train(corpus.iter().copied()).build();
+        for _ in 0..20 {
+            assert_eq!(parallel.generate_one(), sequential.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_context_storages_generate_the_same_names() {
+        let builder = || {
+            CharacterChainGenerator::builder()
+                .with_order(4)
+                .with_seed(6)
+                .train(vec!["julius", "augustus", "tiberius", "claudius"].into_iter())
+        };
+        let mut map = builder().build();
+        let mut trie = builder().with_context_storage(ContextStorage::Trie).build();
+        let mut hashed = builder()
+            .with_context_storage(ContextStorage::Hashed)
+            .build();
+        for _ in 0..20 {
+            let name = map.generate_one();
+            assert_eq!(trie.generate_one(), name);
+            assert_eq!(hashed.generate_one(), name);
+        }
+        assert_eq!(map.config_summary(), trie.config_summary());
+        assert_eq!(map.config_summary(), hashed.config_summary());
+    }
+
+    #[test]
+    fn test_forbidden_transitions_are_never_generated() {
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(5)
+            .with_prior(0.1)
+            .forbid_transitions([("q", "k"), ("u", "u")])
+            .train(vec!["quintus", "quirinus", "qualis"].into_iter())
+            .build();
+        assert!(generator
+            .model
+            .best_model(&['q'])
+            .unwrap()
+            .get(&'k')
+            .is_none());
+        for _ in 0..200 {
+            let name = generator.generate_one().to_lowercase();
+            assert!(!name.contains("qk") && !name.contains("uu"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_required_substring_is_always_generated() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(11)
+            .with_required_substring("xyl")
+            .with_max_attempts(1)
+            .train(corpus.lines())
+            .build();
+        for _ in 0..100 {
+            let name = generator.generate_one();
+            assert!(name.to_lowercase().contains("xyl"), "{}", name);
+        }
+        assert!(generator.config_summary().contains(", requires 'xyl'"));
+        let result = CharacterChainGenerator::builder()
+            .with_required_substring("ж")
+            .train(corpus.lines())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_mutations_are_nearby_spellings() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(2)
+            .train(corpus.lines())
+            .build();
+        let mutations: Vec<String> = (0..20).map(|_| generator.mutate("Cassia", 0.2)).collect();
+        assert!(mutations.iter().filter(|name| *name != "cassia").count() > 15);
+        assert!(mutations
+            .iter()
+            .all(|name| name.chars().count().abs_diff(6) <= 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "The strength of a mutation must be between 0 and 1.")]
+    fn test_mutation_strength_is_a_fraction() {
+        let mut generator = CharacterChainGenerator::builder()
+            .train(vec!["cassia"].into_iter())
+            .build();
+        generator.mutate("cassia", 2.0);
+    }
+
+    #[test]
+    fn test_spelling_variants_must_be_plausible() {
+        use crate::postprocess::SpellingVariants;
+        let mut generator = CharacterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .with_spelling_variants(
+                SpellingVariants::new()
+                    .with_swap("k", "c")
+                    .with_probability(1.0),
+            )
+            .train(vec!["kamila", "camila", "karla"].into_iter())
+            .build();
+        for _ in 0..50 {
+            // "carla" is impossible under the model, so "karla" is never respelled
+            let name = generator.generate_one();
+            assert!(
+                ["kamila", "camila", "karla"].contains(&name.as_str()),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_nicknames_must_be_plausible() {
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .with_diminutives(Diminutives::new().with_ending("ina").with_ending("ka"))
+            .train(vec!["valeria", "marina", "val", "lucina"].into_iter())
+            .build();
+        // of "Leri", "Val", "Valina" and "Valka", only "Val" is possible under the model
+        for _ in 0..10 {
+            assert_eq!(generator.nickname("Valeria"), "Val");
+        }
+        assert_eq!(generator.nickname("xyz"), "xyz");
+        let named = generator.generate_nicknamed();
+        assert!(!named.nickname.is_empty());
+    }
+
+    #[test]
+    fn test_forbidding_more_than_a_character_is_an_error() {
+        let result = CharacterChainGenerator::builder()
+            .forbid_transitions([("qu", "k")])
+            .train(vec!["quintus"].into_iter())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+    }
 }
@@ -0,0 +1,101 @@
+//! Checkpoints of a model part-way through training, written by `.train_with_checkpoints()`
+//! and read back by `.resume_from_checkpoint()`.
+use crate::model::{Chain, MarkovModel, ModelState};
+use multimarkov::builder::MultiMarkovBuilder;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"MNGC";
+
+/// A partly-trained model: the counts of the transitions seen so far, before any prior is
+/// added, and the number of training samples they were counted from.
+pub(crate) struct Checkpoint<T> {
+    pub(crate) chain: Chain<T>,
+    pub(crate) known_states: HashSet<T>,
+    pub(crate) order: usize,
+    pub(crate) trained: usize,
+}
+
+/// Writes a checkpoint of the model being trained by `builder`.  The checkpoint is written to
+/// a temporary file beside `path` first, then moved over it, so that a crash while writing
+/// leaves the last checkpoint intact.
+pub(crate) fn write<T>(
+    path: &Path,
+    builder: &MultiMarkovBuilder<T>,
+    order: usize,
+    trained: usize,
+) -> io::Result<()>
+where
+    T: Eq + Hash + Clone + Ord + ModelState,
+{
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&(trained as u64).to_le_bytes());
+    let contexts = builder
+        .markov_chain
+        .iter()
+        .map(|(context, transitions)| {
+            let weights = transitions.iter().map(|(state, weight)| (state, *weight));
+            (context.clone(), weights.collect())
+        })
+        .collect();
+    let known_states: BTreeSet<&T> = builder.known_states.iter().collect();
+    MarkovModel::encode_chain(order, contexts, known_states.into_iter(), &mut bytes);
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, path)
+}
+
+/// Reads a checkpoint written by `write()`, or returns `None` if there is no file at `path`.
+pub(crate) fn read<T>(path: &Path) -> io::Result<Option<Checkpoint<T>>>
+where
+    T: Eq + Hash + Clone + Ord + ModelState,
+{
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a valid checkpoint");
+    let rest = bytes.strip_prefix(MAGIC.as_slice()).ok_or_else(invalid)?;
+    let trained = rest.get(..8).ok_or_else(invalid)?;
+    let trained = u64::from_le_bytes(trained.try_into().map_err(|_| invalid())?) as usize;
+    let (chain, known_states, order) = MarkovModel::<T>::decode(&rest[8..])
+        .ok_or_else(invalid)?
+        .into_parts();
+    Ok(Some(Checkpoint {
+        chain,
+        known_states: known_states.into_iter().collect(),
+        order,
+        trained,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multimarkov::MultiMarkov;
+
+    #[test]
+    fn test_checkpoint_survives_writing() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.mngc", std::process::id()));
+        assert!(read::<char>(&path).unwrap().is_none());
+        let builder = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .without_prior()
+            .train(vec!["#foo#".chars().collect(), "#bar#".chars().collect()].into_iter());
+        write(&path, &builder, 2, 2).unwrap();
+        let checkpoint = read::<char>(&path).unwrap().unwrap();
+        assert_eq!(checkpoint.chain, builder.markov_chain);
+        assert_eq!(checkpoint.known_states, builder.known_states);
+        assert_eq!((checkpoint.order, checkpoint.trained), (2, 2));
+        // a checkpoint of characters isn't one of clusters, and a model isn't a checkpoint
+        assert!(read::<String>(&path).is_err());
+        fs::write(&path, b"MNG1").unwrap();
+        assert!(read::<char>(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
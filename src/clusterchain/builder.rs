@@ -1,44 +1,684 @@
+use crate::capitalization::CapitalizationModel;
+use crate::checkpoint;
 use crate::clusterchain::generator::ClusterChainGenerator;
-use is_vowel::IsRomanceVowel;
+use crate::clusterchain::start::StartCluster;
+use crate::confusable::ConfusablePolicy;
+use crate::error::NamegenError;
+use crate::hooks::{Hooks, Rejection};
+use crate::identifier::IdentifierStyle;
+use crate::language::{too_many_without_vowels, vowel_rule, Language};
+use crate::memory::{BloomFilter, NameMemory, PersistentMemory};
+use crate::model::{tuned_order_and_prior, untrain, Chain, Endings, MarkovModel, Refinement};
+use crate::nickname::Diminutives;
+use crate::parallel::{train_shards, Shard};
+use crate::phonetic::PhoneticBlocklist;
+use crate::phonotactics::Phonotactics;
+use crate::positional::count_finals;
+use crate::postprocess::{slugify, Affixes, Postprocessor, SpellingVariants, Stylizer};
+use crate::preprocess::{
+    CaseFolding, MultiWordPolicy, Preprocessor, PunctuationPolicy, ReservedCharPolicy, SENTINEL,
+};
+use crate::prior::PriorPolicy;
+use crate::script::Script;
+use crate::state::GeneratorRng;
+use crate::storage::ContextStorage;
+use crate::training::{Trained, Untrained};
+use crate::transliterate::Transliteration;
+use log::{debug, warn};
 use multimarkov::builder::MultiMarkovBuilder;
 use multimarkov::MultiMarkov;
 use rand::RngCore;
-use regex::Regex;
-use std::ops::Deref;
+use regex::RegexBuilder;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::ops::{ControlFlow, Deref};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 /// A Builder pattern for ClusterChainGenerator.
-pub struct ClusterChainGeneratorBuilder<'a> {
+pub struct ClusterChainGeneratorBuilder<'a, State = Untrained> {
+    name: Option<String>,
     model: MultiMarkovBuilder<String>,
+    order: usize,
+    prior: Option<PriorPolicy>,
+    storage: ContextStorage,
+    decay: f64,
+    interpolation: Option<Vec<f64>>,
+    min_occurrences: usize,
+    strict: bool,
+    auto_tune: bool,
+    resume_after: usize,
+    precompiled: Option<MarkovModel<String>>,
     pattern: Option<&'a str>,
+    pattern_ignores_case: bool,
+    pattern_is_full_match: bool,
+    required: Option<String>,
+    start: Option<StartCluster>,
+    phonotactics: Option<Phonotactics>,
+    max_attempts: Option<usize>,
+    hooks: Hooks,
+    memory: Option<NameMemory>,
+    avoid: HashSet<String>,
+    phonetic_blocklist: Option<PhoneticBlocklist>,
+    confusable_policy: Option<ConfusablePolicy>,
+    identifier_style: Option<IdentifierStyle>,
+    forbidden: Vec<(String, String)>,
+    negative: Vec<String>,
+    postprocessors: Vec<Postprocessor>,
+    spelling_variants: Option<SpellingVariants>,
+    pronunciation: Option<Transliteration>,
+    diminutives: Option<Diminutives>,
+    rng: Option<GeneratorRng>,
+    preprocessor: Preprocessor,
+    script: Script,
+    language: Option<Language>,
+    detect_language: bool,
+    /// The language stylizers fall back on, known only once the generator is built.
+    stylizer_language: Arc<OnceLock<Language>>,
+    capitalization: Option<CapitalizationModel>,
+    finals: Option<Chain<String>>,
+    error: Option<NamegenError>,
+    state: PhantomData<State>,
 }
 
-impl<'a> ClusterChainGeneratorBuilder<'a> {
+impl<'a> Default for ClusterChainGeneratorBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ClusterChainGeneratorBuilder<'a, Untrained> {
     /// Instantiate a new builder with default values.
     pub fn new() -> Self {
         Self {
+            name: None,
             model: MultiMarkov::<String>::builder()
-                .with_order(ClusterChainGenerator::DEFAULT_ORDER)
-                .with_prior(ClusterChainGenerator::DEFAULT_PRIOR),
+                .with_order(ClusterChainGenerator::DEFAULT_ORDER as i32)
+                .without_prior(), // we add our own priors in try_build()
+            order: ClusterChainGenerator::DEFAULT_ORDER,
+            prior: Some(PriorPolicy::Constant(ClusterChainGenerator::DEFAULT_PRIOR)),
+            storage: ContextStorage::default(),
+            decay: 0.0,
+            interpolation: None,
+            min_occurrences: 0,
+            strict: false,
+            auto_tune: false,
+            resume_after: 0,
+            precompiled: None,
             pattern: None,
+            pattern_ignores_case: false,
+            pattern_is_full_match: false,
+            required: None,
+            start: None,
+            phonotactics: None,
+            max_attempts: None,
+            hooks: Hooks::default(),
+            memory: None,
+            avoid: HashSet::new(),
+            phonetic_blocklist: None,
+            confusable_policy: None,
+            identifier_style: None,
+            forbidden: Vec::new(),
+            negative: Vec::new(),
+            postprocessors: Vec::new(),
+            spelling_variants: None,
+            pronunciation: None,
+            diminutives: None,
+            rng: None,
+            preprocessor: Preprocessor::default(),
+            script: Script::default(),
+            language: None,
+            detect_language: true,
+            stylizer_language: Arc::new(OnceLock::new()),
+            capitalization: None,
+            finals: None,
+            error: None,
+            state: PhantomData,
+        }
+    }
+    /// Sets a custom value for order of the Markov model.
+    /// Must be from 1 to `ClusterChainGenerator::MAX_ORDER`; values from 1 to 3 are recommended.
+    /// Higher-order models will make procedurally generated text more like the training data,
+    /// and less random, and will be slower and require more memory.
+    ///
+    /// If the order is out of range, `try_build()` returns a `NamegenError::InvalidConfig`
+    /// error.
+    pub fn with_order(mut self, order: usize) -> Self {
+        if !(1..=ClusterChainGenerator::MAX_ORDER).contains(&order) {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "order must be from 1 to {}, not {}",
+                    ClusterChainGenerator::MAX_ORDER,
+                    order
+                )));
+            return self;
         }
+        self.order = order;
+        // update model now, so it'll affect training
+        self.model = self.model.with_order(order as i32);
+        self
+    }
+    /// Picks the order and prior for you when the model is first trained, from the size of the
+    /// corpus and of its alphabet of clusters, overriding `.with_order()` and `.with_prior()`.
+    /// The order is ln(corpus size) / ln(alphabet size), rounded and kept between 1 and 5: the
+    /// longest context the corpus is big enough to see about once.  The prior is 0.1 / alphabet
+    /// size, which keeps unseen transitions rare however large the alphabet is.
+    pub fn auto_tune(mut self) -> Self {
+        self.auto_tune = true;
+        self
+    }
+    /// Sets what to do with training samples that contain `'#'` (reserved internally to mark the
+    /// beginning and end of words) or control characters.  By default they are stripped out.
+    pub fn with_reserved_char_policy(mut self, policy: ReservedCharPolicy) -> Self {
+        self.preprocessor.reserved_char_policy = policy;
+        self
+    }
+    /// Sets how training data is lowercased.  The default, `CaseFolding::Unicode`, is right for
+    /// most languages, but mangles the dotted and dotless i of Turkish and Azerbaijani; use
+    /// `CaseFolding::Turkic` for corpora in those languages.
+    pub fn with_case_folding(mut self, case_folding: CaseFolding) -> Self {
+        self.preprocessor.case_folding = case_folding;
+        self
+    }
+    /// Sets whether training data is lowercased (the default is `true`).  Turn this off if the
+    /// case of your corpus is meaningful, as in "McKinley" or all-caps inscriptions, and the
+    /// generated text will reproduce it.
+    pub fn lowercase_input(mut self, lowercase: bool) -> Self {
+        if !lowercase {
+            self.preprocessor.case_folding = CaseFolding::None;
+        } else if self.preprocessor.case_folding == CaseFolding::None {
+            self.preprocessor.case_folding = CaseFolding::Unicode;
+        }
+        self
+    }
+    /// Learns separate statistics for the letters (or clusters) that end words, and decides
+    /// whether to end a generated name as it draws its last letter, from those statistics,
+    /// rather than after.  (The first letters of words already have statistics of their own,
+    /// from the contexts that begin at the start-of-word marker.)  This makes names end more
+    /// like the training data does, for languages whose final letters differ strongly from the
+    /// rest, like the "-us" and "-a" of Latin names; the default is `false`.  It takes more
+    /// memory, and isn't part of a precompiled model.
+    pub fn with_positional_tables(mut self, positional: bool) -> Self {
+        self.finals = positional.then(Chain::new);
+        self
+    }
+    /// Learns where capital letters appear in the training data (initial letters, after spaces,
+    /// apostrophes and hyphens, and internal capitals like "McCoy") and reapplies those patterns
+    /// to generated names, before any other postprocessors.  The model itself is still trained
+    /// on lowercased text, so it learns as much from "McCoy" as from "mccoy".
+    pub fn learn_capitalization(mut self, learn: bool) -> Self {
+        self.capitalization = learn.then(CapitalizationModel::default);
+        self
+    }
+    /// Sets what to do with training lines containing more than one word, like
+    /// "Gaius Julius Caesar".  By default the whole line is trained, spaces included; you can
+    /// instead train on each word separately, or only on the first or last word of each line.
+    pub fn with_multi_word_policy(mut self, policy: MultiWordPolicy) -> Self {
+        self.preprocessor.multi_word_policy = policy;
+        self
+    }
+    /// Sets whether whitespace, including the carriage returns of Windows line endings, is
+    /// trimmed from both ends of each training line, and blank lines skipped (the default is
+    /// `false`).
+    pub fn trim_input(mut self, trim: bool) -> Self {
+        self.preprocessor.trim = trim;
+        self
+    }
+    /// Skips training lines that start with the given comment marker, like `'#'` or `';'`,
+    /// after any leading whitespace.
+    pub fn skip_comments(mut self, marker: char) -> Self {
+        self.preprocessor.comment_marker = Some(marker);
+        self
+    }
+    /// Sets what to do with punctuation in training data.  By default it's trained on like any
+    /// other character; you can instead strip it all, or all but the apostrophes and hyphens
+    /// inside words.  Lines left empty are skipped.
+    pub fn with_punctuation_policy(mut self, policy: PunctuationPolicy) -> Self {
+        self.preprocessor.punctuation_policy = policy;
+        self
+    }
+    /// Transliterates the training data before it is trained on, e.g. to romanize a Cyrillic
+    /// corpus with `Transliteration::iso9()`, or to normalize one that mixes spellings with a
+    /// table of your own.  The generated names are then romanized too.  See `Transliteration`
+    /// for details.
+    pub fn with_training_romanization(mut self, table: Transliteration) -> Self {
+        self.preprocessor.romanization = Some(Arc::new(table));
+        self
+    }
+    /// Resumes training from a checkpoint written by `.train_with_checkpoints()`: the model
+    /// starts with what was trained before the checkpoint, in the order it was trained with,
+    /// and the next call to `.train_with_checkpoints()` skips the samples it was trained on.
+    /// If there is no checkpoint at `path` yet, this does nothing, so the same code can start
+    /// a training job and resume it.  Other settings, such as the prior, aren't part of the
+    /// checkpoint, so set them as before.
+    ///
+    /// If the checkpoint can't be read, `try_build()` returns a `NamegenError::InvalidConfig`
+    /// error.
+    pub fn resume_from_checkpoint(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match checkpoint::read(path) {
+            Ok(Some(checkpoint)) => {
+                self.auto_tune = false;
+                self.order = checkpoint.order;
+                self.model = self.model.with_order(checkpoint.order as i32);
+                self.model.markov_chain = checkpoint.chain;
+                self.model.known_states = checkpoint.known_states;
+                self.resume_after = checkpoint.trained;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(format!(
+                        "could not read the checkpoint {}: {}",
+                        path.display(),
+                        error
+                    )));
+            }
+        }
+        self
+    }
+    /// Uses a model precompiled from `ClusterChainGenerator::model_bytes()`, e.g. by a build
+    /// script (see the `precompile` module), instead of training one, so that the generator is
+    /// ready instantly.  Training data and training settings such as `.with_order()` are
+    /// ignored, as is capitalization learned with `.learn_capitalization()`, since it isn't part
+    /// of the model.  If the bytes aren't a valid model for this kind of generator,
+    /// `try_build()` returns a `NamegenError::InvalidConfig` error.
+    pub fn with_precompiled_model(
+        mut self,
+        bytes: &[u8],
+    ) -> ClusterChainGeneratorBuilder<'a, Trained> {
+        match MarkovModel::decode(bytes) {
+            Some(model) => self.precompiled = Some(model),
+            None => {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(String::from(
+                        "not a valid precompiled model",
+                    )));
+            }
+        }
+        self.into_state()
+    }
+    /// Sets the script of the training data, which decides which letters count as vowels when
+    /// words are split into vowel and consonant clusters.  By default, the script and its vowels
+    /// are detected from the first corpus trained on, with `Language::detect()`; set this (or
+    /// `.with_language()`) to override the guess.
+    pub fn with_script(mut self, script: Script) -> Self {
+        self.script = script;
+        self.language = None;
+        self.detect_language = false;
+        self
+    }
+    /// Sets the language of the training data, whose own table of vowels (see `Language`)
+    /// decides how words are split into vowel and consonant clusters.  This is more precise
+    /// than `.with_script()`, e.g. treating 'w' and 'y' as vowels in Welsh.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.script = language.script();
+        self.language = Some(language);
+        self.detect_language = false;
+        self
+    }
+    /// Transforms a String into a Vec<String> of vowel and consonant clusters, telling vowels
+    /// from consonants with the given function, e.g. `Language::is_vowel()` for the language
+    /// set with `.with_language()` or detected from the corpus.
+    pub(crate) fn clusterize_with(
+        sequence: String,
+        is_vowel: impl Fn(char) -> bool,
+    ) -> Vec<String> {
+        let mut cluster_chain: Vec<String> = Vec::new();
+        let mut chars = sequence.chars();
+        if let Some(first_character) = chars.nth(0) {
+            // start the first cluster with the first character
+            let mut current_cluster = String::from(first_character);
+            // flag the type of the first cluster (vowel or consonant)
+            let mut is_vowel_cluster = is_vowel(first_character);
+            // now loop through the other characters and build up the vec of clusters
+            for c in chars {
+                if is_vowel(c) == is_vowel_cluster {
+                    // in other words, if the next char is of the same typ (vowel/consonant) as the last one(s), add it to the current cluster
+                    current_cluster.push(c);
+                } else {
+                    // otherwise, add the current cluster to the vec and begin a new cluster with this character
+                    cluster_chain.push(current_cluster);
+                    current_cluster = String::from(c);
+                    is_vowel_cluster = !is_vowel_cluster;
+                }
+            }
+            // finalize the final cluster by adding it to the list
+            cluster_chain.push(current_cluster);
+        }
+        cluster_chain
+    }
+    /// Splits a word into syllables, built from its vowel and consonant clusters: a consonant
+    /// between two vowel clusters starts a new syllable ("fa-bia-na"), while in a run of several
+    /// consonants, the first closes the previous syllable ("kel-thar").  Vowels are told from
+    /// consonants with the given function, as for `clusterize_with()`.
+    pub(crate) fn syllabify(sequence: String, is_vowel: impl Fn(char) -> bool) -> Vec<String> {
+        let clusters = ClusterChainGeneratorBuilder::clusterize_with(sequence, &is_vowel);
+        let is_vowel_cluster = |cluster: &str| cluster.starts_with(&is_vowel);
+        let mut syllables = Vec::new();
+        let mut current_syllable = String::new();
+        for (i, cluster) in clusters.iter().enumerate() {
+            if i > 0
+                && i + 1 < clusters.len()
+                && !is_vowel_cluster(cluster)
+                && is_vowel_cluster(&clusters[i - 1])
+            {
+                let mut chars = cluster.chars();
+                if cluster.chars().count() > 1 {
+                    current_syllable.extend(chars.next());
+                }
+                syllables.push(std::mem::take(&mut current_syllable));
+                current_syllable.extend(chars);
+            } else {
+                current_syllable.push_str(cluster);
+            }
+        }
+        if !current_syllable.is_empty() {
+            syllables.push(current_syllable);
+        }
+        syllables
+    }
+}
+
+impl<'a, State> ClusterChainGeneratorBuilder<'a, State> {
+    /// Gives the generator a name, returned by `RandomTextGenerator::name()`, for registries,
+    /// CLIs and debugging UIs to show.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
     }
     /// Sets a custom regex pattern for pattern matching (filtering) of output.
     /// The generator will generate names repeatedly until it finds one that matches your pattern.
     /// Be warned that if you define an impossible-to-match pattern (e.g. one that includes letters
     /// not found in the training dataset), you could end up with an infinite loop when you try
     /// to generate a name.
+    ///
+    /// The pattern is matched against the name before postprocessing, so before any
+    /// capitalization or stylization is applied.  Like any regex, it matches if it matches
+    /// *anywhere* in the name: `"[a-z]{4,8}"` accepts a name of any length with four letters in
+    /// a row, so anchor it as `"^[a-z]{4,8}$"`, or use `.with_full_match_pattern()`.
     pub fn with_pattern(mut self, pattern: &'a str) -> Self {
         self.pattern = Some(pattern);
+        self.pattern_is_full_match = false;
         self
     }
-    /// Sets a custom value for order of the Markov model.
-    /// Must be an integer greater than zero.  Values from 1 to 3 are recommended.
-    /// Higher-order models will make procedurally generated text more like the training data,
-    /// and less random, and will be slower and require more memory.
+    /// Sets a pattern, like `.with_pattern()`, that the whole name must match: the pattern is
+    /// anchored at both ends for you, so `"[a-z]{4,8}"` accepts only names of four to eight
+    /// letters.
+    pub fn with_full_match_pattern(mut self, pattern: &'a str) -> Self {
+        self.pattern = Some(pattern);
+        self.pattern_is_full_match = true;
+        self
+    }
+    /// Matches `.with_pattern()` without regard to case, so that a pattern like
+    /// `^[a-z]{4,8}$` keeps working whether or not case is kept with `.with_case_folding()`.
+    pub fn ignore_pattern_case(mut self, ignore: bool) -> Self {
+        self.pattern_ignores_case = ignore;
+        self
+    }
+    /// Makes every generated name contain the given substring.  Rather than re-rolling names
+    /// until one happens to contain it, as a pattern would, the generator plans a path through
+    /// the model: it draws the start of the name as usual until it reaches a point where the
+    /// model can go on to produce the substring, splices it in there, and finishes the name as
+    /// usual.  This is fast even for substrings the model would rarely produce by itself.  The
+    /// substring is matched against the clusters of the model, before postprocessing, so it
+    /// should be in lowercase unless case is kept with `.with_case_folding()`.
+    ///
+    /// If the model doesn't know all of its clusters, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error; if the model can't produce them in that order,
+    /// generating a name returns a `NamegenError::DeadEnd` error.
+    pub fn with_required_substring(mut self, substring: &str) -> Self {
+        self.required = Some(substring.to_string());
+        self
+    }
+    /// Chooses the cluster every generated name starts with: exactly one cluster like "th",
+    /// one of several, or any vowel or consonant cluster (see `StartCluster`).  The first
+    /// cluster is drawn from those that fit, in proportion to how often the model starts
+    /// names with each, and the rest of the name follows from it as usual, so the names make a
+    /// family sharing their opening sound.  A required substring, if any, comes after it.
+    ///
+    /// If the model doesn't know a cluster named, or no cluster it knows fits, `try_build()`
+    /// returns a `NamegenError::InvalidConfig` error; if the model never starts a name with any
+    /// that fits (which, with a prior, it always may), generating a name returns a
+    /// `NamegenError::DeadEnd` error.
+    ///
+    /// ```
+    /// use markov_namegen::{ClusterChainGenerator, RandomTextGenerator, StartCluster};
+    /// let mut namegen = ClusterChainGenerator::builder()
+    ///     .with_start_cluster(StartCluster::Cluster(String::from("th")))
+    ///     .train(vec!["theodora", "thomas", "aurelia", "marcus"].into_iter())
+    ///     .build();
+    /// assert!(namegen.generate_one().starts_with("th"));
+    /// ```
+    pub fn with_start_cluster(mut self, start: StartCluster) -> Self {
+        self.start = Some(start);
+        self
+    }
+    /// Rejects generated names that break the given phonotactic rules (too many consonants in
+    /// a row, disallowed onsets or codas, banned clusters), just like names that don't match
+    /// `.with_pattern()`.  See `phonotactics::Phonotactics` for details.
+    pub fn with_phonotactics(mut self, rules: Phonotactics) -> Self {
+        self.phonotactics = Some(rules);
+        self
+    }
+    /// Caps the number of candidates the generator will try, per name, to find one that matches
+    /// the pattern.  When the cap is reached, `try_generate_one()` returns a
+    /// `NamegenError::MaxAttemptsExceeded` error (and `generate_one()` panics), so a bad pattern
+    /// fails loudly instead of hanging the calling thread forever.  By default there is no cap.
+    ///
+    /// If the cap is zero, `try_build()` returns a `NamegenError::InvalidConfig` error.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        if max_attempts == 0 {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(String::from(
+                    "max attempts must be greater than zero",
+                )));
+            return self;
+        }
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+    /// Calls `hook` with every candidate the generator comes up with, before it's checked
+    /// against the pattern and other filters (and before postprocessing), e.g. to log or count
+    /// them.
+    pub fn on_candidate(mut self, hook: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.hooks.on_candidate = Some(Arc::new(hook));
+        self
+    }
+    /// Calls `hook` with every name the generator rejects, the reason it was rejected, and the
+    /// number of attempts made so far to generate this name.  Return `ControlFlow::Break(())`
+    /// to give up, and `try_generate_one()` returns a `NamegenError::Aborted` error (and
+    /// `generate_one()` panics); this lets you cap re-rolls with a policy of your own, e.g. a
+    /// time limit.  See the `hooks` module.
+    pub fn on_reject(
+        mut self,
+        hook: impl Fn(&str, &Rejection, usize) -> ControlFlow<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_reject = Some(Arc::new(hook));
+        self
+    }
+    /// Makes the generator remember every name it returns, and never return the same name twice
+    /// until its `reset_memory()` is called.  Rejected repeats count towards
+    /// `.with_max_attempts()`, so that a generator which has exhausted its possible names can
+    /// fail with an error rather than loop forever.
+    pub fn remember_outputs(mut self, remember: bool) -> Self {
+        self.memory = remember.then(|| NameMemory::Exact(HashSet::new()));
+        self
+    }
+    /// Like `.remember_outputs(true)`, but the names are also kept in a file at `path`, one per
+    /// line, so that a long-running server never repeats a name even across restarts.  Names
+    /// already in the file are loaded now, and each new one is appended as it's returned;
+    /// `reset_memory()` empties the file.  Forks of the generator append to the same file, but
+    /// only see each other's names once it's loaded again.
+    ///
+    /// If the file can't be opened or created, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn remember_outputs_in(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match PersistentMemory::open(path) {
+            Ok(memory) => self.memory = Some(NameMemory::Persistent(memory)),
+            Err(error) => {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(format!(
+                        "could not open {}: {}",
+                        path.display(),
+                        error
+                    )));
+            }
+        }
+        self
+    }
+    /// Like `.remember_outputs(true)`, but for massive batches (tens of millions of names),
+    /// where remembering every name exactly would take too much memory.  Names are remembered
+    /// in a Bloom filter sized for `expected_names` at the given `false_positive_rate`, so
+    /// memory use stays fixed, at the cost of occasionally rejecting a name that is in fact new.
+    ///
+    /// If the false-positive rate isn't strictly between 0.0 and 1.0, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn remember_outputs_approximately(
+        mut self,
+        expected_names: usize,
+        false_positive_rate: f64,
+    ) -> Self {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "the false-positive rate must be between 0.0 and 1.0, not {}",
+                    false_positive_rate
+                )));
+            return self;
+        }
+        self.memory = Some(NameMemory::Approximate(BloomFilter::new(
+            expected_names,
+            false_positive_rate,
+        )));
+        self
+    }
+    /// Makes the given transitions impossible, e.g. `[("q", "k")]` to never let 'k' follow
+    /// 'q', however often the training data or the prior would allow it.  Each transition is
+    /// from one cluster to the next, in lowercase unless case is kept with
+    /// `.with_case_folding()`.  This bans known-ugly combinations outright, where a pattern
+    /// would only reject the names that contain them.  May be called more than once to add
+    /// more transitions.
     ///
-    /// NOTE: Order should be set *before* training the model with `.train()`
-    pub fn with_order(mut self, order: i32) -> Self {
-        assert!(order > 0, "Order must be an integer greater than zero.");
-        self.model = self.model.with_order(order); // update model now, so it'll affect training
+    /// If either side of a transition isn't a single vowel or consonant cluster, under the
+    /// vowels of the corpus's language, `try_build()` returns a `NamegenError::InvalidConfig`
+    /// error.
+    pub fn forbid_transitions<S: Into<String>>(
+        mut self,
+        transitions: impl IntoIterator<Item = (S, S)>,
+    ) -> Self {
+        self.forbidden.extend(
+            transitions
+                .into_iter()
+                .map(|(from, to)| (from.into(), to.into())),
+        );
+        self
+    }
+    /// Trains the model on an "anti-corpus" of names not to resemble, like real trademarks or
+    /// a list of ugly names: every transition in them is made rarer, its weight halved each
+    /// time it appears, so that generation is steered away from their patterns rather than
+    /// re-rolling names that match them.  The names are cleaned up and split into words like
+    /// a training corpus, and applied to the final model, prior included, so it doesn't matter
+    /// whether they're given before or after `.train()`.  May be called more than once.
+    pub fn train_negative(mut self, lines: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        let words = self.preprocessor.prepare_all(lines, None, &mut self.error);
+        self.negative.extend(words);
+        self
+    }
+    /// Makes the generator never return any of the given names, e.g. those already in use in
+    /// your database or game world.  Names are compared exactly to the final output (after
+    /// postprocessing), and rejected ones count towards `.with_max_attempts()`.  May be called
+    /// more than once to add more names.
+    pub fn avoid(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.avoid.extend(names.into_iter().map(Into::into));
+        self
+    }
+    /// Makes the generator never return a name that sounds like an entry of the blocklist,
+    /// e.g. a respelled slur that a substring check would miss.  Names are checked after
+    /// postprocessing, and rejected ones count towards `.with_max_attempts()`.  See
+    /// `phonetic::PhoneticBlocklist` for details.
+    pub fn with_phonetic_blocklist(mut self, blocklist: PhoneticBlocklist) -> Self {
+        self.phonetic_blocklist = Some(blocklist);
+        self
+    }
+    /// Normalizes or rejects names that mix lookalike letters of different scripts, as a
+    /// model trained on a corpus mixing Latin and Cyrillic may generate.  Names are checked
+    /// after postprocessing, and rejected ones count towards `.with_max_attempts()`.  See
+    /// `ConfusablePolicy` for details.
+    pub fn with_confusable_policy(mut self, policy: ConfusablePolicy) -> Self {
+        self.confusable_policy = Some(policy);
+        self
+    }
+    /// Makes every generated name into a programming identifier, in the given case and no
+    /// longer than the given length, re-rolling names that can't be made into one.  This is
+    /// done last, after postprocessing, and re-rolled names count towards
+    /// `.with_max_attempts()`.  See `identifier::IdentifierStyle` for details.
+    pub fn as_identifiers(mut self, style: IdentifierStyle) -> Self {
+        self.identifier_style = Some(style);
+        self
+    }
+    /// Adds a transformation (capitalization, appending a suffix, transliteration...) to be
+    /// applied to every generated name, after pattern filtering.  You can add as many as you
+    /// like; they are applied in the order they were added.
+    pub fn with_postprocessor(
+        mut self,
+        postprocessor: impl Fn(String) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, _rng| postprocessor(name)));
+        self
+    }
+    /// Adds a postprocessor that randomly attaches prefixes and/or suffixes to generated names.
+    /// See `postprocess::Affixes` for details.
+    pub fn with_affixes(mut self, affixes: Affixes) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, rng| affixes.decorate(name, rng)));
+        self
+    }
+    /// Adds a postprocessor that randomly inserts apostrophes and hyphens at natural syllable
+    /// breaks in generated names.  See `postprocess::Stylizer` for details.  Unless the
+    /// stylizer has a language of its own, syllables are found with the vowels of the
+    /// generator's language, as set with `.with_language()` or detected from the corpus.
+    pub fn with_stylizer(mut self, stylizer: Stylizer) -> Self {
+        let language = Arc::clone(&self.stylizer_language);
+        self.postprocessors.push(Arc::new(move |name, rng| {
+            stylizer.stylize(name, rng, language.get().copied())
+        }));
+        self
+    }
+    /// Respells some generated names with historical or dialectal variations (c↔k, i↔y...)
+    /// that the model finds plausible.  See `postprocess::SpellingVariants` for details.  The
+    /// respelling is applied before any other postprocessing, wherever this is called.
+    pub fn with_spelling_variants(mut self, variants: SpellingVariants) -> Self {
+        self.spelling_variants = Some(variants);
+        self
+    }
+    /// Adds a postprocessor that transliterates generated names, e.g. to romanize the output of
+    /// a model trained on a Cyrillic or Greek corpus.  See `Transliteration` for details.
+    pub fn with_romanization(mut self, table: Transliteration) -> Self {
+        self.postprocessors
+            .push(Arc::new(move |name, _rng| table.apply(&name)));
+        self
+    }
+    /// Adds a postprocessor that makes generated names into URL slugs ("Jean-Luc Marais" →
+    /// "jean-luc-marais"), for readable unique URLs.  Postprocessors are applied in the order
+    /// they were added, so add this one after any that add words or punctuation.  See
+    /// `postprocess::slugify` for details.
+    pub fn as_slug(mut self) -> Self {
+        self.postprocessors
+            .push(Arc::new(|name, _rng| slugify(name)));
+        self
+    }
+    /// Sets the grapheme-to-phoneme table used by the generator's `generate_pronounced()` to
+    /// produce an IPA pronunciation of each name.  By default, `Transliteration::ipa()` is used.
+    pub fn with_pronunciation(mut self, table: Transliteration) -> Self {
+        self.pronunciation = Some(table);
+        self
+    }
+    /// Sets the diminutive endings the generator's `nickname()` and `generate_nicknamed()` may
+    /// give nicknames.  By default, `Diminutives::common()` is used.
+    pub fn with_diminutives(mut self, diminutives: Diminutives) -> Self {
+        self.diminutives = Some(diminutives);
         self
     }
     /// Sets a custom value for prior probabilities.
@@ -57,73 +697,564 @@ impl<'a> ClusterChainGeneratorBuilder<'a> {
     ///
     /// By default, they are set to `ClusterChainGenerator::DEFAULT_PRIOR`.
     pub fn with_prior(mut self, prior: f64) -> Self {
-        self.model = self.model.with_prior(prior);
+        self.prior = (prior != 0.0).then_some(PriorPolicy::Constant(prior));
+        self
+    }
+    /// Sets how the prior weight of unseen transitions is decided, e.g. scaling it for each
+    /// context by the number of different states observed after it, instead of using the same
+    /// constant everywhere.  See `PriorPolicy` for the options.
+    pub fn with_prior_policy(mut self, policy: PriorPolicy) -> Self {
+        self.prior = Some(policy);
+        self
+    }
+    /// Interpolates between the model's orders (Jelinek-Mercer smoothing) instead of backing
+    /// off: the probabilities after a context are a mix of those after every tail end of it,
+    /// so that a context seen only a few times in a small corpus doesn't decide the next
+    /// letter alone.  `lambdas` weighs each order, from the 1st up to the model's order, and
+    /// a context shorter than the order mixes only the orders up to its own length, with
+    /// their lambdas scaled to add up to one.  E.g. with `vec![0.2, 0.3, 0.5]` for an order-3
+    /// model, the last three letters count for half, the last two for 30% and the last one for
+    /// 20%.  The mix is worked out once, when the generator is built, so generation is no
+    /// slower.
+    ///
+    /// If a lambda is negative or they're all zero, or there are fewer lambdas than the
+    /// model's order, `try_build()` returns a `NamegenError::InvalidConfig` error.
+    ///
+    /// ```
+    /// use markov_namegen::{ClusterChainGenerator, RandomTextGenerator};
+    /// let mut namegen = ClusterChainGenerator::builder()
+    ///     .with_order(2)
+    ///     .with_interpolation(vec![0.3, 0.7])
+    ///     .train(vec!["julius", "marcus", "lucius", "tiberius"].into_iter())
+    ///     .build();
+    /// println!("{}", namegen.generate_one());
+    /// ```
+    pub fn with_interpolation(mut self, lambdas: Vec<f64>) -> Self {
+        if lambdas
+            .iter()
+            .any(|lambda| !(*lambda >= 0.0 && lambda.is_finite()))
+            || lambdas.iter().all(|lambda| *lambda == 0.0)
+        {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "lambdas must not be negative, nor all zero: {:?}",
+                    lambdas
+                )));
+            return self;
+        }
+        self.interpolation = Some(lambdas);
+        self
+    }
+    /// Never generates the letters (or clusters) seen fewer than `min_occurrences` times in
+    /// the training data, which are often typos or stray foreign letters, without having to
+    /// write a pattern that lists every letter allowed.  They're taken out of the model when
+    /// it's built, so they can't follow anything, even by way of the prior; a draw that only
+    /// ever led to one of them backs off to a shorter context.  The default of 0 keeps them
+    /// all.  It doesn't apply to a precompiled model.
+    pub fn restrict_alphabet(mut self, min_occurrences: usize) -> Self {
+        self.min_occurrences = min_occurrences;
+        self
+    }
+    /// Sets how the trained model stores its contexts.  `ContextStorage::Trie` takes much less
+    /// memory for a high-order model; see `ContextStorage` for the options.  It doesn't change
+    /// what the generator produces.
+    pub fn with_context_storage(mut self, storage: ContextStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+    /// Sets how quickly the generator forgets what it has learned, as it learns more with
+    /// `learn()` after it's built: before each name is learned, every weight in the model is
+    /// multiplied by `1 - decay`.  With the default of 0, names learned later count no more than
+    /// the training corpus; with a decay of, say, 0.001, a long-running generator drifts toward
+    /// the names it has seen lately.
+    ///
+    /// If the decay isn't from 0 up to (but not including) 1, `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.
+    pub fn with_decay(mut self, decay: f64) -> Self {
+        if !(0.0..1.0).contains(&decay) {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(format!(
+                    "decay must be from 0 up to 1, not {}",
+                    decay
+                )));
+            return self;
+        }
+        self.decay = decay;
         self
     }
     /// Set the priors to None.
     pub fn without_prior(mut self) -> Self {
-        self.model = self.model.without_prior();
+        self.prior = None;
+        self
+    }
+    /// Generates only what the training data has shown, but safely: sets the priors to None,
+    /// as `.without_prior()` does, and makes the generator recover from dead ends instead of
+    /// failing.  Without a prior, a context can be left with nothing to follow it (e.g. by
+    /// `.forbid_transitions()`, `.untrain()` or `.restrict_alphabet()`), or a required
+    /// substring can be out of reach.  In strict mode, the generator then backs off to a
+    /// shorter context at the end of the name so far, and if none can go on, starts the name
+    /// over; only if it has started over 100 times does it give up with a
+    /// `NamegenError::DeadEnd` error.
+    pub fn strict(mut self) -> Self {
+        self.prior = None;
+        self.strict = true;
         self
     }
     /// Sets a custom Random Number Generator (RNG) for the model.
+    /// A seeded RNG makes output reproducible: the same seed and the same training data will
+    /// always yield the same sequence of names.  If no RNG is given, one is seeded from entropy.
     pub fn with_rng(mut self, rng: Box<dyn RngCore + Send + Sync>) -> Self {
-        self.model = self.model.with_rng(rng);
+        self.rng = Some(GeneratorRng::Custom(rng));
         self
     }
+    /// Seeds the generator with a portable, pinned RNG algorithm (ChaCha8).
+    /// Unlike `SmallRng`, whose algorithm differs between 32- and 64-bit platforms, this
+    /// guarantees that the same seed and the same training data produce the same names on every
+    /// OS and architecture, e.g. so that networked players can share a world seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some(GeneratorRng::from_seed(seed));
+        self
+    }
+
+    /// Decides whether a letter is a vowel, according to the language or script.
+    fn vowel_rule(&self) -> impl Fn(char) -> bool {
+        vowel_rule(self.script, self.language)
+    }
+
     /// Ingest a training data set to train the model.
     /// The argument 'sequences' is an iterator of either `String` or `&str` values, the words or names
     /// that we want our randomly generated text to resemble.
-    pub fn train(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
-        self.model = self.model.train(
-            sequences
-                .map(|s| s.to_lowercase()) // lowercase the input
-                .map(ClusterChainGeneratorBuilder::clusterize)
-                .map(|mut s| {
-                    s.insert(0, "#".to_string());
-                    s.push("#".to_string());
-                    s
-                }), // add the beginning-of-character and end-of-character strings
+    pub fn train(
+        self,
+        sequences: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> ClusterChainGeneratorBuilder<'a, Trained> {
+        self.learn(sequences).into_state()
+    }
+    /// Trains the model like `.train()`, but on several chunks of a corpus at once, each in a
+    /// thread of its own: each thread trains a partial model on its chunk, and the partial
+    /// models are merged by summing their weights.  The model is exactly the same as if the
+    /// chunks had been trained on in turn, so split a big corpus into as many chunks as you
+    /// have cores.
+    ///
+    /// If `.auto_tune()` is set, or the language is to be detected, the first chunk is trained
+    /// on first, on its own, to tune the model or detect the language.
+    pub fn train_parallel<C, S>(
+        mut self,
+        chunks: impl IntoIterator<Item = C>,
+    ) -> ClusterChainGeneratorBuilder<'a, Trained>
+    where
+        C: IntoIterator<Item = S> + Send,
+        S: Deref<Target = str>,
+    {
+        let mut chunks = chunks.into_iter();
+        if self.auto_tune || self.detect_language {
+            if let Some(first) = chunks.next() {
+                self = self.learn(first.into_iter());
+            }
+        }
+        let preprocessor = self.preprocessor.clone();
+        let order = self.order;
+        let learn_capitalization = self.capitalization.is_some();
+        let count_finals = self.finals.is_some();
+        let is_vowel = self.vowel_rule();
+        let train = |chunk: C| {
+            let mut capitalization = learn_capitalization.then(CapitalizationModel::default);
+            let mut error = None;
+            let words =
+                preprocessor.prepare_all(chunk.into_iter(), capitalization.as_mut(), &mut error);
+            let without_vowels = words
+                .iter()
+                .filter(|word| !word.chars().any(&is_vowel))
+                .count();
+            if too_many_without_vowels(without_vowels, words.len()) {
+                warn!("{} of {} training words have no vowels, and will each be learned as a single cluster; set the language with .with_language()", without_vowels, words.len());
+            }
+            let samples = words.into_iter().map(|word| {
+                let mut sample = ClusterChainGeneratorBuilder::clusterize_with(word, &is_vowel);
+                sample.insert(0, "#".to_string());
+                sample.push("#".to_string());
+                sample
+            });
+            Shard::train(order, samples, capitalization, count_finals, error)
+        };
+        train_shards(
+            chunks,
+            train,
+            &mut self.model,
+            &mut self.capitalization,
+            &mut self.finals,
+            &mut self.error,
         );
+        self.into_state()
+    }
+    /// Trains the model like `.train()`, but in batches of `every` samples, writing a checkpoint
+    /// of the model trained so far to `path` after each one, so that a long training job that
+    /// is interrupted can pick up where it left off.  To resume, call
+    /// `.resume_from_checkpoint(path)` first, then call this with the same training data: the
+    /// samples the checkpoint was trained on are skipped.
+    ///
+    /// If a checkpoint can't be written, training stops, and `try_build()` returns a
+    /// `NamegenError::InvalidConfig` error.  If `every` is zero, the samples are trained on
+    /// without checkpoints, and `try_build()` returns the same error.
+    pub fn train_with_checkpoints<S: Deref<Target = str>>(
+        mut self,
+        sequences: impl Iterator<Item = S>,
+        path: impl AsRef<Path>,
+        every: usize,
+    ) -> ClusterChainGeneratorBuilder<'a, Trained> {
+        if every == 0 {
+            self.error
+                .get_or_insert(NamegenError::InvalidConfig(String::from(
+                    "checkpoints must be at least one sample apart",
+                )));
+            return self.train(sequences);
+        }
+        let path = path.as_ref();
+        let mut sequences = sequences.fuse();
+        let mut trained = std::mem::take(&mut self.resume_after);
+        // the capitalization model, detected language and final counts aren't part of the
+        // checkpoint, so learn them from scratch, detecting the language from the first batch
+        // as before
+        if trained > 0 {
+            let mut skipped = sequences.by_ref().take(trained);
+            let first: Vec<S> = skipped.by_ref().take(every).collect();
+            let mut words = self.preprocessor.prepare_all(
+                first.into_iter(),
+                self.capitalization.as_mut(),
+                &mut self.error,
+            );
+            self.detect_language(&words);
+            words.extend(self.preprocessor.prepare_all(
+                skipped,
+                self.capitalization.as_mut(),
+                &mut self.error,
+            ));
+            if self.finals.is_some() {
+                let samples = self.samples(words);
+                self.count_finals(&samples, 1.0);
+            }
+        }
+        loop {
+            let batch: Vec<S> = sequences.by_ref().take(every).collect();
+            if batch.is_empty() {
+                break;
+            }
+            trained += batch.len();
+            self = self.learn(batch.into_iter());
+            if let Err(error) = checkpoint::write(path, &self.model, self.order, trained) {
+                self.error
+                    .get_or_insert(NamegenError::InvalidConfig(format!(
+                        "could not write a checkpoint to {}: {}",
+                        path.display(),
+                        error
+                    )));
+                break;
+            }
+        }
+        self.into_state()
+    }
+    /// Trains the model on more data, without changing the state of the builder.
+    fn learn(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        let words =
+            self.preprocessor
+                .prepare_all(sequences, self.capitalization.as_mut(), &mut self.error);
+        self.detect_language(&words);
+        let is_vowel = self.vowel_rule();
+        let without_vowels = words
+            .iter()
+            .filter(|word| !word.chars().any(&is_vowel))
+            .count();
+        if too_many_without_vowels(without_vowels, words.len()) {
+            warn!("{} of {} training words have no vowels, and will each be learned as a single cluster; set the language with .with_language()", without_vowels, words.len());
+        }
+        let words: Vec<Vec<String>> = words
+            .into_iter()
+            .map(|s| ClusterChainGeneratorBuilder::clusterize_with(s, &is_vowel))
+            .collect();
+        if self.auto_tune {
+            // tune on the first corpus only, since the order can't change once we've trained
+            self.auto_tune = false;
+            let (order, prior) = tuned_order_and_prior(&words);
+            debug!("auto-tuned to order {} and prior {}", order, prior);
+            self.order = order;
+            self.model = self.model.with_order(order as i32);
+            self.prior = Some(PriorPolicy::Constant(prior));
+        }
+        let samples: Vec<Vec<String>> = words
+            .into_iter()
+            .map(|mut s| {
+                s.insert(0, "#".to_string());
+                s.push("#".to_string());
+                s
+            })
+            .collect(); // add the beginning-of-character and end-of-character strings
+        self.count_finals(&samples, 1.0);
+        self.model = self.model.train(samples.into_iter());
         self
     }
-
-    /// Transforms a String into a Vec<String> of vowel and consonant clusters.
-    /// It depends on the `is_vowel` crate, which only identifies vowels for romance languages.
-    /// Thus, vowels like 'æ', 'œ', and 'ø' will be treated as consonants.
-    /// Also, 'y' and 'w' are treated as consonants, in case you were wondering.
-    fn clusterize(sequence: String) -> Vec<String> {
-        let mut cluster_chain: Vec<String> = Vec::new();
-        let mut chars = sequence.chars();
-        if let Some(first_character) = chars.nth(0) {
-            // start the first cluster with the first character
-            let mut current_cluster = String::from(first_character);
-            // flag the type of the first cluster (vowel or consonant)
-            let mut is_vowel_cluster = first_character.is_romance_vowel();
-            // now loop through the other characters and build up the vec of clusters
-            for c in chars {
-                if c.is_romance_vowel() == is_vowel_cluster {
-                    // in other words, if the next char is of the same typ (vowel/consonant) as the last one(s), add it to the current cluster
-                    current_cluster.push(c);
-                } else {
-                    // otherwise, add the current cluster to the vec and begin a new cluster with this character
-                    cluster_chain.push(current_cluster);
-                    current_cluster = String::from(c);
-                    is_vowel_cluster = !is_vowel_cluster;
-                }
+    /// Detects the language of the training data from some preprocessed words, if it's to be
+    /// detected and hasn't been yet.
+    fn detect_language(&mut self, words: &[String]) {
+        if self.detect_language {
+            // settle on the first corpus, so that any later ones are split the same way
+            self.detect_language = false;
+            if let Some(language) = Language::detect(words) {
+                self.script = language.script();
+                self.language = Some(language);
             }
-            // finalize the final cluster by adding it to the list
-            cluster_chain.push(current_cluster);
         }
-        cluster_chain
     }
+    /// Turns preprocessed words into training samples, beginning and ending with the
+    /// end-of-word marker.
+    fn samples(&self, words: Vec<String>) -> Vec<Vec<String>> {
+        let is_vowel = self.vowel_rule();
+        words
+            .into_iter()
+            .map(|word| {
+                let mut sample = ClusterChainGeneratorBuilder::clusterize_with(word, &is_vowel);
+                sample.insert(0, "#".to_string());
+                sample.push("#".to_string());
+                sample
+            })
+            .collect()
+    }
+    /// Counts the final states of some samples with the given weight, if positional tables are
+    /// being learned.
+    fn count_finals(&mut self, samples: &[Vec<String>], weight: f64) {
+        if let Some(finals) = self.finals.as_mut() {
+            for sample in samples {
+                count_finals(finals, self.order, sample, weight);
+            }
+        }
+    }
+    /// Moves the builder's settings into a builder in another state.
+    fn into_state<Next>(self) -> ClusterChainGeneratorBuilder<'a, Next> {
+        ClusterChainGeneratorBuilder {
+            name: self.name,
+            model: self.model,
+            order: self.order,
+            prior: self.prior,
+            storage: self.storage,
+            decay: self.decay,
+            interpolation: self.interpolation,
+            min_occurrences: self.min_occurrences,
+            strict: self.strict,
+            auto_tune: self.auto_tune,
+            resume_after: self.resume_after,
+            precompiled: self.precompiled,
+            pattern: self.pattern,
+            pattern_ignores_case: self.pattern_ignores_case,
+            pattern_is_full_match: self.pattern_is_full_match,
+            required: self.required,
+            start: self.start,
+            phonotactics: self.phonotactics,
+            max_attempts: self.max_attempts,
+            hooks: self.hooks,
+            memory: self.memory,
+            avoid: self.avoid,
+            phonetic_blocklist: self.phonetic_blocklist,
+            confusable_policy: self.confusable_policy,
+            identifier_style: self.identifier_style,
+            forbidden: self.forbidden,
+            negative: self.negative,
+            postprocessors: self.postprocessors,
+            spelling_variants: self.spelling_variants,
+            pronunciation: self.pronunciation,
+            diminutives: self.diminutives,
+            rng: self.rng,
+            preprocessor: self.preprocessor,
+            script: self.script,
+            language: self.language,
+            detect_language: self.detect_language,
+            stylizer_language: self.stylizer_language,
+            capitalization: self.capitalization,
+            finals: self.finals,
+            error: self.error,
+            state: PhantomData,
+        }
+    }
+}
 
+impl<'a> ClusterChainGeneratorBuilder<'a, Trained> {
+    /// Subtracts a corpus the model was trained on, e.g. to back out a dataset ingested by
+    /// mistake without training on everything else again.  The corpus is cleaned up and split
+    /// into words just as it was for training, and its counts taken out of the model, so that
+    /// the result is the same as if it had never been trained on.  Capitalization patterns
+    /// learned from it are kept.
+    ///
+    /// If the model wasn't trained on all of it (or was loaded precompiled), nothing is
+    /// subtracted, and `try_build()` returns a `NamegenError::InvalidTrainingData` error.
+    pub fn untrain(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        let words = self
+            .preprocessor
+            .prepare_all(sequences, None, &mut self.error);
+        let samples = self.samples(words);
+        let model = &mut self.model;
+        let result = untrain(
+            &mut model.markov_chain,
+            &mut model.known_states,
+            self.order,
+            samples.iter().cloned(),
+        );
+        match result {
+            Ok(()) => self.count_finals(&samples, -1.0),
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+        self
+    }
     /// Build the ClusterChainGenerator (consuming the "Builder" in the process).
+    ///
+    /// Panics if the pattern is not a valid regex or a training sample was rejected; use
+    /// `try_build()` to handle that as an error.
     pub fn build(self) -> ClusterChainGenerator {
-        let pattern = self.pattern.map(|pat| Regex::new(pat).unwrap());
-        ClusterChainGenerator {
-            model: self.model.build(),
-            pattern,
+        self.try_build()
+            .unwrap_or_else(|error| panic!("could not build ClusterChainGenerator: {}", error))
+    }
+    /// Build the ClusterChainGenerator, or return an error if the pattern is not a valid regex
+    /// or a training sample was rejected.
+    pub fn try_build(mut self) -> Result<ClusterChainGenerator, NamegenError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let is_vowel = self.vowel_rule();
+        // the clusters depend on the language, which may only be detected in training, so the
+        // forbidden transitions are checked here rather than when they're given
+        let is_cluster = |s: &str| {
+            ClusterChainGeneratorBuilder::clusterize_with(s.to_string(), &is_vowel).len() == 1
+        };
+        if let Some((from, to)) = self
+            .forbidden
+            .iter()
+            .find(|(from, to)| !is_cluster(from) || !is_cluster(to))
+        {
+            return Err(NamegenError::InvalidConfig(format!(
+                "'{}' -> '{}' is not a transition between two clusters",
+                from, to
+            )));
+        }
+        if let Some(capitalization) = self.capitalization {
+            self.postprocessors
+                .insert(0, Arc::new(move |name, _rng| capitalization.apply(&name)));
+        }
+        let pattern = self
+            .pattern
+            .map(|pattern| {
+                let pattern = if self.pattern_is_full_match {
+                    format!("^(?:{})$", pattern)
+                } else {
+                    pattern.to_string()
+                };
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(self.pattern_ignores_case)
+                    .build()
+            })
+            .transpose()
+            .map_err(|error| NamegenError::InvalidPattern(error.to_string()))?;
+        if self.precompiled.is_some() {
+            self.finals = None;
         }
+        let negative = self
+            .negative
+            .iter()
+            .map(|word| {
+                let mut clusters =
+                    ClusterChainGeneratorBuilder::clusterize_with(word.clone(), &is_vowel);
+                clusters.insert(0, SENTINEL.to_string());
+                clusters.push(SENTINEL.to_string());
+                clusters
+            })
+            .collect();
+        let is_vowel_cluster = vowel_rule(self.script, self.language);
+        let mut refinement = Refinement {
+            start: SENTINEL.to_string(),
+            min_occurrences: self.min_occurrences,
+            interpolation: self.interpolation,
+            prior: self.prior,
+            is_vowel: Arc::new(move |cluster: &String| {
+                (!cluster.starts_with(SENTINEL))
+                    .then(|| cluster.chars().next().is_some_and(&is_vowel_cluster))
+            }),
+            negative,
+            forbidden: self.forbidden,
+            storage: self.storage,
+        };
+        let counts = match self.precompiled {
+            Some(model) => {
+                // a precompiled model was refined when it was trained, but for the anti-corpus
+                // and the forbidden transitions
+                refinement.min_occurrences = 0;
+                refinement.interpolation = None;
+                refinement.prior = None;
+                model
+            }
+            None => self.model.build().into(),
+        };
+        let model = refinement.refine(&counts)?;
+        let required = self
+            .required
+            .map(|substring| {
+                let clusters =
+                    ClusterChainGeneratorBuilder::clusterize_with(substring.clone(), &is_vowel);
+                model.required_states(&substring, clusters)
+            })
+            .transpose()?;
+        let start = self
+            .start
+            .map(|start| start.clusters(&model.known_states, &is_vowel))
+            .transpose()?;
+        let model = Arc::new(model);
+        if let Some(variants) = self.spelling_variants {
+            let model = Arc::clone(&model);
+            let is_vowel = vowel_rule(self.script, self.language);
+            let log_probability = move |variant: &str| {
+                let mut tokens =
+                    ClusterChainGeneratorBuilder::clusterize_with(variant.to_string(), &is_vowel);
+                tokens.insert(0, String::from("#"));
+                tokens.push(String::from("#"));
+                model.log_probability(&tokens)
+            };
+            self.postprocessors.insert(
+                0,
+                Arc::new(move |name, rng| variants.vary(name, rng, &log_probability)),
+            );
+        }
+        let language = self.language.unwrap_or(match self.script {
+            Script::Latin => Language::Romance,
+            Script::Cyrillic => Language::Russian,
+            Script::Greek => Language::Greek,
+        });
+        let _ = self.stylizer_language.set(language);
+        Ok(ClusterChainGenerator {
+            name: self.name,
+            model,
+            pattern,
+            required,
+            start,
+            phonotactics: self.phonotactics.map(|rules| rules.or_language(language)),
+            max_attempts: self.max_attempts,
+            hooks: self.hooks,
+            memory: self.memory,
+            avoid: Arc::new(self.avoid),
+            phonetic_blocklist: self.phonetic_blocklist.map(Arc::new),
+            confusable_policy: self.confusable_policy,
+            identifier_style: self.identifier_style,
+            counts: Arc::new(counts),
+            refinement: Arc::new(refinement),
+            preprocessor: self.preprocessor,
+            decay: self.decay,
+            strict: self.strict,
+            finals: self.finals.map(Arc::new),
+            endings: Endings::new(),
+            postprocessors: self.postprocessors,
+            pronunciation: self.pronunciation,
+            diminutives: self.diminutives.unwrap_or_default(),
+            script: self.script,
+            language: self.language,
+            bits_generated: 0.0,
+            rng: self.rng.unwrap_or_else(GeneratorRng::from_entropy),
+        })
     }
 }
 
@@ -131,8 +1262,10 @@ impl<'a> ClusterChainGeneratorBuilder<'a> {
 mod tests {
     use crate::clusterchain::builder::ClusterChainGeneratorBuilder;
     use crate::clusterchain::generator::ClusterChainGenerator;
+    use crate::interface::RandomTextGenerator;
     use is_vowel::IsRomanceVowel;
     use std::collections::HashSet;
+    use std::sync::Arc;
 
     #[test]
     fn test_is_vowel_crate_works() {
@@ -154,7 +1287,9 @@ mod tests {
     #[test]
     fn test_clusterize() {
         assert_eq!(
-            ClusterChainGeneratorBuilder::clusterize(String::from("foobar")),
+            ClusterChainGeneratorBuilder::clusterize_with(String::from("foobar"), |c| {
+                c.is_romance_vowel()
+            }),
             vec![
                 "f".to_string(),
                 "oo".to_string(),
@@ -165,19 +1300,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_syllabify() {
+        use crate::Language;
+        let romance = |c: char| Language::Romance.is_vowel(c);
+        assert_eq!(
+            ClusterChainGeneratorBuilder::syllabify(String::from("fabiana"), romance),
+            vec!["fa", "bia", "na"]
+        );
+        assert_eq!(
+            ClusterChainGeneratorBuilder::syllabify(String::from("kelthar"), romance),
+            vec!["kel", "thar"]
+        );
+        assert_eq!(
+            ClusterChainGeneratorBuilder::syllabify(String::from("strength"), romance),
+            vec!["strength"]
+        );
+        assert_eq!(
+            ClusterChainGeneratorBuilder::syllabify(String::from("gwynedd"), romance),
+            vec!["gwynedd"]
+        );
+        assert_eq!(
+            ClusterChainGeneratorBuilder::syllabify(String::from("gwynedd"), |c| {
+                Language::Welsh.is_vowel(c)
+            }),
+            vec!["gwy", "nedd"]
+        );
+        assert_eq!(
+            ClusterChainGeneratorBuilder::syllabify(String::from("наталья"), |c| {
+                Language::Russian.is_vowel(c)
+            }),
+            vec!["на", "тал", "ья"]
+        );
+    }
+
     #[test]
     fn test_builder_pattern_works() {
         let _generator = ClusterChainGenerator::builder()
             .with_order(2)
             .with_prior(0.007)
             .with_pattern("foo")
+            .train(std::iter::empty::<&str>())
             .build();
     }
 
     #[test]
-    #[should_panic(expected = "Order must be an integer greater than zero.")]
-    fn test_order_cannot_be_less_than_one() {
-        let _generator = ClusterChainGenerator::builder().with_order(0).build();
+    fn test_order_must_be_in_range() {
+        use crate::NamegenError;
+        let build = |order| {
+            ClusterChainGenerator::builder()
+                .with_order(order)
+                .train(vec!["julius", "augustus"].into_iter())
+                .try_build()
+        };
+        assert!(matches!(build(0), Err(NamegenError::InvalidConfig(_))));
+        assert!(matches!(
+            build(ClusterChainGenerator::MAX_ORDER + 1),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        assert!(build(ClusterChainGenerator::MAX_ORDER).is_ok());
     }
 
     #[test]
@@ -189,4 +1370,987 @@ mod tests {
         .into_iter();
         let _generator = ClusterChainGenerator::builder().train(inputs).build();
     }
+
+    #[test]
+    fn test_config_builds_the_same_generator_as_the_builder() {
+        use crate::{GeneratorConfig, NamegenError, RandomTextGenerator};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let config = GeneratorConfig {
+            order: Some(2),
+            prior: Some(0.001),
+            pattern: Some(String::from("^[aeiou]")),
+            seed: Some(11),
+            ..Default::default()
+        };
+        let mut configured =
+            ClusterChainGenerator::from_config(config.clone(), corpus.lines()).unwrap();
+        let mut built = ClusterChainGenerator::builder()
+            .with_order(2)
+            .with_prior(0.001)
+            .with_pattern("^[aeiou]")
+            .with_seed(11)
+            .train(corpus.lines())
+            .build();
+        for _ in 0..20 {
+            assert_eq!(configured.generate_one(), built.generate_one());
+        }
+        let invalid = GeneratorConfig {
+            pattern: Some(String::from("[a-z")),
+            ..config.clone()
+        };
+        assert!(matches!(
+            ClusterChainGenerator::from_config(invalid, corpus.lines()),
+            Err(NamegenError::InvalidPattern(_))
+        ));
+        let invalid = GeneratorConfig {
+            max_attempts: Some(0),
+            ..config
+        };
+        assert!(matches!(
+            ClusterChainGenerator::from_config(invalid, corpus.lines()),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_learning_with_decay_drifts_toward_new_names() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let build = |decay| {
+            ClusterChainGenerator::builder()
+                .with_order(1)
+                .without_prior()
+                .with_seed(3)
+                .with_decay(decay)
+                .train(vec!["abe"].into_iter())
+                .build()
+        };
+        let mut remembering = build(0.0);
+        let mut fork = remembering.fork(4);
+        remembering.learn(vec!["Ode"].into_iter()).unwrap();
+        let names: Vec<String> = (0..50).map(|_| remembering.generate_one()).collect();
+        assert!(names.iter().any(|name| name.starts_with('a')));
+        assert!(names.iter().any(|name| name.starts_with('o')));
+        // a fork keeps the model it was made with
+        for _ in 0..50 {
+            assert_eq!(fork.generate_one(), "abe");
+        }
+        let mut forgetting = build(0.999_999);
+        forgetting.learn(vec!["ode"].into_iter()).unwrap();
+        for _ in 0..50 {
+            assert_eq!(forgetting.generate_one(), "ode");
+        }
+        let invalid = ClusterChainGenerator::builder()
+            .with_decay(1.0)
+            .train(vec!["abe"].into_iter())
+            .try_build();
+        assert!(matches!(invalid, Err(NamegenError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_learned_names_are_refined_like_the_corpus() {
+        let mut generator = ClusterChainGenerator::builder()
+            .with_order(1)
+            .with_prior(0.1)
+            .train_negative(vec!["tom"].into_iter())
+            .train(vec!["tom", "tim"].into_iter())
+            .build();
+        let weight = |generator: &ClusterChainGenerator, from: &str, to: &str| {
+            let context = [String::from(from)];
+            generator.model.best_model(&context).unwrap()[&String::from(to)]
+        };
+        assert_eq!(weight(&generator, "t", "o"), 0.5);
+        generator.learn(vec!["ana"].into_iter()).unwrap();
+        // the anti-corpus still counts, once, and a context first seen now gets the prior
+        assert_eq!(weight(&generator, "t", "o"), 0.5);
+        assert_eq!(weight(&generator, "n", "t"), 0.1);
+    }
+
+    #[test]
+    fn test_untraining_backs_out_a_corpus() {
+        use crate::{NamegenError, RandomTextGenerator};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mistake = vec!["Xerxes", "Zzyzx 42", "qwerty"];
+        let mut untrained = ClusterChainGenerator::builder()
+            .with_seed(8)
+            .train(corpus.lines())
+            .train(mistake.clone().into_iter())
+            .untrain(mistake.clone().into_iter())
+            .build();
+        let mut clean = ClusterChainGenerator::builder()
+            .with_seed(8)
+            .train(corpus.lines())
+            .build();
+        for _ in 0..20 {
+            assert_eq!(untrained.generate_one(), clean.generate_one());
+        }
+        let never_trained = ClusterChainGenerator::builder()
+            .train(corpus.lines())
+            .untrain(mistake.into_iter())
+            .try_build();
+        assert!(matches!(
+            never_trained,
+            Err(NamegenError::InvalidTrainingData(_))
+        ));
+    }
+
+    #[test]
+    fn test_negative_training_steers_away_from_the_anti_corpus() {
+        use crate::RandomTextGenerator;
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let starting_with_ma = |mut generator: ClusterChainGenerator| {
+            (0..500)
+                .filter(|_| generator.generate_one().starts_with("ma"))
+                .count()
+        };
+        let plain = ClusterChainGenerator::builder()
+            .with_seed(6)
+            .train(corpus.lines())
+            .build();
+        let steered = ClusterChainGenerator::builder()
+            .with_seed(6)
+            .train_negative(vec!["Marcus", "Marius", "Maximus"].into_iter())
+            .train(corpus.lines())
+            .build();
+        let (plain, steered) = (starting_with_ma(plain), starting_with_ma(steered));
+        assert!(steered * 2 < plain, "{} vs {}", steered, plain);
+    }
+
+    #[test]
+    fn test_positional_tables_end_names_like_the_corpus() {
+        use crate::RandomTextGenerator;
+        // "a" follows "t" as often as "k", but only ever ends a word after "k"
+        let corpus = vec!["ka", "ka", "tak", "tat"];
+        let ending_in_ta = |generator: &mut ClusterChainGenerator| {
+            (0..200)
+                .filter(|_| generator.generate_one().ends_with("ta"))
+                .count()
+        };
+        let build = |positional| {
+            ClusterChainGenerator::builder()
+                .with_order(1)
+                .with_positional_tables(positional)
+                .with_seed(3)
+                .train(corpus.clone().into_iter())
+                .build()
+        };
+        assert!(ending_in_ta(&mut build(false)) > 0);
+        let mut positional = build(true);
+        assert_eq!(ending_in_ta(&mut positional), 0);
+        // names learned later count as final too
+        positional.learn(vec!["ta"; 4].into_iter()).unwrap();
+        assert!(ending_in_ta(&mut positional) > 0);
+    }
+
+    #[test]
+    fn test_names_of_an_exact_length() {
+        use crate::NamegenError;
+        let corpus = vec!["julius", "marcus", "lucius", "tiberius", "quintus", "gaius"];
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(4)
+            .train(corpus.into_iter())
+            .build();
+        for length in 1..10 {
+            for _i in 0..10 {
+                let name = generator.generate_with_length(length);
+                assert_eq!(name.chars().count(), length, "{}", name);
+            }
+        }
+        // without a prior, no name can be longer than "abc"
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["ab", "abc"].into_iter())
+            .build();
+        assert!(matches!(
+            generator.try_generate_with_length(4),
+            Err(NamegenError::DeadEnd(_))
+        ));
+    }
+
+    #[test]
+    fn test_names_start_with_the_chosen_cluster() {
+        use crate::{NamegenError, StartCluster};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let build = |start| {
+            ClusterChainGenerator::builder()
+                .with_start_cluster(start)
+                .with_seed(8)
+                .train(corpus.lines())
+                .try_build()
+        };
+        let mut generator = build(StartCluster::Vowel).unwrap();
+        for _i in 0..50 {
+            let name = generator.generate_one();
+            assert!(name.starts_with(|c| "aeiou".contains(c)), "{}", name);
+        }
+        let mut generator = ClusterChainGenerator::builder()
+            .with_start_cluster(StartCluster::OneOf(vec![
+                String::from("m"),
+                String::from("fl"),
+            ]))
+            .with_required_substring("us")
+            .with_seed(8)
+            .train(corpus.lines())
+            .build();
+        for _i in 0..50 {
+            let name = generator.generate_one();
+            assert!(name.starts_with("m") || name.starts_with("fl"), "{}", name);
+            assert!(name.contains("us"), "{}", name);
+        }
+        assert!(matches!(
+            build(StartCluster::Cluster(String::from("xq"))),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_strict_mode_gives_up_on_an_impossible_name() {
+        use crate::NamegenError;
+        // "nn" only ever ends a name, which it's forbidden to do
+        let mut generator = ClusterChainGenerator::builder()
+            .strict()
+            .forbid_transitions([("nn", "#")])
+            .with_required_substring("nn")
+            .train(vec!["ann"].into_iter())
+            .build();
+        assert!(matches!(
+            generator.try_generate_one(),
+            Err(NamegenError::DeadEnd(_))
+        ));
+    }
+
+    #[test]
+    fn test_rare_clusters_are_never_generated() {
+        let corpus = vec!["anna", "annie", "hanna", "hannah", "zoe"];
+        let mut generator = ClusterChainGenerator::builder()
+            .restrict_alphabet(2)
+            .with_seed(5)
+            .train(corpus.into_iter())
+            .build();
+        for _i in 0..200 {
+            let name = generator.generate_one();
+            assert!(!name.contains('z') && !name.contains("oe"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_interpolation_needs_a_lambda_for_each_order() {
+        use crate::NamegenError;
+        let corpus = vec!["julius", "marcus", "lucius", "tiberius", "quintus", "gaius"];
+        let build = |lambdas| {
+            ClusterChainGenerator::builder()
+                .with_order(2)
+                .with_interpolation(lambdas)
+                .train(corpus.clone().into_iter())
+                .try_build()
+        };
+        let mut generator = build(vec![0.4, 0.6]).unwrap();
+        assert!(!generator.generate_one().is_empty());
+        assert!(matches!(
+            build(vec![1.0]),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_seeded_generators_give_the_same_output() {
+        use crate::RandomTextGenerator;
+        use rand::{rngs::SmallRng, SeedableRng};
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut gen1 = ClusterChainGenerator::builder()
+            .with_rng(Box::new(SmallRng::seed_from_u64(42)))
+            .train(corpus.lines())
+            .build();
+        let mut gen2 = ClusterChainGenerator::builder()
+            .with_rng(Box::new(SmallRng::seed_from_u64(42)))
+            .train(corpus.lines())
+            .build();
+        for _i in 0..50 {
+            assert_eq!(gen1.generate_one(), gen2.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_seeded_output_is_the_same_on_every_platform() {
+        // These names were recorded once and must never change: if this test fails, a seed
+        // shared between machines (or saved by a previous version) no longer yields the same names.
+        use crate::RandomTextGenerator;
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(2024)
+            .train(corpus.lines())
+            .build();
+        let names: Vec<String> = (0..6).map(|_| generator.generate_one()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "curius",
+                "albinus",
+                "juncus",
+                "verruco",
+                "marus",
+                "simplicyprianus"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untrained_generator_returns_an_error() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .train(std::iter::empty::<&str>())
+            .build();
+        assert_eq!(generator.try_generate_one(), Err(NamegenError::EmptyModel));
+    }
+
+    #[test]
+    fn test_invalid_pattern_returns_an_error() {
+        use crate::NamegenError;
+        let result = ClusterChainGenerator::builder()
+            .with_pattern("[a-z")
+            .train(std::iter::empty::<&str>())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn test_reserved_characters_in_training_data() {
+        use crate::{NamegenError, RandomTextGenerator, ReservedCharPolicy};
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["a#b"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "ab");
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .with_reserved_char_policy(ReservedCharPolicy::Escape)
+            .train(vec!["a#b"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "a#b");
+        let result = ClusterChainGenerator::builder()
+            .with_reserved_char_policy(ReservedCharPolicy::Error)
+            .train(vec!["a#b"].into_iter())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidTrainingData(_))));
+    }
+
+    #[test]
+    fn test_turkic_case_folding() {
+        use crate::{CaseFolding, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .with_case_folding(CaseFolding::Turkic)
+            .train(vec!["IŞIK"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "ışık");
+    }
+
+    #[test]
+    fn test_lowercasing_can_be_turned_off() {
+        use crate::{CaseFolding, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .lowercase_input(false)
+            .train(vec!["McKinley"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "McKinley");
+        let builder = ClusterChainGenerator::builder()
+            .with_case_folding(CaseFolding::Turkic)
+            .lowercase_input(true);
+        assert_eq!(builder.preprocessor.case_folding, CaseFolding::Turkic);
+    }
+
+    #[test]
+    fn test_multi_word_lines_can_be_split() {
+        use crate::{MultiWordPolicy, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .with_multi_word_policy(MultiWordPolicy::LastWord)
+            .train(vec!["Gaius Julius Caesar"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "caesar");
+    }
+
+    #[test]
+    fn test_impossible_pattern_gives_up_after_max_attempts() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .with_pattern("^zzz$")
+            .with_max_attempts(20)
+            .train(vec!["dopey", "sneezy", "bashful"].into_iter())
+            .build();
+        assert_eq!(
+            generator.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(20))
+        );
+    }
+
+    #[test]
+    fn test_postprocessors_are_applied_in_order() {
+        use crate::RandomTextGenerator;
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .with_pattern("^doc$")
+            .with_postprocessor(|name| name.to_uppercase())
+            .with_postprocessor(|name| format!("{}!", name))
+            .train(vec!["doc"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "DOC!");
+    }
+
+    #[test]
+    fn test_capitalization_is_learned_from_training_data() {
+        use crate::RandomTextGenerator;
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .learn_capitalization(true)
+            .train(vec!["McCoy"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "McCoy");
+    }
+
+    #[test]
+    fn test_remembered_outputs_are_not_repeated() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .remember_outputs(true)
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        let mut names = vec![generator.generate_one(), generator.generate_one()];
+        names.sort();
+        assert_eq!(names, vec!["ab", "ba"]);
+        assert_eq!(
+            generator.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(100))
+        );
+        generator.reset_memory();
+        assert!(generator.try_generate_one().is_ok());
+    }
+
+    #[test]
+    fn test_approximate_memory_does_not_repeat() {
+        use crate::RandomTextGenerator;
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .remember_outputs_approximately(10, 0.001)
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        let mut names = vec![generator.generate_one(), generator.generate_one()];
+        names.sort();
+        assert_eq!(names, vec!["ab", "ba"]);
+        assert!(generator.try_generate_one().is_err());
+    }
+
+    #[test]
+    fn test_false_positive_rate_out_of_range_is_an_error() {
+        use crate::NamegenError;
+        for rate in [0.0, 1.0, f64::NAN] {
+            let result = ClusterChainGenerator::builder()
+                .remember_outputs_approximately(10, rate)
+                .train(vec!["ab", "ba"].into_iter())
+                .try_build();
+            assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn test_avoided_names_are_never_generated() {
+        use crate::{NamegenError, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .avoid(vec!["ab"])
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "ba");
+        }
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .avoid(vec!["ab"])
+            .avoid(vec![String::from("ba")])
+            .with_max_attempts(100)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        assert_eq!(
+            generator.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(100))
+        );
+    }
+
+    #[test]
+    fn test_names_that_sound_blocked_are_never_generated() {
+        use crate::phonetic::{PhoneticAlgorithm, PhoneticBlocklist};
+        use crate::RandomTextGenerator;
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .with_phonetic_blocklist(
+                PhoneticBlocklist::new(PhoneticAlgorithm::Metaphone).with_entries(["phuck"]),
+            )
+            .train(vec!["fuk", "tom"].into_iter())
+            .build();
+        assert!(generator.config_summary().ends_with(", blocks 1 sounds"));
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "tom");
+        }
+    }
+
+    #[test]
+    fn test_names_mixing_scripts_are_rejected() {
+        use crate::{ConfusablePolicy, RandomTextGenerator};
+        // the second "kora" has a Cyrillic "о"
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .with_confusable_policy(ConfusablePolicy::Reject)
+            .train(vec!["kora", "kоra"].into_iter())
+            .build();
+        assert!(generator
+            .config_summary()
+            .ends_with(", rejects confusables"));
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "kora");
+        }
+    }
+
+    #[test]
+    fn test_identifiers_are_kept_short() {
+        use crate::identifier::{IdentifierCase, IdentifierStyle};
+        use crate::RandomTextGenerator;
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .as_identifiers(IdentifierStyle {
+                case: IdentifierCase::Pascal,
+                max_length: Some(4),
+            })
+            .train(vec!["kora", "korana"].into_iter())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "Kora");
+        }
+    }
+
+    #[test]
+    fn test_restoring_a_snapshot_without_memory_clears_it() {
+        use crate::RandomTextGenerator;
+        let build = |remember| {
+            ClusterChainGenerator::builder()
+                .with_seed(3)
+                .without_prior()
+                .with_max_attempts(5)
+                .remember_outputs(remember)
+                .train(vec!["kora"].into_iter())
+                .build()
+        };
+        let snapshot = build(false).save_state().unwrap();
+        let mut generator = build(true);
+        assert_eq!(generator.generate_one(), "kora");
+        assert!(generator.try_generate_one().is_err());
+        generator.restore_state(&snapshot);
+        assert_eq!(generator.generate_one(), "kora");
+    }
+
+    #[test]
+    fn test_entropy_of_names_and_bits_generated() {
+        use crate::{MultiWordPolicy, RandomTextGenerator};
+        let mut generator = ClusterChainGenerator::builder()
+            .with_order(1)
+            .without_prior()
+            .with_multi_word_policy(MultiWordPolicy::Split)
+            .remember_outputs(true)
+            .train(vec!["kora", "kori", "Kora Vel"].into_iter())
+            .build();
+        // "kora" begins with 'k' three times in four and ends in 'a' two times in three
+        assert!((generator.entropy_of("kora") - 1.0).abs() < 1e-9);
+        assert!((generator.entropy_of("Kora Vel") - 3.0).abs() < 1e-9);
+        assert_eq!(generator.entropy_of("koral"), f64::INFINITY);
+        assert_eq!(generator.entropy_of(""), f64::INFINITY);
+        assert_eq!(generator.bits_generated(), 0.0);
+        // the three names it can make: two choices for "kora" and "kori", one for "vel"
+        for _ in 0..3 {
+            generator.generate_one();
+        }
+        let first = -(0.75f64 * 0.75f64.log2() + 0.25 * 0.25f64.log2());
+        let last = -(2.0f64 / 3.0 * (2.0f64 / 3.0).log2() + 1.0 / 3.0 * (1.0f64 / 3.0).log2());
+        let expected = 3.0 * first + 2.0 * last;
+        assert!((generator.bits_generated() - expected).abs() < 1e-9);
+        assert_eq!(generator.fork(1).bits_generated(), 0.0);
+    }
+
+    #[test]
+    fn test_names_are_made_into_slugs() {
+        use crate::RandomTextGenerator;
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .as_slug()
+            .train(vec!["Søren O'Lund"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "soren-olund");
+    }
+
+    #[test]
+    fn test_names_derived_from_keys_are_stable() {
+        use crate::RandomTextGenerator;
+        use std::fs;
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let build = || {
+            ClusterChainGenerator::builder()
+                .with_seed(2024)
+                .train(corpus.lines())
+                .build()
+        };
+        let (mut generator, mut other) = (build(), build());
+        let names: Vec<String> = ["npc:1", "npc:2", "npc:3"]
+            .iter()
+            .map(|key| generator.generate_for_key(99, key))
+            .collect();
+        assert_eq!(names, vec!["triarius", "libenius", "memmiublandius"]);
+        // the generator's own stream is unaffected
+        assert_eq!(generator.generate_one(), other.generate_one());
+        assert_eq!(other.generate_for_key(99, "npc:2"), names[1]);
+        assert_ne!(other.generate_for_key(100, "npc:2"), names[1]);
+    }
+
+    #[test]
+    fn test_records_describe_the_names() {
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(5)
+            .with_pattern("^.{5,}$")
+            .train(vec!["julius", "julia", "junius", "lucius"].into_iter())
+            .build();
+        let records = generator.generate_records(20);
+        assert_eq!(records.len(), 20);
+        for record in records {
+            assert_eq!(record.clusters.concat(), record.name);
+            assert_eq!(record.length, record.name.chars().count());
+            assert!(record.length >= 5);
+            assert!(record.attempts >= 1);
+            assert!(record.log_probability < 0.0 && record.log_probability.is_finite());
+        }
+    }
+
+    #[test]
+    fn test_phonotactics_filter_names() {
+        use crate::{Phonotactics, RandomTextGenerator};
+        let rules = Phonotactics::new().with_max_consonants(1);
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(3)
+            .with_phonotactics(rules.clone())
+            .train(vec!["anstrid", "ola", "brenna", "sigrun", "tove"].into_iter())
+            .build();
+        for _i in 0..20 {
+            assert!(rules.is_valid(&generator.generate_one()));
+        }
+    }
+
+    #[test]
+    fn test_training_romanization_normalizes_the_corpus() {
+        use crate::{RandomTextGenerator, Transliteration};
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(3)
+            .without_prior()
+            .with_training_romanization(Transliteration::iso9())
+            .train(vec!["Иван", "Ivan", "ivan"].into_iter())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(generator.generate_one(), "ivan");
+        }
+    }
+
+    #[test]
+    fn test_forks_share_the_model() {
+        use crate::RandomTextGenerator;
+        let generator = ClusterChainGenerator::builder()
+            .with_postprocessor(|name| name.to_uppercase())
+            .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+            .build();
+        let (mut a, mut b) = (generator.fork(8), generator.fork(8));
+        assert!(Arc::ptr_eq(&a.model, &generator.model));
+        for _i in 0..10 {
+            let name = a.generate_one();
+            assert_eq!(name, b.generate_one());
+            assert_eq!(name, name.to_uppercase());
+        }
+    }
+
+    #[test]
+    fn test_script_decides_the_clusters() {
+        use crate::Script;
+        let clusters = |script: Script| {
+            ClusterChainGeneratorBuilder::clusterize_with(String::from("ольга"), |c| {
+                script.is_vowel(c)
+            })
+        };
+        assert_eq!(clusters(Script::Cyrillic), vec!["о", "льг", "а"]);
+        assert_eq!(clusters(Script::Latin), vec!["ольга"]);
+    }
+
+    #[test]
+    fn test_language_decides_the_clusters() {
+        use crate::Language;
+        assert_eq!(
+            ClusterChainGeneratorBuilder::clusterize_with(String::from("bryn"), |c| {
+                Language::Welsh.is_vowel(c)
+            }),
+            vec!["br", "y", "n"]
+        );
+        let generator = ClusterChainGenerator::builder()
+            .with_language(Language::Welsh)
+            .train(vec!["gwyn"].into_iter())
+            .build();
+        assert!(generator.model.known_states.contains("wy"));
+    }
+
+    #[test]
+    fn test_stylizer_uses_the_generators_language() {
+        use crate::postprocess::Stylizer;
+        use crate::Language;
+        let always = Stylizer::new()
+            .with_apostrophe_probability(1.0)
+            .with_max_marks(usize::MAX);
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .with_language(Language::Welsh)
+            .with_stylizer(always.clone())
+            .train(vec!["gwynedd"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "gwy'nedd");
+        let mut generator = ClusterChainGenerator::builder()
+            .without_prior()
+            .with_stylizer(always)
+            .train(vec!["наталья"].into_iter())
+            .build();
+        assert_eq!(generator.generate_one(), "на'тал'ья");
+    }
+
+    #[test]
+    fn test_vowels_are_detected_from_the_corpus() {
+        use crate::Script;
+        let corpus = vec!["cwm", "gwyn", "bryn", "dafydd"];
+        let generator = ClusterChainGenerator::builder()
+            .train(corpus.clone().into_iter())
+            .build();
+        assert!(generator.model.known_states.contains("wy"));
+        let generator = ClusterChainGenerator::builder()
+            .with_script(Script::Latin)
+            .train(corpus.into_iter())
+            .build();
+        assert!(generator.model.known_states.contains("cwm"));
+    }
+
+    #[test]
+    fn test_training_resumes_from_a_checkpoint_in_the_detected_language() {
+        use crate::RandomTextGenerator;
+        let path = std::env::temp_dir().join(format!("resume-welsh-{}.mngc", std::process::id()));
+        let corpus = [" Cwm", " Gwyn", " Bryn", " Dafydd", " Glyn", " Llyr"];
+        let builder = || {
+            ClusterChainGenerator::builder()
+                .with_seed(4)
+                .trim_input(true)
+                .learn_capitalization(true)
+                .with_positional_tables(true)
+        };
+        let interrupted = builder()
+            .resume_from_checkpoint(&path)
+            .train_with_checkpoints(corpus.iter().copied().take(4), &path, 2);
+        drop(interrupted);
+        let mut resumed = builder()
+            .resume_from_checkpoint(&path)
+            .train_with_checkpoints(corpus.iter().copied(), &path, 2)
+            .build();
+        // the language is detected from the first batch, so compare with checkpointed training
+        let other_path = path.with_extension("uninterrupted");
+        let mut uninterrupted = builder()
+            .train_with_checkpoints(corpus.iter().copied(), &other_path, 2)
+            .build();
+        assert!(resumed.model.known_states.contains("y"));
+        assert_eq!(resumed.finals, uninterrupted.finals);
+        for _ in 0..20 {
+            assert_eq!(resumed.generate_one(), uninterrupted.generate_one());
+        }
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&other_path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoints_zero_samples_apart_are_an_error() {
+        use crate::NamegenError;
+        let path = std::env::temp_dir().join(format!("every-0-{}.mngc", std::process::id()));
+        let result = ClusterChainGenerator::builder()
+            .train_with_checkpoints(["julius", "nero"].into_iter(), &path, 0)
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_auto_tune_picks_the_order() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let generator = ClusterChainGenerator::builder()
+            .with_order(5)
+            .auto_tune()
+            .train(corpus.lines())
+            .build();
+        // there are hundreds of distinct clusters, so contexts are rarer
+        assert_eq!(generator.model.order, 2);
+    }
+
+    #[test]
+    fn test_prior_by_class_keeps_clusters_apart() {
+        use crate::PriorPolicy;
+        let generator = ClusterChainGenerator::builder()
+            .with_order(1)
+            .with_prior_policy(PriorPolicy::ByClass {
+                alternating: 0.01,
+                same: 0.0,
+            })
+            .train(vec!["ab"].into_iter())
+            .build();
+        let after = |cluster: &str| generator.model.best_model(&[cluster.to_string()]).unwrap();
+        assert!(after("a").get("a").is_none());
+        assert!(after("a").get("#").is_some());
+        assert!(after("b").get("b").is_none());
+        assert!(after("b").get("a").is_some());
+    }
+
+    #[test]
+    fn test_required_substring_is_always_generated() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let mut generator = ClusterChainGenerator::builder()
+            .with_seed(11)
+            .with_required_substring("ius")
+            .train(corpus.lines())
+            .build();
+        assert_eq!(
+            generator.required,
+            Some(vec![String::from("iu"), String::from("s")])
+        );
+        for _ in 0..100 {
+            let name = generator.generate_one();
+            assert!(name.to_lowercase().contains("ius"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_recorded_names_can_be_replayed() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let mut recorder = ClusterChainGenerator::builder()
+            .with_seed(1)
+            .with_postprocessor(|name| name.to_uppercase())
+            .train(corpus.lines())
+            .build();
+        let mut replayer = recorder.fork(2);
+        for _ in 0..20 {
+            let recorded = recorder.generate_recorded();
+            assert_eq!(replayer.replay(&recorded.choices).unwrap(), recorded.name);
+        }
+        assert!(replayer.replay(&[usize::MAX]).is_err());
+    }
+
+    #[test]
+    fn test_parallel_training_matches_sequential_training() {
+        let corpus = [
+            "julius",
+            "augustus",
+            "tiberius",
+            "claudius",
+            "nero",
+            "galba",
+            "otho",
+            "vitellius",
+        ];
+        let builder = || ClusterChainGenerator::builder().with_seed(9).auto_tune();
+        let mut parallel = builder()
+            .train_parallel(corpus.chunks(3).map(|chunk| chunk.iter().copied()))
+            .build();
+        let mut chunks = corpus.chunks(3);
+        let first = builder().train(chunks.next().unwrap().iter().copied());
+        let mut sequential = chunks
+            .fold(first, |builder, chunk| builder.train(chunk.iter().copied()))
+            .build();
+        for _ in 0..20 {
+            assert_eq!(parallel.generate_one(), sequential.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_tokens_join_into_the_name() {
+        let builder = || {
+            ClusterChainGenerator::builder()
+                .with_seed(5)
+                .train(vec!["julius", "augustus", "tiberius", "claudius"].into_iter())
+        };
+        let mut tokens = builder().build();
+        let mut names = builder().build();
+        for _ in 0..20 {
+            let clusters = tokens.generate_tokens();
+            assert!(clusters.iter().all(|cluster| !cluster.is_empty()));
+            assert_eq!(clusters.concat(), names.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_forbidden_transitions_are_never_generated() {
+        let generator = ClusterChainGenerator::builder()
+            .with_prior(0.1)
+            .forbid_transitions([("l", "ia"), ("m", "u")])
+            .train(vec!["julia", "marcus", "tullia"].into_iter())
+            .build();
+        for (context, transitions) in generator.model.contexts() {
+            match context.last().map(String::as_str) {
+                Some("l") => assert!(transitions.get("ia").is_none()),
+                Some("m") => assert!(transitions.get("u").is_none()),
+                _ => {}
+            }
+        }
+        assert!(generator
+            .model
+            .best_model(&[String::from("j")])
+            .unwrap()
+            .get("u")
+            .is_some());
+    }
+
+    #[test]
+    fn test_forbidding_more_than_a_cluster_is_an_error() {
+        use crate::{Language, NamegenError};
+        let forbid = |transition: (&str, &str)| {
+            ClusterChainGenerator::builder()
+                .forbid_transitions([transition])
+                .train(vec!["gwyn", "julia"].into_iter())
+                .try_build()
+        };
+        assert!(matches!(
+            forbid(("ul", "ia")),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            forbid(("", "u")),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        assert!(forbid(("l", "ia")).is_ok());
+        // "yn" is a vowel and a consonant in Welsh, but one consonant cluster in Romance
+        let forbid_in = |language: Language| {
+            ClusterChainGenerator::builder()
+                .with_language(language)
+                .forbid_transitions([("w", "yn")])
+                .train(vec!["gwyn"].into_iter())
+                .try_build()
+        };
+        assert!(matches!(
+            forbid_in(Language::Welsh),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        assert!(forbid_in(Language::Romance).is_ok());
+    }
 }
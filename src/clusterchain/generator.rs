@@ -1,8 +1,33 @@
 use crate::clusterchain::builder::ClusterChainGeneratorBuilder;
+use crate::config::GeneratorConfig;
+use crate::confusable::ConfusablePolicy;
+use crate::error::NamegenError;
+use crate::explain::{explain, ExplainedName, RecordedName};
+use crate::export::{json_graph, NameRecord};
+use crate::hooks::{Hooks, Rejection};
+use crate::identifier::IdentifierStyle;
+use crate::language::{vowel_rule, Language};
+use crate::memory::NameMemory;
+use crate::model::{Chain, Endings, MarkovModel, Refinement, STRICT_RESTARTS};
+use crate::nickname::{Diminutives, NicknamedName};
+use crate::phonetic::PhoneticBlocklist;
+use crate::phonotactics::Phonotactics;
+use crate::positional::{self, learn_finals};
+use crate::postprocess::{apply_all, Postprocessor};
+use crate::preprocess::{unescape, Preprocessor, SENTINEL};
+use crate::pronunciation::PronouncedName;
+use crate::report::sealed::{self, ModelSummary};
+use crate::report::Reportable;
+use crate::script::Script;
+use crate::state::{GeneratorRng, GeneratorState};
+use crate::transliterate::Transliteration;
 use crate::RandomTextGenerator;
 use log::{debug, trace};
-use multimarkov::MultiMarkov;
 use regex::Regex;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
 
 /// This struct, once trained on a corpus of training data, can be used repeatedly to generate
 /// random text strings (i.e. names) that sort-of resemble the training data.  At its heart is a
@@ -50,7 +75,8 @@ use regex::Regex;
 /// results must be 4 to 8 characters long.  ClusterChainGenerator will simply re-roll new names
 /// until it finds one that matches.  Be careful: if you supply a difficult-to-match pattern,
 /// name generation may be very slow; if you supply an impossible-to-match pattern, for example
-/// one that requires characters not seen in the training data, you will get an infinite loop.
+/// one that requires characters not seen in the training data, you will get an infinite loop,
+/// unless you cap the number of re-rolls with `.with_max_attempts()`.
 ///
 /// Here's a final example that reads names from a file (one name per line), builds up a
 /// ClusterChainGenerator, and then spits out a few names:
@@ -74,50 +100,895 @@ use regex::Regex;
 /// }
 /// ```
 ///
-#[derive(Debug)]
 pub struct ClusterChainGenerator {
-    pub(super) model: MultiMarkov<String>,
+    pub(super) name: Option<String>,
+    pub(super) model: Arc<MarkovModel<String>>,
     pub(super) pattern: Option<Regex>,
+    pub(super) required: Option<Vec<String>>,
+    pub(super) start: Option<BTreeSet<String>>,
+    pub(super) phonotactics: Option<Phonotactics>,
+    pub(super) max_attempts: Option<usize>,
+    pub(super) hooks: Hooks,
+    pub(super) memory: Option<NameMemory>,
+    pub(super) avoid: Arc<HashSet<String>>,
+    pub(super) phonetic_blocklist: Option<Arc<PhoneticBlocklist>>,
+    pub(super) confusable_policy: Option<ConfusablePolicy>,
+    pub(super) identifier_style: Option<IdentifierStyle>,
+    pub(super) counts: Arc<MarkovModel<String>>,
+    pub(super) refinement: Arc<Refinement<String>>,
+    pub(super) preprocessor: Preprocessor,
+    pub(super) decay: f64,
+    pub(super) strict: bool,
+    pub(super) endings: Endings<String>,
+    pub(super) finals: Option<Arc<Chain<String>>>,
+    pub(super) postprocessors: Vec<Postprocessor>,
+    pub(super) pronunciation: Option<Transliteration>,
+    pub(super) diminutives: Diminutives,
+    pub(super) script: Script,
+    pub(super) language: Option<Language>,
+    pub(super) bits_generated: f64,
+    pub(super) rng: GeneratorRng,
 }
 
 impl<'a> ClusterChainGenerator {
-    pub const DEFAULT_ORDER: i32 = 3;
+    pub const DEFAULT_ORDER: usize = 3;
+    /// The highest order `.with_order()` accepts.
+    pub const MAX_ORDER: usize = 16;
     pub const DEFAULT_PRIOR: f64 = 0.001;
 
     pub fn builder() -> ClusterChainGeneratorBuilder<'a> {
         ClusterChainGeneratorBuilder::new()
     }
 
-    fn generate_string(&mut self) -> String {
-        // start with the beginning-of-word character
+    /// Trains a generator on a corpus with the settings in a `GeneratorConfig`, as a builder
+    /// given the same settings would.  Returns an error if the pattern is invalid or the corpus
+    /// can't be trained on.
+    pub fn from_config(
+        config: GeneratorConfig,
+        corpus: impl IntoIterator<Item = impl Deref<Target = str>>,
+    ) -> Result<Self, NamegenError> {
+        let mut builder = ClusterChainGenerator::builder();
+        if let Some(order) = config.order {
+            builder = builder.with_order(order);
+        }
+        if let Some(marker) = config.comment {
+            builder = builder.skip_comments(marker);
+        }
+        let mut builder = builder
+            .trim_input(config.trim)
+            .with_punctuation_policy(config.punctuation)
+            .train(corpus.into_iter())
+            .ignore_pattern_case(config.ignore_pattern_case)
+            .remember_outputs(config.remember_outputs);
+        if let Some(prior) = config.prior {
+            builder = builder.with_prior(prior);
+        }
+        if let Some(pattern) = &config.pattern {
+            builder = builder.with_pattern(pattern);
+        }
+        if let Some(max_attempts) = config.max_attempts {
+            builder = builder.with_max_attempts(max_attempts);
+        }
+        if let Some(seed) = config.seed {
+            builder = builder.with_seed(seed);
+        }
+        builder.try_build()
+    }
+
+    /// Makes a copy of this generator with its own RNG, seeded with `seed` as by `.with_seed()`.
+    /// The trained model, postprocessors and avoid-list are shared rather than copied, so this
+    /// is much cheaper than training another generator, e.g. to give each thread of a server
+    /// its own (see `pool::GeneratorPool`).  The copy starts out with a copy of this
+    /// generator's memory of past outputs, if any; from then on, each remembers its own, except
+    /// that a memory kept in a file with `.remember_outputs_in()` is appended to by both.
+    pub fn fork(&self, seed: u64) -> Self {
+        ClusterChainGenerator {
+            memory: self.memory.clone(),
+            ..self.fork_forgetful(seed)
+        }
+    }
+
+    /// Makes a copy of this generator, as with `fork()`, that doesn't remember its outputs.
+    pub(crate) fn fork_forgetful(&self, seed: u64) -> Self {
+        ClusterChainGenerator {
+            name: self.name.clone(),
+            model: Arc::clone(&self.model),
+            pattern: self.pattern.clone(),
+            required: self.required.clone(),
+            start: self.start.clone(),
+            phonotactics: self.phonotactics.clone(),
+            max_attempts: self.max_attempts,
+            hooks: self.hooks.clone(),
+            memory: None,
+            avoid: Arc::clone(&self.avoid),
+            phonetic_blocklist: self.phonetic_blocklist.clone(),
+            confusable_policy: self.confusable_policy,
+            identifier_style: self.identifier_style,
+            counts: Arc::clone(&self.counts),
+            refinement: Arc::clone(&self.refinement),
+            preprocessor: self.preprocessor.clone(),
+            decay: self.decay,
+            strict: self.strict,
+            finals: self.finals.clone(),
+            endings: self.endings.clone(),
+            postprocessors: self.postprocessors.clone(),
+            pronunciation: self.pronunciation.clone(),
+            diminutives: self.diminutives.clone(),
+            script: self.script,
+            language: self.language,
+            bits_generated: 0.0,
+            rng: GeneratorRng::from_seed(seed),
+        }
+    }
+
+    /// Makes a copy of this generator, as with `fork()`, whose RNG is substream `index` of the
+    /// master seed `seed`.  Substreams of one seed never overlap, so a batch job can give each
+    /// worker thread or chunk of work its own substream, and its output depends only on the
+    /// seed and the way the work was split (see `pool::generate_in_parallel()`).
+    pub fn fork_substream(&self, seed: u64, index: u64) -> Self {
+        ClusterChainGenerator {
+            rng: GeneratorRng::substream(seed, index),
+            ..self.fork(seed)
+        }
+    }
+
+    /// Learns from more names after the generator is built, e.g. the names players have chosen
+    /// in a long-running game, as if they had been part of the training corpus; they're
+    /// cleaned up and split into words as the corpus was, and the model is refined again from
+    /// all the counts, with the prior, interpolation, restricted alphabet, anti-corpus and
+    /// forbidden transitions it was built with.  If the generator was built `.with_decay()`,
+    /// what it learned earlier counts for less with every name learned.  The model is copied
+    /// rather than changed in place, so forks of this generator don't learn the names, and
+    /// it's best to learn names in batches rather than one at a time.
+    ///
+    /// Returns an error, and learns nothing, if a name is rejected under
+    /// `ReservedCharPolicy::Error`.
+    pub fn learn(
+        &mut self,
+        names: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> Result<(), NamegenError> {
+        let mut error = None;
+        let words = self.preprocessor.prepare_all(names, None, &mut error);
+        if let Some(error) = error {
+            return Err(error);
+        }
+        let is_vowel = vowel_rule(self.script, self.language);
+        let sequences: Vec<Vec<String>> = words
+            .into_iter()
+            .map(|word| {
+                let mut clusters = ClusterChainGeneratorBuilder::clusterize_with(word, &is_vowel);
+                clusters.insert(0, SENTINEL.to_string());
+                clusters.push(SENTINEL.to_string());
+                clusters
+            })
+            .collect();
+        let counts = self.counts.learned(&sequences, self.decay);
+        let model = self.refinement.refine(&counts)?;
+        if let Some(finals) = self.finals.as_mut() {
+            learn_finals(
+                Arc::make_mut(finals),
+                self.model.order,
+                &sequences,
+                self.decay,
+            );
+        }
+        self.counts = Arc::new(counts);
+        self.model = Arc::new(model);
+        self.endings.clear();
+        Ok(())
+    }
+
+    /// Encodes the trained model as a platform-independent byte string, to be loaded with
+    /// `.with_precompiled_model()` instead of training again.  See the `precompile` module.
+    pub fn model_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.model.encode(&mut bytes);
+        bytes
+    }
+
+    /// Describes the trained model as a JSON node-link graph, e.g. to render it with d3 or
+    /// Cytoscape in web documentation or editor tooling.  There is a node for each context
+    /// (the clusters, joined with middle dots, that a transition follows) and one for each
+    /// state, with `"kind"` set to `"context"` or `"state"`, and a link from each context to
+    /// each state that may follow it, with its weight and probability.  Links less probable
+    /// than `min_probability` are left out, as are the states only they lead to; the prior
+    /// gives every context a link to every state, so a graph of all of them is usually too
+    /// dense to be useful.
+    pub fn to_json_graph(&self, min_probability: f64) -> String {
+        json_graph(&self.model, min_probability, |states| states.join("·"))
+    }
+
+    /// Makes a plausible respelling of an existing name, e.g. for quest variants or sibling
+    /// characters: "cassia" might become "cassina" or "cossia".  A few positions of the name
+    /// are re-sampled under the model, substituting, inserting or deleting a cluster while
+    /// keeping the rest fixed; each new cluster is chosen to fit both the clusters before it and
+    /// the one after it.  `strength`, from 0 to 1, is the fraction of the name's clusters to
+    /// edit, but at least one edit is always made.  The name is first lowercased, like the
+    /// training data, if the model doesn't know all its letters as they are; the generator's
+    /// postprocessors are applied to the result, but not its pattern or other filters.
+    ///
+    /// Panics if `strength` is not between 0 and 1.
+    pub fn mutate(&mut self, name: &str, strength: f64) -> String {
+        assert!(
+            (0.0..=1.0).contains(&strength),
+            "The strength of a mutation must be between 0 and 1."
+        );
+        let is_vowel = vowel_rule(self.script, self.language);
+        let known = |cluster: &String| self.model.known_states.contains(cluster);
+        let mut states = ClusterChainGeneratorBuilder::clusterize_with(name.to_string(), &is_vowel);
+        if !states.iter().all(known) {
+            states = ClusterChainGeneratorBuilder::clusterize_with(name.to_lowercase(), &is_vowel);
+        }
+        let edits = ((states.len() as f64 * strength).round() as usize).max(1);
+        let mutated = self
+            .model
+            .mutate(&"#".to_string(), &states, edits, &mut self.rng);
+        let candidate = unescape(mutated.concat());
+        apply_all(&self.postprocessors, candidate, &mut self.rng)
+    }
+
+    /// Generates a new name together with an approximate IPA pronunciation.  See
+    /// `PronouncedName` for details.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_pronounced()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_pronounced(&mut self) -> PronouncedName {
+        self.try_generate_pronounced()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a new name together with an approximate IPA pronunciation, or returns an
+    /// error if the model can't produce one.
+    pub fn try_generate_pronounced(&mut self) -> Result<PronouncedName, NamegenError> {
+        let spelling = self.try_generate_one()?;
+        Ok(PronouncedName::new(spelling, self.pronunciation.as_ref()))
+    }
+
+    /// Derives a nickname from a name ("Valeria" → "Val", "Leri" or "Valina"), by clipping it
+    /// and perhaps adding one of the diminutive endings set with `.with_diminutives()`, and
+    /// choosing among the candidates by how plausible the model finds them.  The nickname is
+    /// capitalized if the name is.  Returns the name itself if the model finds none of the
+    /// candidates possible.
+    pub fn nickname(&mut self, name: &str) -> String {
+        let is_vowel = vowel_rule(self.script, self.language);
+        let log_probability = |nickname: &str| {
+            let mut tokens =
+                ClusterChainGeneratorBuilder::clusterize_with(nickname.to_string(), &is_vowel);
+            tokens.insert(0, String::from("#"));
+            tokens.push(String::from("#"));
+            self.model.log_probability(&tokens)
+        };
+        self.diminutives
+            .nickname(name, &mut self.rng, &is_vowel, log_probability)
+    }
+
+    /// The log-probability of a text under the model, split into clusters the way the
+    /// generator's training data was.
+    pub(crate) fn log_probability_of(&self, text: &str) -> f64 {
+        let is_vowel = vowel_rule(self.script, self.language);
+        let mut tokens = ClusterChainGeneratorBuilder::clusterize_with(text.to_string(), is_vowel);
+        tokens.insert(0, String::from("#"));
+        tokens.push(String::from("#"));
+        self.model.log_probability(&tokens)
+    }
+
+    /// Generates a new name together with a nickname derived from it by `nickname()`.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_nicknamed()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_nicknamed(&mut self) -> NicknamedName {
+        self.try_generate_nicknamed()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a new name together with a nickname derived from it, or returns an error if
+    /// the model can't produce a name.
+    pub fn try_generate_nicknamed(&mut self) -> Result<NicknamedName, NamegenError> {
+        let name = self.try_generate_one()?;
+        let nickname = self.nickname(&name);
+        Ok(NicknamedName { name, nickname })
+    }
+
+    /// Derives a name from a world seed and an entity's key (its id, coordinates, etc.).  The
+    /// same seed and key always give the same name, on every platform, so an entity's name can
+    /// be regenerated on demand instead of being stored.  The generator's own random state is
+    /// left untouched, and names remembered with `.remember_outputs()` are not consulted.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_for_key()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_for_key(&mut self, world_seed: u64, key: &str) -> String {
+        self.try_generate_for_key(world_seed, key)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Derives a name from a world seed and an entity's key, or returns an error if the model
+    /// can't produce one.  See `generate_for_key()`.
+    pub fn try_generate_for_key(
+        &mut self,
+        world_seed: u64,
+        key: &str,
+    ) -> Result<String, NamegenError> {
+        let rng = std::mem::replace(&mut self.rng, GeneratorRng::for_key(world_seed, key));
+        let memory = self.memory.take();
+        let result = self.try_generate_one();
+        self.rng = rng;
+        self.memory = memory;
+        result
+    }
+
+    /// Generates a name of exactly `length` characters (before any postprocessing).  Rather than
+    /// generating names until one happens to be that long, the model only ever chooses clusters
+    /// after which a name of that length can still end, in proportion to how likely it is to end
+    /// there, so the names are as the model would generate them if it only kept those of that
+    /// length.  The pattern and other filters still apply, but a pattern on the length can never
+    /// reject a name.  A required substring or start cluster is not ensured, names don't end by
+    /// the positional tables of `.with_positional_tables()`, and strict mode doesn't back off
+    /// from contexts with nothing left to follow them.  The first call takes the longest,
+    /// working out the probabilities of ending from every context, which are then kept until
+    /// the generator `learn()`s more names.
+    ///
+    /// Panics if the model can't produce a name of that length; use `try_generate_with_length()`
+    /// if you'd rather handle that as an error.
+    ///
+    /// ```
+    /// use markov_namegen::ClusterChainGenerator;
+    /// let mut namegen = ClusterChainGenerator::builder()
+    ///     .train(vec!["julius", "marcus", "lucius", "tiberius"].into_iter())
+    ///     .build();
+    /// assert_eq!(namegen.generate_with_length(6).chars().count(), 6);
+    /// ```
+    pub fn generate_with_length(&mut self, length: usize) -> String {
+        self.try_generate_with_length(length)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a name of exactly `length` characters, or returns an error if the model can't
+    /// produce one (a `NamegenError::DeadEnd`).  See `generate_with_length()`.
+    pub fn try_generate_with_length(&mut self, length: usize) -> Result<String, NamegenError> {
+        self.generate_accepted(Some(length))
+            .map(|(_, record)| record.name)
+    }
+
+    /// Takes a snapshot of the generator's random state, so that it can later be resumed with
+    /// `restore_state()` to produce exactly the same future names.  Returns `None` if the
+    /// generator was built with a custom RNG via `.with_rng()`, since that can't be captured;
+    /// nothing is saved then, not even the memory of past outputs, so a generator that needs
+    /// to be saved should be seeded with `.with_seed()` instead.
+    pub fn save_state(&self) -> Option<GeneratorState> {
+        let mut state = self.rng.save()?;
+        state.memory = self.memory.clone();
+        Some(state)
+    }
+
+    /// Restores a snapshot taken with `save_state()`.  The generator should have been built with
+    /// the same settings and training data as the one that was saved.  If it remembers its
+    /// outputs, its memory becomes the snapshot's, or is cleared if the snapshot has none; a
+    /// memory kept in a file with `.remember_outputs_in()` keeps its file, and ignores a
+    /// snapshot's approximate memory.  A custom RNG is replaced by the snapshot's.
+    pub fn restore_state(&mut self, state: &GeneratorState) {
+        self.rng = GeneratorRng::restore(state);
+        if let Some(memory) = &mut self.memory {
+            match &state.memory {
+                Some(saved) => memory.restore(saved),
+                None => memory.clear(),
+            }
+        }
+    }
+
+    /// Forgets the names already generated, so that a generator that remembers its outputs may
+    /// produce them again.
+    pub fn reset_memory(&mut self) {
+        if let Some(memory) = &mut self.memory {
+            memory.clear();
+        }
+    }
+
+    /// How surprising a name is under the model, in bits: the negative base-2 log of the
+    /// probability that the model generates it, so that a name the model produces one time in
+    /// 1,024 scores 10.  Names typical of the training data score low, and a name the model
+    /// can't produce at all scores infinity.  The name is cleaned up, lowercased and split
+    /// into words as the training data was, and the words' scores are added up; postprocessing
+    /// isn't undone, so a name with a generated affix or spelling variant scores as if it had
+    /// been generated that way.
+    ///
+    /// ```
+    /// use markov_namegen::ClusterChainGenerator;
+    /// let namegen = ClusterChainGenerator::builder()
+    ///     .without_prior()
+    ///     .train(vec!["kora", "kori"].into_iter())
+    ///     .build();
+    /// assert!((namegen.entropy_of("Kora") - 1.0).abs() < 1e-9);
+    /// assert_eq!(namegen.entropy_of("Vela"), f64::INFINITY);
+    /// ```
+    pub fn entropy_of(&self, name: &str) -> f64 {
+        let words = match self.preprocessor.prepare(name) {
+            Ok(words) if !words.is_empty() => words,
+            _ => return f64::INFINITY,
+        };
+        let is_vowel = vowel_rule(self.script, self.language);
+        let log_probability: f64 = words
+            .into_iter()
+            .map(|word| {
+                let mut sequence = ClusterChainGeneratorBuilder::clusterize_with(word, &is_vowel);
+                sequence.insert(0, "#".to_string());
+                sequence.push("#".to_string());
+                self.model.log_probability(&sequence)
+            })
+            .sum();
+        -log_probability / std::f64::consts::LN_2
+    }
+
+    /// The information in the model's choices for every name this generator has returned, in
+    /// bits: at every step of every name, the entropy of the clusters the model could have
+    /// chosen next.  Divided by the number of names, it estimates the entropy of the
+    /// generator's output, and with it the chance that some two of `n` names are the same,
+    /// roughly n² / 2^(h + 1) for h bits per name, or more, since names are far from equally
+    /// likely.  Candidates that were rejected aren't counted, and forks start from zero.
+    ///
+    /// ```
+    /// use markov_namegen::{ClusterChainGenerator, RandomTextGenerator};
+    /// let mut namegen = ClusterChainGenerator::builder()
+    ///     .without_prior()
+    ///     .train(vec!["kora", "kori"].into_iter())
+    ///     .build();
+    /// namegen.generate_one();
+    /// namegen.generate_one();
+    /// assert!((namegen.bits_generated() - 2.0).abs() < 1e-9);
+    /// ```
+    pub fn bits_generated(&self) -> f64 {
+        self.bits_generated
+    }
+
+    /// Generates `count` names, each with a `NameRecord` of statistics about how it was
+    /// generated, e.g. for export with `export::write_json()` or `export::write_markdown()`.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_records()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_records(&mut self, count: usize) -> Vec<NameRecord> {
+        self.try_generate_records(count)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates `count` names with their `NameRecord`s, or returns an error if the model can't
+    /// produce them.
+    pub fn try_generate_records(&mut self, count: usize) -> Result<Vec<NameRecord>, NamegenError> {
+        (0..count).map(|_| self.generate_record()).collect()
+    }
+
+    /// Generates a name and shows how: the context, candidates and choice at every step.  See
+    /// `ExplainedName`.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_explained()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_explained(&mut self) -> ExplainedName {
+        self.try_generate_explained()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a name and shows how, or returns an error if the model can't produce one.  See
+    /// `generate_explained()`.
+    pub fn try_generate_explained(&mut self) -> Result<ExplainedName, NamegenError> {
+        let (sequence, record) = self.generate_accepted(None)?;
+        Ok(ExplainedName {
+            steps: explain(&self.model, &sequence, |c| unescape(c.clone())),
+            name: record.name,
+        })
+    }
+
+    /// Generates a name together with the choices the model made to generate it, to be made
+    /// again with `replay()`.  See `RecordedName`.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_recorded()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_recorded(&mut self) -> RecordedName {
+        self.try_generate_recorded()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a name with the choices made to generate it, or returns an error if the model
+    /// can't produce one.  See `generate_recorded()`.
+    pub fn try_generate_recorded(&mut self) -> Result<RecordedName, NamegenError> {
+        let (sequence, record) = self.generate_accepted(None)?;
+        let choices = self
+            .model
+            .choices(&sequence)
+            .ok_or_else(|| NamegenError::DeadEnd(record.name.clone()))?;
+        Ok(RecordedName {
+            choices,
+            name: record.name,
+        })
+    }
+
+    /// Generates a name and returns the vowel and consonant clusters it's made of, before
+    /// they're joined into a string and postprocessed, so you can style, hyphenate or analyze
+    /// its structure without splitting it up again.  The name has passed the generator's
+    /// pattern and filters, and is remembered if the generator remembers its outputs, just as
+    /// if it had been returned by `generate_one()`.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_tokens()` if you'd
+    /// rather handle that as an error.
+    ///
+    /// ```
+    /// use markov_namegen::ClusterChainGenerator;
+    /// let mut namegen = ClusterChainGenerator::builder()
+    ///     .train(vec!["julius", "marcus", "lucius"].into_iter())
+    ///     .build();
+    /// let clusters: Vec<String> = namegen.generate_tokens();
+    /// println!("{}", clusters.join("·"));
+    /// ```
+    pub fn generate_tokens(&mut self) -> Vec<String> {
+        self.try_generate_tokens()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a name and returns its vowel and consonant clusters, or returns an error if the
+    /// model can't produce one.  See `generate_tokens()`.
+    pub fn try_generate_tokens(&mut self) -> Result<Vec<String>, NamegenError> {
+        let (sequence, _) = self.generate_accepted(None)?;
+        Ok(sequence[1..sequence.len() - 1]
+            .iter()
+            .map(|cluster| unescape(cluster.clone()))
+            .collect())
+    }
+
+    /// Makes the choices recorded with `generate_recorded()` again, to reproduce the name.  The
+    /// generator must have the same trained model as the one that recorded them; its pattern,
+    /// filters and memory are not consulted, but its postprocessors are applied.  (A
+    /// postprocessor that draws random numbers, like a random affix, may give a different
+    /// result.)  Returns a `NamegenError::InvalidConfig` error if the choices don't fit the
+    /// model.
+    pub fn replay(&mut self, choices: &[usize]) -> Result<String, NamegenError> {
+        let sequence = self
+            .model
+            .replay(&"#".to_string(), choices)
+            .ok_or_else(|| {
+                NamegenError::InvalidConfig(String::from(
+                    "the recorded choices don't fit this generator's model",
+                ))
+            })?;
+        let candidate = unescape(sequence[1..sequence.len() - 1].join(""));
+        Ok(apply_all(&self.postprocessors, candidate, &mut self.rng))
+    }
+
+    /// Generates a name as `generate_one()` does, with the information in the model's choices
+    /// that made it, in bits.  See `passphrase::Passphrase`.
+    pub(crate) fn generate_with_bits(&mut self) -> Result<(String, f64), NamegenError> {
+        let (sequence, record) = self.generate_accepted(None)?;
+        Ok((record.name, self.model.branching_bits(&sequence)))
+    }
+
+    fn generate_record(&mut self) -> Result<NameRecord, NamegenError> {
+        self.generate_accepted(None).map(|(_, record)| record)
+    }
+
+    /// Generates a name that passes every filter, with the sequence it was generated from.
+    fn generate_accepted(
+        &mut self,
+        length: Option<usize>,
+    ) -> Result<(Vec<String>, NameRecord), NamegenError> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            if let Some(accepted) = self.attempt(attempts, length)? {
+                return Ok(accepted);
+            }
+        }
+    }
+
+    /// Generates one candidate, and returns it with its sequence if it passes every filter, or
+    /// `None` if it's rejected.  `attempts` counts this attempt and those before it, and
+    /// `length` is the exact length of the name to generate, if any.
+    pub(crate) fn attempt(
+        &mut self,
+        attempts: usize,
+        length: Option<usize>,
+    ) -> Result<Option<(Vec<String>, NameRecord)>, NamegenError> {
+        let sequence = match length {
+            Some(length) => self.generate_sequence_with_length(length)?,
+            None => self.generate_sequence()?,
+        };
+        let candidate = unescape(sequence[1..sequence.len() - 1].join(""));
+        self.hooks.candidate(&candidate);
+        if let Some(re) = &self.pattern {
+            if !re.is_match(&candidate) {
+                debug!("ClusterChainGenerator generated '{}' which doesn't match the regex pattern. Re-rolling!", candidate);
+                self.hooks
+                    .reject(&candidate, Rejection::Pattern, attempts, self.max_attempts)?;
+                return Ok(None);
+            }
+        }
+        if let Some(Err(violation)) = self.phonotactics.as_ref().map(|r| r.validate(&candidate)) {
+            debug!("ClusterChainGenerator generated '{}' which breaks a phonotactic rule ({}). Re-rolling!", candidate, violation);
+            self.hooks.reject(
+                &candidate,
+                Rejection::Phonotactics(violation.to_string()),
+                attempts,
+                self.max_attempts,
+            )?;
+            return Ok(None);
+        }
+        let mut name = apply_all(&self.postprocessors, candidate, &mut self.rng);
+        if let Some(policy) = self.confusable_policy {
+            match policy.apply(name.clone()) {
+                Some(normalized) => name = normalized,
+                None => {
+                    debug!(
+                        "ClusterChainGenerator generated '{}' which mixes scripts. Re-rolling!",
+                        name
+                    );
+                    self.hooks.reject(
+                        &name,
+                        Rejection::MixedScripts,
+                        attempts,
+                        self.max_attempts,
+                    )?;
+                    return Ok(None);
+                }
+            }
+        }
+        if let Some(style) = self.identifier_style {
+            match style.apply(&name) {
+                Some(identifier) => name = identifier,
+                None => {
+                    debug!("ClusterChainGenerator generated '{}' which can't be made into an identifier. Re-rolling!", name);
+                    self.hooks.reject(
+                        &name,
+                        Rejection::NotAnIdentifier,
+                        attempts,
+                        self.max_attempts,
+                    )?;
+                    return Ok(None);
+                }
+            }
+        }
+        if self.avoid.contains(&name) {
+            debug!("ClusterChainGenerator generated '{}' which is on the list of names to avoid. Re-rolling!", name);
+            self.hooks
+                .reject(&name, Rejection::Avoided, attempts, self.max_attempts)?;
+            return Ok(None);
+        }
+        if let Some(entry) = self
+            .phonetic_blocklist
+            .as_ref()
+            .and_then(|blocklist| blocklist.sounds_like(&name))
+        {
+            debug!("ClusterChainGenerator generated '{}' which sounds like '{}' on the blocklist. Re-rolling!", name, entry);
+            self.hooks.reject(
+                &name,
+                Rejection::SoundsLike(entry.to_string()),
+                attempts,
+                self.max_attempts,
+            )?;
+            return Ok(None);
+        }
+        if let Some(memory) = &mut self.memory {
+            if !memory.insert(&name) {
+                debug!("ClusterChainGenerator generated '{}' which it has generated before. Re-rolling!", name);
+                self.hooks
+                    .reject(&name, Rejection::Repeated, attempts, self.max_attempts)?;
+                return Ok(None);
+            }
+        }
+        trace!("ClusterChainGenerator generated '{}'", name);
+        self.bits_generated += self.model.branching_bits(&sequence);
+        let record = NameRecord {
+            log_probability: self.model.log_probability(&sequence),
+            attempts,
+            length: name.chars().count(),
+            clusters: sequence[1..sequence.len() - 1]
+                .iter()
+                .map(|c| unescape(c.clone()))
+                .collect(),
+            name,
+        };
+        Ok(Some((sequence, record)))
+    }
+
+    /// Generates a sequence of clusters that make up exactly `length` characters, including the
+    /// beginning- and end-of-word markers.
+    fn generate_sequence_with_length(
+        &mut self,
+        length: usize,
+    ) -> Result<Vec<String>, NamegenError> {
+        if self.model.is_empty() {
+            return Err(NamegenError::EmptyModel);
+        }
+        self.model
+            .random_with_length(
+                &"#".to_string(),
+                length,
+                |cluster| unescape(cluster.clone()).chars().count(),
+                &mut self.endings,
+                &mut self.rng,
+            )
+            // no path of that length leads on from the start of a word
+            .ok_or_else(|| NamegenError::DeadEnd(String::from("#")))
+    }
+
+    /// Generates a sequence of clusters, including the beginning- and end-of-word markers.  In
+    /// strict mode, a word that reaches a dead end is started over, up to `STRICT_RESTARTS`
+    /// times.
+    fn generate_sequence(&mut self) -> Result<Vec<String>, NamegenError> {
+        let mut restarts = 0;
+        loop {
+            match self.generate_word() {
+                Err(NamegenError::DeadEnd(_)) if self.strict && restarts < STRICT_RESTARTS => {
+                    restarts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Generates one try at a sequence for `generate_sequence()`.
+    fn generate_word(&mut self) -> Result<Vec<String>, NamegenError> {
+        // start with the beginning-of-word character and the chosen first cluster, if any
         let mut name = vec!["#".to_string()];
+        if let Some(start) = &self.start {
+            match self
+                .model
+                .random_next_where(&name, &mut self.rng, |cluster| start.contains(cluster))
+            {
+                Some(cluster) => name.push(cluster),
+                None if self.model.is_empty() => return Err(NamegenError::EmptyModel),
+                None => return Err(NamegenError::DeadEnd(String::new())),
+            }
+        }
+        // then a path to the required clusters
+        if let Some(required) = &self.required {
+            if self.model.is_empty() {
+                return Err(NamegenError::EmptyModel);
+            }
+            name = self
+                .model
+                .random_through(&name, required, &mut self.rng)
+                .ok_or_else(|| NamegenError::DeadEnd(required.join("")))?;
+        }
         loop {
             // keep adding letters until we reach the end-of-word character
-            name.push(self.model.random_next(&name).unwrap());
+            let end = "#".to_string();
+            let next = match &self.finals {
+                Some(finals) => {
+                    positional::random_next(&self.model, finals, &name, &end, &mut self.rng)
+                }
+                None => self
+                    .model
+                    .random_next(&name, &mut self.rng)
+                    .map(|cluster| (cluster, false)),
+            };
+            // in strict mode, a context with nothing left to follow it backs off to a shorter one
+            let next = match next {
+                None if self.strict => self
+                    .model
+                    .random_next_backing_off(&name, &mut self.rng)
+                    .map(|cluster| (cluster, false)),
+                next => next,
+            };
+            match next {
+                Some((cluster, last)) => {
+                    name.push(cluster);
+                    // a final cluster ends the word
+                    if last {
+                        name.push(end);
+                    }
+                }
+                None if self.model.is_empty() => return Err(NamegenError::EmptyModel),
+                None => return Err(NamegenError::DeadEnd(name[1..].join(""))),
+            }
             if name.ends_with(&["#".to_string()]) {
                 break;
             }
         }
-        // remove the trailing and leading "#" signs
-        name.pop();
-        name.remove(0);
-        name.join("")
+        Ok(name)
+    }
+}
+
+/// Trains a generator with the default settings, for quick scripts:
+///
+/// ```
+/// use markov_namegen::{ClusterChainGenerator, RandomTextGenerator};
+/// let names = vec!["julius", "augustus", "tiberius", "claudius"];
+/// let mut namegen: ClusterChainGenerator = names.into_iter().collect();
+/// println!("{}", namegen.generate_one());
+/// ```
+impl<S: Deref<Target = str>> FromIterator<S> for ClusterChainGenerator {
+    fn from_iter<I: IntoIterator<Item = S>>(names: I) -> Self {
+        ClusterChainGenerator::builder()
+            .train(names.into_iter())
+            .build()
+    }
+}
+
+/// Trains a generator with the default settings on a list of names.
+impl From<Vec<String>> for ClusterChainGenerator {
+    fn from(names: Vec<String>) -> Self {
+        names.into_iter().collect()
+    }
+}
+
+impl fmt::Debug for ClusterChainGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClusterChainGenerator")
+            .field("name", &self.name)
+            .field("model", &self.model)
+            .field("pattern", &self.pattern)
+            .field("postprocessors", &self.postprocessors.len())
+            .finish_non_exhaustive()
     }
 }
 
 impl RandomTextGenerator for ClusterChainGenerator {
-    fn generate_one(&mut self) -> String {
-        match self.pattern.clone() {
-            None => self.generate_string(),
-            Some(re) => {
-                let mut candidate = self.generate_string();
-                while !re.is_match(&candidate) {
-                    debug!("ClusterChainGenerator generated '{}' which doesn't match the regex pattern. Re-rolling!", candidate);
-                    candidate = self.generate_string();
-                }
-                trace!("ClusterChainGenerator generated '{}'",candidate);
-                candidate
-            }
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        self.generate_record().map(|record| record.name)
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn kind(&self) -> &'static str {
+        "clusterchain"
+    }
+
+    fn fork_boxed(&self, seed: u64) -> Option<Box<dyn RandomTextGenerator + Send>> {
+        Some(Box::new(self.fork_forgetful(seed)))
+    }
+
+    /// Summarizes the model and the filters and postprocessors applied to its output, e.g.
+    /// "order 3, 412 contexts, pattern '^b', remembers outputs".
+    fn config_summary(&self) -> String {
+        let mut summary = format!(
+            "order {}, {} contexts",
+            self.model.order,
+            self.model.context_count()
+        );
+        if let Some(pattern) = &self.pattern {
+            summary.push_str(&format!(", pattern '{}'", pattern));
+        }
+        if let Some(required) = &self.required {
+            summary.push_str(&format!(", requires '{}'", required.join("")));
+        }
+        if let Some(start) = &self.start {
+            summary.push_str(&format!(", starts with one of {} clusters", start.len()));
+        }
+        if self.phonotactics.is_some() {
+            summary.push_str(", phonotactic rules");
+        }
+        if let Some(max_attempts) = self.max_attempts {
+            summary.push_str(&format!(", at most {} attempts", max_attempts));
+        }
+        if self.memory.is_some() {
+            summary.push_str(", remembers outputs");
+        }
+        if !self.avoid.is_empty() {
+            summary.push_str(&format!(", avoids {} names", self.avoid.len()));
+        }
+        if let Some(blocklist) = &self.phonetic_blocklist {
+            summary.push_str(&format!(", blocks {} sounds", blocklist.len()));
         }
+        match self.confusable_policy {
+            Some(ConfusablePolicy::Normalize) => summary.push_str(", normalizes confusables"),
+            Some(ConfusablePolicy::Reject) => summary.push_str(", rejects confusables"),
+            None => {}
+        }
+        if self.identifier_style.is_some() {
+            summary.push_str(", identifiers");
+        }
+        if !self.postprocessors.is_empty() {
+            summary.push_str(&format!(", {} postprocessors", self.postprocessors.len()));
+        }
+        summary
     }
 }
+
+impl sealed::Sealed for ClusterChainGenerator {
+    fn model_summary(&self) -> ModelSummary {
+        ModelSummary::new(&self.model, |states| states.join("·"))
+    }
+
+    fn fork_for_report(&self, seed: u64) -> Box<dyn RandomTextGenerator> {
+        Box::new(self.fork_forgetful(seed))
+    }
+}
+
+impl Reportable for ClusterChainGenerator {}
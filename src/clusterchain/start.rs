@@ -0,0 +1,96 @@
+//! Choosing the cluster that generated names start with, for families of names that share
+//! their opening sound.
+use crate::error::NamegenError;
+use std::collections::BTreeSet;
+
+/// Which clusters the names a `ClusterChainGenerator` generates may start with.  See
+/// `.with_start_cluster()`.
+///
+/// Clusters are matched against those of the model, before postprocessing, so they should be
+/// in lowercase unless case is kept with `.with_case_folding()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartCluster {
+    /// Exactly this cluster, e.g. "th".
+    Cluster(String),
+    /// Any one of these clusters, e.g. "th", "st" and "str".
+    OneOf(Vec<String>),
+    /// Any vowel cluster.
+    Vowel,
+    /// Any consonant cluster.
+    Consonant,
+}
+
+impl StartCluster {
+    /// The clusters that fit, out of the `known` clusters of a model.  Returns a
+    /// `NamegenError::InvalidConfig` error if a cluster named isn't known, or if none fits.
+    pub(crate) fn clusters(
+        &self,
+        known: &BTreeSet<String>,
+        is_vowel: impl Fn(char) -> bool,
+    ) -> Result<BTreeSet<String>, NamegenError> {
+        let named =
+            |clusters: &[String]| match clusters.iter().find(|cluster| !known.contains(*cluster)) {
+                Some(unknown) => Err(NamegenError::InvalidConfig(format!(
+                    "the model has never seen the start cluster '{}'",
+                    unknown
+                ))),
+                None => Ok(clusters.iter().cloned().collect()),
+            };
+        let of_class = |vowels: bool| {
+            known
+                .iter()
+                .filter(|cluster| *cluster != "#")
+                .filter(|cluster| cluster.starts_with(&is_vowel) == vowels)
+                .cloned()
+                .collect()
+        };
+        let clusters: BTreeSet<String> = match self {
+            StartCluster::Cluster(cluster) => named(std::slice::from_ref(cluster))?,
+            StartCluster::OneOf(clusters) => named(clusters)?,
+            StartCluster::Vowel => of_class(true),
+            StartCluster::Consonant => of_class(false),
+        };
+        if clusters.is_empty() {
+            return Err(NamegenError::InvalidConfig(String::from(
+                "no cluster the model knows can start a name",
+            )));
+        }
+        Ok(clusters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_clusters_are_chosen_from_the_known_ones() {
+        let known: BTreeSet<String> = ["#", "a", "io", "th", "str"]
+            .iter()
+            .map(|cluster| cluster.to_string())
+            .collect();
+        let is_vowel = |c: char| "aeiou".contains(c);
+        let clusters = |start: StartCluster| {
+            start
+                .clusters(&known, is_vowel)
+                .map(|clusters| clusters.into_iter().collect::<Vec<_>>())
+        };
+        assert_eq!(clusters(StartCluster::Vowel).unwrap(), ["a", "io"]);
+        assert_eq!(clusters(StartCluster::Consonant).unwrap(), ["str", "th"]);
+        assert_eq!(
+            clusters(StartCluster::Cluster(String::from("th"))).unwrap(),
+            ["th"]
+        );
+        assert!(matches!(
+            clusters(StartCluster::OneOf(vec![
+                String::from("th"),
+                String::from("ph")
+            ])),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            clusters(StartCluster::OneOf(Vec::new())),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+//! Generator settings as a plain struct, an alternative to a long builder chain.
+use crate::preprocess::PunctuationPolicy;
+
+/// The settings of a generator, for `CharacterChainGenerator::from_config()` and
+/// `ClusterChainGenerator::from_config()`.  Unset settings take the builders' defaults, so
+/// a config need only name the ones that matter:
+///
+/// ```
+/// use markov_namegen::{CharacterChainGenerator, GeneratorConfig, RandomTextGenerator};
+/// let config = GeneratorConfig {
+///     order: Some(2),
+///     pattern: Some(String::from("^.{4,8}$")),
+///     seed: Some(42),
+///     ..Default::default()
+/// };
+/// let corpus = vec!["julius", "augustus", "tiberius", "claudius"];
+/// let mut namegen = CharacterChainGenerator::from_config(config, corpus).unwrap();
+/// println!("{}", namegen.generate_one());
+/// ```
+///
+/// With the `serde` feature enabled, a config can be saved and loaded in any format serde
+/// supports, and the same config always builds the same generator from the same corpus.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GeneratorConfig {
+    /// The order of the Markov model; see `.with_order()`.
+    pub order: Option<usize>,
+    /// A constant prior; see `.with_prior()`.
+    pub prior: Option<f64>,
+    /// A pattern generated names must match; see `.with_pattern()`.
+    pub pattern: Option<String>,
+    /// Whether to match the pattern without regard to case; see `.ignore_pattern_case()`.
+    pub ignore_pattern_case: bool,
+    /// The number of candidates to try for each name; see `.with_max_attempts()`.
+    pub max_attempts: Option<usize>,
+    /// A seed for the RNG; see `.with_seed()`.
+    pub seed: Option<u64>,
+    /// Whether to re-roll names already generated; see `.remember_outputs()`.
+    pub remember_outputs: bool,
+    /// Whether to trim the corpus's lines and skip blank ones; see `.trim_input()`.
+    pub trim: bool,
+    /// A marker for comment lines in the corpus, to skip; see `.skip_comments()`.
+    pub comment: Option<char>,
+    /// What to do with punctuation in the corpus; see `.with_punctuation_policy()`.
+    pub punctuation: PunctuationPolicy,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trips() {
+        let config = GeneratorConfig {
+            order: Some(2),
+            pattern: Some(String::from("^v")),
+            seed: Some(7),
+            comment: Some('#'),
+            punctuation: PunctuationPolicy::Strip,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<GeneratorConfig>(&json).unwrap(),
+            config
+        );
+        let sparse: GeneratorConfig = serde_json::from_str(r#"{ "order": 2 }"#).unwrap();
+        assert_eq!(
+            sparse,
+            GeneratorConfig {
+                order: Some(2),
+                ..Default::default()
+            }
+        );
+    }
+}
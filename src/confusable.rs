@@ -0,0 +1,136 @@
+//! Names that mix lookalike letters of different scripts, like a Latin "Kora" with a Cyrillic
+//! "о" in it, which look like one name on screen but compare, sort and search as another.
+use crate::script::Script;
+
+/// Letters that look alike in Latin, Cyrillic and Greek, in that order, with '-' where a
+/// script has no lookalike.
+#[rustfmt::skip]
+const LOOKALIKES: &[(char, char, char)] = &[
+    ('a', 'а', '-'), ('c', 'с', '-'), ('d', 'ԁ', '-'), ('e', 'е', '-'), ('h', 'һ', '-'),
+    ('i', 'і', 'ι'), ('j', 'ј', '-'), ('k', 'к', 'κ'), ('o', 'о', 'ο'), ('p', 'р', 'ρ'),
+    ('q', 'ԛ', '-'), ('s', 'ѕ', '-'), ('v', '-', 'ν'), ('w', 'ԝ', '-'), ('x', 'х', 'χ'),
+    ('y', 'у', 'γ'), ('A', 'А', 'Α'), ('B', 'В', 'Β'), ('C', 'С', '-'), ('E', 'Е', 'Ε'),
+    ('H', 'Н', 'Η'), ('I', 'І', 'Ι'), ('J', 'Ј', '-'), ('K', 'К', 'Κ'), ('M', 'М', 'Μ'),
+    ('N', '-', 'Ν'), ('O', 'О', 'Ο'), ('P', 'Р', 'Ρ'), ('S', 'Ѕ', '-'), ('T', 'Т', 'Τ'),
+    ('X', 'Х', 'Χ'), ('Y', 'Ү', 'Υ'), ('Z', '-', 'Ζ'),
+];
+
+/// What a generator does with a name that mixes letters of different scripts.  Set it with
+/// the builders' `.with_confusable_policy()`; by default, names aren't checked.
+///
+/// Such names turn up when a model is trained on a corpus that mixes scripts, as corpora
+/// scraped from the web often do, and are a hazard wherever names are shown to people as
+/// identifiers, since two names that look the same may not be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfusablePolicy {
+    /// Replaces each letter with its lookalike in the script of most of the name's letters
+    /// ("Kоra" with a Cyrillic "о" becomes all Latin), and rejects the name if some letter
+    /// has no lookalike.
+    Normalize,
+    /// Rejects every name that mixes scripts.
+    Reject,
+}
+
+impl ConfusablePolicy {
+    /// The name to keep under this policy: the name itself if it's in one script, its
+    /// normalized form if there is one and the policy is to normalize, or else `None`.
+    pub(crate) fn apply(self, name: String) -> Option<String> {
+        if !is_mixed_script(&name) {
+            return Some(name);
+        }
+        match self {
+            ConfusablePolicy::Normalize => normalize(&name),
+            ConfusablePolicy::Reject => None,
+        }
+    }
+}
+
+/// Whether a name has letters of more than one of the supported scripts.
+///
+/// ```
+/// use markov_namegen::confusable::is_mixed_script;
+/// assert!(!is_mixed_script("Kora"));
+/// assert!(is_mixed_script("Kоra"));
+/// ```
+pub fn is_mixed_script(name: &str) -> bool {
+    let mut scripts = name.chars().filter_map(Script::of);
+    scripts
+        .next()
+        .is_some_and(|first| scripts.any(|script| script != first))
+}
+
+/// Rewrites a name in a single script, by replacing letters with their lookalikes, or returns
+/// `None` if some letter has no lookalike in any script the name could be written in.  The
+/// script of most of the name's letters is tried first, then the others, Latin first on a tie.
+///
+/// ```
+/// use markov_namegen::confusable::normalize;
+/// assert_eq!(normalize("Kоra").as_deref(), Some("Kora"));
+/// assert_eq!(normalize("Оlgа").as_deref(), Some("Olga"));
+/// assert_eq!(normalize("Ивaн").as_deref(), Some("Иван"));
+/// assert_eq!(normalize("Жora"), None);
+/// ```
+pub fn normalize(name: &str) -> Option<String> {
+    let mut scripts = [Script::Latin, Script::Cyrillic, Script::Greek].map(|script| {
+        (
+            script,
+            name.chars()
+                .filter(|&c| Script::of(c) == Some(script))
+                .count(),
+        )
+    });
+    scripts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    scripts
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .find_map(|(script, _)| name.chars().map(|c| lookalike(c, script)).collect())
+}
+
+/// The letter itself if it's in the script, or its lookalike in the script if it has one.
+fn lookalike(c: char, script: Script) -> Option<char> {
+    match Script::of(c) {
+        None => Some(c),
+        Some(of) if of == script => Some(c),
+        Some(of) => {
+            let column = |(latin, cyrillic, greek): (char, char, char), script| match script {
+                Script::Latin => latin,
+                Script::Cyrillic => cyrillic,
+                Script::Greek => greek,
+            };
+            LOOKALIKES
+                .iter()
+                .find(|&&row| column(row, of) == c)
+                .map(|&row| column(row, script))
+                .filter(|&lookalike| lookalike != '-')
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies() {
+        let mixed = String::from("Kоrа Vеl");
+        assert_eq!(
+            ConfusablePolicy::Normalize.apply(mixed.clone()).as_deref(),
+            Some("Kora Vel")
+        );
+        assert_eq!(ConfusablePolicy::Reject.apply(mixed), None);
+        assert_eq!(
+            ConfusablePolicy::Reject
+                .apply(String::from("Νίκος"))
+                .as_deref(),
+            Some("Νίκος")
+        );
+    }
+
+    #[test]
+    fn test_normalizing_into_greek_and_between_scripts() {
+        assert_eq!(normalize("Νίkος").as_deref(), Some("Νίκος"));
+        assert_eq!(normalize("Αрοллο").as_deref(), Some("Аролло"));
+        assert_eq!(normalize("ΤаΚΟ").as_deref(), Some("ТаКО"));
+        assert_eq!(normalize("Νίkος ζ").as_deref(), Some("Νίκος ζ"));
+    }
+}
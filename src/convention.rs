@@ -0,0 +1,349 @@
+//! One culture's naming convention: which generators make up a full name, and how the parts
+//! are put together.
+//!
+//! ```
+//! use markov_namegen::convention::{Casing, NameFormat, NameOrder, NamingConvention};
+//! use markov_namegen::epithet::EpithetGenerator;
+//! use markov_namegen::CharacterChainGenerator;
+//! use rand::SeedableRng;
+//! let given = CharacterChainGenerator::builder()
+//!     .train(vec!["ludwig", "friedrich", "otto", "wilhelm"].into_iter())
+//!     .build();
+//! let family = CharacterChainGenerator::builder()
+//!     .train(vec!["hammerstein", "falkenberg", "rosenthal"].into_iter())
+//!     .build();
+//! let mut convention = NamingConvention::new(given)
+//!     .with_family_names(family)
+//!     .with_epithets(EpithetGenerator::fantasy())
+//!     .with_format(NameFormat {
+//!         particles: vec![String::from("von")],
+//!         particle_probability: 0.5,
+//!         casing: Casing::Titlecase,
+//!         epithet_probability: 0.2,
+//!         ..NameFormat::default()
+//!     });
+//! let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+//! let name = convention.generate_full_name(&mut rng); // e.g. "Otto von Falkenthal"
+//! ```
+//!
+//! With the `serde` feature enabled, a `ConventionSpec` describes a whole convention, with
+//! the corpora and settings of its generators, in any format serde supports, and `build()`s it.
+use crate::epithet::EpithetSpec;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::postprocess::titlecase;
+use crate::registry::{BoxedGenerator, GeneratorSpec};
+use crate::state::GeneratorRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use std::fmt;
+
+/// Which part of a full name comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NameOrder {
+    /// "Ludwig von Hammerstein".  This is the default.
+    #[default]
+    GivenFirst,
+    /// "Hammerstein Ludwig", as in Chinese, Japanese, Korean or Hungarian names.
+    FamilyFirst,
+}
+
+/// How the generated parts of a full name are cased.  Particles and epithets are left as they
+/// are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Casing {
+    /// As the generators produce them.  This is the default.
+    #[default]
+    AsGenerated,
+    /// With each word capitalized (see `postprocess::titlecase`).
+    Titlecase,
+    Uppercase,
+    Lowercase,
+}
+
+impl Casing {
+    fn apply(self, name: String) -> String {
+        match self {
+            Casing::AsGenerated => name,
+            Casing::Titlecase => titlecase(name),
+            Casing::Uppercase => name.to_uppercase(),
+            Casing::Lowercase => name.to_lowercase(),
+        }
+    }
+}
+
+/// The rules for putting together a full name from its parts.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct NameFormat {
+    pub order: NameOrder,
+    /// Particles that may come before the family name ("von", "de la", "ibn"), one chosen at
+    /// random when one is used.
+    pub particles: Vec<String>,
+    /// The probability, from 0 to 1, that the family name gets a particle.
+    pub particle_probability: f64,
+    pub casing: Casing,
+    /// The probability, from 0 to 1, that an epithet is added at the end of the name.
+    pub epithet_probability: f64,
+}
+
+/// A naming convention: a generator of given names, perhaps one of family names and one of
+/// epithets, and a `NameFormat` for putting their output together.
+pub struct NamingConvention {
+    given: BoxedGenerator,
+    family: Option<BoxedGenerator>,
+    epithets: Option<BoxedGenerator>,
+    format: NameFormat,
+    rng: GeneratorRng,
+}
+
+impl NamingConvention {
+    /// A convention of given names alone, in the default format.
+    pub fn new(given: impl RandomTextGenerator + Send + 'static) -> Self {
+        NamingConvention {
+            given: Box::new(given),
+            family: None,
+            epithets: None,
+            format: NameFormat::default(),
+            rng: GeneratorRng::from_entropy(),
+        }
+    }
+    /// Adds family names.
+    pub fn with_family_names(mut self, family: impl RandomTextGenerator + Send + 'static) -> Self {
+        self.family = Some(Box::new(family));
+        self
+    }
+    /// Adds epithets, used as often as the format's `epithet_probability` says.
+    pub fn with_epithets(mut self, epithets: impl RandomTextGenerator + Send + 'static) -> Self {
+        self.epithets = Some(Box::new(epithets));
+        self
+    }
+    /// Sets the rules for putting a full name together.
+    pub fn with_format(mut self, format: NameFormat) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&format.particle_probability)
+                && (0.0..=1.0).contains(&format.epithet_probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.format = format;
+        self
+    }
+    /// Seeds the RNG that `generate_one()` makes the convention's own choices with.  Seed the
+    /// generators too, for reproducible names.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = GeneratorRng::from_seed(seed);
+        self
+    }
+
+    /// Generates a full name.  The generators use their own random state; `rng` makes the
+    /// convention's own choices, of particles and whether to add an epithet.
+    ///
+    /// Panics if one of the generators can't produce a name; use `try_generate_full_name()` if
+    /// you'd rather handle that as an error.
+    pub fn generate_full_name(&mut self, rng: &mut impl RngCore) -> String {
+        self.try_generate_full_name(rng)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a full name, or returns an error if one of the generators can't produce a
+    /// name.
+    pub fn try_generate_full_name(
+        &mut self,
+        rng: &mut impl RngCore,
+    ) -> Result<String, NamegenError> {
+        let format = &self.format;
+        let mut parts = vec![format.casing.apply(self.given.try_generate_one()?)];
+        if let Some(family) = &mut self.family {
+            let mut family_name = format.casing.apply(family.try_generate_one()?);
+            if rng.gen_bool(format.particle_probability) {
+                if let Some(particle) = format.particles.choose(rng) {
+                    family_name = format!("{} {}", particle, family_name);
+                }
+            }
+            match format.order {
+                NameOrder::GivenFirst => parts.push(family_name),
+                NameOrder::FamilyFirst => parts.insert(0, family_name),
+            }
+        }
+        if let Some(epithets) = &mut self.epithets {
+            if rng.gen_bool(format.epithet_probability) {
+                parts.push(epithets.try_generate_one()?);
+            }
+        }
+        Ok(parts.join(" "))
+    }
+}
+
+impl fmt::Debug for NamingConvention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamingConvention")
+            .field("given", &self.given.kind())
+            .field("family", &self.family.as_ref().map(|g| g.kind()))
+            .field("epithets", &self.epithets.as_ref().map(|g| g.kind()))
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Generates full names with the convention's own RNG (see `with_seed()`), for use wherever a
+/// generator is expected.  Use `generate_full_name()` to supply your own.
+impl RandomTextGenerator for NamingConvention {
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        let mut rng = std::mem::replace(&mut self.rng, GeneratorRng::from_seed(0));
+        let result = self.try_generate_full_name(&mut rng);
+        self.rng = rng;
+        result
+    }
+
+    fn kind(&self) -> &'static str {
+        "convention"
+    }
+
+    /// Lists the kinds of the generators, e.g. "given clusterchain, family characterchain".
+    fn config_summary(&self) -> String {
+        let mut summary = format!("given {}", self.given.kind());
+        if let Some(family) = &self.family {
+            summary.push_str(&format!(", family {}", family.kind()));
+        }
+        if let Some(epithets) = &self.epithets {
+            summary.push_str(&format!(", epithets {}", epithets.kind()));
+        }
+        summary
+    }
+}
+
+/// A description of a whole naming convention: specs for its generators and its format.
+/// With the `serde` feature enabled, it can be serialized and deserialized, e.g.:
+///
+/// ```json
+/// {
+///   "given": { "type": "clusterchain", "corpus": "german_given.txt" },
+///   "family": { "type": "characterchain", "corpus": "german_family.txt", "order": 4 },
+///   "epithets": { "templates": [["the {virtue}", 1.0]], "words": { "virtue": ["Bold"] } },
+///   "format": { "particles": ["von"], "particle_probability": 0.3, "casing": "titlecase" }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConventionSpec {
+    pub given: GeneratorSpec,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub family: Option<GeneratorSpec>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub epithets: Option<EpithetSpec>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub format: NameFormat,
+}
+
+impl ConventionSpec {
+    /// Reads the corpora and trains the generators, returning the first error encountered.
+    pub fn build(&self) -> Result<NamingConvention, NamegenError> {
+        if !(0.0..=1.0).contains(&self.format.particle_probability)
+            || !(0.0..=1.0).contains(&self.format.epithet_probability)
+        {
+            return Err(NamegenError::InvalidConfig(String::from(
+                "probabilities must be between 0.0 and 1.0",
+            )));
+        }
+        Ok(NamingConvention {
+            given: self.given.build()?,
+            family: self.family.as_ref().map(GeneratorSpec::build).transpose()?,
+            epithets: self
+                .epithets
+                .as_ref()
+                .map(|spec| Box::new(spec.build()) as BoxedGenerator),
+            format: self.format.clone(),
+            rng: GeneratorRng::from_entropy(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epithet::EpithetGenerator;
+    use crate::registry::GeneratorKind;
+    use crate::CharacterChainGenerator;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    fn single(name: &str) -> CharacterChainGenerator {
+        CharacterChainGenerator::builder()
+            .without_prior()
+            .train(vec![name].into_iter())
+            .build()
+    }
+
+    #[test]
+    fn test_parts_are_put_together() {
+        let mut convention = NamingConvention::new(single("ludwig"))
+            .with_family_names(single("hammer"))
+            .with_epithets(
+                EpithetGenerator::new()
+                    .with_template("the {virtue}", 1.0)
+                    .with_words("virtue", ["Bold"]),
+            )
+            .with_format(NameFormat {
+                order: NameOrder::FamilyFirst,
+                particles: vec![String::from("von")],
+                particle_probability: 1.0,
+                casing: Casing::Titlecase,
+                epithet_probability: 1.0,
+            });
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            convention.generate_full_name(&mut rng),
+            "von Hammer Ludwig the Bold"
+        );
+        assert_eq!(
+            convention.config_summary(),
+            "given characterchain, family characterchain, epithets epithet"
+        );
+        let mut plain = NamingConvention::new(single("ludwig"));
+        assert_eq!(plain.generate_full_name(&mut rng), "ludwig");
+    }
+
+    #[test]
+    fn test_conventions_build_from_specs() {
+        let spec = ConventionSpec {
+            given: GeneratorSpec::new(GeneratorKind::ClusterChain, "resources/romans.txt"),
+            family: Some(GeneratorSpec::new(
+                GeneratorKind::CharacterChain,
+                "resources/romans.txt",
+            )),
+            epithets: None,
+            format: NameFormat {
+                casing: Casing::Uppercase,
+                ..NameFormat::default()
+            },
+        };
+        let mut convention = spec.build().unwrap();
+        let name = convention.generate_one();
+        assert_eq!(name.split(' ').count(), 2);
+        assert_eq!(name, name.to_uppercase());
+        let mut bad = spec.clone();
+        bad.format.epithet_probability = 2.0;
+        assert!(matches!(bad.build(), Err(NamegenError::InvalidConfig(_))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_specs_survive_serialization() {
+        let json = r#"{
+            "given": { "type": "clusterchain", "corpus": "given.txt" },
+            "epithets": { "templates": [["the {virtue}", 1.0]], "words": { "virtue": ["Bold"] } },
+            "format": { "particles": ["von"], "particle_probability": 0.3, "casing": "titlecase" }
+        }"#;
+        let spec: ConventionSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(spec.format.casing, Casing::Titlecase);
+        assert_eq!(spec.format.order, NameOrder::GivenFirst);
+        assert_eq!(spec.family, None);
+        let round_trip: ConventionSpec =
+            serde_json::from_str(&serde_json::to_string(&spec).unwrap()).unwrap();
+        assert_eq!(round_trip, spec);
+    }
+}
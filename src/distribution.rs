@@ -0,0 +1,87 @@
+//! An adapter that makes a generator a `rand::distributions::Distribution<String>`, so that it
+//! plugs into the idioms of the rand ecosystem, like `rng.sample()` and `sample_iter()`.
+//!
+//! ```
+//! use markov_namegen::distribution::NameDistribution;
+//! use markov_namegen::CharacterChainGenerator;
+//! use rand::{rngs::StdRng, Rng, SeedableRng};
+//! let namegen = CharacterChainGenerator::builder()
+//!     .train(vec!["julius", "augustus", "tiberius", "claudius"].into_iter())
+//!     .build();
+//! let names = NameDistribution::new(&namegen);
+//! let mut rng = StdRng::seed_from_u64(7);
+//! let name: String = rng.sample(&names);
+//! let more: Vec<String> = (&mut rng).sample_iter(&names).take(10).collect();
+//! // the names depend only on the RNG, not on the generator's own
+//! let again: String = StdRng::seed_from_u64(7).sample(&names);
+//! assert_eq!(name, again);
+//! ```
+use crate::characterchain::generator::CharacterChainGenerator;
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::interface::RandomTextGenerator;
+use rand::distributions::Distribution;
+use rand::Rng;
+
+/// The names a generator can produce, as a `Distribution<String>`.  Each sample is drawn with
+/// the RNG it's given, by a copy of the generator seeded from it, so samples are independent:
+/// the generator's own RNG is not used, and its memory of past outputs, if any, is neither
+/// consulted nor added to.  Its pattern, filters and postprocessors all apply.
+///
+/// Sampling panics if the generator can't produce a name, as `generate_one()` does.
+#[derive(Debug, Clone, Copy)]
+pub struct NameDistribution<'g, G> {
+    generator: &'g G,
+}
+
+impl<'g, G> NameDistribution<'g, G> {
+    /// Makes the generator's names a distribution.
+    pub fn new(generator: &'g G) -> Self {
+        NameDistribution { generator }
+    }
+}
+
+impl Distribution<String> for NameDistribution<'_, CharacterChainGenerator> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        self.generator.fork_forgetful(rng.gen()).generate_one()
+    }
+}
+
+impl Distribution<String> for NameDistribution<'_, ClusterChainGenerator> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        self.generator.fork_forgetful(rng.gen()).generate_one()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sampling_leaves_the_generator_alone() {
+        let corpus = vec!["julius", "augustus", "tiberius", "claudius"];
+        let build = || {
+            ClusterChainGenerator::builder()
+                .with_seed(3)
+                .remember_outputs(true)
+                .train(corpus.clone().into_iter())
+                .build()
+        };
+        let mut sampled = build();
+        let first: Vec<String> = StdRng::seed_from_u64(1)
+            .sample_iter(NameDistribution::new(&sampled))
+            .take(5)
+            .collect();
+        let second: Vec<String> = StdRng::seed_from_u64(1)
+            .sample_iter(NameDistribution::new(&sampled))
+            .take(5)
+            .collect();
+        assert_eq!(first, second);
+        // neither the generator's RNG nor its memory was touched
+        let mut untouched = build();
+        for _ in 0..5 {
+            assert_eq!(sampled.generate_one(), untouched.generate_one());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+/// Embeds a corpus file in the binary at compile time and trains a builder on it, so that a
+/// shipped game needs no corpus files at runtime.
+///
+/// The path is relative to the root of your crate (the directory with its `Cargo.toml`), not
+/// to the source file the macro is used in, and a missing file is a compile error rather than
+/// a runtime one.  With just a path, the corpus is trained into a fresh
+/// `CharacterChainGenerator::builder()`; pass a builder first to use another generator or
+/// other settings.  Either way, the result is the trained builder, ready for `.build()`.
+///
+/// ```
+/// use markov_namegen::{train_embedded, ClusterChainGenerator, RandomTextGenerator};
+/// let mut namegen = train_embedded!("resources/romans.txt").build();
+/// let name = namegen.generate_one();
+///
+/// let mut namegen = train_embedded!(
+///     ClusterChainGenerator::builder().with_order(2),
+///     "resources/romans.txt"
+/// )
+/// .build();
+/// let name = namegen.generate_one();
+/// ```
+#[macro_export]
+macro_rules! train_embedded {
+    ($path:literal) => {
+        $crate::train_embedded!($crate::CharacterChainGenerator::builder(), $path)
+    };
+    ($builder:expr, $path:literal) => {
+        $builder.train(
+            ::core::include_str!(::core::concat!(
+                ::core::env!("CARGO_MANIFEST_DIR"),
+                "/",
+                $path
+            ))
+            .lines(),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RandomTextGenerator;
+    use std::fs;
+
+    #[test]
+    fn test_embedded_corpus_matches_file() {
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut embedded = crate::train_embedded!(
+            crate::CharacterChainGenerator::builder().with_seed(7),
+            "resources/romans.txt"
+        )
+        .build();
+        let mut loaded = crate::CharacterChainGenerator::builder()
+            .with_seed(7)
+            .train(corpus.lines())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(embedded.generate_one(), loaded.generate_one());
+        }
+    }
+
+    #[cfg(feature = "embedded-corpus")]
+    #[test]
+    fn test_default_generator_is_trained_on_english_names() {
+        let mut namegen = crate::CharacterChainGenerator::default();
+        let corpus = fs::read_to_string("resources/english.txt").unwrap();
+        assert!(corpus.lines().count() > 200);
+        for _i in 0..10 {
+            assert!(!namegen.generate_one().is_empty());
+        }
+    }
+}
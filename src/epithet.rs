@@ -0,0 +1,241 @@
+//! Epithets and titles ("the Bold", "Stormcaller of the North") from templates and word lists.
+//!
+//! ```
+//! use markov_namegen::epithet::EpithetGenerator;
+//! use markov_namegen::RandomTextGenerator;
+//! let mut epithets = EpithetGenerator::new()
+//!     .with_template("the {virtue}", 2.0)
+//!     .with_template("{element}caller of the {direction}", 1.0)
+//!     .with_words("virtue", ["Bold", "Wise", "Just"])
+//!     .with_words("element", ["Storm", "Flame", "Frost"])
+//!     .with_words("direction", ["North", "South"])
+//!     .with_seed(1);
+//! let epithet = epithets.generate_one();
+//! // or one of the ready-made sets
+//! let title = EpithetGenerator::fantasy().generate_one();
+//! ```
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::postprocess::weighted_choice;
+use crate::state::GeneratorRng;
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A generator of epithets from weighted templates, in which each `{list}` placeholder is
+/// replaced by a word drawn at random from the word list of that name.  Write `{{` and `}}` for
+/// literal braces.
+pub struct EpithetGenerator {
+    templates: Vec<(String, f64)>,
+    words: BTreeMap<String, Vec<String>>,
+    rng: GeneratorRng,
+}
+
+impl Default for EpithetGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EpithetGenerator {
+    /// A generator with no templates or word lists.
+    pub fn new() -> Self {
+        EpithetGenerator {
+            templates: Vec::new(),
+            words: BTreeMap::new(),
+            rng: GeneratorRng::from_entropy(),
+        }
+    }
+    /// Templates and words for heroic fantasy: "the Bold", "Stormcaller", "Breaker of Chains",
+    /// "the Twice-Crowned" and the like.
+    pub fn fantasy() -> Self {
+        Self::new()
+            .with_template("the {virtue}", 4.0)
+            .with_template("the {number}-{deed}", 1.0)
+            .with_template("{element}{agent}", 2.0)
+            .with_template("{element}{agent} of the {direction}", 1.0)
+            .with_template("{deed_noun} of {objects}", 2.0)
+            .with_words(
+                "virtue",
+                [
+                    "Bold", "Wise", "Just", "Fair", "Cruel", "Silent", "Unbowed", "Old", "Red",
+                ],
+            )
+            .with_words("number", ["Twice", "Thrice", "Never"])
+            .with_words("deed", ["Crowned", "Born", "Slain", "Blessed", "Exiled"])
+            .with_words(
+                "element",
+                ["Storm", "Flame", "Frost", "Stone", "Shadow", "Sun"],
+            )
+            .with_words(
+                "agent",
+                ["caller", "born", "bane", "walker", "singer", "brand"],
+            )
+            .with_words("direction", ["North", "South", "East", "West", "Deep"])
+            .with_words(
+                "deed_noun",
+                ["Breaker", "Slayer", "Keeper", "Bane", "Scourge"],
+            )
+            .with_words(
+                "objects",
+                ["Chains", "Kings", "Dragons", "the Wild", "Oaths"],
+            )
+    }
+    /// Adds a template with a relative weight.
+    pub fn with_template(mut self, template: &str, weight: f64) -> Self {
+        assert!(weight > 0.0, "Weights must be greater than zero.");
+        self.templates.push((template.to_string(), weight));
+        self
+    }
+    /// Adds words to the named word list, creating it if need be.
+    pub fn with_words<S: AsRef<str>>(
+        mut self,
+        list: &str,
+        words: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.words
+            .entry(list.to_string())
+            .or_default()
+            .extend(words.into_iter().map(|word| word.as_ref().to_string()));
+        self
+    }
+    /// Seeds the generator for reproducible, cross-platform output.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = GeneratorRng::from_seed(seed);
+        self
+    }
+
+    /// Fills in a template's placeholders with random words.
+    fn fill(&mut self, template: &str) -> Result<String, NamegenError> {
+        let mut filled = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            filled.push_str(&rest[..i]);
+            let (brace, after) = rest[i..].split_at(1);
+            if let Some(after) = after.strip_prefix(brace) {
+                filled.push_str(brace);
+                rest = after;
+                continue;
+            }
+            let end = match brace {
+                "{" => after.find('}'),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                NamegenError::InvalidConfig(format!("unmatched brace in template '{}'", template))
+            })?;
+            let list = &after[..end];
+            let word = self
+                .words
+                .get(list)
+                .and_then(|words| words.choose(&mut self.rng))
+                .ok_or_else(|| {
+                    NamegenError::InvalidConfig(format!("no words in the list '{}'", list))
+                })?;
+            filled.push_str(word);
+            rest = &after[end + 1..];
+        }
+        filled.push_str(rest);
+        Ok(filled)
+    }
+}
+
+/// A description of an `EpithetGenerator`: its templates, word lists and seed.  With the
+/// `serde` feature enabled, it can be serialized and deserialized, e.g. as part of a
+/// `NamingConvention`'s `ConventionSpec`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EpithetSpec {
+    /// Templates and their relative weights.
+    pub templates: Vec<(String, f64)>,
+    /// Word lists by name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub words: BTreeMap<String, Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: Option<u64>,
+}
+
+impl EpithetSpec {
+    /// Builds the generator.
+    pub fn build(&self) -> EpithetGenerator {
+        let mut generator = EpithetGenerator::new();
+        for (template, weight) in &self.templates {
+            generator = generator.with_template(template, *weight);
+        }
+        for (list, words) in &self.words {
+            generator = generator.with_words(list, words);
+        }
+        match self.seed {
+            Some(seed) => generator.with_seed(seed),
+            None => generator,
+        }
+    }
+}
+
+impl fmt::Debug for EpithetGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EpithetGenerator")
+            .field("templates", &self.templates)
+            .field("words", &self.words)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for EpithetGenerator {
+    /// Fills in a random template, or returns an error if there are no templates, or the one
+    /// chosen is malformed or names a word list that is empty or doesn't exist.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        let template = weighted_choice(&self.templates, &mut self.rng)
+            .ok_or_else(|| NamegenError::InvalidConfig(String::from("there are no templates")))?
+            .to_string();
+        self.fill(&template)
+    }
+
+    fn kind(&self) -> &'static str {
+        "epithet"
+    }
+
+    /// Counts the templates and word lists, e.g. "5 templates, 8 word lists".
+    fn config_summary(&self) -> String {
+        format!(
+            "{} templates, {} word lists",
+            self.templates.len(),
+            self.words.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_are_filled() {
+        let mut epithets = EpithetGenerator::new()
+            .with_template("{adjective}{{{noun}}}", 1.0)
+            .with_words("adjective", ["Bold"])
+            .with_words("noun", ["Heart"])
+            .with_seed(3);
+        assert_eq!(epithets.generate_one(), "Bold{Heart}");
+        let mut fantasy = EpithetGenerator::fantasy().with_seed(3);
+        assert_eq!(fantasy.config_summary(), "5 templates, 8 word lists");
+        for _ in 0..50 {
+            let epithet = fantasy.generate_one();
+            assert!(!epithet.contains(['{', '}']), "{}", epithet);
+        }
+    }
+
+    #[test]
+    fn test_bad_templates_are_errors() {
+        let mut unknown = EpithetGenerator::new().with_template("the {virtue}", 1.0);
+        assert_eq!(
+            unknown.try_generate_one(),
+            Err(NamegenError::InvalidConfig(String::from(
+                "no words in the list 'virtue'"
+            )))
+        );
+        let mut unmatched = EpithetGenerator::new().with_template("the {virtue", 1.0);
+        assert!(unmatched.try_generate_one().is_err());
+        assert!(EpithetGenerator::new().try_generate_one().is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors that can occur while building a generator or generating text with one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamegenError {
+    /// The model has no trained transitions at all, e.g. because `.train()` was never called or
+    /// every training sequence was empty.
+    EmptyModel,
+    /// Generation reached a sequence for which the model knows no possible next state, or, for
+    /// `generate_with_length()`, no path from the start of a word ends at the requested length.
+    DeadEnd(String),
+    /// The regex pattern supplied with `.with_pattern()` could not be compiled.
+    InvalidPattern(String),
+    /// No candidate matched the pattern within the number of attempts set by
+    /// `.with_max_attempts()`.
+    MaxAttemptsExceeded(usize),
+    /// A callback set with `.on_reject()` aborted generation after this many attempts.
+    Aborted(usize),
+    /// A training sample was rejected, e.g. under `ReservedCharPolicy::Error`.
+    InvalidTrainingData(String),
+    /// A `MultilingualGenerator` was asked for a language it wasn't trained on.
+    UnknownLanguage(String),
+    /// A `Registry` was asked for a generator it doesn't have.
+    UnknownGenerator(String),
+    /// A builder setting is invalid, e.g. an out-of-range order, an unreadable checkpoint or
+    /// corpus file, or a `GeneratorSpec` that can't be built.  Reported by `try_build()`.
+    InvalidConfig(String),
+}
+
+impl fmt::Display for NamegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamegenError::EmptyModel => write!(f, "the model has not been trained on any data"),
+            NamegenError::DeadEnd(sequence) => {
+                write!(f, "no known transition follows the sequence '{}'", sequence)
+            }
+            NamegenError::InvalidPattern(message) => {
+                write!(f, "invalid regex pattern: {}", message)
+            }
+            NamegenError::MaxAttemptsExceeded(attempts) => write!(
+                f,
+                "no candidate matched the pattern in {} attempts",
+                attempts
+            ),
+            NamegenError::Aborted(attempts) => {
+                write!(f, "generation was aborted after {} attempts", attempts)
+            }
+            NamegenError::InvalidTrainingData(message) => {
+                write!(f, "invalid training data: {}", message)
+            }
+            NamegenError::UnknownLanguage(language) => {
+                write!(
+                    f,
+                    "the generator was not trained on language '{}'",
+                    language
+                )
+            }
+            NamegenError::UnknownGenerator(key) => write!(f, "no generator named '{}'", key),
+            NamegenError::InvalidConfig(message) => {
+                write!(f, "invalid generator configuration: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for NamegenError {}
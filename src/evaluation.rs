@@ -0,0 +1,288 @@
+//! Measuring how a generated batch relates to the names the generator was trained on, and
+//! sorting through the batch itself.
+//!
+//! A model that only ever echoes its training names is useless, and one whose names are all
+//! novel may have drifted too far from the corpus to sound right.  `novelty()` tells you where
+//! a batch falls between the two, so you can adjust the order and prior until it lands where
+//! you want: raising the order or lowering the prior makes echoes more common.
+//!
+//! ```
+//! use markov_namegen::{evaluation, ClusterChainGenerator, RandomTextGenerator};
+//! let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+//! let mut namegen = ClusterChainGenerator::builder()
+//!     .with_seed(1)
+//!     .train(corpus.lines())
+//!     .build();
+//! let batch: Vec<String> = (0..100).map(|_| namegen.generate_one()).collect();
+//! let report = evaluation::novelty(&batch, corpus.lines());
+//! assert_eq!(report.total(), 100);
+//! println!("{}", report);
+//! ```
+//!
+//! A big batch tends to hold families of near-duplicates ("Marcus", "Marcius", "Marcinus").
+//! `cluster()` groups them, so you can pick one name from each:
+//!
+//! ```
+//! use markov_namegen::evaluation::{cluster, Similarity};
+//! let batch = ["Marcus", "Julia", "Marcius", "Julian", "Titus"];
+//! let clusters = cluster(&batch, Similarity::EditDistance(1));
+//! assert_eq!(clusters, vec![vec!["Marcus", "Marcius"], vec!["Julia", "Julian"], vec!["Titus"]]);
+//! let representatives: Vec<&String> = clusters.iter().map(|names| &names[0]).collect();
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+
+/// How many names of a batch exactly match a training name, differ from one by a single edit,
+/// or are novel.  Displays as a line of percentages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoveltyReport {
+    /// Names identical to a training name (ignoring case).
+    pub exact: usize,
+    /// Names one insertion, deletion or substitution away from a training name, but not
+    /// identical to any.
+    pub near: usize,
+    /// Names at least two edits away from every training name.
+    pub novel: usize,
+}
+
+impl NoveltyReport {
+    /// The number of names in the batch.
+    pub fn total(&self) -> usize {
+        self.exact + self.near + self.novel
+    }
+    /// The fraction of the batch that exactly matches a training name.
+    pub fn exact_rate(&self) -> f64 {
+        self.rate(self.exact)
+    }
+    /// The fraction of the batch within one edit of a training name, but not identical to one.
+    pub fn near_rate(&self) -> f64 {
+        self.rate(self.near)
+    }
+    /// The fraction of the batch at least two edits away from every training name.
+    pub fn novel_rate(&self) -> f64 {
+        self.rate(self.novel)
+    }
+    fn rate(&self, count: usize) -> f64 {
+        if self.total() == 0 {
+            0.0
+        } else {
+            count as f64 / self.total() as f64
+        }
+    }
+}
+
+impl fmt::Display for NoveltyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} names: {:.1}% exact, {:.1}% within one edit, {:.1}% novel",
+            self.total(),
+            self.exact_rate() * 100.0,
+            self.near_rate() * 100.0,
+            self.novel_rate() * 100.0
+        )
+    }
+}
+
+/// Sorts each name in a generated batch by how close it comes to the training names: an exact
+/// match, one edit (an insertion, deletion or substitution of a character) away from one, or
+/// novel.  Names are compared ignoring case, since the generators lowercase their training
+/// data and capitalize their output.
+pub fn novelty(
+    batch: &[impl AsRef<str>],
+    training: impl Iterator<Item = impl Deref<Target = str>>,
+) -> NoveltyReport {
+    let training: HashSet<Vec<char>> = training
+        .map(|name| name.trim().to_lowercase().chars().collect())
+        .collect();
+    // only names within one character of the same length can be one edit apart
+    let mut by_length: HashMap<usize, Vec<&Vec<char>>> = HashMap::new();
+    for name in &training {
+        by_length.entry(name.len()).or_default().push(name);
+    }
+    let mut report = NoveltyReport::default();
+    for name in batch {
+        let name: Vec<char> = name.as_ref().to_lowercase().chars().collect();
+        if training.contains(&name) {
+            report.exact += 1;
+            continue;
+        }
+        let near = (name.len().saturating_sub(1)..=name.len() + 1)
+            .filter_map(|length| by_length.get(&length))
+            .flatten()
+            .any(|known| within_one_edit(&name, known));
+        if near {
+            report.near += 1;
+        } else {
+            report.novel += 1;
+        }
+    }
+    report
+}
+
+/// When `cluster()` considers two names similar.  Names are always compared ignoring case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Similarity {
+    /// Names at most this many insertions, deletions or substitutions of a character apart.
+    /// Similarity is chained, so a cluster may hold names further apart than this, as long as
+    /// each is this close to some other name in it.
+    EditDistance(usize),
+    /// Names beginning with the same this many characters.  Names shorter than this are only
+    /// similar to identical names.
+    SharedPrefix(usize),
+}
+
+/// Groups a batch of names into clusters of similar ones, so that you can keep one
+/// representative of each.  Clusters are listed in the order of their first names in the
+/// batch, and the names in each cluster keep their order in the batch, so the first name of
+/// each cluster is the one the generator produced first.  Comparing every pair of names by
+/// edit distance takes time quadratic in the size of the batch, which is fine for thousands
+/// of names but not millions; comparing prefixes takes linear time.
+pub fn cluster(batch: &[impl AsRef<str>], similarity: Similarity) -> Vec<Vec<String>> {
+    let names: Vec<Vec<char>> = batch
+        .iter()
+        .map(|name| name.as_ref().to_lowercase().chars().collect())
+        .collect();
+    // a union-find forest over the names, where each root is the earliest name of its tree
+    let mut parent: Vec<usize> = (0..names.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    match similarity {
+        Similarity::EditDistance(distance) => {
+            for i in 0..names.len() {
+                for j in 0..i {
+                    if names[i].len().abs_diff(names[j].len()) <= distance
+                        && edit_distance(&names[i], &names[j]) <= distance
+                    {
+                        let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                        parent[a.max(b)] = a.min(b);
+                    }
+                }
+            }
+        }
+        Similarity::SharedPrefix(length) => {
+            let mut first_with: HashMap<&[char], usize> = HashMap::new();
+            for (i, name) in names.iter().enumerate() {
+                let prefix = &name[..length.min(name.len())];
+                parent[i] = *first_with.entry(prefix).or_insert(i);
+            }
+        }
+    }
+    let mut clusters: Vec<Vec<String>> = Vec::new();
+    let mut cluster_of_root: HashMap<usize, usize> = HashMap::new();
+    for (i, name) in batch.iter().enumerate() {
+        let r = root(&mut parent, i);
+        let index = *cluster_of_root.entry(r).or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[index].push(name.as_ref().to_string());
+    }
+    clusters
+}
+
+/// The Levenshtein distance between two sequences: the fewest insertions, deletions and
+/// substitutions that turn one into the other.
+pub(crate) fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Whether two sequences are at most one insertion, deletion or substitution apart.
+pub(crate) fn within_one_edit<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if longer.len() - shorter.len() > 1 {
+        return false;
+    }
+    let prefix = shorter
+        .iter()
+        .zip(longer)
+        .take_while(|(x, y)| x == y)
+        .count();
+    if shorter.len() == longer.len() {
+        // everything after the one substitution must match
+        prefix == shorter.len() || shorter[prefix + 1..] == longer[prefix + 1..]
+    } else {
+        // everything after the one insertion must match
+        shorter[prefix..] == longer[prefix + 1..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_edit() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        assert!(within_one_edit(&chars("julius"), &chars("julius")));
+        assert!(!within_one_edit(&chars("julius"), &chars("julian")));
+        assert!(within_one_edit(&chars("julius"), &chars("iulius")));
+        assert!(within_one_edit(&chars("julius"), &chars("julios")));
+        assert!(within_one_edit(&chars("julius"), &chars("julus")));
+        assert!(within_one_edit(&chars("julius"), &chars("juliuss")));
+        assert!(!within_one_edit(&chars("julius"), &chars("juliusus")));
+        assert!(!within_one_edit(&chars("marcus"), &chars("marcia")));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+        assert_eq!(edit_distance(&chars("julius"), &chars("julius")), 0);
+    }
+
+    #[test]
+    fn test_clusters_chain_and_keep_batch_order() {
+        let batch = vec!["Cassia", "Brutus", "Cassio", "Bruttus", "Casso", "Tullia"];
+        let clusters = cluster(&batch, Similarity::EditDistance(1));
+        // "Cassia" and "Casso" are two edits apart, but both are one edit from "Cassio"
+        assert_eq!(
+            clusters,
+            vec![
+                vec!["Cassia", "Cassio", "Casso"],
+                vec!["Brutus", "Bruttus"],
+                vec!["Tullia"]
+            ]
+        );
+        let clusters = cluster(&batch, Similarity::SharedPrefix(5));
+        assert_eq!(clusters.len(), 5);
+        assert_eq!(clusters[0], vec!["Cassia", "Cassio"]);
+    }
+
+    #[test]
+    fn test_novelty_sorts_the_batch() {
+        let training = ["Julius", "Marcus", "Lucius"];
+        let batch = ["Julius", "Marcos", "Lucia", "Tiberius", "MARCUS"];
+        let report = novelty(&batch, training.into_iter());
+        assert_eq!(
+            report,
+            NoveltyReport {
+                exact: 2,
+                near: 1,
+                novel: 2
+            }
+        );
+        assert_eq!(report.exact_rate(), 0.4);
+        assert_eq!(
+            report.to_string(),
+            "5 names: 40.0% exact, 20.0% within one edit, 40.0% novel"
+        );
+    }
+}
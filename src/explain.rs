@@ -0,0 +1,165 @@
+//! Traces of how a name was generated, step by step, for debugging weird outputs.
+//!
+//! ```
+//! use markov_namegen::CharacterChainGenerator;
+//! let mut namegen = CharacterChainGenerator::builder()
+//!     .with_seed(1)
+//!     .train(vec!["julius", "marcus", "lucius", "tiberius"].into_iter())
+//!     .build();
+//! let explained = namegen.generate_explained();
+//! // one step per letter, and one more for the end of the word
+//! assert_eq!(explained.steps.len(), explained.name.chars().count() + 1);
+//! println!("{}", explained);
+//! ```
+use crate::model::MarkovModel;
+use std::fmt;
+use std::hash::Hash;
+
+/// How many of the most probable candidates the `Display` impl shows for each step.
+const SHOWN_CANDIDATES: usize = 3;
+
+/// A generated name, with a trace of every step the model took to generate it.  Displays as
+/// one line per step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainedName {
+    /// The generated name, after postprocessing.
+    pub name: String,
+    /// One step for each letter (or cluster) of the name before postprocessing, and a last one
+    /// choosing the end of the word, which is shown as "#".
+    pub steps: Vec<ExplainedStep>,
+}
+
+/// One step in the generation of a name: what the model had to choose from, and what it chose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainedStep {
+    /// The letter (or cluster) that was chosen, or "#" for the end of the word.
+    pub chosen: String,
+    /// The context the model looked up the candidates for: the longest tail-end of the name so
+    /// far that it has seen in training, starting with "#" if it reaches back to the start of
+    /// the word.
+    pub context: Vec<String>,
+    /// Whether the context is shorter than the model's order allows, because it had never seen
+    /// the longer ones (the "back-off").
+    pub backed_off: bool,
+    /// Every candidate the model could have chosen, with its probability, in sorted order.
+    pub candidates: Vec<(String, f64)>,
+    /// The probability of the candidate that was chosen.
+    pub probability: f64,
+    /// Whether the chosen transition was never seen in training, and was only possible thanks
+    /// to the prior.  This is judged by its weight being less than one observation, which is
+    /// true of any prior that's a fraction of one (as all sensible priors are).
+    pub unseen: bool,
+}
+
+/// Traces the steps of a sequence generated from the model, including the beginning- and
+/// end-of-word markers, labelling states with `label`.
+pub(crate) fn explain<T>(
+    model: &MarkovModel<T>,
+    sequence: &[T],
+    label: impl Fn(&T) -> String,
+) -> Vec<ExplainedStep>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    (1..sequence.len())
+        .filter_map(|i| {
+            let (context, transitions) = model.best_context(&sequence[..i])?;
+            let total: f64 = transitions.values().sum();
+            let weight = transitions.get(&sequence[i]).copied().unwrap_or(0.0);
+            Some(ExplainedStep {
+                chosen: label(&sequence[i]),
+                context: context.iter().map(&label).collect(),
+                backed_off: context.len() < model.order.min(i),
+                candidates: transitions
+                    .iter()
+                    .map(|(state, weight)| (label(state), weight / total))
+                    .collect(),
+                probability: weight / total,
+                unseen: weight < 1.0,
+            })
+        })
+        .collect()
+}
+
+/// A generated name together with the choices the model made to generate it, which
+/// `replay()` can make again to reproduce the name without knowing the seed or how many names
+/// came before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedName {
+    /// The generated name, after postprocessing.
+    pub name: String,
+    /// At each step, the index of the letter (or cluster) chosen among the candidates the
+    /// model offered, in their sorted order, the last choice being the end of the word.  The
+    /// indices only make sense for the same trained model (same training data and settings).
+    pub choices: Vec<usize>,
+}
+
+impl fmt::Display for ExplainedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        for step in &self.steps {
+            let mut likeliest: Vec<&(String, f64)> = step.candidates.iter().collect();
+            likeliest.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let likeliest: Vec<String> = likeliest
+                .iter()
+                .take(SHOWN_CANDIDATES)
+                .map(|(state, probability)| format!("{} {:.2}", state, probability))
+                .collect();
+            write!(
+                f,
+                "  {} -> {} ({:.3}, of {} candidates: {}",
+                step.context.concat(),
+                step.chosen,
+                step.probability,
+                step.candidates.len(),
+                likeliest.join(", ")
+            )?;
+            if step.candidates.len() > SHOWN_CANDIDATES {
+                write!(f, ", ...")?;
+            }
+            write!(f, ")")?;
+            if step.backed_off {
+                write!(f, " backed off")?;
+            }
+            if step.unseen {
+                write!(f, " unseen")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multimarkov::MultiMarkov;
+
+    #[test]
+    fn test_steps_show_contexts_and_candidates() {
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .with_prior(0.5)
+            .train(
+                vec!["#ab#", "#ac#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        let steps = explain(&model, &['#', 'a', 'c', 'b', '#'], |c| c.to_string());
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0].context, vec!["#"]);
+        assert!(!steps[0].backed_off);
+        assert_eq!(steps[1].context, vec!["#", "a"]);
+        assert_eq!(steps[1].chosen, "c");
+        assert!(!steps[1].unseen);
+        // "ac" was followed by '#' in training, never by 'b', and "cb" was never seen at all
+        assert!(steps[2].unseen);
+        assert_eq!(steps[3].context, vec!["b"]);
+        assert!(steps[3].backed_off);
+        let total: f64 = steps[2].candidates.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::model::MarkovModel;
+use std::collections::BTreeSet;
+use std::hash::Hash;
+use std::io::{self, Write};
+
+/// A generated name together with some statistics about how it was generated, for curating
+/// long lists of names by hand.  Use the generators' `generate_records()` to produce them, and
+/// `write_json()` or `write_markdown()` to save them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameRecord {
+    /// The generated name, after postprocessing.
+    pub name: String,
+    /// The natural log of the probability that the model generates this name's letters (before
+    /// postprocessing).  Names closer to zero are more typical of the training data; very
+    /// negative ones are more unusual.
+    pub log_probability: f64,
+    /// How many candidates were generated, including this one, before one was accepted.
+    pub attempts: usize,
+    /// The length of the name, in characters.
+    pub length: usize,
+    /// The clusters (for `ClusterChainGenerator`) or characters (for `CharacterChainGenerator`)
+    /// the name was built from, before postprocessing.
+    pub clusters: Vec<String>,
+}
+
+/// Writes the records as a JSON array of objects.  A log-probability that isn't finite, as for
+/// a name drawn by strict back-off or from the word-final table that the model alone rates
+/// impossible, is written as `null`, since JSON has no infinity.
+///
+/// ```
+/// use markov_namegen::export::{write_json, NameRecord};
+/// let record = NameRecord {
+///     name: String::from("julius"),
+///     log_probability: -4.5,
+///     attempts: 1,
+///     length: 6,
+///     clusters: vec![String::from("j"), String::from("u"), String::from("l")],
+/// };
+/// let mut json = Vec::new();
+/// write_json(&[record], &mut json).unwrap();
+/// assert!(String::from_utf8(json).unwrap().contains(r#""name": "julius""#));
+/// ```
+pub fn write_json(records: &[NameRecord], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (i, record) in records.iter().enumerate() {
+        let clusters: Vec<String> = record.clusters.iter().map(|c| json_string(c)).collect();
+        writeln!(out, "  {{")?;
+        writeln!(out, "    \"name\": {},", json_string(&record.name))?;
+        writeln!(
+            out,
+            "    \"log_probability\": {},",
+            json_number(record.log_probability, 4)
+        )?;
+        writeln!(out, "    \"attempts\": {},", record.attempts)?;
+        writeln!(out, "    \"length\": {},", record.length)?;
+        writeln!(out, "    \"clusters\": [{}]", clusters.join(", "))?;
+        let separator = if i + 1 < records.len() { "," } else { "" };
+        writeln!(out, "  }}{}", separator)?;
+    }
+    writeln!(out, "]")
+}
+
+/// Writes the records as a Markdown table, with the clusters separated by middle dots.
+pub fn write_markdown(records: &[NameRecord], out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "| Name | Log-probability | Attempts | Length | Clusters |"
+    )?;
+    writeln!(out, "|---|---:|---:|---:|---|")?;
+    for record in records {
+        writeln!(
+            out,
+            "| {} | {:.4} | {} | {} | {} |",
+            markdown_cell(&record.name),
+            record.log_probability,
+            record.attempts,
+            record.length,
+            markdown_cell(&record.clusters.join("·"))
+        )?;
+    }
+    Ok(())
+}
+
+/// Describes a model as a node-link graph in JSON, the format d3's force layouts (and
+/// networkx's `node_link_graph()`) read, for the generators' `to_json_graph()`.  There is a
+/// node for each context and for each state, and a link from a context to each state that may
+/// follow it with at least `min_probability`.
+pub(crate) fn json_graph<T>(
+    model: &MarkovModel<T>,
+    min_probability: f64,
+    label: impl Fn(&[T]) -> String,
+) -> String
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    let mut contexts: Vec<_> = model.contexts().collect();
+    contexts.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut states = BTreeSet::new();
+    let mut links = Vec::new();
+    for (context, transitions) in &contexts {
+        let total: f64 = transitions.values().sum();
+        for (state, weight) in transitions.iter() {
+            let probability = weight / total;
+            if probability >= min_probability {
+                states.insert(state);
+                links.push(format!(
+                    "    {{\"source\": {}, \"target\": {}, \"weight\": {}, \"probability\": {}}}",
+                    json_string(&format!("context:{}", label(context))),
+                    json_string(&format!("state:{}", label(std::slice::from_ref(state)))),
+                    weight,
+                    json_number(probability, 6)
+                ));
+            }
+        }
+    }
+    let node = |kind: &str, label: String| {
+        format!(
+            "    {{\"id\": {}, \"label\": {}, \"kind\": \"{}\"}}",
+            json_string(&format!("{}:{}", kind, label)),
+            json_string(&label),
+            kind
+        )
+    };
+    let nodes: Vec<String> = contexts
+        .iter()
+        .map(|(context, _)| node("context", label(context)))
+        .chain(
+            states
+                .into_iter()
+                .map(|state| node("state", label(std::slice::from_ref(state)))),
+        )
+        .collect();
+    format!(
+        "{{\n  \"directed\": true,\n  \"nodes\": [\n{}\n  ],\n  \"links\": [\n{}\n  ]\n}}\n",
+        nodes.join(",\n"),
+        links.join(",\n")
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A number with the given number of decimals, or `null` if it isn't finite.
+fn json_number(value: f64, decimals: usize) -> String {
+    if value.is_finite() {
+        format!("{:.*}", decimals, value)
+    } else {
+        String::from("null")
+    }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<NameRecord> {
+        vec![
+            NameRecord {
+                name: String::from("ab"),
+                log_probability: -1.0,
+                attempts: 2,
+                length: 2,
+                clusters: vec![String::from("a"), String::from("b")],
+            },
+            NameRecord {
+                name: String::from("say \"hi|\""),
+                log_probability: -0.5,
+                attempts: 1,
+                length: 9,
+                clusters: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut out = Vec::new();
+        write_json(&records(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"[
+  {
+    "name": "ab",
+    "log_probability": -1.0000,
+    "attempts": 2,
+    "length": 2,
+    "clusters": ["a", "b"]
+  },
+  {
+    "name": "say \"hi|\"",
+    "log_probability": -0.5000,
+    "attempts": 1,
+    "length": 9,
+    "clusters": []
+  }
+]
+"#
+        );
+    }
+
+    #[test]
+    fn test_write_json_without_infinities() {
+        let mut records = records();
+        records[0].log_probability = f64::NEG_INFINITY;
+        records[1].log_probability = f64::NAN;
+        let mut out = Vec::new();
+        write_json(&records, &mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert_eq!(json.matches(r#""log_probability": null,"#).count(), 2);
+        assert!(!json.contains("inf") && !json.contains("NaN"));
+    }
+
+    #[test]
+    fn test_json_graph() {
+        use multimarkov::MultiMarkov;
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(1)
+            .without_prior()
+            .train(vec!["#ab#", "#a#"].into_iter().map(|s| s.chars().collect()))
+            .build()
+            .into();
+        let graph = json_graph(&model, 0.0, |states| states.iter().collect());
+        assert_eq!(
+            graph,
+            r##"{
+  "directed": true,
+  "nodes": [
+    {"id": "context:#", "label": "#", "kind": "context"},
+    {"id": "context:a", "label": "a", "kind": "context"},
+    {"id": "context:b", "label": "b", "kind": "context"},
+    {"id": "state:#", "label": "#", "kind": "state"},
+    {"id": "state:a", "label": "a", "kind": "state"},
+    {"id": "state:b", "label": "b", "kind": "state"}
+  ],
+  "links": [
+    {"source": "context:#", "target": "state:a", "weight": 2, "probability": 1.000000},
+    {"source": "context:a", "target": "state:#", "weight": 1, "probability": 0.500000},
+    {"source": "context:a", "target": "state:b", "weight": 1, "probability": 0.500000},
+    {"source": "context:b", "target": "state:#", "weight": 1, "probability": 1.000000}
+  ]
+}
+"##
+        );
+        assert!(!json_graph(&model, 0.6, |states| states.iter().collect()).contains("state:b"));
+    }
+
+    #[test]
+    fn test_write_markdown() {
+        let mut out = Vec::new();
+        write_markdown(&records(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "| Name | Log-probability | Attempts | Length | Clusters |\n\
+             |---|---:|---:|---:|---|\n\
+             | ab | -1.0000 | 2 | 2 | a·b |\n\
+             | say \"hi\\|\" | -0.5000 | 1 | 9 |  |\n"
+        );
+    }
+}
@@ -0,0 +1,338 @@
+//! Full names in two parts, like "Anna Petrova", from a corpus of whole names.
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::language::Language;
+use crate::memory::fnv1a;
+use crate::postprocess::{titlecase, weighted_choice};
+use crate::state::GeneratorRng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
+
+/// How many surnames `generate_full_name()` tries for one with the ending it chose, before
+/// settling for the last one.
+const MAX_SURNAMES: usize = 100;
+
+/// A generated given name and surname.  Displays as "Given Surname".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullName {
+    pub given: String,
+    pub surname: String,
+}
+
+impl fmt::Display for FullName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.given, self.surname)
+    }
+}
+
+/// A generator trained on a corpus of "Given Surname" lines, that generates full names whose
+/// two parts go together.
+///
+/// Each training line is split at its whitespace; the first word is the given name and the
+/// last is the surname, and any words in between are ignored.  One model learns the given
+/// names and another the surnames, and the generator also counts which surname endings (last
+/// letters) were seen with which given-name endings, so that agreement like "Anna Petrova" and
+/// "Ivan Petrov" carries over.  A full name is made by generating a given name, choosing a
+/// surname ending in proportion to how often it followed that given-name ending in the corpus
+/// (or how often it was seen at all, if the given name's ending never was), then generating
+/// surnames until one has it.
+///
+/// Names are lowercase, like the training data, unless most of the training names were
+/// capitalized.
+///
+/// ```
+/// use markov_namegen::fullname::FullNameGenerator;
+/// let mut namegen = FullNameGenerator::builder()
+///     .without_prior()
+///     .train(vec!["anna petrova", "ivan petrov", "olga ivanova", "boris ivanov"].into_iter())
+///     .build();
+/// let name = namegen.generate_full_name();
+/// assert!(name.surname.starts_with("petrov") || name.surname.starts_with("ivanov"));
+/// ```
+pub struct FullNameGenerator {
+    given: ClusterChainGenerator,
+    surnames: ClusterChainGenerator,
+    pairings: BTreeMap<char, Vec<(String, f64)>>,
+    endings: Vec<(String, f64)>,
+    capitalized: bool,
+    rng: GeneratorRng,
+}
+
+impl FullNameGenerator {
+    pub fn builder() -> FullNameGeneratorBuilder {
+        FullNameGeneratorBuilder::new()
+    }
+
+    /// Generates a given name and a surname that goes with it.
+    ///
+    /// Panics if the generator can't produce one; use `try_generate_full_name()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_full_name(&mut self) -> FullName {
+        self.try_generate_full_name()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a given name and a surname that goes with it, or returns an error if either
+    /// model can't produce a name.  Surnames are generated until one has the chosen ending, up
+    /// to a hundred times, after which the last one is kept.
+    pub fn try_generate_full_name(&mut self) -> Result<FullName, NamegenError> {
+        let given = self.given.try_generate_one()?;
+        let pairing = given
+            .chars()
+            .last()
+            .and_then(|ending| self.pairings.get(&ending))
+            .unwrap_or(&self.endings);
+        let ending = weighted_choice(pairing, &mut self.rng).map(String::from);
+        let mut surname = self.surnames.try_generate_one()?;
+        for _ in 1..MAX_SURNAMES {
+            if ending
+                .as_ref()
+                .is_none_or(|ending| surname.ends_with(ending))
+            {
+                break;
+            }
+            surname = self.surnames.try_generate_one()?;
+        }
+        Ok(FullName {
+            given: self.finish(given),
+            surname: self.finish(surname),
+        })
+    }
+
+    fn finish(&self, name: String) -> String {
+        if self.capitalized {
+            titlecase(name)
+        } else {
+            name
+        }
+    }
+}
+
+impl fmt::Debug for FullNameGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FullNameGenerator")
+            .field("pairings", &self.pairings)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for FullNameGenerator {
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        self.try_generate_full_name().map(|name| name.to_string())
+    }
+
+    fn kind(&self) -> &'static str {
+        "fullname"
+    }
+
+    /// Counts the pairings of endings, e.g. "3 given-name endings, 2 surname endings".
+    fn config_summary(&self) -> String {
+        format!(
+            "{} given-name endings, {} surname endings",
+            self.pairings.len(),
+            self.endings.len()
+        )
+    }
+}
+
+/// A Builder pattern for FullNameGenerator.  The settings apply to both models.
+pub struct FullNameGeneratorBuilder {
+    order: usize,
+    prior: Option<f64>,
+    seed: Option<u64>,
+    names: Vec<(String, String)>,
+}
+
+impl Default for FullNameGeneratorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FullNameGeneratorBuilder {
+    pub fn new() -> Self {
+        FullNameGeneratorBuilder {
+            order: ClusterChainGenerator::DEFAULT_ORDER,
+            prior: Some(ClusterChainGenerator::DEFAULT_PRIOR),
+            seed: None,
+            names: Vec::new(),
+        }
+    }
+    /// Sets the order of both Markov models.  See `ClusterChainGeneratorBuilder::with_order()`.
+    pub fn with_order(mut self, order: usize) -> Self {
+        self.order = order;
+        self
+    }
+    /// Sets the prior of both Markov models.  See `ClusterChainGeneratorBuilder::with_prior()`.
+    pub fn with_prior(mut self, prior: f64) -> Self {
+        self.prior = Some(prior);
+        self
+    }
+    /// Set the priors to None.
+    pub fn without_prior(mut self) -> Self {
+        self.prior = None;
+        self
+    }
+    /// Seeds the generator for reproducible, cross-platform output, as with
+    /// `ClusterChainGeneratorBuilder::with_seed()`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Ingests a corpus of "Given Surname" lines.  Lines of a single word are skipped, since
+    /// they can't be told apart into a given name and a surname.
+    pub fn train(mut self, sequences: impl Iterator<Item = impl Deref<Target = str>>) -> Self {
+        self.names.extend(sequences.filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [given, .., surname] => Some((given.to_string(), surname.to_string())),
+                _ => None,
+            }
+        }));
+        self
+    }
+
+    pub fn build(self) -> FullNameGenerator {
+        self.try_build()
+            .unwrap_or_else(|error| panic!("could not build FullNameGenerator: {}", error))
+    }
+
+    /// Trains the models, or returns an error if no training line has two parts.
+    pub fn try_build(self) -> Result<FullNameGenerator, NamegenError> {
+        if self.names.is_empty() {
+            return Err(NamegenError::InvalidTrainingData(String::from(
+                "no training line has both a given name and a surname",
+            )));
+        }
+        let all: Vec<&String> = self
+            .names
+            .iter()
+            .flat_map(|(given, surname)| [given, surname])
+            .collect();
+        let capitalized = all
+            .iter()
+            .filter(|name| name.starts_with(char::is_uppercase))
+            .count()
+            * 2
+            > all.len();
+        let language = Language::detect(&all).unwrap_or(Language::Romance);
+        let mut pairings: BTreeMap<char, BTreeMap<String, usize>> = BTreeMap::new();
+        let mut endings: BTreeMap<String, usize> = BTreeMap::new();
+        for (given, surname) in &self.names {
+            let (given, surname) = (given.to_lowercase(), surname.to_lowercase());
+            let (Some(given_ending), Some(surname_ending)) =
+                (given.chars().last(), surname.chars().last())
+            else {
+                continue;
+            };
+            *pairings
+                .entry(given_ending)
+                .or_default()
+                .entry(surname_ending.to_string())
+                .or_default() += 1;
+            *endings.entry(surname_ending.to_string()).or_default() += 1;
+        }
+        let weighted = |counts: BTreeMap<String, usize>| -> Vec<(String, f64)> {
+            counts
+                .into_iter()
+                .map(|(ending, count)| (ending, count as f64))
+                .collect()
+        };
+        let seed = |label: &str| self.seed.map(|seed| seed ^ fnv1a(label.as_bytes()));
+        let train = |corpus: Vec<String>, seed: Option<u64>| {
+            let mut builder = ClusterChainGenerator::builder()
+                .with_order(self.order)
+                .with_language(language);
+            builder = match self.prior {
+                Some(prior) => builder.with_prior(prior),
+                None => builder.without_prior(),
+            };
+            if let Some(seed) = seed {
+                builder = builder.with_seed(seed);
+            }
+            builder.train(corpus.iter().map(String::as_str)).try_build()
+        };
+        let lowercase = |part: fn(&(String, String)) -> &String| {
+            self.names
+                .iter()
+                .map(|name| part(name).to_lowercase())
+                .collect()
+        };
+        Ok(FullNameGenerator {
+            given: train(lowercase(|(given, _)| given), seed("given"))?,
+            surnames: train(lowercase(|(_, surname)| surname), seed("surnames"))?,
+            pairings: pairings
+                .into_iter()
+                .map(|(ending, counts)| (ending, weighted(counts)))
+                .collect(),
+            endings: weighted(endings),
+            capitalized,
+            rng: self
+                .seed
+                .map_or_else(GeneratorRng::from_entropy, GeneratorRng::from_seed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trained() -> FullNameGenerator {
+        FullNameGenerator::builder()
+            .with_seed(11)
+            .without_prior()
+            .train(
+                vec![
+                    "Anna Petrova",
+                    "Ivan Petrov",
+                    "Olga Ivanova",
+                    "Boris Ivanov",
+                    "Irina Sokolova",
+                    "Oleg Sokolov",
+                    "Nina Orlova",
+                    "Igor Orlov",
+                    "Cher",
+                ]
+                .into_iter(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_surnames_agree_with_given_names() {
+        let mut namegen = trained();
+        assert_eq!(
+            namegen.config_summary(),
+            "5 given-name endings, 2 surname endings"
+        );
+        for _ in 0..50 {
+            let name = namegen.generate_full_name();
+            assert!(name.given.starts_with(char::is_uppercase), "{}", name);
+            assert_eq!(
+                name.given.ends_with('a'),
+                name.surname.ends_with('a'),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_generation_is_reproducible() {
+        let (mut a, mut b) = (trained(), trained());
+        for _ in 0..10 {
+            assert_eq!(a.generate_one(), b.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_two_part_lines_are_needed() {
+        let result = FullNameGenerator::builder()
+            .train(vec!["cher", "madonna"].into_iter())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidTrainingData(_))));
+    }
+}
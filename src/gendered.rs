@@ -0,0 +1,412 @@
+//! Masculine and feminine variants of the same name, like "Julius" and "Julia".
+use crate::clusterchain::builder::ClusterChainGeneratorBuilder;
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::language::Language;
+use crate::memory::fnv1a;
+use crate::postprocess::{titlecase, weighted_choice};
+use crate::state::GeneratorRng;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
+
+/// How many roots `generate_pair()` tries before giving up on finding one that takes both a
+/// masculine and a feminine ending.
+const MAX_ROOTS: usize = 100;
+
+/// The grammatical gender of a name, for `GenderedGenerator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gender {
+    Masculine,
+    Feminine,
+}
+
+/// A matched pair of names sharing a root.  Displays as "Masculine/Feminine".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenderedPair {
+    pub masculine: String,
+    pub feminine: String,
+}
+
+impl fmt::Display for GenderedPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.masculine, self.feminine)
+    }
+}
+
+/// A generator trained on a corpus of masculine names and one of feminine names, that can
+/// generate a matched pair of them with `generate_pair()`: "Julius" and "Julia", say, or
+/// "Marcus" and "Marcia".
+///
+/// Each training name is split into a root and an ending, the ending being its last vowel
+/// cluster and any consonants after it ("jul" and "ius").  One model learns the roots, from
+/// both corpora together, and each gender's endings are learned from its own corpus.  A pair
+/// is made by generating a root once, then giving it an ending of each gender, chosen by how
+/// likely the whole name is under a model of that gender's names, so that endings go with the
+/// roots they suit.  `generate_one()` generates a single name of a random gender, in proportion
+/// to the sizes of the corpora.
+///
+/// Names are lowercase, like the training data, unless most of the training names were
+/// capitalized.
+///
+/// ```
+/// use markov_namegen::gendered::{Gender, GenderedGenerator};
+/// let mut namegen = GenderedGenerator::builder()
+///     .without_prior()
+///     .train(Gender::Masculine, vec!["julius", "marcus", "lucius", "claudius"].into_iter())
+///     .train(Gender::Feminine, vec!["julia", "marcia", "lucia", "claudia"].into_iter())
+///     .build();
+/// let pair = namegen.generate_pair();
+/// let root = pair
+///     .masculine
+///     .strip_suffix("ius")
+///     .or_else(|| pair.masculine.strip_suffix("us"));
+/// assert!(root.is_some());
+/// assert_eq!(root, pair.feminine.strip_suffix("ia"));
+/// let daughter = namegen.generate(Gender::Feminine);
+/// ```
+pub struct GenderedGenerator {
+    roots: ClusterChainGenerator,
+    masculine: Endings,
+    feminine: Endings,
+    capitalized: bool,
+    rng: GeneratorRng,
+}
+
+/// One gender's endings, and a model of its names to judge how well they fit a root.
+struct Endings {
+    endings: Vec<String>,
+    names: ClusterChainGenerator,
+    weight: f64,
+}
+
+impl Endings {
+    /// An ending for the root, chosen by the likelihood of the whole name, except `excluded`.
+    fn choose(&self, root: &str, excluded: Option<&str>, rng: &mut GeneratorRng) -> Option<String> {
+        let candidates: Vec<(String, f64)> = self
+            .endings
+            .iter()
+            .map(|ending| format!("{}{}", root, ending))
+            .filter(|name| Some(name.as_str()) != excluded)
+            .filter_map(|name| {
+                let probability = self.names.log_probability_of(&name).exp();
+                (probability > 0.0).then_some((name, probability))
+            })
+            .collect();
+        weighted_choice(&candidates, rng).map(String::from)
+    }
+}
+
+impl GenderedGenerator {
+    pub fn builder() -> GenderedGeneratorBuilder {
+        GenderedGeneratorBuilder::new()
+    }
+
+    /// Generates a masculine and a feminine name sharing a root.
+    ///
+    /// Panics if the generator can't produce a pair; use `try_generate_pair()` if you'd rather
+    /// handle that as an error.
+    pub fn generate_pair(&mut self) -> GenderedPair {
+        self.try_generate_pair()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a masculine and a feminine name sharing a root, or returns an error if the
+    /// generator can't produce one.  Roots are generated until one can take an ending of each
+    /// gender (the two names being different), up to a hundred times.
+    pub fn try_generate_pair(&mut self) -> Result<GenderedPair, NamegenError> {
+        for _ in 0..MAX_ROOTS {
+            let root = self.roots.try_generate_one()?;
+            let Some(masculine) = self.masculine.choose(&root, None, &mut self.rng) else {
+                continue;
+            };
+            if let Some(feminine) = self.feminine.choose(&root, Some(&masculine), &mut self.rng) {
+                return Ok(GenderedPair {
+                    masculine: self.finish(masculine),
+                    feminine: self.finish(feminine),
+                });
+            }
+        }
+        Err(NamegenError::MaxAttemptsExceeded(MAX_ROOTS))
+    }
+
+    /// Generates a name of the given gender.
+    ///
+    /// Panics if the generator can't produce one; use `try_generate()` if you'd rather handle
+    /// that as an error.
+    pub fn generate(&mut self, gender: Gender) -> String {
+        self.try_generate(gender)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a name of the given gender, or returns an error if the generator can't
+    /// produce one.
+    pub fn try_generate(&mut self, gender: Gender) -> Result<String, NamegenError> {
+        let endings = match gender {
+            Gender::Masculine => &self.masculine,
+            Gender::Feminine => &self.feminine,
+        };
+        for _ in 0..MAX_ROOTS {
+            let root = self.roots.try_generate_one()?;
+            if let Some(name) = endings.choose(&root, None, &mut self.rng) {
+                return Ok(self.finish(name));
+            }
+        }
+        Err(NamegenError::MaxAttemptsExceeded(MAX_ROOTS))
+    }
+
+    fn finish(&self, name: String) -> String {
+        if self.capitalized {
+            titlecase(name)
+        } else {
+            name
+        }
+    }
+}
+
+impl fmt::Debug for GenderedGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GenderedGenerator")
+            .field("masculine_endings", &self.masculine.endings)
+            .field("feminine_endings", &self.feminine.endings)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for GenderedGenerator {
+    /// Generates a name of a random gender, chosen in proportion to the size of its corpus.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        let sum_of_weights = self.masculine.weight + self.feminine.weight;
+        if self.rng.gen::<f64>() * sum_of_weights < self.masculine.weight {
+            self.try_generate(Gender::Masculine)
+        } else {
+            self.try_generate(Gender::Feminine)
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "gendered"
+    }
+
+    /// Counts the endings of each gender, e.g. "4 masculine endings, 2 feminine endings".
+    fn config_summary(&self) -> String {
+        format!(
+            "{} masculine endings, {} feminine endings",
+            self.masculine.endings.len(),
+            self.feminine.endings.len()
+        )
+    }
+}
+
+/// A Builder pattern for GenderedGenerator.  The settings apply to every model.
+pub struct GenderedGeneratorBuilder {
+    order: usize,
+    prior: Option<f64>,
+    seed: Option<u64>,
+    masculine: Vec<String>,
+    feminine: Vec<String>,
+}
+
+impl Default for GenderedGeneratorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenderedGeneratorBuilder {
+    pub fn new() -> Self {
+        GenderedGeneratorBuilder {
+            order: ClusterChainGenerator::DEFAULT_ORDER,
+            prior: Some(ClusterChainGenerator::DEFAULT_PRIOR),
+            seed: None,
+            masculine: Vec::new(),
+            feminine: Vec::new(),
+        }
+    }
+    /// Sets the order of every Markov model.  See `ClusterChainGeneratorBuilder::with_order()`.
+    pub fn with_order(mut self, order: usize) -> Self {
+        self.order = order;
+        self
+    }
+    /// Sets the prior of every Markov model.  See `ClusterChainGeneratorBuilder::with_prior()`.
+    pub fn with_prior(mut self, prior: f64) -> Self {
+        self.prior = Some(prior);
+        self
+    }
+    /// Set the priors to None.
+    pub fn without_prior(mut self) -> Self {
+        self.prior = None;
+        self
+    }
+    /// Seeds the generator for reproducible, cross-platform output, as with
+    /// `ClusterChainGeneratorBuilder::with_seed()`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Ingests a corpus of names of the given gender.  Training the same gender again adds to
+    /// its corpus.
+    pub fn train(
+        mut self,
+        gender: Gender,
+        sequences: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> Self {
+        let corpus = match gender {
+            Gender::Masculine => &mut self.masculine,
+            Gender::Feminine => &mut self.feminine,
+        };
+        corpus.extend(
+            sequences
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+        );
+        self
+    }
+
+    pub fn build(self) -> GenderedGenerator {
+        self.try_build()
+            .unwrap_or_else(|error| panic!("could not build GenderedGenerator: {}", error))
+    }
+
+    /// Trains the models, or returns an error if either gender has no training names or no
+    /// name has a root.
+    pub fn try_build(self) -> Result<GenderedGenerator, NamegenError> {
+        if self.masculine.is_empty() || self.feminine.is_empty() {
+            return Err(NamegenError::InvalidTrainingData(String::from(
+                "both masculine and feminine names are needed",
+            )));
+        }
+        let all: Vec<&String> = self.masculine.iter().chain(&self.feminine).collect();
+        let capitalized = all
+            .iter()
+            .filter(|name| name.starts_with(char::is_uppercase))
+            .count()
+            * 2
+            > all.len();
+        let language = Language::detect(&all).unwrap_or(Language::Romance);
+        let split = |name: &String| {
+            let clusters =
+                ClusterChainGeneratorBuilder::clusterize_with(name.to_lowercase(), |c| {
+                    language.is_vowel(c)
+                });
+            let last_vowel = clusters
+                .iter()
+                .rposition(|cluster| cluster.starts_with(|c| language.is_vowel(c)))
+                .unwrap_or(clusters.len());
+            (
+                clusters[..last_vowel].concat(),
+                clusters[last_vowel..].concat(),
+            )
+        };
+        let mut roots = Vec::new();
+        let mut endings: [BTreeMap<String, usize>; 2] = Default::default();
+        for (corpus, endings) in [&self.masculine, &self.feminine]
+            .into_iter()
+            .zip(&mut endings)
+        {
+            for name in corpus {
+                let (root, ending) = split(name);
+                if !root.is_empty() {
+                    roots.push(root);
+                }
+                *endings.entry(ending).or_default() += 1;
+            }
+        }
+        if roots.is_empty() {
+            return Err(NamegenError::InvalidTrainingData(String::from(
+                "no training name has a root before its ending",
+            )));
+        }
+        let seed = |label: &str| self.seed.map(|seed| seed ^ fnv1a(label.as_bytes()));
+        let train = |corpus: Vec<String>, seed: Option<u64>| {
+            let mut builder = ClusterChainGenerator::builder()
+                .with_order(self.order)
+                .with_language(language);
+            builder = match self.prior {
+                Some(prior) => builder.with_prior(prior),
+                None => builder.without_prior(),
+            };
+            if let Some(seed) = seed {
+                builder = builder.with_seed(seed);
+            }
+            builder.train(corpus.iter().map(String::as_str)).try_build()
+        };
+        let [masculine_endings, feminine_endings] = endings;
+        let lowercase = |corpus: &[String]| corpus.iter().map(|name| name.to_lowercase()).collect();
+        Ok(GenderedGenerator {
+            roots: train(roots, seed("roots"))?,
+            masculine: Endings {
+                endings: masculine_endings.into_keys().collect(),
+                names: train(lowercase(&self.masculine), None)?,
+                weight: self.masculine.len() as f64,
+            },
+            feminine: Endings {
+                endings: feminine_endings.into_keys().collect(),
+                names: train(lowercase(&self.feminine), None)?,
+                weight: self.feminine.len() as f64,
+            },
+            capitalized,
+            rng: self
+                .seed
+                .map_or_else(GeneratorRng::from_entropy, GeneratorRng::from_seed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trained() -> GenderedGenerator {
+        GenderedGenerator::builder()
+            .with_seed(5)
+            .without_prior()
+            .train(
+                Gender::Masculine,
+                vec!["Julius", "Marcus", "Lucius", "Claudius", "Tullius"].into_iter(),
+            )
+            .train(
+                Gender::Feminine,
+                vec!["Julia", "Marcia", "Lucia", "Claudia", "Tullia"].into_iter(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_pairs_share_a_root() {
+        let mut namegen = trained();
+        assert_eq!(
+            namegen.config_summary(),
+            "2 masculine endings, 1 feminine endings"
+        );
+        for _ in 0..20 {
+            let pair = namegen.generate_pair();
+            let root = pair
+                .masculine
+                .strip_suffix("ius")
+                .or_else(|| pair.masculine.strip_suffix("us"));
+            assert!(root.is_some(), "{}", pair);
+            assert_eq!(root, pair.feminine.strip_suffix("ia"), "{}", pair);
+            assert!(pair.masculine.starts_with(char::is_uppercase));
+        }
+    }
+
+    #[test]
+    fn test_generation_is_reproducible() {
+        let (mut a, mut b) = (trained(), trained());
+        for _ in 0..10 {
+            assert_eq!(a.generate_pair(), b.generate_pair());
+            assert_eq!(a.generate_one(), b.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_both_genders_are_needed() {
+        let result = GenderedGenerator::builder()
+            .train(Gender::Masculine, vec!["julius"].into_iter())
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidTrainingData(_))));
+    }
+}
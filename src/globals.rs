@@ -0,0 +1,41 @@
+//! Global generators trained on the corpora bundled with this crate (requires the `globals`
+//! feature), so that quick scripts can generate names with no setup at all:
+//!
+//! ```
+//! use markov_namegen::{globals, RandomTextGenerator};
+//! let name = globals::roman().generate_one();
+//! ```
+//!
+//! Each generator is trained the first time it's used, and shared by every thread of the
+//! program behind a lock.  For serious multithreaded work, `fork()` it or build your own.
+use crate::ClusterChainGenerator;
+use once_cell::sync::Lazy;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+static ROMAN: Lazy<Mutex<ClusterChainGenerator>> = Lazy::new(|| {
+    Mutex::new(
+        crate::train_embedded!(ClusterChainGenerator::builder(), "resources/romans.txt").build(),
+    )
+});
+
+/// A `ClusterChainGenerator` trained on the names of ancient Romans, with the default settings.
+/// The generator stays locked for as long as the returned guard is kept.
+pub fn roman() -> MutexGuard<'static, ClusterChainGenerator> {
+    // a generator that panicked (e.g. in a postprocessor) is still usable
+    ROMAN.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RandomTextGenerator;
+
+    #[test]
+    fn test_roman_is_shared_between_threads() {
+        let mut forked = roman().fork(3);
+        std::thread::spawn(|| roman().generate_one())
+            .join()
+            .unwrap();
+        assert!(!forked.generate_one().is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+//! Callbacks that a generator calls as it generates a name, for applications to log or count
+//! its candidates and rejections, or to cut a long run of re-rolls short with a policy of
+//! their own.
+//!
+//! ```
+//! use markov_namegen::{CharacterChainGenerator, NamegenError, RandomTextGenerator};
+//! use std::ops::ControlFlow;
+//! let mut namegen = CharacterChainGenerator::builder()
+//!     .with_pattern("^zzz$")
+//!     .on_reject(|name, rejection, attempts| {
+//!         println!("attempt {}: '{}' {}", attempts, name, rejection);
+//!         if attempts < 50 {
+//!             ControlFlow::Continue(())
+//!         } else {
+//!             ControlFlow::Break(())
+//!         }
+//!     })
+//!     .train(vec!["dopey", "sneezy", "bashful"].into_iter())
+//!     .build();
+//! assert_eq!(namegen.try_generate_one(), Err(NamegenError::Aborted(50)));
+//! ```
+use crate::error::NamegenError;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+/// Why a generator rejected a candidate name and re-rolled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// The candidate didn't match the pattern set with `.with_pattern()`.
+    Pattern,
+    /// The candidate broke a rule set with `.with_phonotactics()`, described here.
+    Phonotactics(String),
+    /// The name was on the list set with `.avoid()`.
+    Avoided,
+    /// The name mixes scripts, and the policy set with `.with_confusable_policy()` rejects it.
+    MixedScripts,
+    /// The name can't be made into an identifier in the style set with `.as_identifiers()`.
+    NotAnIdentifier,
+    /// The name sounds like this entry of the blocklist set with `.with_phonetic_blocklist()`.
+    SoundsLike(String),
+    /// The generator remembers its outputs, and has returned the name before.
+    Repeated,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Pattern => write!(f, "doesn't match the pattern"),
+            Rejection::Phonotactics(violation) => {
+                write!(f, "breaks a phonotactic rule ({})", violation)
+            }
+            Rejection::Avoided => write!(f, "is on the list of names to avoid"),
+            Rejection::MixedScripts => write!(f, "mixes letters of different scripts"),
+            Rejection::NotAnIdentifier => write!(f, "can't be made into an identifier"),
+            Rejection::SoundsLike(entry) => write!(f, "sounds like '{}' on the blocklist", entry),
+            Rejection::Repeated => write!(f, "has been generated before"),
+        }
+    }
+}
+
+pub(crate) type CandidateHook = Arc<dyn Fn(&str) + Send + Sync>;
+pub(crate) type RejectHook = Arc<dyn Fn(&str, &Rejection, usize) -> ControlFlow<()> + Send + Sync>;
+
+/// The callbacks set on a generator, shared with its forks.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) on_candidate: Option<CandidateHook>,
+    pub(crate) on_reject: Option<RejectHook>,
+}
+
+impl Hooks {
+    /// Reports a candidate, before it's checked against any filter.
+    pub(crate) fn candidate(&self, candidate: &str) {
+        if let Some(hook) = &self.on_candidate {
+            hook(candidate);
+        }
+    }
+
+    /// Reports a rejection on the given attempt, and returns an error if the callback aborts
+    /// generation or the attempt was the last of `max_attempts`.
+    pub(crate) fn reject(
+        &self,
+        name: &str,
+        rejection: Rejection,
+        attempts: usize,
+        max_attempts: Option<usize>,
+    ) -> Result<(), NamegenError> {
+        if let Some(hook) = &self.on_reject {
+            if hook(name, &rejection, attempts).is_break() {
+                return Err(NamegenError::Aborted(attempts));
+            }
+        }
+        if max_attempts.is_some_and(|max| attempts >= max) {
+            return Err(NamegenError::MaxAttemptsExceeded(attempts));
+        }
+        Ok(())
+    }
+}
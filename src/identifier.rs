@@ -0,0 +1,139 @@
+//! Turning names into programming identifiers ("ala_kora", "alaKora"), for naming generated
+//! code entities, test fixtures and database objects.
+use crate::postprocess::strip_diacritics;
+
+/// Keywords of common programming and query languages, which are rejected as identifiers even
+/// though they are well-formed.
+#[rustfmt::skip]
+const RESERVED: &[&str] = &[
+    "and", "as", "async", "await", "break", "case", "catch", "class", "const", "continue",
+    "def", "default", "del", "delete", "do", "elif", "else", "enum", "except", "extends",
+    "false", "finally", "fn", "for", "from", "func", "function", "go", "if", "impl",
+    "import", "in", "is", "let", "loop", "match", "mod", "mut", "new", "nil", "none", "not",
+    "null", "or", "pass", "pub", "raise", "return", "select", "self", "static", "struct",
+    "super", "switch", "table", "this", "throw", "true", "try", "type", "use", "var",
+    "where", "while", "with", "yield",
+];
+
+/// How the words of an identifier are joined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdentifierCase {
+    /// "ala_kora".  This is the default.
+    #[default]
+    Snake,
+    /// "ALA_KORA".
+    ScreamingSnake,
+    /// "alaKora".
+    Camel,
+    /// "AlaKora".
+    Pascal,
+}
+
+/// The rules that make a name into an identifier.  Set them with the builders'
+/// `.as_identifiers()`, which re-rolls names that can't be made into one.
+///
+/// A name is made into an identifier by taking off its diacritics, splitting it into words at
+/// anything that isn't an ASCII letter or digit (spaces, hyphens, apostrophes and letters of
+/// other scripts), and joining the words in the chosen case.  It can't be made into one if
+/// nothing is left, if it would begin with a digit or be longer than `max_length`, or if it's
+/// a keyword of a common language, like "match" or "type".
+///
+/// ```
+/// use markov_namegen::identifier::{IdentifierCase, IdentifierStyle};
+/// let style = IdentifierStyle { case: IdentifierCase::Camel, max_length: Some(12) };
+/// assert_eq!(style.apply("Ærø-Kora").as_deref(), Some("aeroKora"));
+/// assert_eq!(style.apply("Vel'Tharion Dusk"), None);
+/// assert_eq!(style.apply("Type"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IdentifierStyle {
+    pub case: IdentifierCase,
+    /// The most characters an identifier may have, e.g. 63 for PostgreSQL.
+    pub max_length: Option<usize>,
+}
+
+impl IdentifierStyle {
+    /// The name as an identifier, or `None` if it can't be made into one.
+    pub fn apply(&self, name: &str) -> Option<String> {
+        let stripped = strip_diacritics(name.to_string());
+        let words: Vec<String> = stripped
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let capitalized = |word: &String| {
+            let mut word = word.clone();
+            word[..1].make_ascii_uppercase();
+            word
+        };
+        let identifier = match self.case {
+            IdentifierCase::Snake => words.join("_"),
+            IdentifierCase::ScreamingSnake => words.join("_").to_ascii_uppercase(),
+            IdentifierCase::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 {
+                        word.clone()
+                    } else {
+                        capitalized(word)
+                    }
+                })
+                .collect(),
+            IdentifierCase::Pascal => words.iter().map(capitalized).collect(),
+        };
+        let well_formed = identifier
+            .chars()
+            .next()
+            .is_some_and(|first| !first.is_ascii_digit());
+        let short_enough = self
+            .max_length
+            .is_none_or(|max_length| identifier.len() <= max_length);
+        let reserved = RESERVED.contains(&identifier.to_ascii_lowercase().as_str());
+        (well_formed && short_enough && !reserved).then_some(identifier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cases() {
+        let apply = |case| {
+            IdentifierStyle {
+                case,
+                max_length: None,
+            }
+            .apply("Ala-kora d'Vel")
+        };
+        assert_eq!(
+            apply(IdentifierCase::Snake).as_deref(),
+            Some("ala_kora_d_vel")
+        );
+        assert_eq!(
+            apply(IdentifierCase::ScreamingSnake).as_deref(),
+            Some("ALA_KORA_D_VEL")
+        );
+        assert_eq!(apply(IdentifierCase::Camel).as_deref(), Some("alaKoraDVel"));
+        assert_eq!(
+            apply(IdentifierCase::Pascal).as_deref(),
+            Some("AlaKoraDVel")
+        );
+    }
+
+    #[test]
+    fn test_names_that_cant_be_identifiers() {
+        let style = IdentifierStyle::default();
+        assert_eq!(style.apply("Щукин"), None);
+        assert_eq!(style.apply("4ever"), None);
+        assert_eq!(style.apply("Match"), None);
+        assert_eq!(style.apply("Щукин Ivan").as_deref(), Some("ivan"));
+        let style = IdentifierStyle {
+            max_length: Some(4),
+            ..style
+        };
+        assert_eq!(style.apply("Kora").as_deref(), Some("kora"));
+        assert_eq!(style.apply("Koral"), None);
+    }
+}
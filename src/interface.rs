@@ -1,3 +1,213 @@
+use crate::error::NamegenError;
+
 pub trait RandomTextGenerator {
-    fn generate_one(&mut self) -> String;
+    /// Generates a new, procedurally-generated text string.
+    ///
+    /// Panics if the generator can't produce a name, e.g. because it was never trained.  Use
+    /// `try_generate_one()` if you'd rather handle that as an error.
+    fn generate_one(&mut self) -> String {
+        self.try_generate_one()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a new, procedurally-generated text string, or returns an error if the model
+    /// can't produce one.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError>;
+
+    /// Generates `n` names into a vector you provide, so that a game generating names on every
+    /// level load can reuse one vector rather than allocating a new one each time.  If `append`
+    /// is false, the vector is cleared first; if it's true, the names are added after those
+    /// already in it.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_n_into()` if you'd rather
+    /// handle that as an error.
+    ///
+    /// ```
+    /// use markov_namegen::{CharacterChainGenerator, RandomTextGenerator};
+    /// let mut namegen = CharacterChainGenerator::builder()
+    ///     .train(vec!["julius", "marcus", "lucius"].into_iter())
+    ///     .build();
+    /// let mut names = Vec::with_capacity(10);
+    /// namegen.generate_n_into(&mut names, 10, false);
+    /// namegen.generate_n_into(&mut names, 10, false);
+    /// assert_eq!(names.len(), 10);
+    /// namegen.generate_n_into(&mut names, 5, true);
+    /// assert_eq!(names.len(), 15);
+    /// ```
+    fn generate_n_into(&mut self, names: &mut Vec<String>, n: usize, append: bool) {
+        self.try_generate_n_into(names, n, append)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Like `generate_n_into()`, but returns an error if the model can't produce a name.  The
+    /// names generated before the error are left in the vector.
+    fn try_generate_n_into(
+        &mut self,
+        names: &mut Vec<String>,
+        n: usize,
+        append: bool,
+    ) -> Result<(), NamegenError> {
+        if !append {
+            names.clear();
+        }
+        names.reserve(n);
+        for _ in 0..n {
+            names.push(self.try_generate_one()?);
+        }
+        Ok(())
+    }
+
+    /// The name given to this particular generator, e.g. with the builder's `.with_name()`, for
+    /// registries, CLIs and debugging UIs to show.  `None` by default.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// The kind of generator this is, e.g. "characterchain".  By default, the name of its type.
+    fn kind(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// A one-line, human-readable summary of the generator's settings.  Empty by default.
+    fn config_summary(&self) -> String {
+        String::new()
+    }
+
+    /// A copy of this generator with its own random number generator, seeded with `seed`, for
+    /// kinds that can be copied cheaply (like `ClusterChainGenerator::fork()`), e.g. to serve
+    /// each request from a fresh, repeatable generator.  `None` by default.
+    fn fork_boxed(&self, _seed: u64) -> Option<Box<dyn RandomTextGenerator + Send>> {
+        None
+    }
+}
+
+/// Lets a boxed generator of any kind, e.g. `Box<dyn RandomTextGenerator + Send>`, be used
+/// wherever a generator is expected, so that collections of different kinds of generators
+/// need no wrapper type.
+///
+/// ```
+/// use markov_namegen::{CharacterChainGenerator, ClusterChainGenerator, RandomTextGenerator};
+/// let corpus = vec!["julius", "marcus", "lucius", "tiberius"];
+/// let mut generators: Vec<Box<dyn RandomTextGenerator + Send>> = vec![
+///     Box::new(CharacterChainGenerator::builder().train(corpus.clone().into_iter()).build()),
+///     Box::new(ClusterChainGenerator::builder().train(corpus.into_iter()).build()),
+/// ];
+/// let names: Vec<String> = generators.iter_mut().map(|g| g.generate_one()).collect();
+/// ```
+impl<G: RandomTextGenerator + ?Sized> RandomTextGenerator for Box<G> {
+    fn generate_one(&mut self) -> String {
+        (**self).generate_one()
+    }
+
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        (**self).try_generate_one()
+    }
+
+    fn generate_n_into(&mut self, names: &mut Vec<String>, n: usize, append: bool) {
+        (**self).generate_n_into(names, n, append)
+    }
+
+    fn try_generate_n_into(
+        &mut self,
+        names: &mut Vec<String>,
+        n: usize,
+        append: bool,
+    ) -> Result<(), NamegenError> {
+        (**self).try_generate_n_into(names, n, append)
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn kind(&self) -> &'static str {
+        (**self).kind()
+    }
+
+    fn config_summary(&self) -> String {
+        (**self).config_summary()
+    }
+
+    fn fork_boxed(&self, seed: u64) -> Option<Box<dyn RandomTextGenerator + Send>> {
+        (**self).fork_boxed(seed)
+    }
+}
+
+/// Lets a generic function that takes a generator by value be handed a borrowed one instead.
+impl<G: RandomTextGenerator + ?Sized> RandomTextGenerator for &mut G {
+    fn generate_one(&mut self) -> String {
+        (**self).generate_one()
+    }
+
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        (**self).try_generate_one()
+    }
+
+    fn generate_n_into(&mut self, names: &mut Vec<String>, n: usize, append: bool) {
+        (**self).generate_n_into(names, n, append)
+    }
+
+    fn try_generate_n_into(
+        &mut self,
+        names: &mut Vec<String>,
+        n: usize,
+        append: bool,
+    ) -> Result<(), NamegenError> {
+        (**self).try_generate_n_into(names, n, append)
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn kind(&self) -> &'static str {
+        (**self).kind()
+    }
+
+    fn config_summary(&self) -> String {
+        (**self).config_summary()
+    }
+
+    fn fork_boxed(&self, seed: u64) -> Option<Box<dyn RandomTextGenerator + Send>> {
+        (**self).fork_boxed(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CharacterChainGenerator;
+
+    fn first_names(mut generator: impl RandomTextGenerator, count: usize) -> Vec<String> {
+        (0..count).map(|_| generator.generate_one()).collect()
+    }
+
+    #[test]
+    fn test_borrowed_and_boxed_generators_are_generators() {
+        let build = || {
+            CharacterChainGenerator::builder()
+                .with_seed(3)
+                .train(vec!["julius", "marcus", "lucius"].into_iter())
+                .build()
+        };
+        let mut generator = build();
+        let borrowed = first_names(&mut generator, 3);
+        // the borrowed generator carries on from where it was left
+        assert_ne!(first_names(&mut generator, 3), borrowed);
+        let boxed: Box<dyn RandomTextGenerator + Send> = Box::new(build());
+        assert_eq!(boxed.kind(), "characterchain");
+        assert_eq!(first_names(boxed, 3), borrowed);
+    }
+
+    #[test]
+    fn test_failed_bulk_generation_clears_the_vector() {
+        let mut untrained = CharacterChainGenerator::builder()
+            .train(std::iter::empty::<&str>())
+            .build();
+        let mut names = vec![String::from("stale")];
+        assert!(untrained.try_generate_n_into(&mut names, 3, true).is_err());
+        assert_eq!(names, vec!["stale"]);
+        assert!(untrained.try_generate_n_into(&mut names, 3, false).is_err());
+        assert!(names.is_empty());
+    }
 }
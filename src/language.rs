@@ -0,0 +1,172 @@
+use crate::script::Script;
+use is_vowel::IsRomanceVowel;
+
+/// A language (or family of languages) whose vowels `ClusterChainGenerator` should use when it
+/// splits words into vowel and consonant clusters.  Select one with the builder's
+/// `.with_language()`.
+///
+/// `Script` only knows the vowels of each writing system in general, and the Latin alphabet is
+/// used very differently from one language to the next: 'y' is a vowel in Finnish and Czech,
+/// 'w' is one in Welsh, and each language has its own accented vowels.  Getting this wrong
+/// leaves runs like "bryn" unsplit, and the model learns whole words instead of syllables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    /// Classical Latin, with the long vowels marked by macrons; 'y' is a vowel.
+    Latin,
+    /// The Romance languages (Italian, Spanish, Portuguese, French, Romanian...), with their
+    /// accented vowels; 'y' and 'w' are consonants.  This is what `Script::Latin` assumes.
+    Romance,
+    /// The Germanic languages (English, German, Dutch and the Scandinavian languages); 'y' is a
+    /// vowel, as are 'ä', 'ö', 'ü', 'å', 'æ', 'ø' and 'œ'.
+    Germanic,
+    /// The Slavic languages, written in or transliterated into the Latin alphabet (Czech,
+    /// Polish, Slovak, Croatian...); 'y' is a vowel, as are 'ě', 'ů', 'ą' and 'ę'.
+    Slavic,
+    /// Finnish (and Estonian); 'y', 'ä' and 'ö' are vowels.
+    Finnish,
+    /// Welsh; 'w' and 'y' are vowels, with or without a circumflex.
+    Welsh,
+    /// Irish and Scottish Gaelic, with the acute and grave accents.
+    Gaelic,
+    /// Turkish, including the dotless 'ı'.
+    Turkish,
+    /// Hungarian, including 'ő' and 'ű'.
+    Hungarian,
+    /// Russian and the other languages written in Cyrillic.  Same as `Script::Cyrillic`.
+    Russian,
+    /// Modern Greek.  Same as `Script::Greek`.
+    Greek,
+}
+
+impl Language {
+    /// The script the language is written in.
+    pub fn script(self) -> Script {
+        match self {
+            Language::Russian => Script::Cyrillic,
+            Language::Greek => Script::Greek,
+            _ => Script::Latin,
+        }
+    }
+
+    /// Whether the letter is a vowel in this language.
+    ///
+    /// ```
+    /// use markov_namegen::Language;
+    /// assert!(Language::Welsh.is_vowel('w'));
+    /// assert!(!Language::Romance.is_vowel('w'));
+    /// assert!(Language::Finnish.is_vowel('Ä'));
+    /// ```
+    pub fn is_vowel(self, c: char) -> bool {
+        match self.vowels() {
+            Some(vowels) => c.to_lowercase().all(|c| vowels.contains(c)),
+            None if self == Language::Romance => c.is_romance_vowel(),
+            None => self.script().is_vowel(c),
+        }
+    }
+
+    /// Guesses a language whose vowels suit a corpus.  The script is detected with
+    /// `Script::detect()`; for the Latin alphabet, the Romance vowels are assumed unless more
+    /// than one word in twenty has none of them, in which case the table that leaves the fewest
+    /// words without any vowel is chosen, as 'y' does for "Lynn" or 'w' for "Cwm".  Returns
+    /// `None` if there are no letters at all.
+    ///
+    /// ```
+    /// use markov_namegen::Language;
+    /// assert_eq!(Language::detect(["julius", "marcus"]), Some(Language::Romance));
+    /// assert_eq!(Language::detect(["lynn", "smyth", "tom"]), Some(Language::Germanic));
+    /// assert_eq!(Language::detect(["иван", "ольга"]), Some(Language::Russian));
+    /// ```
+    pub fn detect<S: AsRef<str>>(samples: impl IntoIterator<Item = S>) -> Option<Language> {
+        let samples: Vec<S> = samples.into_iter().collect();
+        match Script::detect(&samples)? {
+            Script::Cyrillic => Some(Language::Russian),
+            Script::Greek => Some(Language::Greek),
+            Script::Latin => {
+                let words_without_vowels = |language: Language| {
+                    samples
+                        .iter()
+                        .filter(|sample| !sample.as_ref().chars().any(|c| language.is_vowel(c)))
+                        .count()
+                };
+                if !too_many_without_vowels(words_without_vowels(Language::Romance), samples.len())
+                {
+                    return Some(Language::Romance);
+                }
+                // min_by_key keeps the first of equals, so Romance wins ties
+                [
+                    Language::Romance,
+                    Language::Germanic,
+                    Language::Turkish,
+                    Language::Welsh,
+                ]
+                .into_iter()
+                .min_by_key(|&language| words_without_vowels(language))
+            }
+        }
+    }
+
+    /// The lowercase vowels of the languages that have their own table here.
+    fn vowels(self) -> Option<&'static str> {
+        match self {
+            Language::Latin => Some("aeiouyāēīōūȳ"),
+            Language::Germanic => Some("aeiouyäöüåæøœáéíóúýàèìòùâêîôû"),
+            Language::Slavic => Some("aeiouyáéíóúýěůąęôä"),
+            Language::Finnish => Some("aeiouyäöå"),
+            Language::Welsh => Some("aeiouwyâêîôûŵŷáéíóúẃýàèìòùẁỳäëïöüẅÿ"),
+            Language::Gaelic => Some("aeiouáéíóúàèìòù"),
+            Language::Turkish => Some("aeıioöuüâîû"),
+            Language::Hungarian => Some("aáeéiíoóöőuúüű"),
+            Language::Romance | Language::Russian | Language::Greek => None,
+        }
+    }
+}
+
+/// Tells vowels by the language's table if there is one, else by the script's.
+pub(crate) fn vowel_rule(script: Script, language: Option<Language>) -> impl Fn(char) -> bool {
+    move |c: char| match language {
+        Some(language) => language.is_vowel(c),
+        None => script.is_vowel(c),
+    }
+}
+
+/// Whether so many words have no vowels that the cluster model would be degenerate, learning
+/// those words whole.
+pub(crate) fn too_many_without_vowels(without_vowels: usize, words: usize) -> bool {
+    without_vowels * 20 > words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vowels_by_language() {
+        assert!(Language::Latin.is_vowel('ā'));
+        assert!(Language::Romance.is_vowel('é'));
+        assert!(!Language::Romance.is_vowel('y'));
+        assert!(Language::Germanic.is_vowel('ø'));
+        assert!(Language::Slavic.is_vowel('ě'));
+        assert!(!Language::Slavic.is_vowel('č'));
+        assert!(Language::Welsh.is_vowel('Ŵ'));
+        assert!(!Language::Gaelic.is_vowel('y'));
+        assert!(Language::Turkish.is_vowel('ı'));
+        assert!(Language::Hungarian.is_vowel('ű'));
+        assert!(Language::Russian.is_vowel('я'));
+        assert_eq!(Language::Greek.script(), Script::Greek);
+    }
+
+    #[test]
+    fn test_detect_vowel_inventory() {
+        assert_eq!(
+            Language::detect(["yolanda", "maria"]),
+            Some(Language::Romance)
+        );
+        assert_eq!(
+            Language::detect(["kılıç", "ılgın"]),
+            Some(Language::Turkish)
+        );
+        assert_eq!(Language::detect(["cwm", "bryn"]), Some(Language::Welsh));
+        assert_eq!(Language::detect(["νίκος"]), Some(Language::Greek));
+        assert_eq!(Language::detect(["42"]), None);
+    }
+}
@@ -1,7 +1,80 @@
+mod capitalization;
 pub mod characterchain;
+mod checkpoint;
 pub mod clusterchain;
+pub mod config;
+pub mod confusable;
+pub mod convention;
+pub mod distribution;
+mod embed;
+pub mod epithet;
+pub mod error;
+pub mod evaluation;
+pub mod explain;
+pub mod export;
+pub mod fullname;
+pub mod gendered;
+#[cfg(feature = "globals")]
+pub mod globals;
+pub mod hooks;
+pub mod identifier;
 pub mod interface;
+pub mod language;
+mod memory;
+mod model;
+pub mod morphology;
+pub mod multilingual;
+pub mod nickname;
+mod parallel;
+pub mod passphrase;
+pub mod patronymic;
+pub mod phonetic;
+pub mod phonology;
+pub mod phonotactics;
+pub mod place;
+pub mod pool;
+mod positional;
+pub mod postprocess;
+pub mod precompile;
+pub mod preprocess;
+pub mod prior;
+pub mod pronunciation;
+pub mod registry;
+pub mod report;
+pub mod script;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod state;
+pub mod storage;
+#[cfg(feature = "futures")]
+pub mod stream;
+pub mod training;
+pub mod transliterate;
+pub mod tuning;
+pub mod username;
+pub mod validation;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 pub use characterchain::generator::CharacterChainGenerator;
 pub use clusterchain::generator::ClusterChainGenerator;
+pub use clusterchain::start::StartCluster;
+pub use config::GeneratorConfig;
+pub use confusable::ConfusablePolicy;
+pub use error::NamegenError;
+pub use export::NameRecord;
+pub use fullname::{FullName, FullNameGenerator};
 pub use interface::RandomTextGenerator;
+pub use language::Language;
+pub use multilingual::MultilingualGenerator;
+pub use nickname::{Diminutives, NicknamedName};
+pub use phonotactics::Phonotactics;
+pub use pool::{GeneratorPool, NamePool, PerThreadGenerator};
+pub use preprocess::{CaseFolding, MultiWordPolicy, PunctuationPolicy, ReservedCharPolicy};
+pub use prior::PriorPolicy;
+pub use pronunciation::PronouncedName;
+pub use registry::{GeneratorSpec, NamedPostprocessor, Registry};
+pub use script::Script;
+pub use state::GeneratorState;
+pub use storage::ContextStorage;
+pub use transliterate::Transliteration;
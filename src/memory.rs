@@ -0,0 +1,317 @@
+use log::warn;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The names a generator has already produced, kept so that it never repeats one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NameMemory {
+    /// Every name, exactly.
+    Exact(HashSet<String>),
+    /// A Bloom filter: constant memory, but with a small chance of wrongly believing a new name
+    /// has already been produced.
+    Approximate(BloomFilter),
+    /// Every name, exactly, and also appended to a file, so that it's remembered after a
+    /// restart.
+    Persistent(PersistentMemory),
+}
+
+impl NameMemory {
+    /// Records a name, returning `false` if it (probably) had already been recorded.
+    pub(crate) fn insert(&mut self, name: &str) -> bool {
+        match self {
+            NameMemory::Exact(names) => names.insert(name.to_string()),
+            NameMemory::Approximate(filter) => filter.insert(name),
+            NameMemory::Persistent(memory) => memory.insert(name),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            NameMemory::Exact(names) => names.clear(),
+            NameMemory::Approximate(filter) => filter.clear(),
+            NameMemory::Persistent(memory) => memory.clear(),
+        }
+    }
+
+    /// Goes back to a memory saved in a `GeneratorState`.  A persistent memory takes the saved
+    /// names, but keeps appending to its file; a saved Bloom filter has no names to give it, so
+    /// it's ignored rather than letting the file go.
+    pub(crate) fn restore(&mut self, saved: &NameMemory) {
+        match (self, saved) {
+            (
+                NameMemory::Persistent(memory),
+                NameMemory::Exact(names) | NameMemory::Persistent(PersistentMemory { names, .. }),
+            ) => {
+                memory.names = names.clone();
+            }
+            (NameMemory::Persistent(memory), NameMemory::Approximate(_)) => {
+                warn!(
+                    "not restoring an approximate memory over the names in {}",
+                    memory.path.display()
+                );
+            }
+            (memory, saved) => *memory = saved.clone(),
+        }
+    }
+
+    /// Appends a platform-independent encoding of the memory to `bytes`.
+    pub(crate) fn encode(&self, bytes: &mut Vec<u8>) {
+        match self {
+            // a persistent memory is saved as an exact one; its file is not part of the state
+            NameMemory::Exact(names) | NameMemory::Persistent(PersistentMemory { names, .. }) => {
+                bytes.push(0);
+                let mut names: Vec<&String> = names.iter().collect();
+                names.sort();
+                for name in names {
+                    bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(name.as_bytes());
+                }
+            }
+            NameMemory::Approximate(filter) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&filter.num_hashes.to_le_bytes());
+                for word in &filter.bits {
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    /// Decodes a memory encoded with `encode()`.
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        let (tag, mut rest) = bytes.split_first()?;
+        match tag {
+            0 => {
+                let mut names = HashSet::new();
+                while !rest.is_empty() {
+                    let len = u32::from_le_bytes(rest.get(0..4)?.try_into().ok()?) as usize;
+                    let name = rest.get(4..4 + len)?;
+                    names.insert(String::from_utf8(name.to_vec()).ok()?);
+                    rest = &rest[4 + len..];
+                }
+                Some(NameMemory::Exact(names))
+            }
+            1 => {
+                let num_hashes = u32::from_le_bytes(rest.get(0..4)?.try_into().ok()?);
+                let words = rest.get(4..)?;
+                // a filter with no hashes or no bits would report every name as seen
+                if num_hashes == 0 || words.is_empty() || words.len() % 8 != 0 {
+                    return None;
+                }
+                let bits = words
+                    .chunks(8)
+                    .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+                    .collect();
+                Some(NameMemory::Approximate(BloomFilter { bits, num_hashes }))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A set of names backed by an append-only file with one name per line: the names in the file
+/// are loaded when it's opened, and each new one is appended to it as it's recorded.  Copies
+/// (e.g. of forked generators) append to the same file, but only learn of each other's names
+/// when it's opened again.
+#[derive(Debug, Clone)]
+pub(crate) struct PersistentMemory {
+    names: HashSet<String>,
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl PersistentMemory {
+    /// Opens the file, creating it if it doesn't exist, and loads the names in it.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let names = match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(String::from).collect(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(error) => return Err(error),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(PersistentMemory {
+            names,
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    fn insert(&mut self, name: &str) -> bool {
+        if !self.names.insert(name.to_string()) {
+            return false;
+        }
+        // one write per line, so that copies appending at the same time don't interleave
+        let line = format!("{}\n", name);
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(error) = file.write_all(line.as_bytes()) {
+            warn!(
+                "could not remember '{}' in {}: {}",
+                name,
+                self.path.display(),
+                error
+            );
+        }
+        true
+    }
+
+    fn clear(&mut self) {
+        self.names.clear();
+        let file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(error) = file.set_len(0) {
+            warn!("could not empty {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+impl PartialEq for PersistentMemory {
+    fn eq(&self, other: &Self) -> bool {
+        self.names == other.names && self.path == other.path
+    }
+}
+
+impl Eq for PersistentMemory {}
+
+/// A Bloom filter over strings, sized for an expected number of items and a target
+/// false-positive rate.  It hashes with FNV-1a rather than the standard library's randomly-keyed
+/// hasher, so that it behaves identically from run to run and can be saved and restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BloomFilter {
+    bits: Vec<u64>,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    pub(crate) fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(expected_items.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as usize;
+        let num_hashes = ((num_bits as f64 / expected_items.max(1) as f64) * ln2)
+            .round()
+            .max(1.0) as u32;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64)],
+            num_hashes,
+        }
+    }
+
+    /// Sets the item's bits, returning `false` if they were all set already.
+    fn insert(&mut self, item: &str) -> bool {
+        let num_bits = self.bits.len() as u64 * 64;
+        let (h1, h2) = fnv1a_pair(item.as_bytes());
+        let mut is_new = false;
+        for i in 0..self.num_hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                is_new = true;
+            }
+        }
+        is_new
+    }
+
+    fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+    }
+}
+
+/// Two hashes of the same bytes, for double hashing: an FNV-1a hash, passed through the
+/// SplitMix64 finalizer twice to spread its bits.
+fn fnv1a_pair(bytes: &[u8]) -> (u64, u64) {
+    let h1 = splitmix64(fnv1a(bytes));
+    (h1, splitmix64(h1) | 1)
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hasher is the same everywhere.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub(crate) fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_false_positive_rate() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        for i in 0..10_000 {
+            filter.insert(&format!("name{}", i));
+        }
+        assert!(!filter.clone().insert("name42"));
+        let false_positives = (0..10_000)
+            .filter(|i| !filter.clone().insert(&format!("other{}", i)))
+            .count();
+        assert!(false_positives < 200, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_persistent_memory_survives_reopening() {
+        let path =
+            std::env::temp_dir().join(format!("markov_namegen_seen_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut memory = NameMemory::Persistent(PersistentMemory::open(&path).unwrap());
+        assert!(memory.insert("romulus"));
+        assert!(memory.insert("remus"));
+        assert!(!memory.insert("romulus"));
+        let mut reopened = NameMemory::Persistent(PersistentMemory::open(&path).unwrap());
+        assert!(!reopened.insert("remus"));
+        assert!(reopened.insert("numa"));
+        reopened.clear();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_persistent_memory_is_not_replaced_on_restore() {
+        let path = std::env::temp_dir().join(format!(
+            "markov_namegen_restored_{}.txt",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut memory = NameMemory::Persistent(PersistentMemory::open(&path).unwrap());
+        memory.insert("romulus");
+        memory.restore(&NameMemory::Approximate(BloomFilter::new(100, 0.01)));
+        assert!(matches!(memory, NameMemory::Persistent(_)));
+        assert!(!memory.insert("romulus"));
+        memory.restore(&NameMemory::Exact(HashSet::from([String::from("numa")])));
+        assert!(!memory.insert("numa"));
+        assert!(memory.insert("romulus"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "romulus\nromulus\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_memory_survives_encoding() {
+        let mut exact = NameMemory::Exact(HashSet::new());
+        exact.insert("ærøskøbing");
+        exact.insert("");
+        let mut approximate = NameMemory::Approximate(BloomFilter::new(100, 0.01));
+        approximate.insert("romulus");
+        for memory in [exact, approximate] {
+            let mut bytes = Vec::new();
+            memory.encode(&mut bytes);
+            assert_eq!(NameMemory::decode(&bytes), Some(memory));
+            bytes.pop();
+            assert_eq!(NameMemory::decode(&bytes), None);
+        }
+        let mut no_hashes = vec![1, 0, 0, 0, 0];
+        no_hashes.extend_from_slice(&[0; 8]);
+        assert_eq!(NameMemory::decode(&no_hashes), None);
+        assert_eq!(NameMemory::decode(&[1, 3, 0, 0, 0]), None);
+    }
+}
@@ -0,0 +1,1403 @@
+use crate::error::NamegenError;
+use crate::prior::PriorPolicy;
+use crate::storage::{ContextStorage, Contexts, Transitions};
+use multimarkov::MultiMarkov;
+use rand::{Rng, RngCore};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// A Markov chain in the form training produces: a map from each context to a map from the
+/// states seen after it to their weights.
+pub(crate) type Chain<T> = HashMap<Vec<T>, BTreeMap<T, f64>>;
+
+/// The probabilities, worked out by `MarkovModel::random_with_length()`, of a sequence ending
+/// after states of exactly a given total size, by its best context and that size.
+pub(crate) type Endings<T> = HashMap<(Vec<T>, usize), f64>;
+
+/// What `.train_negative()` multiplies a transition's weight by, each time the transition
+/// appears in the anti-corpus.
+pub(crate) const NEGATIVE_FACTOR: f64 = 0.5;
+
+/// Classifies states as vowels or not for the prior; see `MarkovModel::add_prior()`.
+pub(crate) type VowelClassifier<T> = Arc<dyn Fn(&T) -> Option<bool> + Send + Sync>;
+
+/// The steps that turn the counts of the transitions seen in training into the model a
+/// generator draws from.  A generator keeps them, with the counts, so that the names it
+/// `learn()`s later are refined just as its training corpus was.
+pub(crate) struct Refinement<T> {
+    /// The marker at both ends of a sequence.
+    pub(crate) start: T,
+    /// See `MarkovModel::restrict_alphabet()`.
+    pub(crate) min_occurrences: usize,
+    /// See `MarkovModel::interpolate()`.
+    pub(crate) interpolation: Option<Vec<f64>>,
+    pub(crate) prior: Option<PriorPolicy>,
+    pub(crate) is_vowel: VowelClassifier<T>,
+    /// The anti-corpus, as sequences to penalize.
+    pub(crate) negative: Vec<Vec<T>>,
+    pub(crate) forbidden: Vec<(T, T)>,
+    pub(crate) storage: ContextStorage,
+}
+
+impl<T> Refinement<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    /// A refined copy of a model of counts: its rare states taken out, its orders interpolated,
+    /// the prior added, the anti-corpus penalized and the forbidden transitions removed, in
+    /// that order, and its contexts moved into the chosen storage.
+    pub(crate) fn refine(&self, counts: &MarkovModel<T>) -> Result<MarkovModel<T>, NamegenError> {
+        let mut model = MarkovModel::new(
+            counts.contexts.to_map(),
+            counts.known_states.clone(),
+            counts.order,
+        );
+        model.restrict_alphabet(&self.start, self.min_occurrences);
+        if let Some(lambdas) = &self.interpolation {
+            model.interpolate(lambdas)?;
+        }
+        if let Some(prior) = self.prior {
+            model.add_prior(prior, &*self.is_vowel);
+        }
+        if !self.negative.is_empty() {
+            model.penalize(&self.negative, NEGATIVE_FACTOR);
+        }
+        for (from, to) in &self.forbidden {
+            model.forbid(from, to);
+        }
+        Ok(model.with_storage(self.storage))
+    }
+}
+
+/// Contexts, each with the states that have followed it in sorted order and their weights, for
+/// `MarkovModel::encode_chain()`.
+pub(crate) type WeightedContexts<'m, T> = Vec<(Vec<T>, Vec<(&'m T, f64)>)>;
+
+/// The trained Markov chain behind both generators.
+///
+/// Training is delegated to the `multimarkov` crate, but the trained chain is moved into this
+/// struct so that sampling is under our control.  Contexts are only ever *looked up* in the
+/// `HashMap`; anything we iterate over (the transitions from a context, the alphabet of known
+/// states) lives in an ordered `BTree` collection.  That way, given the same training data and
+/// an RNG with the same seed, every draw walks the candidates in the same order and the output
+/// is fully deterministic.
+///
+/// The contexts are stored as chosen with `ContextStorage`, each with its transitions' weights
+/// laid out as a running total, so that a draw is a binary search instead of a walk summing
+/// every weight.  Changes to the transitions go through `edit()`, which brings all that up to
+/// date.
+pub(crate) struct MarkovModel<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    contexts: Contexts<T>,
+    pub(crate) known_states: BTreeSet<T>,
+    pub(crate) order: usize,
+}
+
+impl<T> MarkovModel<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    fn new(chain: Chain<T>, known_states: BTreeSet<T>, order: usize) -> Self {
+        MarkovModel {
+            contexts: Contexts::new(ContextStorage::default(), chain),
+            known_states,
+            order,
+        }
+    }
+
+    /// Moves the contexts into the given kind of storage.
+    pub(crate) fn with_storage(mut self, storage: ContextStorage) -> Self {
+        if self.contexts.storage() != storage {
+            self.contexts = Contexts::new(storage, self.contexts.to_map());
+        }
+        self
+    }
+
+    /// Changes the transitions after each context, in the form of a map from contexts to maps
+    /// from states to weights, then stores them again.
+    fn edit(&mut self, change: impl FnOnce(&mut Chain<T>)) {
+        let mut chain = self.contexts.to_map();
+        change(&mut chain);
+        self.contexts = Contexts::new(self.contexts.storage(), chain);
+    }
+
+    /// Every context with its transitions, in no particular order.
+    pub(crate) fn contexts(&self) -> impl Iterator<Item = (Vec<T>, &Transitions<T>)> {
+        self.contexts.iter()
+    }
+
+    /// How many contexts the model has.
+    pub(crate) fn context_count(&self) -> usize {
+        self.contexts.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+
+    /// Takes the model apart into its chain, as a map from contexts to maps from states to
+    /// weights, its known states and its order.
+    pub(crate) fn into_parts(self) -> (Chain<T>, BTreeSet<T>, usize) {
+        (self.contexts.to_map(), self.known_states, self.order)
+    }
+
+    /// For a given sequence, find the most tightly-fitted model we have for its tail-end
+    /// subsequence, backing off one state at a time (the "Katz back-off").
+    pub(crate) fn best_model(&self, current_sequence: &[T]) -> Option<&Transitions<T>> {
+        self.best_context(current_sequence)
+            .map(|(_, transitions)| transitions)
+    }
+
+    /// Like `best_model()`, but also returns the context it found, i.e. the tail-end
+    /// subsequence it backed off to.
+    pub(crate) fn best_context<'s>(
+        &self,
+        current_sequence: &'s [T],
+    ) -> Option<(&'s [T], &Transitions<T>)> {
+        self.contexts.best(current_sequence, self.order)
+    }
+
+    /// Draw a new state to follow the given sequence, with every candidate having a chance of
+    /// being selected in proportion to its weight.  Candidates are visited in sorted order.
+    pub(crate) fn random_next(&self, current_sequence: &[T], rng: &mut dyn RngCore) -> Option<T> {
+        let r: f64 = rng.gen();
+        self.best_model(current_sequence)?.draw(r).cloned()
+    }
+
+    /// Like `random_next()`, but if the best context has nothing left to follow it (e.g. after
+    /// `forbid()`), backs off to ever shorter contexts at the tail end of the sequence until
+    /// one has.
+    pub(crate) fn random_next_backing_off(
+        &self,
+        sequence: &[T],
+        rng: &mut dyn RngCore,
+    ) -> Option<T> {
+        let r: f64 = rng.gen();
+        self.backed_off_model(sequence)?.draw(r).cloned()
+    }
+
+    /// The transitions `random_next_backing_off()` draws from: those of the longest context at
+    /// the tail end of the sequence that has anything to follow it.
+    fn backed_off_model(&self, sequence: &[T]) -> Option<&Transitions<T>> {
+        let mut tail = sequence;
+        while let Some((context, transitions)) = self.best_context(tail) {
+            if !transitions.is_empty() {
+                return Some(transitions);
+            }
+            tail = &context[1..];
+        }
+        None
+    }
+
+    /// Like `random_next()`, but only candidates for which `allowed` returns true can be drawn.
+    /// The running totals don't apply, so this walks the candidates summing their weights.
+    pub(crate) fn random_next_where(
+        &self,
+        current_sequence: &[T],
+        rng: &mut dyn RngCore,
+        allowed: impl Fn(&T) -> bool,
+    ) -> Option<T> {
+        let r: f64 = rng.gen();
+        let bestmodel = self.best_model(current_sequence)?;
+        let sum_of_weights: f64 = bestmodel
+            .iter()
+            .filter(|(k, _)| allowed(k))
+            .map(|(_, v)| v)
+            .sum();
+        let mut randomroll = r * sum_of_weights;
+        for (k, v) in bestmodel.iter().filter(|(k, _)| allowed(k)) {
+            if randomroll > *v {
+                randomroll -= v;
+            } else {
+                return Some(k.clone());
+            }
+        }
+        // floating-point residue can leave a sliver of the roll after the last candidate
+        bestmodel
+            .iter()
+            .filter(|(k, v)| allowed(k) && **v > 0.0)
+            .last()
+            .map(|(k, _)| k.clone())
+    }
+
+    /// Draws the beginning of a sequence, from `prefix` (which begins with the `start` state)
+    /// up to and including the `required` states, so that a name can be made to contain them
+    /// without rejection sampling.  The states after the prefix are drawn as by
+    /// `random_next()`, except that they can't end the sequence (reach `start` again);
+    /// at each point where the model could go on to produce every required state in turn, it
+    /// does so with the probability the model gives the first one.  If it hasn't done so after
+    /// `PLANNING_HORIZON` states, it goes back to one of those points, chosen in proportion to
+    /// the same probabilities, and does so there.
+    /// Returns `None` if the prefix never reached a point from which the required states can
+    /// follow.
+    pub(crate) fn random_through(
+        &self,
+        prefix: &[T],
+        required: &[T],
+        rng: &mut dyn RngCore,
+    ) -> Option<Vec<T>> {
+        let start = &prefix[0];
+        let mut sequence = prefix.to_vec();
+        let mut entries = Vec::new();
+        for _step in 0..=PLANNING_HORIZON {
+            if let Some(probability) = self.entry_probability(&sequence, required) {
+                if rng.gen::<f64>() < probability {
+                    sequence.extend_from_slice(required);
+                    return Some(sequence);
+                }
+                entries.push((sequence.len(), probability));
+            }
+            match self.random_next_where(&sequence, rng, |state| state != start) {
+                Some(state) => sequence.push(state),
+                None => break,
+            }
+        }
+        let total: f64 = entries.iter().map(|(_, probability)| probability).sum();
+        let mut roll = rng.gen::<f64>() * total;
+        let (entry, _) = entries
+            .iter()
+            .find(|(_, probability)| {
+                roll -= probability;
+                roll <= 0.0
+            })
+            .or_else(|| entries.last())?;
+        sequence.truncate(*entry);
+        sequence.extend_from_slice(required);
+        Some(sequence)
+    }
+
+    /// Draws a whole sequence, from `start` back to `start`, whose states add up to exactly
+    /// `length` by `size` (e.g. their number of characters), as `random_next()` would draw it
+    /// if we only kept the sequences of that length.  At each step, every candidate is weighed
+    /// by its probability times the probability of the sequence ending after exactly the size
+    /// left over, worked out backward over the contexts and the sizes left.  They're remembered
+    /// in `endings`, for as long as the model and `size` stay the same, since the same context
+    /// and size are reached by many paths and for many lengths.  States of size zero are
+    /// skipped.  Returns `None` if the model can't produce a sequence of that length.
+    pub(crate) fn random_with_length(
+        &self,
+        start: &T,
+        length: usize,
+        size: impl Fn(&T) -> usize,
+        endings: &mut Endings<T>,
+        rng: &mut dyn RngCore,
+    ) -> Option<Vec<T>> {
+        let mut sequence = vec![start.clone()];
+        let mut left = length;
+        loop {
+            let (context, transitions) = self.best_context(&sequence)?;
+            let context = context.to_vec();
+            let weights: Vec<(&T, f64)> = transitions
+                .iter()
+                .map(|(state, weight)| {
+                    let ending =
+                        self.ending_probability(&context, state, left, start, &size, endings);
+                    (state, weight * ending)
+                })
+                .collect();
+            let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+            if total <= 0.0 {
+                return None;
+            }
+            let mut roll = rng.gen::<f64>() * total;
+            let state = weights
+                .iter()
+                .find(|(_, weight)| {
+                    roll -= weight;
+                    roll <= 0.0
+                })
+                .or_else(|| weights.iter().rev().find(|(_, weight)| *weight > 0.0))
+                .map(|(state, _)| (*state).clone())?;
+            sequence.push(state);
+            if sequence[sequence.len() - 1] == *start {
+                return Some(sequence);
+            }
+            left -= size(&sequence[sequence.len() - 1]);
+        }
+    }
+
+    /// The probability that a sequence whose best context is `context` goes on to `state`, and
+    /// from there ends after states adding up to exactly the rest of `left`; 1 or 0 for `start`,
+    /// which ends it, depending on whether nothing is left.  `endings` remembers the
+    /// probabilities already worked out, by context and size left.
+    fn ending_probability(
+        &self,
+        context: &[T],
+        state: &T,
+        left: usize,
+        start: &T,
+        size: &impl Fn(&T) -> usize,
+        endings: &mut Endings<T>,
+    ) -> f64 {
+        if state == start {
+            return if left == 0 { 1.0 } else { 0.0 };
+        }
+        let state_size = size(state);
+        if state_size == 0 || state_size > left {
+            return 0.0;
+        }
+        let left = left - state_size;
+        let mut sequence = context.to_vec();
+        sequence.push(state.clone());
+        let Some((next, transitions)) = self.best_context(&sequence) else {
+            return 0.0;
+        };
+        let key = (next.to_vec(), left);
+        if let Some(&probability) = endings.get(&key) {
+            return probability;
+        }
+        let probability = transitions
+            .iter()
+            .map(|(after, weight)| {
+                weight * self.ending_probability(&key.0, after, left, start, size, endings)
+            })
+            .sum::<f64>()
+            / transitions.total();
+        endings.insert(key, probability);
+        probability
+    }
+
+    /// The choices made in generating a sequence that starts from its first state: at each
+    /// step, the index of the state that came next among the candidates after that context, in
+    /// sorted order.  A context with nothing to follow it backs off as in strict mode.
+    pub(crate) fn choices(&self, sequence: &[T]) -> Option<Vec<usize>> {
+        (1..sequence.len())
+            .map(|i| {
+                self.backed_off_model(&sequence[..i])?
+                    .keys()
+                    .position(|state| *state == sequence[i])
+            })
+            .collect()
+    }
+
+    /// Replays choices recorded with `choices()`, from `start` until it's reached again.
+    /// Returns `None` if they don't fit the model: if a choice is out of range, or they run out
+    /// before the end of the sequence or go on after it.
+    pub(crate) fn replay(&self, start: &T, choices: &[usize]) -> Option<Vec<T>> {
+        let mut sequence = vec![start.clone()];
+        for (i, &choice) in choices.iter().enumerate() {
+            let state = self
+                .backed_off_model(&sequence)?
+                .keys()
+                .nth(choice)?
+                .clone();
+            let ended = state == *start;
+            sequence.push(state);
+            if ended {
+                return (i + 1 == choices.len()).then_some(sequence);
+            }
+        }
+        None
+    }
+
+    /// Makes `edits` random edits to a sequence of states (without the markers at either end): each
+    /// one substitutes a state, inserts one or deletes one, at a random position.  A new state is
+    /// drawn in proportion to the probability of the first `order` transitions it takes part in (to
+    /// it, and on from it), so that it fits in on both sides.  A deletion is kept with the
+    /// probability of the transitions that take its place relative to that of the transitions it
+    /// removes, if that's less than one.  Only transitions seen in training count, not those the
+    /// prior allows, so that the edits stay as plausible as the training data.  An edit that isn't
+    /// possible is tried again elsewhere, up to `MUTATION_TRIES` times.
+    pub(crate) fn mutate(
+        &self,
+        start: &T,
+        states: &[T],
+        edits: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<T> {
+        let mut sequence: Vec<T> = std::iter::once(start)
+            .chain(states)
+            .chain(std::iter::once(start))
+            .cloned()
+            .collect();
+        let mut made = 0;
+        for _try in 0..edits * MUTATION_TRIES {
+            if made == edits {
+                break;
+            }
+            // the states are at 1..end; a new one may be inserted before any of 1..=end
+            let end = sequence.len() - 1;
+            let position = rng.gen_range(1..=end);
+            match rng.gen_range(0..3) {
+                0 if position < end => {
+                    let current = sequence[position].clone();
+                    let mut candidate = sequence.clone();
+                    if let Some(state) = self.random_fitting(
+                        &mut candidate,
+                        position,
+                        |state| *state != current && state != start,
+                        rng,
+                    ) {
+                        sequence[position] = state;
+                        made += 1;
+                    }
+                }
+                1 if position < end && end > 2 => {
+                    let before = self.affected_probability(&sequence, position);
+                    let mut shorter = sequence.clone();
+                    shorter.remove(position);
+                    let after = self.affected_probability(&shorter, position);
+                    if after > 0.0 && rng.gen::<f64>() * before < after {
+                        sequence = shorter;
+                        made += 1;
+                    }
+                }
+                _ => {
+                    let mut candidate = sequence.clone();
+                    candidate.insert(position, start.clone());
+                    if let Some(state) =
+                        self.random_fitting(&mut candidate, position, |state| state != start, rng)
+                    {
+                        sequence.insert(position, state);
+                        made += 1;
+                    }
+                }
+            }
+        }
+        sequence[1..sequence.len() - 1].to_vec()
+    }
+
+    /// Draws a state to put at `position` in the sequence, in proportion to the probability of
+    /// the transitions it affects.  Leaves the sequence with an arbitrary state there.
+    fn random_fitting(
+        &self,
+        sequence: &mut [T],
+        position: usize,
+        allowed: impl Fn(&T) -> bool,
+        rng: &mut dyn RngCore,
+    ) -> Option<T> {
+        let weights: Vec<(T, f64)> = self
+            .best_model(&sequence[..position])?
+            .keys()
+            .filter(|state| allowed(state))
+            .map(|state| {
+                sequence[position] = state.clone();
+                (state.clone(), self.affected_probability(sequence, position))
+            })
+            .collect();
+        let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut roll = rng.gen::<f64>() * total;
+        weights
+            .iter()
+            .find(|(_, weight)| {
+                roll -= weight;
+                roll <= 0.0
+            })
+            .or_else(|| weights.last())
+            .map(|(state, _)| state.clone())
+    }
+
+    /// The probability of the first `order` transitions a state at `position` takes part in,
+    /// i.e. to it and to the states after it (or as many as there are), counting
+    /// those that were never seen in training (whose weight, from the prior, is less than one
+    /// observation) as impossible.
+    fn affected_probability(&self, sequence: &[T], position: usize) -> f64 {
+        (position..sequence.len().min(position + self.order))
+            .map(|i| {
+                self.best_model(&sequence[..i]).map_or(0.0, |transitions| {
+                    let weight = transitions.get(&sequence[i]).copied().unwrap_or(0.0);
+                    if weight < 1.0 {
+                        0.0
+                    } else {
+                        weight / transitions.total()
+                    }
+                })
+            })
+            .product()
+    }
+
+    /// Checks that `states`, the tokens of the `substring` passed to
+    /// `.with_required_substring()`, are all known to the model.
+    pub(crate) fn required_states(
+        &self,
+        substring: &str,
+        states: Vec<T>,
+    ) -> Result<Vec<T>, NamegenError> {
+        if states.is_empty() {
+            return Err(NamegenError::InvalidConfig(String::from(
+                "the required substring is empty",
+            )));
+        }
+        if !states.iter().all(|state| self.known_states.contains(state)) {
+            return Err(NamegenError::InvalidConfig(format!(
+                "the model has never seen some part of the required substring '{}'",
+                substring
+            )));
+        }
+        Ok(states)
+    }
+
+    /// The probability of the first of the `required` states following the sequence, if the
+    /// model can go on to produce the rest of them after it; `None` if it can't.
+    fn entry_probability(&self, sequence: &[T], required: &[T]) -> Option<f64> {
+        let mut history = sequence.to_vec();
+        let mut probability = None;
+        for state in required {
+            let transitions = self.best_model(&history)?;
+            let weight = transitions.get(state).filter(|weight| **weight > 0.0)?;
+            probability.get_or_insert(weight / transitions.total());
+            history.push(state.clone());
+        }
+        probability
+    }
+
+    /// Takes the states seen fewer than `min_occurrences` times out of the model: the
+    /// transitions to them are removed (and any context left with none, so that a draw backs
+    /// off to a shorter one), and they're no longer known, so a prior added later can't bring
+    /// them back.  A state's occurrences are counted from the contexts of one state, since
+    /// each occurrence follows exactly one.  `start`, the marker at both ends of a sequence,
+    /// is always kept.
+    pub(crate) fn restrict_alphabet(&mut self, start: &T, min_occurrences: usize) {
+        let mut occurrences: HashMap<T, f64> = HashMap::new();
+        for (context, transitions) in self.contexts.iter() {
+            if context.len() == 1 {
+                for (state, weight) in transitions {
+                    *occurrences.entry(state.clone()).or_default() += weight;
+                }
+            }
+        }
+        let rare: BTreeSet<T> = self
+            .known_states
+            .iter()
+            .filter(|state| *state != start)
+            .filter(|state| {
+                occurrences.get(*state).copied().unwrap_or(0.0) < min_occurrences as f64
+            })
+            .cloned()
+            .collect();
+        if rare.is_empty() {
+            return;
+        }
+        self.known_states.retain(|state| !rare.contains(state));
+        self.edit(|chain| {
+            for transitions in chain.values_mut() {
+                transitions.retain(|state, _| !rare.contains(state));
+            }
+            chain.retain(|_, transitions| !transitions.is_empty());
+        });
+    }
+
+    /// Mixes each context's transitions with those of the shorter contexts at its tail end
+    /// (Jelinek-Mercer interpolation), instead of leaving every draw to the longest context
+    /// alone.  The probabilities after a context of length `k`, and after each of its tail
+    /// ends, are weighted by `lambdas[k - 1]` and so on down to `lambdas[0]`, scaled so that
+    /// the weights used add up to one, and the mix takes the place of the context's
+    /// transitions, with the same total weight.  Returns a `NamegenError::InvalidConfig` error
+    /// if there are fewer lambdas than the model's order.
+    pub(crate) fn interpolate(&mut self, lambdas: &[f64]) -> Result<(), NamegenError> {
+        if lambdas.len() < self.order {
+            return Err(NamegenError::InvalidConfig(format!(
+                "interpolation needs a lambda for each of the model's {} orders, not {}",
+                self.order,
+                lambdas.len()
+            )));
+        }
+        self.edit(|chain| {
+            let original = chain.clone();
+            for (context, transitions) in chain.iter_mut() {
+                let mut mixed: BTreeMap<T, f64> = BTreeMap::new();
+                let mut used = 0.0;
+                for length in 1..=context.len() {
+                    let lambda = lambdas[length - 1];
+                    let Some(tail) = original.get(&context[context.len() - length..]) else {
+                        continue;
+                    };
+                    let total: f64 = tail.values().sum();
+                    if lambda <= 0.0 || total <= 0.0 {
+                        continue;
+                    }
+                    used += lambda;
+                    for (state, weight) in tail {
+                        *mixed.entry(state.clone()).or_default() += lambda * weight / total;
+                    }
+                }
+                if used > 0.0 {
+                    let total: f64 = transitions.values().sum();
+                    for (_, probability) in mixed.iter_mut() {
+                        *probability *= total / used;
+                    }
+                    *transitions = mixed;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Gives weight to the transitions never seen after each context, so that any known state
+    /// may follow any context.  `is_vowel` classifies states for `PriorPolicy::ByClass`, and
+    /// returns `None` for the '#' marking the start and end of a word, which belongs to neither
+    /// class.
+    pub(crate) fn add_prior(&mut self, policy: PriorPolicy, is_vowel: impl Fn(&T) -> Option<bool>) {
+        let known_states = self.known_states.clone();
+        let states = known_states.len();
+        self.edit(|chain| {
+            for (context, transitions) in chain.iter_mut() {
+                let class = context.last().and_then(&is_vowel);
+                let alternatives = transitions.len();
+                for state in &known_states {
+                    if !transitions.contains_key(state) {
+                        let same_class = class.is_some() && class == is_vowel(state);
+                        let weight = policy.weight(alternatives, states, same_class);
+                        // a transition with no weight is one that can't happen, so leave it out
+                        if weight > 0.0 {
+                            transitions.insert(state.clone(), weight);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// A copy of the model that has learned from more sequences, counting each state after
+    /// each of the contexts before it up to the model's order, as training does.  Before each
+    /// sequence is counted, every weight already in the model is multiplied by `1 - decay`, so
+    /// that with a decay above zero the older observations count for less and less.
+    pub(crate) fn learned(&self, sequences: &[Vec<T>], decay: f64) -> Self {
+        let mut chain = self.contexts.to_map();
+        let mut known_states = self.known_states.clone();
+        let retained = |sequences: usize| (1.0 - decay).powi(sequences as i32);
+        if decay > 0.0 {
+            let factor = retained(sequences.len());
+            for weight in chain.values_mut().flat_map(BTreeMap::values_mut) {
+                *weight *= factor;
+            }
+        }
+        for (i, sequence) in sequences.iter().enumerate() {
+            // the later sequences have decayed less by the time the last is counted
+            let weight = retained(sequences.len() - 1 - i);
+            for position in 1..sequence.len() {
+                let state = &sequence[position];
+                known_states.insert(state.clone());
+                for start in position.saturating_sub(self.order)..position {
+                    *chain
+                        .entry(sequence[start..position].to_vec())
+                        .or_default()
+                        .entry(state.clone())
+                        .or_insert(0.0) += weight;
+                }
+            }
+        }
+        MarkovModel {
+            contexts: Contexts::new(self.contexts.storage(), chain),
+            known_states,
+            order: self.order,
+        }
+    }
+
+    /// Makes the transitions in some sequences less likely, by multiplying the weight of each
+    /// one by `factor` for every time it appears after each of the contexts before it up to
+    /// the model's order.  Transitions are made rarer but never impossible; contexts and
+    /// transitions the model doesn't have are ignored.
+    pub(crate) fn penalize(&mut self, sequences: &[Vec<T>], factor: f64) {
+        let order = self.order;
+        self.edit(|chain| {
+            for sequence in sequences {
+                for position in 1..sequence.len() {
+                    for start in position.saturating_sub(order)..position {
+                        let weight = chain
+                            .get_mut(&sequence[start..position])
+                            .and_then(|transitions| transitions.get_mut(&sequence[position]));
+                        if let Some(weight) = weight {
+                            *weight *= factor;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Makes `to` impossible straight after `from`, whatever the weight the training data or
+    /// the prior gave it.
+    pub(crate) fn forbid(&mut self, from: &T, to: &T) {
+        self.edit(|chain| {
+            for (context, transitions) in chain.iter_mut() {
+                if context.last() == Some(from) {
+                    transitions.remove(to);
+                }
+            }
+        });
+    }
+
+    /// The natural log of the probability of the model producing the given sequence, starting
+    /// from its first state.
+    pub(crate) fn log_probability(&self, sequence: &[T]) -> f64 {
+        (1..sequence.len())
+            .map(|i| {
+                let transitions = self.best_model(&sequence[..i]);
+                let weight = transitions.and_then(|t| t.get(&sequence[i])).copied();
+                match (transitions, weight) {
+                    (Some(t), Some(w)) => (w / t.total()).ln(),
+                    _ => f64::NEG_INFINITY,
+                }
+            })
+            .sum()
+    }
+
+    /// The information in the model's choices along the given sequence, in bits: for each
+    /// state after the first, the entropy of the transitions it was drawn from.  Averaged over
+    /// the sequences the model produces, this is the entropy of its output.
+    pub(crate) fn branching_bits(&self, sequence: &[T]) -> f64 {
+        (1..sequence.len())
+            .filter_map(|i| self.best_model(&sequence[..i]))
+            .map(|transitions| {
+                let total = transitions.total();
+                transitions
+                    .iter()
+                    .map(|(_, weight)| weight / total)
+                    .filter(|&p| p > 0.0)
+                    .map(|p| -p * p.log2())
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+}
+
+impl<T> MarkovModel<T>
+where
+    T: Eq + Hash + Clone + Ord + ModelState,
+{
+    const MAGIC: &'static [u8; 4] = b"MNG1";
+
+    /// Encodes the model as a platform-independent byte string, e.g. to be precompiled by a
+    /// build script.  Contexts are written in sorted order, so the same model always encodes
+    /// to the same bytes.
+    pub(crate) fn encode(&self, bytes: &mut Vec<u8>) {
+        let contexts = self
+            .contexts()
+            .map(|(context, transitions)| {
+                let weights = transitions.iter().map(|(state, weight)| (state, *weight));
+                (context, weights.collect())
+            })
+            .collect();
+        Self::encode_chain(self.order, contexts, self.known_states.iter(), bytes);
+    }
+
+    /// Encodes a chain as `encode()` does, given its contexts, each with the states that have
+    /// followed it in sorted order and their weights, and its known states in sorted order.
+    pub(crate) fn encode_chain<'m>(
+        order: usize,
+        mut contexts: WeightedContexts<'m, T>,
+        known_states: impl ExactSizeIterator<Item = &'m T>,
+        bytes: &mut Vec<u8>,
+    ) where
+        T: 'm,
+    {
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.push(T::TAG);
+        bytes.extend_from_slice(&(order as u32).to_le_bytes());
+        contexts.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bytes.extend_from_slice(&(contexts.len() as u32).to_le_bytes());
+        for (context, transitions) in contexts {
+            encode_states(context.iter(), bytes);
+            encode_states(transitions.iter().map(|(state, _)| *state), bytes);
+            for (_, weight) in transitions {
+                bytes.extend_from_slice(&weight.to_le_bytes());
+            }
+        }
+        encode_states(known_states, bytes);
+    }
+
+    /// Decodes a model encoded with `encode()`.  Returns `None` if the bytes are not a valid
+    /// model with states of this type.
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut rest = bytes.strip_prefix(Self::MAGIC.as_slice())?;
+        if take(&mut rest, 1)? != [T::TAG] {
+            return None;
+        }
+        let order = take_u32(&mut rest)? as usize;
+        let mut chain = HashMap::new();
+        for _i in 0..take_u32(&mut rest)? {
+            let context = decode_states(&mut rest)?;
+            let states: Vec<T> = decode_states(&mut rest)?;
+            let mut transitions = BTreeMap::new();
+            for state in states {
+                let weight = f64::from_le_bytes(take(&mut rest, 8)?.try_into().ok()?);
+                transitions.insert(state, weight);
+            }
+            chain.insert(context, transitions);
+        }
+        let known_states = decode_states(&mut rest)?.into_iter().collect();
+        if !rest.is_empty() {
+            return None;
+        }
+        Some(MarkovModel::new(chain, known_states, order))
+    }
+}
+
+/// A state of a Markov chain that can be encoded as text: a character for
+/// `CharacterChainGenerator`, a cluster for `ClusterChainGenerator`.
+pub(crate) trait ModelState: Sized {
+    /// Distinguishes models of different state types in the encoding.
+    const TAG: u8;
+    fn encode(&self, bytes: &mut Vec<u8>);
+    fn decode(text: &str) -> Option<Self>;
+}
+
+impl ModelState for char {
+    const TAG: u8 = b'c';
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(self.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    fn decode(text: &str) -> Option<Self> {
+        let mut chars = text.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    }
+}
+
+impl ModelState for String {
+    const TAG: u8 = b's';
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(self.as_bytes());
+    }
+    fn decode(text: &str) -> Option<Self> {
+        Some(text.to_string())
+    }
+}
+
+fn encode_states<'t, T: ModelState + 't>(
+    states: impl ExactSizeIterator<Item = &'t T>,
+    bytes: &mut Vec<u8>,
+) {
+    bytes.extend_from_slice(&(states.len() as u32).to_le_bytes());
+    for state in states {
+        let mut encoded = Vec::new();
+        state.encode(&mut encoded);
+        bytes.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&encoded);
+    }
+}
+
+fn decode_states<T: ModelState>(rest: &mut &[u8]) -> Option<Vec<T>> {
+    (0..take_u32(rest)?)
+        .map(|_i| {
+            let len = take_u32(rest)? as usize;
+            T::decode(std::str::from_utf8(take(rest, len)?).ok()?)
+        })
+        .collect()
+}
+
+fn take<'b>(rest: &mut &'b [u8], len: usize) -> Option<&'b [u8]> {
+    let taken = rest.get(..len)?;
+    *rest = &rest[len..];
+    Some(taken)
+}
+
+fn take_u32(rest: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(take(rest, 4)?.try_into().ok()?))
+}
+
+/// How many times a generator in strict mode starts a word over after reaching a dead end,
+/// before it gives up with a `NamegenError::DeadEnd` error.
+pub(crate) const STRICT_RESTARTS: usize = 100;
+
+/// How many states `MarkovModel::random_through()` draws before the required states, at most.
+/// Names are seldom longer than this, so the prefix has every chance to end up where the model
+/// would naturally produce the required states.
+const PLANNING_HORIZON: usize = 10;
+
+/// How many times `MarkovModel::mutate()` tries to make each edit, at most.
+const MUTATION_TRIES: usize = 20;
+
+/// Picks an order and prior suited to a corpus of tokenized words, for `.auto_tune()`.
+///
+/// The order is the context length at which the corpus is just big enough to see every
+/// context about once, i.e. ln(tokens) / ln(alphabet size), rounded and kept between 1 and 5.
+/// The prior spreads a tenth of an observation over the whole alphabet, i.e. 0.1 / alphabet
+/// size, so that unseen transitions stay rare however many distinct tokens there are.
+pub(crate) fn tuned_order_and_prior<T: Eq + Hash>(words: &[Vec<T>]) -> (usize, f64) {
+    let tokens: usize = words.iter().map(Vec::len).sum();
+    let alphabet = words.iter().flatten().collect::<HashSet<_>>().len().max(2);
+    let order = ((tokens.max(1) as f64).ln() / (alphabet as f64).ln())
+        .round()
+        .clamp(1.0, 5.0) as usize;
+    (order, 0.1 / alphabet as f64)
+}
+
+/// Subtracts from a chain of raw counts, before any prior is added, the counts that training
+/// on `sequences` added to it, and takes the states no longer seen out of `known_states`.
+/// Transitions (and contexts) whose counts fall to zero are removed, as if they had never been
+/// seen.  If the chain doesn't have all the counts to subtract, it's left as it was.
+pub(crate) fn untrain<T: Eq + Hash + Clone + Ord>(
+    chain: &mut Chain<T>,
+    known_states: &mut HashSet<T>,
+    order: usize,
+    sequences: impl Iterator<Item = Vec<T>>,
+) -> Result<(), NamegenError> {
+    let mut counts: Chain<T> = HashMap::new();
+    // sequences too short to train on weren't trained on
+    for sequence in sequences.filter(|sequence| sequence.len() > 1) {
+        for position in 1..sequence.len() {
+            for start in position.saturating_sub(order)..position {
+                *counts
+                    .entry(sequence[start..position].to_vec())
+                    .or_default()
+                    .entry(sequence[position].clone())
+                    .or_insert(0.0) += 1.0;
+            }
+        }
+    }
+    let untrained = counts.iter().all(|(context, transitions)| {
+        transitions.iter().all(|(state, count)| {
+            let weight = chain.get(context).and_then(|seen| seen.get(state));
+            weight.is_some_and(|weight| weight >= count)
+        })
+    });
+    if !untrained {
+        return Err(NamegenError::InvalidTrainingData(String::from(
+            "the model wasn't trained on the samples to untrain",
+        )));
+    }
+    for (context, transitions) in counts {
+        let seen = chain.get_mut(&context).expect("checked above");
+        for (state, count) in transitions {
+            let weight = seen.get_mut(&state).expect("checked above");
+            *weight -= count;
+            if *weight <= 0.0 {
+                seen.remove(&state);
+            }
+        }
+        if seen.is_empty() {
+            chain.remove(&context);
+        }
+    }
+    *known_states = chain.values().flat_map(BTreeMap::keys).cloned().collect();
+    Ok(())
+}
+
+impl<T> From<MultiMarkov<T>> for MarkovModel<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    fn from(trained: MultiMarkov<T>) -> Self {
+        MarkovModel::new(
+            trained.markov_chain,
+            trained.known_states.into_iter().collect(),
+            trained.order as usize,
+        )
+    }
+}
+
+impl<T> fmt::Debug for MarkovModel<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(&format!("MarkovModel<{}>", std::any::type_name::<T>()))
+            .field("order", &self.order)
+            .field("storage", &self.contexts.storage())
+            .field("contexts", &self.contexts.len())
+            .field("known_states", &self.known_states.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    fn trained_model() -> MarkovModel<char> {
+        MultiMarkov::<char>::builder()
+            .with_order(2)
+            .with_prior(0.01)
+            .train(
+                vec!["#ace#", "#foobar#", "#baz#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into()
+    }
+
+    #[test]
+    fn test_back_off_to_shorter_context() {
+        let model = trained_model();
+        // "xb" was never seen, but "b" was, so we should back off to the model for "b"
+        assert_eq!(model.best_model(&['x', 'b']), model.best_model(&['b']));
+        assert!(model.best_model(&['x', 'y']).is_none());
+    }
+
+    #[test]
+    fn test_log_probability() {
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(1)
+            .without_prior()
+            .train(
+                vec!["#ab#", "#ac#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        assert!((model.log_probability(&['#', 'a', 'b', '#']) - 0.5f64.ln()).abs() < 1e-9);
+        assert_eq!(model.log_probability(&['#', 'b']), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_branching_bits() {
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(1)
+            .without_prior()
+            .train(
+                vec!["#ab#", "#ac#", "#ad#", "#ae#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        // only the letter after 'a' is a choice, one of four
+        assert!((model.branching_bits(&['#', 'a', 'b', '#']) - 2.0).abs() < 1e-9);
+        assert_eq!(model.branching_bits(&['#', 'a']), 0.0);
+    }
+
+    #[test]
+    fn test_model_survives_encoding() {
+        let model = trained_model();
+        let mut bytes = Vec::new();
+        model.encode(&mut bytes);
+        let decoded = MarkovModel::<char>::decode(&bytes).unwrap();
+        assert_eq!(decoded.contexts.to_map(), model.contexts.to_map());
+        assert_eq!(decoded.known_states, model.known_states);
+        assert_eq!(decoded.order, model.order);
+        // a model of characters isn't a model of clusters, and a truncated one isn't a model
+        assert!(MarkovModel::<String>::decode(&bytes).is_none());
+        bytes.pop();
+        assert!(MarkovModel::<char>::decode(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_tuned_order_and_prior() {
+        let words: Vec<Vec<char>> = vec!["abcd"; 100]
+            .into_iter()
+            .map(|s| s.chars().collect())
+            .collect();
+        // 400 tokens from an alphabet of 4: ln 400 / ln 4 = 4.3
+        assert_eq!(tuned_order_and_prior(&words), (4, 0.025));
+        assert_eq!(tuned_order_and_prior::<char>(&[]), (1, 0.05));
+    }
+
+    #[test]
+    fn test_prior_policies() {
+        let untrained = || -> MarkovModel<char> {
+            MultiMarkov::<char>::builder()
+                .with_order(1)
+                .without_prior()
+                .train(
+                    vec!["#ab#", "#ac#", "#cd#"]
+                        .into_iter()
+                        .map(|s| s.chars().collect()),
+                )
+                .build()
+                .into()
+        };
+        let mut constant = untrained();
+        constant.add_prior(PriorPolicy::Constant(0.01), |_| None);
+        assert_eq!(constant.best_model(&['a']).unwrap()[&'d'], 0.01);
+        // 'a' has been followed by two different states, out of five, 'b' by one
+        let mut per_alternative = untrained();
+        per_alternative.add_prior(PriorPolicy::PerAlternative(0.3), |_| None);
+        assert!((per_alternative.best_model(&['a']).unwrap()[&'d'] - 0.2).abs() < 1e-9);
+        assert!((per_alternative.best_model(&['b']).unwrap()[&'a'] - 0.075).abs() < 1e-9);
+        assert_eq!(per_alternative.best_model(&['a']).unwrap()[&'b'], 1.0);
+        let mut by_class = untrained();
+        let policy = PriorPolicy::ByClass {
+            alternating: 0.01,
+            same: 0.0,
+        };
+        by_class.add_prior(policy, |c| (*c != '#').then(|| "aeiou".contains(*c)));
+        assert!(by_class.best_model(&['b']).unwrap().get(&'d').is_none());
+        assert_eq!(by_class.best_model(&['b']).unwrap()[&'a'], 0.01);
+        assert_eq!(by_class.best_model(&['#']).unwrap()[&'b'], 0.01);
+    }
+
+    #[test]
+    fn test_forbidden_transitions_are_removed() {
+        let mut model = trained_model();
+        assert!(model.best_model(&['o', 'o']).unwrap().get(&'b').is_some());
+        model.forbid(&'o', &'b');
+        assert!(model.best_model(&['o', 'o']).unwrap().get(&'b').is_none());
+        assert!(model.best_model(&['o']).unwrap().get(&'b').is_none());
+        assert!(model.best_model(&['a']).unwrap().get(&'b').is_some());
+    }
+
+    #[test]
+    fn test_planned_sequences_pass_through_the_required_states() {
+        let model = trained_model();
+        let mut rng = SmallRng::seed_from_u64(7);
+        for _i in 0..50 {
+            let sequence = model.random_through(&['#'], &['o', 'b'], &mut rng).unwrap();
+            assert!(sequence.ends_with(&['o', 'b']));
+            assert!(!sequence[1..].contains(&'#'));
+            assert!(model.log_probability(&sequence).is_finite());
+        }
+        // without a prior, 'b' never follows 'b'
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .without_prior()
+            .train(
+                vec!["#foobar#", "#baz#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        assert_eq!(model.random_through(&['#'], &['b', 'b'], &mut rng), None);
+    }
+
+    #[test]
+    fn test_sequences_of_a_given_length_are_drawn_as_if_filtered() {
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(1)
+            .without_prior()
+            .train(
+                vec!["#abba#", "#ab#", "#ba#", "#aab#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut endings = Endings::new();
+        assert_eq!(
+            model.random_with_length(&'#', 0, |_| 1, &mut endings, &mut rng),
+            None
+        );
+        // how often "abab" comes up among the names of four letters, drawn either way
+        let mut exact = 0;
+        for _i in 0..2000 {
+            let sequence = model
+                .random_with_length(&'#', 4, |_| 1, &mut endings, &mut rng)
+                .unwrap();
+            assert_eq!(sequence.len(), 6);
+            assert!(model.log_probability(&sequence).is_finite());
+            exact += usize::from(sequence == ['#', 'a', 'b', 'a', 'b', '#']);
+        }
+        let (mut filtered, mut kept) = (0, 0);
+        while kept < 2000 {
+            let mut sequence = vec!['#'];
+            while sequence.len() < 7 {
+                sequence.push(model.random_next(&sequence, &mut rng).unwrap());
+                if sequence.ends_with(&['#']) {
+                    break;
+                }
+            }
+            if sequence.len() == 6 && sequence.ends_with(&['#']) {
+                kept += 1;
+                filtered += usize::from(sequence == ['#', 'a', 'b', 'a', 'b', '#']);
+            }
+        }
+        assert!(exact.abs_diff(filtered) < 100, "{} vs {}", exact, filtered);
+    }
+
+    #[test]
+    fn test_backing_off_past_a_dead_end() {
+        let mut model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .without_prior()
+            .train(
+                vec!["#ab#", "#cbd#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        model.edit(|chain| chain.get_mut(&vec!['a', 'b']).unwrap().clear());
+        let mut rng = SmallRng::seed_from_u64(3);
+        assert_eq!(model.random_next(&['#', 'a', 'b'], &mut rng), None);
+        for _i in 0..20 {
+            let next = model.random_next_backing_off(&['#', 'a', 'b'], &mut rng);
+            assert!(matches!(next, Some('#' | 'd')));
+        }
+        model.edit(|chain| chain.get_mut(&vec!['b']).unwrap().clear());
+        assert_eq!(
+            model.random_next_backing_off(&['#', 'a', 'b'], &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rare_states_are_taken_out() {
+        let mut model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .without_prior()
+            .train(
+                vec!["#ana#", "#anna#", "#nox#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        model.restrict_alphabet(&'#', 2);
+        assert_eq!(model.known_states, BTreeSet::from(['#', 'a', 'n']));
+        assert!(model
+            .contexts()
+            .all(|(_, transitions)| !transitions.keys().any(|c| *c == 'o' || *c == 'x')));
+        // a word starting with "n" only ever went on to "o", so "#n" backs off to "n"
+        assert_eq!(model.best_context(&['#', 'n']).unwrap().0, ['n']);
+        model.add_prior(PriorPolicy::Constant(0.1), |_| None);
+        assert!(!model.best_model(&['a']).unwrap().keys().any(|c| *c == 'x'));
+    }
+
+    #[test]
+    fn test_interpolation_mixes_the_orders() {
+        let mut model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .without_prior()
+            .train(
+                vec!["#xac#", "#ab#", "#ab#"]
+                    .into_iter()
+                    .map(|s| s.chars().collect()),
+            )
+            .build()
+            .into();
+        assert!(matches!(
+            model.interpolate(&[1.0]),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+        model.interpolate(&[0.5, 0.5]).unwrap();
+        // after "xa", "c" is certain, but after "a", "b" is twice as likely
+        let after_xa = model.best_model(&['x', 'a']).unwrap();
+        assert!((after_xa[&'c'] - 2.0 / 3.0).abs() < 1e-9);
+        assert!((after_xa[&'b'] - 1.0 / 3.0).abs() < 1e-9);
+        let after_a = model.best_model(&['a']).unwrap();
+        assert_eq!((after_a[&'b'], after_a[&'c']), (2.0, 1.0));
+    }
+
+    #[test]
+    fn test_choices_replay_the_sequence() {
+        let model = trained_model();
+        let sequence: Vec<char> = "#foobaz#".chars().collect();
+        let choices = model.choices(&sequence).unwrap();
+        assert_eq!(choices.len(), sequence.len() - 1);
+        assert_eq!(model.replay(&'#', &choices), Some(sequence));
+        assert_eq!(model.replay(&'#', &choices[..3]), None);
+        assert_eq!(model.replay(&'#', &[0, 0, 0, 99]), None);
+    }
+
+    #[test]
+    fn test_mutations_edit_a_few_states() {
+        let model = trained_model();
+        let mut rng = SmallRng::seed_from_u64(3);
+        let original: Vec<char> = "foobar".chars().collect();
+        let mut changed = 0;
+        for _i in 0..50 {
+            let mutated = model.mutate(&'#', &original, 1, &mut rng);
+            assert!(!mutated.contains(&'#'));
+            assert!(mutated.len().abs_diff(original.len()) <= 1);
+            if mutated != original {
+                changed += 1;
+            }
+        }
+        assert!(changed > 40, "{}", changed);
+        assert_eq!(model.mutate(&'#', &original, 0, &mut rng), original);
+    }
+
+    #[test]
+    fn test_learning_counts_like_training() {
+        let sequences: Vec<Vec<char>> = vec!["#ace#", "#foobar#", "#baz#"]
+            .into_iter()
+            .map(|s| s.chars().collect())
+            .collect();
+        let trained: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(2)
+            .without_prior()
+            .train(sequences.clone().into_iter())
+            .build()
+            .into();
+        let learned = MarkovModel::<char>::from(
+            MultiMarkov::<char>::builder()
+                .with_order(2)
+                .without_prior()
+                .train(sequences[..1].iter().cloned())
+                .build(),
+        )
+        .learned(&sequences[1..], 0.0);
+        assert_eq!(learned.contexts.to_map(), trained.contexts.to_map());
+        assert_eq!(learned.known_states, trained.known_states);
+    }
+
+    #[test]
+    fn test_decay_forgets_older_observations() {
+        let model = trained_model();
+        let before = model.best_model(&['#']).unwrap()[&'f'];
+        let sequences = vec!["#zed#".chars().collect(), "#zoe#".chars().collect()];
+        let learned = model.learned(&sequences, 0.5);
+        let transitions = learned.best_model(&['#']).unwrap();
+        assert!((transitions[&'f'] - before * 0.25).abs() < 1e-9);
+        // the first new sequence has decayed once, by the time the second is counted
+        assert!((transitions[&'z'] - (0.01 * 0.25 + 0.5 + 1.0)).abs() < 1e-9);
+        assert!(learned.best_model(&['z', 'o']).is_some());
+    }
+
+    #[test]
+    fn test_untraining_subtracts_a_corpus() {
+        let train = |words: &[&str]| {
+            MultiMarkov::<char>::builder()
+                .with_order(2)
+                .without_prior()
+                .train(words.iter().map(|word| word.chars().collect()))
+        };
+        let mut builder = train(&["#ace#", "#foobar#", "#baz#", "#zoo#"]);
+        let expected = train(&["#ace#", "#baz#"]);
+        let samples = ["#zoo#", "#foobar#"].map(|word| word.chars().collect());
+        untrain(
+            &mut builder.markov_chain,
+            &mut builder.known_states,
+            2,
+            samples.clone().into_iter(),
+        )
+        .unwrap();
+        assert_eq!(builder.markov_chain, expected.markov_chain);
+        assert_eq!(builder.known_states, expected.known_states);
+        // what was never trained on can't be untrained, and leaves the chain alone
+        let result = untrain(
+            &mut builder.markov_chain,
+            &mut builder.known_states,
+            2,
+            samples.into_iter(),
+        );
+        assert!(matches!(result, Err(NamegenError::InvalidTrainingData(_))));
+        assert_eq!(builder.markov_chain, expected.markov_chain);
+    }
+
+    #[test]
+    fn test_penalized_transitions_become_rarer() {
+        let mut model = trained_model();
+        let before = model.best_model(&['o', 'o']).unwrap()[&'b'];
+        let shorter = model.best_model(&['o']).unwrap()[&'b'];
+        model.penalize(&[vec!['#', 'o', 'o', 'b'], vec!['x', 'y']], 0.5);
+        assert_eq!(model.best_model(&['o', 'o']).unwrap()[&'b'], before * 0.5);
+        assert_eq!(model.best_model(&['o']).unwrap()[&'b'], shorter * 0.5);
+        assert!(model.best_model(&['x']).is_none());
+    }
+
+    #[test]
+    fn test_forbidding_updates_the_running_totals() {
+        let mut model = trained_model();
+        model.forbid(&'o', &'b');
+        let mut rng = SmallRng::seed_from_u64(5);
+        for _i in 0..100 {
+            assert_ne!(model.random_next(&['o', 'o'], &mut rng), Some('b'));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_draws() {
+        let model = trained_model();
+        let mut rng1 = SmallRng::seed_from_u64(1234);
+        let mut rng2 = SmallRng::seed_from_u64(1234);
+        for _i in 0..100 {
+            assert_eq!(
+                model.random_next(&['#'], &mut rng1),
+                model.random_next(&['#'], &mut rng2)
+            );
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Word families for constructed languages: a stem, Markov-generated or your own, with
+//! inflectional and derivational affixes attached to make its noun, adjective, place-name and
+//! other forms ("Kar", "Karan", "Karic", "Karland").
+//!
+//! ```
+//! use markov_namegen::morphology::{Affix, Attachment, Morphology, MorphologyGenerator};
+//! use markov_namegen::CharacterChainGenerator;
+//! let morphology = Morphology::new()
+//!     .with_form("noun", vec![Affix::suffix("an")])
+//!     .with_form("adjective", vec![Affix::suffix("ic").with_attachment(Attachment::Elide)])
+//!     .with_form("place", vec![Affix::suffix("land")]);
+//! let family = morphology.family("kar");
+//! assert_eq!(family.get("adjective"), Some("karic"));
+//! let stems = CharacterChainGenerator::builder()
+//!     .train(vec!["kar", "vel", "dun", "mor"].into_iter())
+//!     .build();
+//! let mut words = MorphologyGenerator::new(stems, morphology);
+//! let family = words.generate_family();
+//! assert_eq!(family.forms.len(), 3);
+//! ```
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::postprocess::titlecase;
+use is_vowel::IsRomanceVowel;
+use std::fmt;
+
+/// Whether an affix goes before the stem or after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Prefix,
+    Suffix,
+}
+
+/// How an affix is joined to the stem, where the two meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attachment {
+    /// Written as they are, side by side.
+    Plain,
+    /// A letter that would be doubled across the join is written once, and a vowel on the
+    /// stem's side gives way to a vowel on the affix's side ("kara" + "ic" → "karic").
+    Elide,
+    /// This linking string is put between them when they would meet vowel to vowel or
+    /// consonant to consonant ("kar" + "land" with "e" → "kareland").
+    Linked(String),
+}
+
+/// An inflectional or derivational affix, e.g. the plural "-s" or the adjectival "-ic".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Affix {
+    form: String,
+    position: Position,
+    attachment: Attachment,
+}
+
+impl Affix {
+    /// An affix that goes before the stem, attached plainly.
+    pub fn prefix(form: &str) -> Self {
+        Affix {
+            form: form.to_string(),
+            position: Position::Prefix,
+            attachment: Attachment::Plain,
+        }
+    }
+    /// An affix that goes after the stem, attached plainly.
+    pub fn suffix(form: &str) -> Self {
+        Affix {
+            form: form.to_string(),
+            position: Position::Suffix,
+            attachment: Attachment::Plain,
+        }
+    }
+    /// Sets how the affix is joined to the stem.
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachment = attachment;
+        self
+    }
+
+    /// Attaches the affix to a lowercase stem.
+    fn attach(&self, stem: &str) -> String {
+        let (left, right) = match self.position {
+            Position::Prefix => (self.form.as_str(), stem),
+            Position::Suffix => (stem, self.form.as_str()),
+        };
+        let (Some(last), Some(first)) = (left.chars().last(), right.chars().next()) else {
+            return format!("{}{}", left, right);
+        };
+        match &self.attachment {
+            Attachment::Plain => format!("{}{}", left, right),
+            Attachment::Elide => {
+                let mut left = left.to_string();
+                if last == first || (last.is_romance_vowel() && first.is_romance_vowel()) {
+                    left.pop();
+                }
+                left + right
+            }
+            Attachment::Linked(linker) => {
+                if last.is_romance_vowel() == first.is_romance_vowel() {
+                    format!("{}{}{}", left, linker, right)
+                } else {
+                    format!("{}{}", left, right)
+                }
+            }
+        }
+    }
+}
+
+/// A stem and the words formed from it, in the order the forms were added to the
+/// `Morphology`.  Displays as "stem: form, form, ...".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordFamily {
+    pub stem: String,
+    /// Each form's label and word, e.g. ("adjective", "karic").
+    pub forms: Vec<(String, String)>,
+}
+
+impl WordFamily {
+    /// The word of the form with this label, if there is one.
+    pub fn get(&self, label: &str) -> Option<&str> {
+        self.forms
+            .iter()
+            .find(|(form, _)| form == label)
+            .map(|(_, word)| word.as_str())
+    }
+}
+
+impl fmt::Display for WordFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words: Vec<&str> = self.forms.iter().map(|(_, word)| word.as_str()).collect();
+        write!(f, "{}: {}", self.stem, words.join(", "))
+    }
+}
+
+/// The forms a stem can take, each a labeled list of affixes that are attached one after
+/// another, so that a derivational affix can be followed by an inflectional one (an adjective
+/// "-ic" then a plural "-s").
+///
+/// Affixes are attached to the lowercased stem; a capitalized stem gives capitalized words.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Morphology {
+    forms: Vec<(String, Vec<Affix>)>,
+}
+
+impl Morphology {
+    /// A morphology without forms; add them with `with_form()`.
+    pub fn new() -> Self {
+        Morphology::default()
+    }
+    /// Adds a form, labeled e.g. "adjective", made by attaching these affixes in turn.  A form
+    /// without affixes is the bare stem.
+    pub fn with_form(mut self, label: &str, affixes: Vec<Affix>) -> Self {
+        self.forms.push((label.to_string(), affixes));
+        self
+    }
+
+    /// Forms every word of the family of a stem.
+    pub fn family(&self, stem: &str) -> WordFamily {
+        let capitalized = stem.starts_with(char::is_uppercase);
+        let lowercased = stem.to_lowercase();
+        let forms = self
+            .forms
+            .iter()
+            .map(|(label, affixes)| {
+                let word = affixes
+                    .iter()
+                    .fold(lowercased.clone(), |word, affix| affix.attach(&word));
+                let word = if capitalized { titlecase(word) } else { word };
+                (label.clone(), word)
+            })
+            .collect();
+        WordFamily {
+            stem: stem.to_string(),
+            forms,
+        }
+    }
+}
+
+/// A generator of word families, each formed from a stem generated by another generator
+/// (trained, say, on the roots of a conlang's lexicon) by a `Morphology`.
+pub struct MorphologyGenerator {
+    stems: Box<dyn RandomTextGenerator + Send>,
+    morphology: Morphology,
+}
+
+impl MorphologyGenerator {
+    /// Forms families by `morphology` from stems generated by `stems`.
+    pub fn new(stems: impl RandomTextGenerator + Send + 'static, morphology: Morphology) -> Self {
+        MorphologyGenerator {
+            stems: Box::new(stems),
+            morphology,
+        }
+    }
+
+    /// Generates a stem and forms its family.
+    ///
+    /// Panics if the generator can't produce one; use `try_generate_family()` if you'd rather
+    /// handle that as an error.
+    pub fn generate_family(&mut self) -> WordFamily {
+        self.try_generate_family()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a stem and forms its family, or returns an error if the stems' generator
+    /// fails or the morphology has no forms.
+    pub fn try_generate_family(&mut self) -> Result<WordFamily, NamegenError> {
+        if self.morphology.forms.is_empty() {
+            return Err(NamegenError::InvalidConfig(String::from(
+                "the morphology has no forms",
+            )));
+        }
+        let stem = self.stems.try_generate_one()?;
+        Ok(self.morphology.family(&stem))
+    }
+}
+
+impl fmt::Debug for MorphologyGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MorphologyGenerator")
+            .field("stems", &self.stems.kind())
+            .field("morphology", &self.morphology)
+            .finish()
+    }
+}
+
+impl RandomTextGenerator for MorphologyGenerator {
+    /// Generates a word family, displayed as "stem: form, form, ...".
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        self.try_generate_family().map(|family| family.to_string())
+    }
+
+    fn kind(&self) -> &'static str {
+        "morphology"
+    }
+
+    /// Describes the stems and forms, e.g. "characterchain stems, 3 forms".
+    fn config_summary(&self) -> String {
+        format!(
+            "{} stems, {} forms",
+            self.stems.kind(),
+            self.morphology.forms.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CharacterChainGenerator;
+
+    #[test]
+    fn test_attachment_rules() {
+        assert_eq!(Affix::suffix("an").attach("kar"), "karan");
+        assert_eq!(Affix::prefix("un").attach("kar"), "unkar");
+        let elided = Affix::suffix("ic").with_attachment(Attachment::Elide);
+        assert_eq!(elided.attach("kara"), "karic");
+        assert_eq!(elided.attach("kar"), "karic");
+        let doubled = Affix::suffix("ra").with_attachment(Attachment::Elide);
+        assert_eq!(doubled.attach("kar"), "kara");
+        let linked = Affix::suffix("land").with_attachment(Attachment::Linked(String::from("e")));
+        assert_eq!(linked.attach("kar"), "kareland");
+        assert_eq!(linked.attach("kara"), "karaland");
+        let linked = Affix::prefix("a").with_attachment(Attachment::Linked(String::from("n")));
+        assert_eq!(linked.attach("orin"), "anorin");
+        assert_eq!(Affix::suffix("an").attach(""), "an");
+    }
+
+    #[test]
+    fn test_affixes_stack_in_order() {
+        let morphology = Morphology::new()
+            .with_form("stem", Vec::new())
+            .with_form(
+                "adjectives",
+                vec![
+                    Affix::suffix("ic").with_attachment(Attachment::Elide),
+                    Affix::suffix("s"),
+                ],
+            )
+            .with_form("negative", vec![Affix::prefix("un"), Affix::suffix("ic")]);
+        let family = morphology.family("Vela");
+        assert_eq!(family.to_string(), "Vela: Vela, Velics, Unvelaic");
+        assert_eq!(family.get("negative"), Some("Unvelaic"));
+        assert_eq!(family.get("plural"), None);
+    }
+
+    #[test]
+    fn test_families_share_a_generated_stem() {
+        let stems = CharacterChainGenerator::builder()
+            .with_seed(4)
+            .without_prior()
+            .train(vec!["kar", "vel", "dun", "mor"].into_iter())
+            .build();
+        let morphology = Morphology::new()
+            .with_form("noun", vec![Affix::suffix("an")])
+            .with_form("place", vec![Affix::suffix("land")]);
+        let mut words = MorphologyGenerator::new(stems, morphology);
+        assert_eq!(words.config_summary(), "characterchain stems, 2 forms");
+        for _ in 0..20 {
+            let family = words.generate_family();
+            assert_eq!(
+                family.get("noun"),
+                Some(format!("{}an", family.stem).as_str())
+            );
+            assert_eq!(
+                family.get("place"),
+                Some(format!("{}land", family.stem).as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn test_a_morphology_without_forms_is_an_error() {
+        let stems = CharacterChainGenerator::builder()
+            .train(vec!["kar"].into_iter())
+            .build();
+        let mut words = MorphologyGenerator::new(stems, Morphology::new());
+        assert!(matches!(
+            words.try_generate_one(),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,304 @@
+//! Generators trained on several corpora at once, one per language.
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::memory::fnv1a;
+use crate::script::Script;
+use crate::state::GeneratorRng;
+use rand::Rng;
+use std::fmt;
+use std::ops::Deref;
+
+/// A generator trained on several corpora, each tagged with a language, that can generate
+/// names in one particular language with `generate_in()`, or in a random one with
+/// `generate_one()`.
+///
+/// Each language gets its own `ClusterChainGenerator`, so that the languages don't bleed into
+/// each other, and each is tokenized according to its own script (see `Script`): the script is
+/// detected from the corpus unless you name it.  Mixed generation picks a language in
+/// proportion to the size of its corpus.
+///
+/// ```
+/// use markov_namegen::{MultilingualGenerator, RandomTextGenerator};
+/// let mut namegen = MultilingualGenerator::builder()
+///     .train_language("latin", vec!["julius", "marcus", "lucius", "tiberius"].into_iter())
+///     .train_language(
+///         "russian",
+///         vec!["иван", "ольга", "борис", "мария"].into_iter(),
+///     )
+///     .build();
+/// let roman = namegen.generate_in("latin");
+/// assert!(roman.chars().all(|c| c.is_ascii_lowercase()));
+/// let any = namegen.generate_one();
+/// ```
+pub struct MultilingualGenerator {
+    languages: Vec<Language>,
+    rng: GeneratorRng,
+}
+
+#[derive(Debug)]
+struct Language {
+    name: String,
+    script: Script,
+    generator: ClusterChainGenerator,
+    weight: f64,
+}
+
+impl MultilingualGenerator {
+    pub fn builder() -> MultilingualGeneratorBuilder {
+        MultilingualGeneratorBuilder::new()
+    }
+
+    /// The languages this generator was trained on, in the order they were first trained.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.languages.iter().map(|language| language.name.as_str())
+    }
+
+    /// The script a language was tokenized as, if the generator was trained on it.
+    pub fn script_of(&self, language: &str) -> Option<Script> {
+        self.find(language).map(|language| language.script)
+    }
+
+    /// Generates a name in the given language.
+    ///
+    /// Panics if the generator wasn't trained on that language, or can't produce a name; use
+    /// `try_generate_in()` if you'd rather handle that as an error.
+    pub fn generate_in(&mut self, language: &str) -> String {
+        self.try_generate_in(language)
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a name in the given language, or returns an error if the generator wasn't
+    /// trained on that language or can't produce a name.
+    pub fn try_generate_in(&mut self, language: &str) -> Result<String, NamegenError> {
+        let index = self
+            .languages
+            .iter()
+            .position(|l| l.name == language)
+            .ok_or_else(|| NamegenError::UnknownLanguage(language.to_string()))?;
+        self.languages[index].generator.try_generate_one()
+    }
+
+    fn find(&self, language: &str) -> Option<&Language> {
+        self.languages.iter().find(|l| l.name == language)
+    }
+}
+
+impl fmt::Debug for MultilingualGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultilingualGenerator")
+            .field("languages", &self.languages)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for MultilingualGenerator {
+    /// Generates a name in a random language, chosen in proportion to the size of its corpus.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        let sum_of_weights: f64 = self.languages.iter().map(|l| l.weight).sum();
+        let mut randomroll = self.rng.gen::<f64>() * sum_of_weights;
+        for language in self.languages.iter_mut() {
+            if randomroll > language.weight {
+                randomroll -= language.weight;
+            } else {
+                return language.generator.try_generate_one();
+            }
+        }
+        Err(NamegenError::EmptyModel)
+    }
+
+    fn kind(&self) -> &'static str {
+        "multilingual"
+    }
+
+    /// Lists the languages, e.g. "languages latin, greek".
+    fn config_summary(&self) -> String {
+        format!(
+            "languages {}",
+            self.languages().collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+/// A Builder pattern for MultilingualGenerator.  The settings apply to every language.
+pub struct MultilingualGeneratorBuilder {
+    order: usize,
+    prior: Option<f64>,
+    max_attempts: Option<usize>,
+    seed: Option<u64>,
+    corpora: Vec<(String, Option<Script>, Vec<String>)>,
+}
+
+impl Default for MultilingualGeneratorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultilingualGeneratorBuilder {
+    pub fn new() -> Self {
+        MultilingualGeneratorBuilder {
+            order: ClusterChainGenerator::DEFAULT_ORDER,
+            prior: Some(ClusterChainGenerator::DEFAULT_PRIOR),
+            max_attempts: None,
+            seed: None,
+            corpora: Vec::new(),
+        }
+    }
+    /// Sets the order of every language's Markov model.  See
+    /// `ClusterChainGeneratorBuilder::with_order()`.
+    pub fn with_order(mut self, order: usize) -> Self {
+        self.order = order;
+        self
+    }
+    /// Sets the prior of every language's Markov model.  See
+    /// `ClusterChainGeneratorBuilder::with_prior()`.
+    pub fn with_prior(mut self, prior: f64) -> Self {
+        self.prior = Some(prior);
+        self
+    }
+    /// Set the priors to None.
+    pub fn without_prior(mut self) -> Self {
+        self.prior = None;
+        self
+    }
+    /// Gives up after this many rejected candidates.  See
+    /// `ClusterChainGeneratorBuilder::with_max_attempts()`.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        assert!(max_attempts > 0, "Max attempts must be greater than zero.");
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+    /// Seeds the generator for reproducible, cross-platform output, as with
+    /// `ClusterChainGeneratorBuilder::with_seed()`.  Each language gets its own random stream
+    /// derived from this seed and its name.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Ingests a corpus in the given language, detecting its script with `Script::detect()`.
+    /// Training the same language again adds to its corpus.
+    pub fn train_language(
+        self,
+        language: &str,
+        sequences: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> Self {
+        self.add_corpus(language, None, sequences)
+    }
+    /// Ingests a corpus in the given language, written in the given script.
+    pub fn train_language_with_script(
+        self,
+        language: &str,
+        script: Script,
+        sequences: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> Self {
+        self.add_corpus(language, Some(script), sequences)
+    }
+
+    fn add_corpus(
+        mut self,
+        language: &str,
+        script: Option<Script>,
+        sequences: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> Self {
+        let lines = sequences.map(|s| s.to_string());
+        match self
+            .corpora
+            .iter_mut()
+            .find(|(name, _, _)| name == language)
+        {
+            Some((_, existing_script, corpus)) => {
+                *existing_script = script.or(*existing_script);
+                corpus.extend(lines);
+            }
+            None => self
+                .corpora
+                .push((language.to_string(), script, lines.collect())),
+        }
+        self
+    }
+
+    pub fn build(self) -> MultilingualGenerator {
+        self.try_build()
+            .unwrap_or_else(|error| panic!("could not build MultilingualGenerator: {}", error))
+    }
+
+    /// Trains a generator for each language, returning the first error encountered.
+    pub fn try_build(self) -> Result<MultilingualGenerator, NamegenError> {
+        let mut languages = Vec::with_capacity(self.corpora.len());
+        for (name, script, corpus) in self.corpora {
+            let script = script
+                .or_else(|| Script::detect(&corpus))
+                .unwrap_or_default();
+            let mut builder = ClusterChainGenerator::builder()
+                .with_order(self.order)
+                .with_script(script);
+            builder = match self.prior {
+                Some(prior) => builder.with_prior(prior),
+                None => builder.without_prior(),
+            };
+            if let Some(max_attempts) = self.max_attempts {
+                builder = builder.with_max_attempts(max_attempts);
+            }
+            if let Some(seed) = self.seed {
+                builder = builder.with_seed(seed ^ fnv1a(name.as_bytes()));
+            }
+            let generator = builder
+                .train(corpus.iter().map(String::as_str))
+                .try_build()?;
+            languages.push(Language {
+                name,
+                script,
+                generator,
+                weight: corpus.len() as f64,
+            });
+        }
+        Ok(MultilingualGenerator {
+            languages,
+            rng: self
+                .seed
+                .map_or_else(GeneratorRng::from_entropy, GeneratorRng::from_seed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trained() -> MultilingualGenerator {
+        MultilingualGenerator::builder()
+            .with_seed(11)
+            .train_language("latin", vec!["julius", "marcus", "lucius"].into_iter())
+            .train_language("greek", vec!["νίκος", "ελένη", "γιώργος"].into_iter())
+            .train_language("latin", vec!["tiberius"].into_iter())
+            .build()
+    }
+
+    #[test]
+    fn test_languages_are_kept_apart() {
+        let mut namegen = trained();
+        assert_eq!(
+            namegen.languages().collect::<Vec<_>>(),
+            vec!["latin", "greek"]
+        );
+        assert_eq!(namegen.script_of("greek"), Some(Script::Greek));
+        assert_eq!(namegen.config_summary(), "languages latin, greek");
+        for _i in 0..20 {
+            let name = namegen.generate_in("greek");
+            assert_eq!(Script::detect([&name]), Some(Script::Greek));
+        }
+        assert_eq!(
+            namegen.try_generate_in("klingon"),
+            Err(NamegenError::UnknownLanguage(String::from("klingon")))
+        );
+    }
+
+    #[test]
+    fn test_mixed_generation_is_reproducible() {
+        let (mut a, mut b) = (trained(), trained());
+        for _i in 0..20 {
+            assert_eq!(a.generate_one(), b.generate_one());
+        }
+    }
+}
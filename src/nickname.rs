@@ -0,0 +1,223 @@
+//! Nicknames and diminutives derived from full names, for character sheets.
+//!
+//! A nickname is made by clipping the name, from the back ("Valeria" → "Val") or the front
+//! ("Valeria" → "Leri"), and perhaps adding a diminutive ending ("Valeria" → "Valina").  The
+//! generator's model scores every candidate, so the endings that suit the training data win
+//! out: "-ka" for Slavic names and "-ino" for Italian ones, say, from the same list.
+//!
+//! ```
+//! use markov_namegen::ClusterChainGenerator;
+//! let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+//! let mut namegen = ClusterChainGenerator::builder()
+//!     .with_seed(1)
+//!     .train(corpus.lines())
+//!     .build();
+//! let character = namegen.generate_nicknamed();
+//! println!("{}", character); // e.g. "Valeria (Val)"
+//! let nickname = namegen.nickname("Valeria");
+//! ```
+use crate::postprocess::{titlecase, weighted_choice};
+use rand::RngCore;
+use std::fmt;
+
+/// A name together with a nickname derived from it.  Displays as "Name (Nickname)".  Get one
+/// from a generator's `generate_nicknamed()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NicknamedName {
+    pub name: String,
+    pub nickname: String,
+}
+
+impl fmt::Display for NicknamedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.nickname)
+    }
+}
+
+/// The diminutive endings that nicknames may be given, on top of plain clippings of the name.
+/// Set them with the builders' `.with_diminutives()`; by default, `Diminutives::common()` is
+/// used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diminutives {
+    endings: Vec<String>,
+}
+
+impl Default for Diminutives {
+    fn default() -> Self {
+        Self::common()
+    }
+}
+
+impl Diminutives {
+    /// No endings at all: nicknames are only ever clippings of the name.
+    pub fn new() -> Self {
+        Diminutives {
+            endings: Vec::new(),
+        }
+    }
+    /// Endings from several European languages ("-ie", "-y", "-ino", "-ita", "-ka", "-sha" and
+    /// more), which the model sorts out: the ones unlike anything in the training data are
+    /// rarely chosen.
+    pub fn common() -> Self {
+        [
+            "a", "i", "ie", "y", "o", "ino", "ina", "ito", "ita", "ka", "sha", "kin",
+        ]
+        .into_iter()
+        .fold(Self::new(), Self::with_ending)
+    }
+    /// Adds a diminutive ending.
+    pub fn with_ending(mut self, ending: &str) -> Self {
+        self.endings.push(ending.to_lowercase());
+        self
+    }
+
+    /// Every nickname that can be made from the (lowercase) name: clippings of it, with and
+    /// without each ending.
+    pub(crate) fn candidates(&self, name: &str, is_vowel: impl Fn(char) -> bool) -> Vec<String> {
+        let chunks = syllable_chunks(name, &is_vowel);
+        let mut stems = Vec::new();
+        if chunks.len() > 1 {
+            // the first syllable, closed by the consonant after it: "valeria" → "val"
+            let mut stem = chunks[0].clone();
+            stem.extend(chunks[1].chars().next().filter(|&c| !is_vowel(c)));
+            stems.push(stem);
+        } else {
+            stems.push(name.to_string());
+        }
+        let mut candidates = stems.clone();
+        if chunks.len() > 2 {
+            // the second syllable, and the third up to its first vowel: "valeria" → "leri"
+            let third = &chunks[2];
+            let end = third
+                .char_indices()
+                .find(|&(_, c)| is_vowel(c))
+                .map_or(third.len(), |(i, c)| i + c.len_utf8());
+            candidates.push(format!("{}{}", chunks[1], &third[..end]));
+        }
+        for stem in &stems {
+            for ending in &self.endings {
+                // an ending starting with a vowel replaces a vowel at the end of the stem
+                let stem = match (stem.chars().last(), ending.chars().next()) {
+                    (Some(last), Some(first)) if is_vowel(last) && is_vowel(first) => {
+                        &stem[..stem.len() - last.len_utf8()]
+                    }
+                    _ => stem.as_str(),
+                };
+                candidates.push(format!("{}{}", stem, ending));
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+        candidates.retain(|candidate| candidate != name && candidate.chars().count() >= 2);
+        candidates
+    }
+
+    /// Picks a nickname for the name from the candidates, weighted by how plausible
+    /// `log_probability` finds each one per letter, so that long candidates aren't penalized
+    /// just for their length.  Returns the name itself if no candidate is possible at all.
+    /// The nickname is capitalized if the name is.
+    pub(crate) fn nickname(
+        &self,
+        name: &str,
+        rng: &mut dyn RngCore,
+        is_vowel: impl Fn(char) -> bool,
+        log_probability: impl Fn(&str) -> f64,
+    ) -> String {
+        let lowercased = name.to_lowercase();
+        let scored: Vec<(String, f64)> = self
+            .candidates(&lowercased, is_vowel)
+            .into_iter()
+            .filter_map(|candidate| {
+                let score = log_probability(&candidate) / (candidate.chars().count() + 1) as f64;
+                score.is_finite().then(|| (candidate, score.exp()))
+            })
+            .collect();
+        let nickname = match weighted_choice(&scored, rng) {
+            Some(nickname) => nickname.to_string(),
+            None => return name.to_string(),
+        };
+        if name.starts_with(char::is_uppercase) {
+            titlecase(nickname)
+        } else {
+            nickname
+        }
+    }
+}
+
+/// Splits a word into rough syllables, each some consonants followed by some vowels, with any
+/// consonants at the end of the word joining the last one: "valeria" → "va", "le", "ria".
+fn syllable_chunks(word: &str, is_vowel: impl Fn(char) -> bool) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut after_vowel = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if after_vowel && !vowel {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+        after_vowel = vowel;
+    }
+    if !current.is_empty() {
+        match chunks.last_mut() {
+            Some(last) if !after_vowel => last.push_str(&current),
+            _ => chunks.push(current),
+        }
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use is_vowel::IsRomanceVowel;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn test_syllable_chunks() {
+        let vowel = |c: char| c.is_romance_vowel();
+        assert_eq!(syllable_chunks("valeria", vowel), vec!["va", "le", "ria"]);
+        assert_eq!(syllable_chunks("marcus", vowel), vec!["ma", "rcus"]);
+        assert_eq!(syllable_chunks("max", vowel), vec!["max"]);
+    }
+
+    #[test]
+    fn test_nickname_candidates() {
+        let vowel = |c: char| c.is_romance_vowel();
+        let candidates = Diminutives::new()
+            .with_ending("ina")
+            .with_ending("ka")
+            .candidates("valeria", vowel);
+        assert_eq!(candidates, vec!["leri", "val", "valina", "valka"]);
+        // a vowel at the end of the stem gives way to one at the start of the ending
+        let candidates = Diminutives::new()
+            .with_ending("ie")
+            .with_ending("ka")
+            .candidates("bea", vowel);
+        assert_eq!(candidates, vec!["beaka", "beie"]);
+    }
+
+    #[test]
+    fn test_plausible_nicknames_are_chosen() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let diminutives = Diminutives::new().with_ending("ina").with_ending("ka");
+        // a "model" that only likes names ending in "a"
+        let score = |name: &str| {
+            if name.ends_with('a') {
+                -1.0
+            } else {
+                f64::NEG_INFINITY
+            }
+        };
+        for _ in 0..10 {
+            let nickname =
+                diminutives.nickname("Valeria", &mut rng, |c| c.is_romance_vowel(), score);
+            assert!(["Valina", "Valka"].contains(&nickname.as_str()));
+        }
+        let nothing = |_: &str| f64::NEG_INFINITY;
+        assert_eq!(
+            diminutives.nickname("Valeria", &mut rng, |c| c.is_romance_vowel(), nothing),
+            "Valeria"
+        );
+    }
+}
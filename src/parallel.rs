@@ -0,0 +1,110 @@
+//! Training on several chunks of a corpus at once, for `.train_parallel()`.
+use crate::capitalization::CapitalizationModel;
+use crate::error::NamegenError;
+use crate::model::Chain;
+use crate::positional::count_finals;
+use multimarkov::builder::MultiMarkovBuilder;
+use multimarkov::MultiMarkov;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::thread;
+
+/// A partial model, trained on one chunk of a corpus, with what was learned alongside it.
+pub(crate) struct Shard<T> {
+    chain: Chain<T>,
+    known_states: HashSet<T>,
+    capitalization: Option<CapitalizationModel>,
+    finals: Option<Chain<T>>,
+    error: Option<NamegenError>,
+}
+
+impl<T> Shard<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    /// Trains a partial model of the given order, without a prior, on samples that begin and
+    /// end with the end-of-word marker, counting their final states too if `finals` is set.
+    pub(crate) fn train(
+        order: usize,
+        samples: impl Iterator<Item = Vec<T>>,
+        capitalization: Option<CapitalizationModel>,
+        finals: bool,
+        error: Option<NamegenError>,
+    ) -> Self {
+        let samples: Vec<Vec<T>> = samples.collect();
+        let finals = finals.then(|| {
+            let mut finals = Chain::new();
+            for sample in &samples {
+                count_finals(&mut finals, order, sample, 1.0);
+            }
+            finals
+        });
+        let trained = MultiMarkov::<T>::builder()
+            .with_order(order as i32)
+            .without_prior()
+            .train(samples.into_iter());
+        Shard {
+            chain: trained.markov_chain,
+            known_states: trained.known_states,
+            capitalization,
+            finals,
+            error,
+        }
+    }
+}
+
+/// Trains a shard on each chunk with `train`, each in a thread of its own, and merges them into
+/// `model` in the order of the chunks, summing their weights.  Since the weights are counts of
+/// observations, the result is exactly what training on the chunks in turn would give.  The
+/// capitalization learned from each chunk is merged into `capitalization`, and its final counts
+/// into `finals`, if they're being learned; the first error of the first chunk that had one goes
+/// into `error`.
+pub(crate) fn train_shards<C, T>(
+    chunks: impl Iterator<Item = C>,
+    train: impl Fn(C) -> Shard<T> + Sync,
+    model: &mut MultiMarkovBuilder<T>,
+    capitalization: &mut Option<CapitalizationModel>,
+    finals: &mut Option<Chain<T>>,
+    error: &mut Option<NamegenError>,
+) where
+    C: Send,
+    T: Eq + Hash + Clone + Ord + Send,
+{
+    let train = &train;
+    let shards: Vec<Shard<T>> = thread::scope(|scope| {
+        let threads: Vec<_> = chunks
+            .map(|chunk| scope.spawn(move || train(chunk)))
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    for shard in shards {
+        for (context, transitions) in shard.chain {
+            let merged = model.markov_chain.entry(context).or_default();
+            for (state, weight) in transitions {
+                *merged.entry(state).or_insert(0.0) += weight;
+            }
+        }
+        model.known_states.extend(shard.known_states);
+        if let (Some(merged), Some(learned)) = (capitalization.as_mut(), shard.capitalization) {
+            merged.merge(learned);
+        }
+        if let (Some(merged), Some(learned)) = (finals.as_mut(), shard.finals) {
+            for (context, transitions) in learned {
+                let merged = merged.entry(context).or_default();
+                for (state, count) in transitions {
+                    *merged.entry(state).or_insert(0.0) += count;
+                }
+            }
+        }
+        if let Some(shard_error) = shard.error {
+            error.get_or_insert(shard_error);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Passphrases of pronounceable generated words, like "korvel-anthe-sulira-domak", with an
+//! estimate of how hard they are to guess.
+//!
+//! ```
+//! use markov_namegen::passphrase::PassphraseGenerator;
+//! use markov_namegen::ClusterChainGenerator;
+//! let words = ClusterChainGenerator::builder()
+//!     .train(vec!["anthea", "korvel", "sulira", "domak", "velina", "tarsus"].into_iter())
+//!     .build();
+//! let mut passphrases = PassphraseGenerator::new(words)
+//!     .with_words(4)
+//!     .with_separator("-");
+//! let passphrase = passphrases.generate_passphrase();
+//! assert_eq!(passphrase.words.len(), 4);
+//! println!("{} ({:.0} bits)", passphrase, passphrase.entropy_bits);
+//! ```
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use std::fmt;
+
+/// The most words `PassphraseGenerator` adds to a passphrase to reach its minimum entropy.
+const MAX_WORDS: usize = 64;
+
+/// A generated passphrase, its words, and an estimate of the information in it.  Displays as
+/// the passphrase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passphrase {
+    /// The words, joined with the separator.
+    pub phrase: String,
+    /// The words, in order.
+    pub words: Vec<String>,
+    /// The information in the model's choices that made the words, in bits: at every step of
+    /// every word, the entropy of the letters the model could have chosen next.  On average,
+    /// it's the entropy of the model's output, so about this many bits would have to be guessed
+    /// by an attacker who has the model and knows how many words there are.
+    pub entropy_bits: f64,
+}
+
+impl fmt::Display for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.phrase)
+    }
+}
+
+/// A generator of passphrases, each several words from a trained model joined with a separator,
+/// so that they're easier to remember and type than random characters.
+///
+/// Each passphrase comes with an estimate of its entropy, computed from the model's actual
+/// branching rather than the size of its training data: a model that always follows "qu" with
+/// a vowel gets no credit for the "u".  Set `with_min_entropy()` to add words until the
+/// estimate reaches a target.  The estimate is an upper bound of sorts: the generator's
+/// filters, memory and postprocessors, and a model trained on too few words, can only make
+/// passphrases easier to guess, so train on a large corpus and leave a margin.
+pub struct PassphraseGenerator {
+    words: ClusterChainGenerator,
+    count: usize,
+    separator: String,
+    min_entropy: Option<f64>,
+}
+
+impl PassphraseGenerator {
+    /// Makes passphrases of four words from `words`, separated by '-'.
+    pub fn new(words: ClusterChainGenerator) -> Self {
+        PassphraseGenerator {
+            words,
+            count: 4,
+            separator: String::from("-"),
+            min_entropy: None,
+        }
+    }
+    /// Sets how many words a passphrase has, or at least has if a minimum entropy is set.
+    pub fn with_words(mut self, count: usize) -> Self {
+        assert!(count > 0, "Word count must be greater than zero.");
+        self.count = count;
+        self
+    }
+    /// Sets what goes between the words, e.g. " " or "."; by default, "-".
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+    /// Adds words to each passphrase until its estimated entropy is at least this many bits,
+    /// e.g. 60 for a password protected by slow hashing, or 80 or more otherwise.
+    pub fn with_min_entropy(mut self, bits: f64) -> Self {
+        assert!(bits > 0.0, "Minimum entropy must be greater than zero.");
+        self.min_entropy = Some(bits);
+        self
+    }
+
+    /// Generates a passphrase with its estimated entropy.
+    ///
+    /// Panics if the generator can't produce one; use `try_generate_passphrase()` if you'd
+    /// rather handle that as an error.
+    pub fn generate_passphrase(&mut self) -> Passphrase {
+        self.try_generate_passphrase()
+            .unwrap_or_else(|error| panic!("could not generate text: {}", error))
+    }
+
+    /// Generates a passphrase with its estimated entropy, or returns an error if the model
+    /// can't produce a word, or a `NamegenError::InvalidConfig` error if 64 words don't reach
+    /// the minimum entropy.
+    pub fn try_generate_passphrase(&mut self) -> Result<Passphrase, NamegenError> {
+        let mut words = Vec::with_capacity(self.count);
+        let mut entropy_bits = 0.0;
+        while words.len() < self.count || self.min_entropy.is_some_and(|min| entropy_bits < min) {
+            if words.len() == MAX_WORDS {
+                return Err(NamegenError::InvalidConfig(format!(
+                    "the minimum entropy can't be reached with {} words",
+                    MAX_WORDS
+                )));
+            }
+            let (word, bits) = self.words.generate_with_bits()?;
+            words.push(word);
+            entropy_bits += bits;
+        }
+        Ok(Passphrase {
+            phrase: words.join(&self.separator),
+            words,
+            entropy_bits,
+        })
+    }
+}
+
+impl fmt::Debug for PassphraseGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PassphraseGenerator")
+            .field("count", &self.count)
+            .field("separator", &self.separator)
+            .field("min_entropy", &self.min_entropy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for PassphraseGenerator {
+    /// Generates a passphrase, without its estimated entropy.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        self.try_generate_passphrase()
+            .map(|passphrase| passphrase.phrase)
+    }
+
+    fn kind(&self) -> &'static str {
+        "passphrase"
+    }
+
+    /// Describes the passphrases, e.g. "4 words separated by '-', at least 60 bits".
+    fn config_summary(&self) -> String {
+        let mut summary = format!("{} words separated by '{}'", self.count, self.separator);
+        if let Some(bits) = self.min_entropy {
+            summary.push_str(&format!(", at least {} bits", bits));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(corpus: Vec<&str>) -> ClusterChainGenerator {
+        ClusterChainGenerator::builder()
+            .with_seed(1)
+            .without_prior()
+            .train(corpus.into_iter())
+            .build()
+    }
+
+    #[test]
+    fn test_passphrases_are_joined_words() {
+        let mut passphrases = PassphraseGenerator::new(words(vec!["kora"]))
+            .with_words(3)
+            .with_separator(" ");
+        let passphrase = passphrases.generate_passphrase();
+        assert_eq!(passphrase.to_string(), "kora kora kora");
+        assert_eq!(passphrase.words.len(), 3);
+        // a model that knows one word makes no choices
+        assert_eq!(passphrase.entropy_bits, 0.0);
+        assert_eq!(passphrases.config_summary(), "3 words separated by ' '");
+    }
+
+    #[test]
+    fn test_words_are_added_to_reach_the_minimum_entropy() {
+        let corpus = vec![
+            "kora", "vela", "dusa", "tomi", "anthe", "sulir", "marek", "ilsa",
+        ];
+        let mut passphrases = PassphraseGenerator::new(words(corpus))
+            .with_words(2)
+            .with_min_entropy(20.0);
+        for _ in 0..10 {
+            let passphrase = passphrases.generate_passphrase();
+            assert!(passphrase.entropy_bits >= 20.0);
+            assert!(passphrase.words.len() >= 2);
+        }
+        let mut passphrases = PassphraseGenerator::new(words(vec!["kora"])).with_min_entropy(1.0);
+        assert!(matches!(
+            passphrases.try_generate_passphrase(),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+//! Surnames derived from a parent's given name ("Jónsdóttir", "Ivanovich"), following a
+//! culture's rules.
+//!
+//! ```
+//! use markov_namegen::gendered::Gender;
+//! use markov_namegen::patronymic::Patronymics;
+//! let icelandic = Patronymics::icelandic();
+//! assert_eq!(icelandic.patronymic("Jón", Gender::Feminine), "Jónsdóttir");
+//! assert_eq!(icelandic.patronymic("Bjarni", Gender::Masculine), "Bjarnason");
+//! assert_eq!(icelandic.full_name("Sigrún", "Jón", Gender::Feminine), "Sigrún Jónsdóttir");
+//! assert_eq!(Patronymics::russian().patronymic("Ivan", Gender::Masculine), "Ivanovich");
+//! ```
+use crate::error::NamegenError;
+use crate::gendered::Gender;
+use crate::interface::RandomTextGenerator;
+
+/// A culture's rules for deriving a patronymic (or matronymic) from a parent's given name.
+/// Each rule replaces an ending of the parent's name with a masculine or feminine suffix; the
+/// rule with the longest matching ending applies, and the catch-all rule, with an empty
+/// ending, just appends its suffix.  Endings are matched ignoring case, and the rest of the
+/// parent's name keeps its case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patronymics {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    ending: String,
+    masculine: String,
+    feminine: String,
+}
+
+impl Patronymics {
+    /// Rules that append `masculine` or `feminine` to every name, e.g. `"son"` and `"dottir"`.
+    /// Add exceptions with `with_rule()`.
+    pub fn new(masculine: &str, feminine: &str) -> Self {
+        Patronymics { rules: Vec::new() }.with_rule("", masculine, feminine)
+    }
+    /// Icelandic: the genitive of the parent's name, then "-son" or "-dóttir".  Names ending in
+    /// "-i" or "-a" take the weak genitive ("Bjarni" → "Bjarnason", "Sturla" → "Sturluson"),
+    /// "-ur" is dropped before the "s" ("Ólafur" → "Ólafsson"), and others take "s" ("Jón" →
+    /// "Jónsson").
+    pub fn icelandic() -> Self {
+        Self::new("sson", "sdóttir")
+            .with_rule("i", "ason", "adóttir")
+            .with_rule("a", "uson", "udóttir")
+            .with_rule("ur", "sson", "sdóttir")
+    }
+    /// Russian, romanized: "-ovich" and "-ovna" ("Ivan" → "Ivanovich"), "-evich" and "-evna"
+    /// after a soft ending ("Sergey" → "Sergeyevich", "Dmitri" → "Dmitriyevich", "Igor" →
+    /// "Igorevich"), and "-ich" and "-ichna" for names in "-a" ("Nikita" → "Nikitich").
+    pub fn russian() -> Self {
+        Self::new("ovich", "ovna")
+            .with_rule("y", "yevich", "yevna")
+            .with_rule("i", "iyevich", "iyevna")
+            .with_rule("or", "orevich", "orevna")
+            .with_rule("a", "ich", "ichna")
+    }
+    /// Swedish: "-son" and "-dotter" after the genitive "s", which names already ending in "s"
+    /// keep ("Nils" → "Nilsson", "Anders" → "Andersson").
+    pub fn swedish() -> Self {
+        Self::new("sson", "sdotter").with_rule("s", "sson", "sdotter")
+    }
+    /// Danish and Norwegian: "-sen" and "-sdatter", the "s" of which names already ending in
+    /// "s" share ("Hans" → "Hansen", "Jens" → "Jensen").
+    pub fn danish() -> Self {
+        Self::new("sen", "sdatter").with_rule("s", "sen", "sdatter")
+    }
+    /// Adds a rule: a parent's name ending in `ending` has it replaced by `masculine` or
+    /// `feminine`.  A rule for the same ending replaces the old one.
+    pub fn with_rule(mut self, ending: &str, masculine: &str, feminine: &str) -> Self {
+        let rule = Rule {
+            ending: ending.to_lowercase(),
+            masculine: masculine.to_string(),
+            feminine: feminine.to_string(),
+        };
+        match self.rules.iter_mut().find(|r| r.ending == rule.ending) {
+            Some(existing) => *existing = rule,
+            None => self.rules.push(rule),
+        }
+        self
+    }
+
+    /// The patronymic (or matronymic, given a mother's name) of a child of the given gender.
+    pub fn patronymic(&self, parent: &str, gender: Gender) -> String {
+        let lowercased = parent.to_lowercase();
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| lowercased.ends_with(&rule.ending))
+            .max_by_key(|rule| rule.ending.chars().count());
+        let Some(rule) = rule else {
+            return parent.to_string();
+        };
+        let kept = parent
+            .chars()
+            .count()
+            .saturating_sub(rule.ending.chars().count());
+        let mut name: String = parent.chars().take(kept).collect();
+        name.push_str(match gender {
+            Gender::Masculine => &rule.masculine,
+            Gender::Feminine => &rule.feminine,
+        });
+        name
+    }
+
+    /// A full name: the given name followed by the patronymic from the parent's name.
+    pub fn full_name(&self, given: &str, parent: &str, gender: Gender) -> String {
+        format!("{} {}", given, self.patronymic(parent, gender))
+    }
+
+    /// Generates a full name, the given name from `given_names` and the parent's from
+    /// `parent_names` (masculine names for patronymics, feminine ones for matronymics), or
+    /// returns an error if either generator can't produce a name.
+    pub fn generate_full_name(
+        &self,
+        given_names: &mut impl RandomTextGenerator,
+        parent_names: &mut impl RandomTextGenerator,
+        gender: Gender,
+    ) -> Result<String, NamegenError> {
+        let given = given_names.try_generate_one()?;
+        let parent = parent_names.try_generate_one()?;
+        Ok(self.full_name(&given, &parent, gender))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CharacterChainGenerator;
+
+    #[test]
+    fn test_culture_rules() {
+        let icelandic = Patronymics::icelandic();
+        assert_eq!(
+            icelandic.patronymic("Ólafur", Gender::Masculine),
+            "Ólafsson"
+        );
+        assert_eq!(
+            icelandic.patronymic("Sturla", Gender::Feminine),
+            "Sturludóttir"
+        );
+        let russian = Patronymics::russian();
+        assert_eq!(russian.patronymic("Sergey", Gender::Feminine), "Sergeyevna");
+        assert_eq!(russian.patronymic("Nikita", Gender::Masculine), "Nikitich");
+        assert_eq!(russian.patronymic("Igor", Gender::Masculine), "Igorevich");
+        assert_eq!(
+            russian.patronymic("Dmitri", Gender::Masculine),
+            "Dmitriyevich"
+        );
+        assert_eq!(russian.patronymic("Boris", Gender::Feminine), "Borisovna");
+        let swedish = Patronymics::swedish();
+        assert_eq!(swedish.patronymic("Anders", Gender::Masculine), "Andersson");
+        assert_eq!(swedish.patronymic("Per", Gender::Feminine), "Persdotter");
+        assert_eq!(
+            Patronymics::danish().patronymic("Jens", Gender::Masculine),
+            "Jensen"
+        );
+    }
+
+    #[test]
+    fn test_rules_replace_each_other_and_keep_case() {
+        let rules = Patronymics::new("son", "dottir").with_rule("son", "sen", "sdatter");
+        assert_eq!(rules.patronymic("ERIK", Gender::Masculine), "ERIKson");
+        assert_eq!(rules.patronymic("Jonson", Gender::Feminine), "Jonsdatter");
+        let rules = rules.with_rule("son", "s", "s");
+        assert_eq!(rules.patronymic("Jonson", Gender::Masculine), "Jons");
+    }
+
+    #[test]
+    fn test_full_names_from_generators() {
+        let mut given = CharacterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["sigrún"].into_iter())
+            .build();
+        let mut fathers = CharacterChainGenerator::builder()
+            .without_prior()
+            .train(vec!["bjarni"].into_iter())
+            .build();
+        let name = Patronymics::icelandic()
+            .generate_full_name(&mut given, &mut fathers, Gender::Feminine)
+            .unwrap();
+        assert_eq!(name, "sigrún bjarnadóttir");
+    }
+}
@@ -0,0 +1,298 @@
+//! Phonetic codes (Soundex and Metaphone), and a blocklist that rejects names that sound like
+//! any of its entries, catching respellings like "Phuk" that a list of exact names or
+//! substrings would miss.
+use crate::postprocess::strip_diacritics;
+use std::collections::HashMap;
+
+/// How names are reduced to a code of how they sound, for `PhoneticBlocklist`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhoneticAlgorithm {
+    /// American Soundex: the first letter and three digits for the consonants after it, e.g.
+    /// "R163" for both "Robert" and "Rupert".  Coarse, so it blocks generously.
+    Soundex,
+    /// Lawrence Philips' original Metaphone, which knows more about English spelling ("ph" is
+    /// "f", "c" before "e" is "s"...) and keeps every consonant sound, so it blocks more
+    /// narrowly.
+    #[default]
+    Metaphone,
+}
+
+impl PhoneticAlgorithm {
+    /// The code of a word under this algorithm.
+    pub fn encode(self, word: &str) -> String {
+        match self {
+            PhoneticAlgorithm::Soundex => soundex(word),
+            PhoneticAlgorithm::Metaphone => metaphone(word),
+        }
+    }
+}
+
+/// The American Soundex code of a word, e.g. "R163" for "Robert", or an empty string if it
+/// has no Latin letters.  Diacritics are ignored, as is anything that isn't a letter.
+///
+/// ```
+/// use markov_namegen::phonetic::soundex;
+/// assert_eq!(soundex("Robert"), "R163");
+/// assert_eq!(soundex("Rupert"), "R163");
+/// assert_eq!(soundex("Ashcraft"), "A261");
+/// assert_eq!(soundex("Lee"), "L000");
+/// ```
+pub fn soundex(word: &str) -> String {
+    let letters = ascii_letters(word);
+    let Some(&first) = letters.first() else {
+        return String::new();
+    };
+    let digit = |c: u8| match c {
+        b'B' | b'F' | b'P' | b'V' => Some(b'1'),
+        b'C' | b'G' | b'J' | b'K' | b'Q' | b'S' | b'X' | b'Z' => Some(b'2'),
+        b'D' | b'T' => Some(b'3'),
+        b'L' => Some(b'4'),
+        b'M' | b'N' => Some(b'5'),
+        b'R' => Some(b'6'),
+        _ => None,
+    };
+    let mut code = vec![first];
+    let mut last = digit(first);
+    for &c in &letters[1..] {
+        match (c, digit(c)) {
+            // 'h' and 'w' don't separate two consonants with the same digit, but vowels do
+            (b'H' | b'W', _) => {}
+            (_, None) => last = None,
+            (_, Some(d)) => {
+                if last != Some(d) {
+                    code.push(d);
+                }
+                last = Some(d);
+            }
+        }
+    }
+    code.resize(4, b'0');
+    code.truncate(4);
+    String::from_utf8(code).unwrap_or_default()
+}
+
+/// The Metaphone code of a word, e.g. "FLP" for "Philip", or an empty string if it has no
+/// Latin letters.  Diacritics are ignored, as is anything that isn't a letter.  '0' stands for
+/// "th" and 'X' for "sh".
+///
+/// ```
+/// use markov_namegen::phonetic::metaphone;
+/// assert_eq!(metaphone("Philip"), "FLP");
+/// assert_eq!(metaphone("Filip"), "FLP");
+/// assert_eq!(metaphone("Knight"), "NT");
+/// assert_eq!(metaphone("Thatcher"), "0XR");
+/// ```
+pub fn metaphone(word: &str) -> String {
+    let mut letters = ascii_letters(word);
+    match letters.as_slice() {
+        [b'A', b'E', ..] | [b'G' | b'K' | b'P', b'N', ..] | [b'W', b'R', ..] => {
+            letters.remove(0);
+        }
+        [b'X', ..] => letters[0] = b'S',
+        [b'W', b'H', ..] => {
+            letters.remove(1);
+        }
+        _ => {}
+    }
+    let at = |i: usize| letters.get(i).copied().unwrap_or(0);
+    let is_vowel = |c: u8| matches!(c, b'A' | b'E' | b'I' | b'O' | b'U');
+    let is_front = |c: u8| matches!(c, b'E' | b'I' | b'Y');
+    let mut code = String::new();
+    for i in 0..letters.len() {
+        let (c, previous, next) = (letters[i], i.checked_sub(1).map_or(0, at), at(i + 1));
+        if c == previous && c != b'C' {
+            continue;
+        }
+        match c {
+            b'A' | b'E' | b'I' | b'O' | b'U' => {
+                if i == 0 {
+                    code.push(c as char);
+                }
+            }
+            b'B' => {
+                if !(previous == b'M' && i + 1 == letters.len()) {
+                    code.push('B');
+                }
+            }
+            b'C' => {
+                if next == b'I' && at(i + 2) == b'A' {
+                    code.push('X');
+                } else if next == b'H' {
+                    code.push(if previous == b'S' { 'K' } else { 'X' });
+                } else if is_front(next) {
+                    if previous != b'S' {
+                        code.push('S');
+                    }
+                } else {
+                    code.push('K');
+                }
+            }
+            b'D' => {
+                if next == b'G' && is_front(at(i + 2)) {
+                    code.push('J');
+                } else {
+                    code.push('T');
+                }
+            }
+            b'G' => {
+                let silent_h = next == b'H' && i + 2 < letters.len() && !is_vowel(at(i + 2));
+                let silent_n = next == b'N'
+                    && (i + 2 == letters.len() || letters[i + 2..] == *b"ED".as_slice());
+                if silent_h || silent_n || (previous == b'D' && is_front(next)) {
+                    continue;
+                }
+                code.push(if is_front(next) && previous != b'G' {
+                    'J'
+                } else {
+                    'K'
+                });
+            }
+            b'H' => {
+                if !matches!(previous, b'C' | b'S' | b'P' | b'T' | b'G') && is_vowel(next) {
+                    code.push('H');
+                }
+            }
+            b'K' => {
+                if previous != b'C' {
+                    code.push('K');
+                }
+            }
+            b'P' => code.push(if next == b'H' { 'F' } else { 'P' }),
+            b'Q' => code.push('K'),
+            b'S' => {
+                if next == b'H' || (next == b'I' && matches!(at(i + 2), b'O' | b'A')) {
+                    code.push('X');
+                } else {
+                    code.push('S');
+                }
+            }
+            b'T' => {
+                if next == b'I' && matches!(at(i + 2), b'O' | b'A') {
+                    code.push('X');
+                } else if next == b'H' {
+                    code.push('0');
+                } else if !(next == b'C' && at(i + 2) == b'H') {
+                    code.push('T');
+                }
+            }
+            b'V' => code.push('F'),
+            b'W' | b'Y' => {
+                if is_vowel(next) {
+                    code.push(c as char);
+                }
+            }
+            b'X' => code.push_str("KS"),
+            b'Z' => code.push('S'),
+            _ => code.push(c as char),
+        }
+    }
+    code
+}
+
+/// The Latin letters of a word, without diacritics, in uppercase.
+fn ascii_letters(word: &str) -> Vec<u8> {
+    strip_diacritics(word.to_string())
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// A list of words, like slurs and profanity, that names must not sound like.  Each entry is
+/// reduced to a phonetic code, and a name is blocked if the code of any of its words, or of
+/// the whole name run together, is the code of an entry.  Use the builders'
+/// `.with_phonetic_blocklist()` to re-roll generated names that are blocked, or call
+/// `sounds_like()` directly, e.g. on names typed in by players.
+///
+/// Phonetic codes are tuned for English spelling, and match generously by design: expect a
+/// few innocent names to be blocked along with the homophones, more so with `Soundex`.
+///
+/// ```
+/// use markov_namegen::phonetic::{PhoneticAlgorithm, PhoneticBlocklist};
+/// let blocklist = PhoneticBlocklist::new(PhoneticAlgorithm::Metaphone).with_entries(["fink"]);
+/// assert_eq!(blocklist.sounds_like("Phinck"), Some("fink"));
+/// assert_eq!(blocklist.sounds_like("Ulric Phynk"), Some("fink"));
+/// assert_eq!(blocklist.sounds_like("Finn"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PhoneticBlocklist {
+    algorithm: PhoneticAlgorithm,
+    codes: HashMap<String, String>,
+}
+
+impl PhoneticBlocklist {
+    /// An empty blocklist that compares names by the given algorithm.
+    pub fn new(algorithm: PhoneticAlgorithm) -> Self {
+        PhoneticBlocklist {
+            algorithm,
+            codes: HashMap::new(),
+        }
+    }
+    /// Adds entries to the blocklist.  Entries without Latin letters are ignored.
+    pub fn with_entries(mut self, entries: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        for entry in entries {
+            let entry = entry.into();
+            let code = self.algorithm.encode(&entry);
+            if !code.is_empty() {
+                self.codes.entry(code).or_insert(entry);
+            }
+        }
+        self
+    }
+    /// The number of entries, counting those with the same code once.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+    /// Whether the blocklist has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The entry a name sounds like, if there is one.
+    pub fn sounds_like(&self, name: &str) -> Option<&str> {
+        let whole: String = name.split(|c: char| !c.is_alphabetic()).collect();
+        name.split(|c: char| !c.is_alphabetic())
+            .filter(|word| !word.is_empty())
+            .chain(std::iter::once(whole.as_str()))
+            .find_map(|word| self.codes.get(&self.algorithm.encode(word)))
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soundex() {
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Pfister"), "P236");
+        assert_eq!(soundex("Honeyman"), "H555");
+        assert_eq!(soundex("Ærøskøbing"), "A621");
+        assert_eq!(soundex("Щукин"), "");
+    }
+
+    #[test]
+    fn test_metaphone() {
+        assert_eq!(metaphone("Thompson"), "0MPSN");
+        assert_eq!(metaphone("Schmidt"), "SKMTT");
+        assert_eq!(metaphone("Xavier"), "SFR");
+        assert_eq!(metaphone("Wright"), "RT");
+        assert_eq!(metaphone("Cecily"), "SSL");
+        assert_eq!(metaphone("Edge"), "EJ");
+        assert_eq!(metaphone("Gnash"), "NX");
+        assert_eq!(metaphone(""), "");
+    }
+
+    #[test]
+    fn test_algorithms_block_more_or_less_broadly() {
+        let entries = ["Robert"];
+        let soundex = PhoneticBlocklist::new(PhoneticAlgorithm::Soundex).with_entries(entries);
+        let metaphone = PhoneticBlocklist::new(PhoneticAlgorithm::Metaphone).with_entries(entries);
+        assert_eq!(soundex.sounds_like("Rupert"), Some("Robert"));
+        assert_eq!(metaphone.sounds_like("Rupert"), None);
+        assert_eq!(metaphone.sounds_like("Robbert"), Some("Robert"));
+        assert_eq!(metaphone.len(), 1);
+        assert!(PhoneticBlocklist::default().with_entries(["ъ"]).is_empty());
+    }
+}
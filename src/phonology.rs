@@ -0,0 +1,390 @@
+//! Phoneme classes and syllable shapes, for conlangs whose sound systems are designed rather
+//! than learned: words are built syllable by syllable from shapes like "CV" and "CVC", each
+//! symbol standing for a class of phonemes.
+//!
+//! ```
+//! use markov_namegen::phonology::{Phonology, PhonologyGenerator};
+//! use markov_namegen::RandomTextGenerator;
+//! let phonology = Phonology::new()
+//!     .with_class('C', ["p", "t", "k", "m", "n", "s", "th"])
+//!     .with_class('V', ["a", "e", "i", "o", "u"])
+//!     .with_class('N', ["m", "n"])
+//!     .with_shape("CV", 3.0)
+//!     .with_shape("CV(N)", 1.0)
+//!     .with_syllables(2, 3);
+//! assert!(phonology.matches("thanoku"));
+//! assert!(!phonology.matches("akta"));
+//! let mut words = PhonologyGenerator::new(phonology).with_seed(1);
+//! let word = words.generate_one();
+//! ```
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::postprocess::weighted_choice;
+use crate::state::GeneratorRng;
+use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// How many words `PhonologyGenerator` builds to choose among when it is weighted by a model.
+const CANDIDATES: usize = 20;
+
+/// One symbol of a syllable shape: a phoneme class, or a letter written as it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Symbol {
+    Class(char),
+    Literal(char),
+}
+
+/// A run of symbols in a syllable shape, which may be left out if it was in parentheses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Group {
+    symbols: Vec<Symbol>,
+    optional: bool,
+}
+
+/// A sound system: classes of phonemes, each named by a symbol (conventionally 'C' for
+/// consonants, 'V' for vowels, 'L' for liquids, 'N' for nasals and so on), the shapes a
+/// syllable may take, written in those symbols, and how many syllables a word has.
+///
+/// In a shape, symbols in parentheses may be left out, each such group half of the time, and
+/// any character that isn't a declared class stands for itself, so "s(C)V" is an 's', perhaps
+/// a consonant, then a vowel.  A phoneme may be more than one letter, like "th".  Phonemes and
+/// the words checked against them are compared in lowercase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phonology {
+    classes: BTreeMap<char, Vec<String>>,
+    shapes: Vec<(String, f64)>,
+    parsed: Vec<Vec<Group>>,
+    syllables: (usize, usize),
+}
+
+impl Default for Phonology {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Phonology {
+    /// A sound system without classes or shapes, of words of one to three syllables.
+    pub fn new() -> Self {
+        Phonology {
+            classes: BTreeMap::new(),
+            shapes: Vec::new(),
+            parsed: Vec::new(),
+            syllables: (1, 3),
+        }
+    }
+    /// Declares a class of phonemes, named by `symbol`.  Declaring the same symbol again
+    /// replaces the class.  Declare classes before the shapes that use them.
+    pub fn with_class<'a>(
+        mut self,
+        symbol: char,
+        phonemes: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.classes.insert(
+            symbol,
+            phonemes.into_iter().map(str::to_lowercase).collect(),
+        );
+        self
+    }
+    /// Adds a syllable shape, like "CVC" or "(C)V(N)", with a relative weight.
+    pub fn with_shape(mut self, shape: &str, weight: f64) -> Self {
+        assert!(weight > 0.0, "Weights must be greater than zero.");
+        let mut groups = Vec::new();
+        let mut optional: Option<Vec<Symbol>> = None;
+        for c in shape.chars() {
+            match (c, &mut optional) {
+                ('(', None) => optional = Some(Vec::new()),
+                (')', Some(symbols)) => {
+                    groups.push(Group {
+                        symbols: std::mem::take(symbols),
+                        optional: true,
+                    });
+                    optional = None;
+                }
+                ('(' | ')', _) => panic!("Unbalanced parentheses in syllable shape."),
+                (c, symbols) => {
+                    let symbol = if self.classes.contains_key(&c) {
+                        Symbol::Class(c)
+                    } else {
+                        Symbol::Literal(c)
+                    };
+                    match symbols {
+                        Some(symbols) => symbols.push(symbol),
+                        None => groups.push(Group {
+                            symbols: vec![symbol],
+                            optional: false,
+                        }),
+                    }
+                }
+            }
+        }
+        assert!(
+            optional.is_none(),
+            "Unbalanced parentheses in syllable shape."
+        );
+        self.shapes.push((shape.to_string(), weight));
+        self.parsed.push(groups);
+        self
+    }
+    /// Sets the least and the most syllables a word may have.
+    pub fn with_syllables(mut self, min: usize, max: usize) -> Self {
+        assert!(
+            min > 0 && min <= max,
+            "Syllable counts must be positive, and the minimum no more than the maximum."
+        );
+        self.syllables = (min, max);
+        self
+    }
+
+    /// Whether a word can be read as a string of syllables of the allowed shapes and count.
+    pub fn matches(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        let (min, max) = self.syllables;
+        let mut positions = BTreeSet::from([0]);
+        for syllables in 1..=max {
+            positions = self
+                .parsed
+                .iter()
+                .flat_map(|shape| {
+                    shape.iter().fold(positions.clone(), |positions, group| {
+                        self.advance(&word, positions, group)
+                    })
+                })
+                .collect();
+            if syllables >= min && positions.contains(&word.len()) {
+                return true;
+            }
+            if positions.is_empty() {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Where the word may continue after matching a group, from each of the positions.
+    fn advance(&self, word: &str, positions: BTreeSet<usize>, group: &Group) -> BTreeSet<usize> {
+        let mut advanced = group
+            .symbols
+            .iter()
+            .fold(positions.clone(), |positions, symbol| {
+                positions
+                    .into_iter()
+                    .flat_map(|position| {
+                        let rest = &word[position..];
+                        let lengths: Vec<usize> = match symbol {
+                            Symbol::Class(class) => self.classes[class]
+                                .iter()
+                                .filter(|phoneme| rest.starts_with(phoneme.as_str()))
+                                .map(String::len)
+                                .collect(),
+                            Symbol::Literal(c) => rest
+                                .starts_with(*c)
+                                .then_some(c.len_utf8())
+                                .into_iter()
+                                .collect(),
+                        };
+                        lengths.into_iter().map(move |length| position + length)
+                    })
+                    .collect()
+            });
+        if group.optional {
+            advanced.extend(positions);
+        }
+        advanced
+    }
+
+    /// Builds a word at random, or returns an error if there are no shapes or a class in use
+    /// has no phonemes.
+    fn random_word(&self, rng: &mut GeneratorRng) -> Result<String, NamegenError> {
+        if self.shapes.is_empty() {
+            return Err(NamegenError::InvalidConfig(String::from(
+                "no syllable shapes are set",
+            )));
+        }
+        let (min, max) = self.syllables;
+        let mut word = String::new();
+        for _ in 0..rng.gen_range(min..=max) {
+            let shape = weighted_choice(&self.shapes, rng)
+                .and_then(|chosen| self.shapes.iter().position(|(shape, _)| shape == chosen))
+                .unwrap_or(0);
+            for group in &self.parsed[shape] {
+                if group.optional && rng.gen_bool(0.5) {
+                    continue;
+                }
+                for symbol in &group.symbols {
+                    match symbol {
+                        Symbol::Class(class) => {
+                            let phonemes = &self.classes[class];
+                            if phonemes.is_empty() {
+                                return Err(NamegenError::InvalidConfig(format!(
+                                    "the class '{}' has no phonemes",
+                                    class
+                                )));
+                            }
+                            word.push_str(&phonemes[rng.gen_range(0..phonemes.len())]);
+                        }
+                        Symbol::Literal(c) => word.push(*c),
+                    }
+                }
+            }
+        }
+        Ok(word)
+    }
+}
+
+/// A generator of words that follow a `Phonology`.
+///
+/// On its own, it picks every shape by its weight and every phoneme with equal chances.  Give
+/// it a trained model with `with_model()` to favor the words that sound like the model's
+/// training data: it then builds twenty words and chooses one by how likely the model finds
+/// it, per letter, so that long words aren't held against.  The words still never break the
+/// phonology, which is the difference from filtering a model's output with a pattern.
+pub struct PhonologyGenerator {
+    phonology: Phonology,
+    model: Option<ClusterChainGenerator>,
+    rng: GeneratorRng,
+}
+
+impl PhonologyGenerator {
+    /// Generates words that follow `phonology`.
+    pub fn new(phonology: Phonology) -> Self {
+        PhonologyGenerator {
+            phonology,
+            model: None,
+            rng: GeneratorRng::from_entropy(),
+        }
+    }
+    /// Weights the words by how likely a trained model finds them.
+    pub fn with_model(mut self, model: ClusterChainGenerator) -> Self {
+        self.model = Some(model);
+        self
+    }
+    /// Seeds the generator for reproducible output.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = GeneratorRng::from_seed(seed);
+        self
+    }
+}
+
+impl fmt::Debug for PhonologyGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhonologyGenerator")
+            .field("phonology", &self.phonology)
+            .field("weighted", &self.model.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for PhonologyGenerator {
+    /// Builds a word, or returns an error if the phonology has no shapes or a class in use has
+    /// no phonemes.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        let Some(model) = &self.model else {
+            return self.phonology.random_word(&mut self.rng);
+        };
+        let candidates = (0..CANDIDATES)
+            .map(|_| {
+                let word = self.phonology.random_word(&mut self.rng)?;
+                let letters = word.chars().count() as f64 + 1.0;
+                let weight = (model.log_probability_of(&word) / letters).exp();
+                Ok((word, weight))
+            })
+            .collect::<Result<Vec<(String, f64)>, NamegenError>>()?;
+        Ok(weighted_choice(&candidates, &mut self.rng)
+            .unwrap_or(&candidates[0].0)
+            .to_string())
+    }
+
+    fn kind(&self) -> &'static str {
+        "phonology"
+    }
+
+    /// Counts the classes and shapes, e.g. "3 classes, 2 shapes, weighted by a model".
+    fn config_summary(&self) -> String {
+        let mut summary = format!(
+            "{} classes, {} shapes",
+            self.phonology.classes.len(),
+            self.phonology.shapes.len()
+        );
+        if self.model.is_some() {
+            summary.push_str(", weighted by a model");
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phonology() -> Phonology {
+        Phonology::new()
+            .with_class('C', ["p", "t", "k", "th"])
+            .with_class('V', ["a", "i", "u"])
+            .with_class('L', ["l", "r"])
+            .with_shape("C(L)V", 2.0)
+            .with_shape("sVC", 1.0)
+            .with_syllables(1, 2)
+    }
+
+    #[test]
+    fn test_words_are_matched_against_the_shapes() {
+        let phonology = phonology();
+        assert!(phonology.matches("ka"));
+        assert!(phonology.matches("Tra"));
+        assert!(phonology.matches("thusik"));
+        assert!(phonology.matches("sakplu"));
+        assert!(!phonology.matches("a"));
+        assert!(!phonology.matches("kak"));
+        assert!(!phonology.matches("kakaka"));
+        assert!(!phonology.matches(""));
+    }
+
+    #[test]
+    fn test_generated_words_follow_the_phonology() {
+        let mut words = PhonologyGenerator::new(phonology()).with_seed(3);
+        assert_eq!(words.config_summary(), "3 classes, 2 shapes");
+        for _ in 0..50 {
+            let word = words.generate_one();
+            assert!(phonology().matches(&word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_a_model_favors_words_like_its_corpus() {
+        let model = ClusterChainGenerator::builder()
+            .with_seed(3)
+            .train(vec!["tala", "tila", "tula", "tali"].into_iter())
+            .build();
+        let count = |generator: &mut PhonologyGenerator| {
+            (0..200)
+                .filter(|_| generator.generate_one().starts_with('t'))
+                .count()
+        };
+        let phonology = Phonology::new()
+            .with_class('C', ["p", "t", "k", "l"])
+            .with_class('V', ["a", "i", "u"])
+            .with_shape("CV", 1.0)
+            .with_syllables(2, 2);
+        let mut plain = PhonologyGenerator::new(phonology.clone()).with_seed(3);
+        let mut weighted = PhonologyGenerator::new(phonology)
+            .with_model(model)
+            .with_seed(3);
+        assert_eq!(
+            weighted.config_summary(),
+            "2 classes, 1 shapes, weighted by a model"
+        );
+        assert!(count(&mut weighted) > count(&mut plain) * 2);
+    }
+
+    #[test]
+    fn test_a_phonology_without_shapes_is_an_error() {
+        let mut words = PhonologyGenerator::new(Phonology::new().with_class('V', ["a"]));
+        assert!(matches!(
+            words.try_generate_one(),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+}
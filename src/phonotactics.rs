@@ -0,0 +1,221 @@
+//! Configurable rules about which sounds may follow one another, for filtering generated names
+//! or validating names submitted by users.
+use crate::clusterchain::builder::ClusterChainGeneratorBuilder;
+use crate::language::Language;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A set of phonotactic rules that a name must obey: a limit on consecutive consonants, the
+/// consonant clusters allowed to begin (onsets) and end (codas) a word, and clusters that are
+/// banned anywhere.  Rules left unset are not checked.
+///
+/// Each word of a name (separated by spaces, hyphens, apostrophes and the like) is checked
+/// separately, ignoring case.  Vowels and consonants are told apart by the vowels of a
+/// `Language`: the one set with `with_language()`, or else that of the `ClusterChainGenerator`
+/// the rules are given to, or else the Romance vowels, in which 'y' and 'w' count as
+/// consonants.
+///
+/// Use the builders' `.with_phonotactics()` to filter generated names, or call `validate()`
+/// directly, e.g. on names typed in by players.
+///
+/// ```
+/// use markov_namegen::phonotactics::{Phonotactics, PhonotacticViolation};
+/// let rules = Phonotactics::new()
+///     .with_max_consonants(2)
+///     .with_onsets(["", "b", "br", "d", "dr", "k", "l", "m", "s", "t", "th"])
+///     .with_banned_cluster("tl");
+/// assert!(rules.is_valid("Thorin"));
+/// assert!(rules.is_valid("Bram-Dalin"));
+/// assert_eq!(
+///     rules.validate("Ngarn"),
+///     Err(PhonotacticViolation::DisallowedOnset(String::from("ng")))
+/// );
+/// assert_eq!(
+///     rules.validate("Markstan"),
+///     Err(PhonotacticViolation::TooManyConsonants(String::from("rkst")))
+/// );
+/// assert!(!rules.is_valid("Atla"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Phonotactics {
+    max_consonants: Option<usize>,
+    onsets: Option<HashSet<String>>,
+    codas: Option<HashSet<String>>,
+    banned_clusters: Vec<String>,
+    language: Option<Language>,
+}
+
+/// The reason a name broke a set of `Phonotactics` rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PhonotacticViolation {
+    /// A run of consonants was longer than the maximum.
+    TooManyConsonants(String),
+    /// A word began with a consonant cluster that isn't an allowed onset.
+    DisallowedOnset(String),
+    /// A word ended with a consonant cluster that isn't an allowed coda.
+    DisallowedCoda(String),
+    /// The name contained a banned cluster.
+    BannedCluster(String),
+}
+
+impl fmt::Display for PhonotacticViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhonotacticViolation::TooManyConsonants(cluster) => {
+                write!(f, "too many consonants in a row: '{}'", cluster)
+            }
+            PhonotacticViolation::DisallowedOnset(cluster) => {
+                write!(f, "words may not begin with '{}'", cluster)
+            }
+            PhonotacticViolation::DisallowedCoda(cluster) => {
+                write!(f, "words may not end with '{}'", cluster)
+            }
+            PhonotacticViolation::BannedCluster(cluster) => {
+                write!(f, "'{}' is not allowed", cluster)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PhonotacticViolation {}
+
+impl Phonotactics {
+    /// Creates a set of rules that allows everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of consonants that may appear in a row.
+    pub fn with_max_consonants(mut self, max: usize) -> Self {
+        self.max_consonants = Some(max);
+        self
+    }
+
+    /// Sets the consonant clusters a word may begin with.  Include `""` to allow words that
+    /// begin with a vowel.
+    pub fn with_onsets<'a>(mut self, onsets: impl IntoIterator<Item = &'a str>) -> Self {
+        self.onsets = Some(onsets.into_iter().map(str::to_lowercase).collect());
+        self
+    }
+
+    /// Sets the consonant clusters a word may end with.  Include `""` to allow words that end
+    /// with a vowel.
+    pub fn with_codas<'a>(mut self, codas: impl IntoIterator<Item = &'a str>) -> Self {
+        self.codas = Some(codas.into_iter().map(str::to_lowercase).collect());
+        self
+    }
+
+    /// Bans a sequence of letters from appearing anywhere within a word.
+    pub fn with_banned_cluster(mut self, cluster: &str) -> Self {
+        self.banned_clusters.push(cluster.to_lowercase());
+        self
+    }
+
+    /// Tells vowels from consonants by the vowels of this language, e.g. `Language::Welsh` to
+    /// count 'w' and 'y' as vowels.
+    ///
+    /// ```
+    /// use markov_namegen::phonotactics::Phonotactics;
+    /// use markov_namegen::Language;
+    /// let rules = Phonotactics::new().with_max_consonants(2);
+    /// assert!(!rules.is_valid("Llyn Cwm"));
+    /// assert!(rules.with_language(Language::Welsh).is_valid("Llyn Cwm"));
+    /// ```
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// The rules, telling vowels by `language` unless they were given a language of their own.
+    pub(crate) fn or_language(mut self, language: Language) -> Self {
+        self.language.get_or_insert(language);
+        self
+    }
+
+    /// Checks a name against the rules, returning the first rule it breaks, if any.
+    pub fn validate(&self, name: &str) -> Result<(), PhonotacticViolation> {
+        let name = name.to_lowercase();
+        for word in name.split(|c: char| !c.is_alphabetic()) {
+            if word.is_empty() {
+                continue;
+            }
+            if let Some(banned) = self.banned_clusters.iter().find(|b| word.contains(*b)) {
+                return Err(PhonotacticViolation::BannedCluster(banned.clone()));
+            }
+            let language = self.language.unwrap_or(Language::Romance);
+            let clusters = ClusterChainGeneratorBuilder::clusterize_with(word.to_string(), |c| {
+                language.is_vowel(c)
+            });
+            let is_consonants =
+                |cluster: &String| !cluster.starts_with(|c: char| language.is_vowel(c));
+            if let Some(max) = self.max_consonants {
+                if let Some(cluster) = clusters
+                    .iter()
+                    .find(|c| is_consonants(c) && c.chars().count() > max)
+                {
+                    return Err(PhonotacticViolation::TooManyConsonants(cluster.clone()));
+                }
+            }
+            let onset = clusters.first().filter(|c| is_consonants(c));
+            let onset = onset.map_or("", String::as_str);
+            if self
+                .onsets
+                .as_ref()
+                .is_some_and(|onsets| !onsets.contains(onset))
+            {
+                return Err(PhonotacticViolation::DisallowedOnset(onset.to_string()));
+            }
+            let coda = clusters.last().filter(|c| is_consonants(c));
+            let coda = coda.map_or("", String::as_str);
+            if self
+                .codas
+                .as_ref()
+                .is_some_and(|codas| !codas.contains(coda))
+            {
+                return Err(PhonotacticViolation::DisallowedCoda(coda.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a name obeys all of the rules.
+    pub fn is_valid(&self, name: &str) -> bool {
+        self.validate(name).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codas_and_banned_clusters() {
+        let rules = Phonotactics::new()
+            .with_codas(["", "n", "r", "s"])
+            .with_banned_cluster("Aa");
+        assert!(rules.is_valid("Maren Tor"));
+        assert_eq!(
+            rules.validate("Maren Tork"),
+            Err(PhonotacticViolation::DisallowedCoda(String::from("rk")))
+        );
+        assert_eq!(
+            rules.validate("Kaala"),
+            Err(PhonotacticViolation::BannedCluster(String::from("aa")))
+        );
+    }
+
+    #[test]
+    fn test_unset_rules_allow_everything() {
+        assert!(Phonotactics::new().is_valid("Ngrzbtl"));
+    }
+
+    #[test]
+    fn test_own_language_overrides_the_generator_language() {
+        let rules = Phonotactics::new().with_max_consonants(2);
+        assert!(rules.clone().or_language(Language::Welsh).is_valid("Cwm"));
+        assert!(!rules
+            .with_language(Language::Romance)
+            .or_language(Language::Welsh)
+            .is_valid("Cwm"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Place names: a Markov-generated root with a suffix from a lexicon ("Ashton", "Kelgrad").
+//!
+//! ```
+//! use markov_namegen::place::{PlaceNameGenerator, SuffixLexicon};
+//! use markov_namegen::{CharacterChainGenerator, RandomTextGenerator};
+//! let roots = CharacterChainGenerator::builder()
+//!     .train(vec!["ash", "brad", "wes", "kings", "nor"].into_iter())
+//!     .build();
+//! let mut places = PlaceNameGenerator::new(roots, SuffixLexicon::english()).with_seed(1);
+//! let town = places.generate_one();
+//! ```
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::postprocess::weighted_choice;
+use crate::state::GeneratorRng;
+use is_vowel::IsRomanceVowel;
+use std::fmt;
+
+/// How many roots the generator tries before giving up on finding one short enough.
+const MAX_ROOTS: usize = 100;
+
+/// Suffixes for place names, each with a relative weight.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SuffixLexicon {
+    suffixes: Vec<(String, f64)>,
+}
+
+impl SuffixLexicon {
+    /// An empty lexicon; add suffixes with `with_suffix()`.
+    pub fn new() -> Self {
+        SuffixLexicon::default()
+    }
+    /// English: "-ton", "-ham", "-ford", "-bury", "-stead", "-wick", "-by" and more.
+    pub fn english() -> Self {
+        Self::from_weights(&[
+            ("ton", 5.0),
+            ("ham", 3.0),
+            ("ford", 3.0),
+            ("bury", 2.0),
+            ("ley", 3.0),
+            ("field", 2.0),
+            ("stead", 1.0),
+            ("wick", 1.0),
+            ("by", 1.0),
+            ("thorpe", 0.5),
+            ("mouth", 0.5),
+        ])
+    }
+    /// German: "-burg", "-berg", "-dorf", "-hausen", "-heim" and more.
+    pub fn germanic() -> Self {
+        Self::from_weights(&[
+            ("burg", 3.0),
+            ("berg", 2.0),
+            ("dorf", 3.0),
+            ("hausen", 2.0),
+            ("heim", 2.0),
+            ("feld", 1.0),
+            ("stadt", 1.0),
+            ("bach", 1.0),
+        ])
+    }
+    /// Slavic, romanized: "-grad", "-ovo", "-sk", "-ice" and more.
+    pub fn slavic() -> Self {
+        Self::from_weights(&[
+            ("grad", 2.0),
+            ("ovo", 2.0),
+            ("sk", 2.0),
+            ("ice", 1.0),
+            ("in", 1.0),
+            ("gorod", 0.5),
+            ("ograd", 0.5),
+        ])
+    }
+    /// Norse: "-vik", "-heim", "-fjord", "-by", "-stad" and more.
+    pub fn nordic() -> Self {
+        Self::from_weights(&[
+            ("vik", 2.0),
+            ("heim", 2.0),
+            ("fjord", 1.0),
+            ("by", 2.0),
+            ("stad", 2.0),
+            ("holm", 1.0),
+            ("dal", 1.0),
+        ])
+    }
+    /// Adds a suffix with a relative weight.
+    pub fn with_suffix(mut self, suffix: &str, weight: f64) -> Self {
+        assert!(weight > 0.0, "Weights must be greater than zero.");
+        self.suffixes.push((suffix.to_string(), weight));
+        self
+    }
+
+    fn from_weights(suffixes: &[(&str, f64)]) -> Self {
+        suffixes
+            .iter()
+            .fold(Self::new(), |lexicon, &(suffix, weight)| {
+                lexicon.with_suffix(suffix, weight)
+            })
+    }
+}
+
+/// A generator of place names, each a root from another generator (trained, say, on the
+/// region's personal names, or on the roots of its real place names) joined to a suffix drawn
+/// from a lexicon.  The join follows some rules of English orthography, which suit most
+/// languages written in the Latin alphabet:
+///
+/// * a root that already ends in the suffix is left as it is ("Brandenburg" + "burg");
+/// * a letter that would be doubled across the join is written once ("Wes" + "ston" →
+///   "Weston");
+/// * a vowel at the end of the root gives way to one at the start of the suffix ("Mira" +
+///   "ovo" → "Mirovo").
+pub struct PlaceNameGenerator {
+    roots: Box<dyn RandomTextGenerator + Send>,
+    lexicon: SuffixLexicon,
+    max_root_length: Option<usize>,
+    rng: GeneratorRng,
+}
+
+impl PlaceNameGenerator {
+    /// Joins roots from `roots` to suffixes from `lexicon`.
+    pub fn new(roots: impl RandomTextGenerator + Send + 'static, lexicon: SuffixLexicon) -> Self {
+        PlaceNameGenerator {
+            roots: Box::new(roots),
+            lexicon,
+            max_root_length: None,
+            rng: GeneratorRng::from_entropy(),
+        }
+    }
+    /// Seeds the choice of suffixes for reproducible output.  Seed the roots' generator too.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = GeneratorRng::from_seed(seed);
+        self
+    }
+    /// Rejects roots longer than this many characters, which would make unwieldy place names.
+    pub fn with_max_root_length(mut self, length: usize) -> Self {
+        assert!(length > 0, "Max root length must be greater than zero.");
+        self.max_root_length = Some(length);
+        self
+    }
+}
+
+impl fmt::Debug for PlaceNameGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlaceNameGenerator")
+            .field("roots", &self.roots.kind())
+            .field("lexicon", &self.lexicon)
+            .field("max_root_length", &self.max_root_length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for PlaceNameGenerator {
+    /// Generates a root and joins a suffix to it, or returns an error if the roots' generator
+    /// fails, the lexicon is empty, or no root short enough turns up in a hundred tries.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        for _ in 0..MAX_ROOTS {
+            let root = self.roots.try_generate_one()?;
+            if self
+                .max_root_length
+                .is_some_and(|length| root.chars().count() > length)
+            {
+                continue;
+            }
+            let suffix =
+                weighted_choice(&self.lexicon.suffixes, &mut self.rng).ok_or_else(|| {
+                    NamegenError::InvalidConfig(String::from("the suffix lexicon is empty"))
+                })?;
+            return Ok(join(&root, suffix));
+        }
+        Err(NamegenError::MaxAttemptsExceeded(MAX_ROOTS))
+    }
+
+    fn kind(&self) -> &'static str {
+        "placename"
+    }
+
+    /// Describes the roots and suffixes, e.g. "characterchain roots, 11 suffixes".
+    fn config_summary(&self) -> String {
+        format!(
+            "{} roots, {} suffixes",
+            self.roots.kind(),
+            self.lexicon.suffixes.len()
+        )
+    }
+}
+
+/// Joins a suffix to a root by the rules described on `PlaceNameGenerator`.
+pub(crate) fn join(root: &str, suffix: &str) -> String {
+    let lowercased = root.to_lowercase();
+    if lowercased.ends_with(&suffix.to_lowercase()) {
+        return root.to_string();
+    }
+    let mut joined = root.to_string();
+    let (Some(last), Some(first)) = (lowercased.chars().last(), suffix.chars().next()) else {
+        return joined + suffix;
+    };
+    if last == first.to_lowercase().next().unwrap_or(first)
+        || (last.is_romance_vowel() && first.is_romance_vowel())
+    {
+        joined.pop();
+    }
+    joined + suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CharacterChainGenerator;
+
+    #[test]
+    fn test_joining_rules() {
+        assert_eq!(join("Ash", "ton"), "Ashton");
+        assert_eq!(join("Wes", "ston"), "Weston");
+        assert_eq!(join("Mira", "ovo"), "Mirovo");
+        assert_eq!(join("Brandenburg", "burg"), "Brandenburg");
+        assert_eq!(join("", "by"), "by");
+    }
+
+    #[test]
+    fn test_places_take_suffixes_from_the_lexicon() {
+        let roots = CharacterChainGenerator::builder()
+            .with_seed(2)
+            .without_prior()
+            .train(vec!["kel", "dun", "mor", "tavistockington"].into_iter())
+            .build();
+        let mut places = PlaceNameGenerator::new(
+            roots,
+            SuffixLexicon::new()
+                .with_suffix("grad", 1.0)
+                .with_suffix("heim", 1.0),
+        )
+        .with_seed(2)
+        .with_max_root_length(5);
+        assert_eq!(places.config_summary(), "characterchain roots, 2 suffixes");
+        for _ in 0..20 {
+            let place = places.generate_one();
+            let root = place
+                .strip_suffix("grad")
+                .or_else(|| place.strip_suffix("heim"))
+                .unwrap();
+            assert!(root.chars().count() <= 5, "{}", place);
+        }
+    }
+
+    #[test]
+    fn test_empty_lexicon_is_an_error() {
+        let roots = CharacterChainGenerator::builder()
+            .train(vec!["kel"].into_iter())
+            .build();
+        let mut places = PlaceNameGenerator::new(roots, SuffixLexicon::new());
+        assert!(matches!(
+            places.try_generate_one(),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,464 @@
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError, Weak};
+
+/// How many repeats in a row `NamePool::fill()` will put up with before concluding that the
+/// generator can't produce enough distinct names.
+const MAX_CONSECUTIVE_REPEATS: usize = 1000;
+
+/// A pool of distinct names generated up front and then handed out one at a time, without
+/// replacement.  All of the generation work (including re-rolls for pattern filtering and
+/// duplicates) happens in `fill()`, so taking a name from the pool is cheap and predictable,
+/// which suits servers handing out names on request.
+///
+/// ```
+/// use markov_namegen::{CharacterChainGenerator, NamePool};
+/// let mut generator = CharacterChainGenerator::builder()
+///     .with_seed(1)
+///     .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+///     .build();
+/// let mut pool = NamePool::fill(&mut generator, 10).unwrap();
+/// assert_eq!(pool.len(), 10);
+/// let name = pool.draw().unwrap();
+/// assert_eq!(pool.len(), 9);
+/// assert!(pool.all(|other| other != name));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NamePool {
+    names: VecDeque<String>,
+}
+
+impl NamePool {
+    /// Generates `size` distinct names with the given generator.  Returns an error if the
+    /// generator fails, or if it keeps repeating itself because it can't produce that many
+    /// distinct names.
+    pub fn fill<G: RandomTextGenerator + ?Sized>(
+        generator: &mut G,
+        size: usize,
+    ) -> Result<Self, NamegenError> {
+        let mut seen = HashSet::with_capacity(size);
+        let mut names = VecDeque::with_capacity(size);
+        let mut repeats = 0;
+        while names.len() < size {
+            let name = generator.try_generate_one()?;
+            if seen.insert(name.clone()) {
+                names.push_back(name);
+                repeats = 0;
+            } else {
+                repeats += 1;
+                if repeats >= MAX_CONSECUTIVE_REPEATS {
+                    return Err(NamegenError::MaxAttemptsExceeded(repeats));
+                }
+            }
+        }
+        Ok(NamePool { names })
+    }
+
+    /// Takes the next name out of the pool, or returns `None` if the pool is empty.
+    pub fn draw(&mut self) -> Option<String> {
+        self.names.pop_front()
+    }
+
+    /// The number of names left in the pool.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether every name has been taken.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl Iterator for NamePool {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.draw()
+    }
+}
+
+/// A fixed set of generators behind separate locks, so that many threads (e.g. the handlers of
+/// a web server) can generate names at the same time without each training its own model.
+/// Each request is handed to the next free generator, so threads only wait for one another
+/// when every generator in the pool is busy.
+///
+/// Fill the pool with `fork()`s of one trained generator, which share its model:
+///
+/// ```
+/// use markov_namegen::{pool::GeneratorPool, ClusterChainGenerator};
+/// let generator = ClusterChainGenerator::builder()
+///     .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+///     .build();
+/// let pool = GeneratorPool::from_fn(4, |i| generator.fork(i as u64));
+/// std::thread::scope(|scope| {
+///     for _thread in 0..8 {
+///         scope.spawn(|| pool.generate_one());
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct GeneratorPool<G> {
+    generators: Vec<Mutex<G>>,
+    next: AtomicUsize,
+}
+
+impl<G: RandomTextGenerator> GeneratorPool<G> {
+    /// Creates a pool of the given generators.  Panics if there are none.
+    pub fn new(generators: Vec<G>) -> Self {
+        assert!(
+            !generators.is_empty(),
+            "A pool needs at least one generator."
+        );
+        GeneratorPool {
+            generators: generators.into_iter().map(Mutex::new).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a pool of `size` generators, each made by calling `make` with its index.
+    pub fn from_fn(size: usize, make: impl FnMut(usize) -> G) -> Self {
+        Self::new((0..size).map(make).collect())
+    }
+
+    /// Generates a name with the next free generator.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_one()` if you'd rather
+    /// handle that as an error.
+    pub fn generate_one(&self) -> String {
+        self.with_generator(|generator| generator.generate_one())
+    }
+
+    /// Generates a name with the next free generator, or returns an error if it can't produce
+    /// one.
+    pub fn try_generate_one(&self) -> Result<String, NamegenError> {
+        self.with_generator(|generator| generator.try_generate_one())
+    }
+
+    /// Calls `f` with exclusive use of the next free generator, e.g. to call methods not
+    /// covered by `RandomTextGenerator`.
+    pub fn with_generator<R>(&self, f: impl FnOnce(&mut G) -> R) -> R {
+        f(&mut self.lock_next())
+    }
+
+    /// The number of generators in the pool.
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    /// Always `false`, since a pool can't be empty.
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+
+    /// Locks the first free generator, starting from a different one each time so that the
+    /// work is spread evenly.  If all are busy, waits for the one we started from.
+    fn lock_next(&self) -> MutexGuard<'_, G> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.generators.len();
+        for i in 0..self.generators.len() {
+            let index = (start + i) % self.generators.len();
+            match self.generators[index].try_lock() {
+                Ok(guard) => return guard,
+                // a generator that panicked (e.g. in a postprocessor) is still usable
+                Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => continue,
+            }
+        }
+        self.generators[start]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Generates `count` names on `threads` threads at once, giving each thread its own generator
+/// made by calling `make` with the thread's index, and returns the names in order: those of
+/// thread 0, then those of thread 1, and so on.
+///
+/// Make each thread's generator with `fork_substream()`, and the names depend only on the
+/// seed and the number of threads, however the threads happen to be scheduled:
+///
+/// ```
+/// use markov_namegen::{pool, ClusterChainGenerator};
+/// let generator = ClusterChainGenerator::builder()
+///     .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+///     .build();
+/// let names = pool::generate_in_parallel(100, 4, |i| generator.fork_substream(42, i as u64));
+/// let again = pool::generate_in_parallel(100, 4, |i| generator.fork_substream(42, i as u64));
+/// assert_eq!(names.unwrap(), again.unwrap());
+/// ```
+///
+/// Returns the first error encountered, if any thread's generator can't produce a name.
+pub fn generate_in_parallel<G>(
+    count: usize,
+    threads: usize,
+    make: impl Fn(usize) -> G + Sync,
+) -> Result<Vec<String>, NamegenError>
+where
+    G: RandomTextGenerator,
+{
+    assert!(threads > 0, "Need at least one thread.");
+    let make = &make;
+    let chunks: Vec<Result<Vec<String>, NamegenError>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                // spread the remainder over the first few threads
+                let chunk = count / threads + usize::from(i < count % threads);
+                scope.spawn(move || {
+                    let mut generator = make(i);
+                    (0..chunk).map(|_| generator.try_generate_one()).collect()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    let mut names = Vec::with_capacity(count);
+    for chunk in chunks {
+        names.extend(chunk?);
+    }
+    Ok(names)
+}
+
+/// A thread's generator, and a handle on the `PerThreadGenerator` that made it, which tells
+/// whether that is still alive.
+type PerThreadEntry = (Weak<()>, Box<dyn Any>);
+
+thread_local! {
+    /// Each thread's generators, keyed by the id of the `PerThreadGenerator` that made them.
+    static PER_THREAD: RefCell<HashMap<u64, PerThreadEntry>> = RefCell::new(HashMap::new());
+}
+
+/// The id of the next `PerThreadGenerator`.  Ids are never reused (a 64-bit counter won't wrap),
+/// so a new one can't be handed a generator left behind by one that was dropped.
+static NEXT_PER_THREAD_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Gives every thread that uses it its own generator, kept in thread-local storage, so that
+/// workloads spread across many threads (e.g. with rayon) can generate names freely without
+/// ever waiting on a lock.  A thread's generator is made the first time that thread uses it,
+/// by calling `make` with the number of threads served so far, and lives until the thread
+/// exits or the `PerThreadGenerator` is dropped.  Dropping it drops the generator of the thread
+/// that drops it at once; every other thread drops its generator the next time it uses any
+/// `PerThreadGenerator`, or when it exits.
+///
+/// Make each thread's generator as a `fork()` of one trained generator, which shares its
+/// model:
+///
+/// ```
+/// use markov_namegen::{pool::PerThreadGenerator, ClusterChainGenerator};
+/// let generator = ClusterChainGenerator::builder()
+///     .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+///     .build();
+/// let per_thread = PerThreadGenerator::new(move |i| generator.fork(i as u64));
+/// std::thread::scope(|scope| {
+///     for _thread in 0..8 {
+///         scope.spawn(|| per_thread.generate_one());
+///     }
+/// });
+/// ```
+pub struct PerThreadGenerator<G> {
+    id: u64,
+    alive: Arc<()>,
+    make: Box<dyn Fn(usize) -> G + Send + Sync>,
+    threads: AtomicUsize,
+}
+
+impl<G: RandomTextGenerator + 'static> PerThreadGenerator<G> {
+    /// Creates a `PerThreadGenerator` that makes each thread's generator by calling `make`.
+    pub fn new(make: impl Fn(usize) -> G + Send + Sync + 'static) -> Self {
+        PerThreadGenerator {
+            id: NEXT_PER_THREAD_ID.fetch_add(1, Ordering::Relaxed),
+            alive: Arc::new(()),
+            make: Box::new(make),
+            threads: AtomicUsize::new(0),
+        }
+    }
+
+    /// Generates a name with this thread's generator.
+    ///
+    /// Panics if the generator can't produce a name; use `try_generate_one()` if you'd rather
+    /// handle that as an error.
+    pub fn generate_one(&self) -> String {
+        self.with_generator(|generator| generator.generate_one())
+    }
+
+    /// Generates a name with this thread's generator, or returns an error if it can't produce
+    /// one.
+    pub fn try_generate_one(&self) -> Result<String, NamegenError> {
+        self.with_generator(|generator| generator.try_generate_one())
+    }
+
+    /// Calls `f` with this thread's generator, making it first if this thread hasn't used it
+    /// yet, e.g. to call methods not covered by `RandomTextGenerator`.
+    pub fn with_generator<R>(&self, f: impl FnOnce(&mut G) -> R) -> R {
+        // take the generator out while `f` runs, so that `f` may use other per-thread
+        // generators too
+        let mut generator = PER_THREAD
+            .with(|generators| {
+                let mut generators = generators.borrow_mut();
+                // drop the generators of any `PerThreadGenerator`s dropped on other threads
+                generators.retain(|_, (owner, _)| owner.strong_count() > 0);
+                generators.remove(&self.id)
+            })
+            .and_then(|(_, generator)| generator.downcast::<G>().ok())
+            .unwrap_or_else(|| Box::new((self.make)(self.threads.fetch_add(1, Ordering::Relaxed))));
+        let result = f(&mut generator);
+        let entry = (Arc::downgrade(&self.alive), generator as Box<dyn Any>);
+        PER_THREAD.with(|generators| generators.borrow_mut().insert(self.id, entry));
+        result
+    }
+
+    /// The number of threads that have been given a generator so far.
+    pub fn threads(&self) -> usize {
+        self.threads.load(Ordering::Relaxed)
+    }
+}
+
+impl<G> Drop for PerThreadGenerator<G> {
+    fn drop(&mut self) {
+        // the thread-local storage is gone if this is dropped while its thread exits
+        let _ = PER_THREAD.try_with(|generators| generators.borrow_mut().remove(&self.id));
+    }
+}
+
+impl<G> fmt::Debug for PerThreadGenerator<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerThreadGenerator")
+            .field("threads", &self.threads.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharacterChainGenerator, ClusterChainGenerator};
+
+    #[test]
+    fn test_pool_hands_out_every_name_once() {
+        let mut generator = CharacterChainGenerator::builder()
+            .without_prior()
+            .with_seed(7)
+            .train(vec!["ab", "ba"].into_iter())
+            .build();
+        let mut names: Vec<String> = NamePool::fill(&mut generator, 2).unwrap().collect();
+        names.sort();
+        assert_eq!(names, vec!["ab", "ba"]);
+        assert_eq!(
+            NamePool::fill(&mut generator, 3).unwrap_err(),
+            NamegenError::MaxAttemptsExceeded(MAX_CONSECUTIVE_REPEATS)
+        );
+    }
+
+    #[test]
+    fn test_generator_pool_is_shared_between_threads() {
+        let generator = ClusterChainGenerator::builder()
+            .with_seed(1)
+            .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+            .build();
+        let pool = GeneratorPool::from_fn(3, |i| generator.fork(i as u64));
+        assert_eq!(pool.len(), 3);
+        let names: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| (0..25).map(|_| pool.generate_one()).collect::<Vec<_>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        assert_eq!(names.len(), 200);
+        assert!(names.iter().all(|name| !name.is_empty()));
+    }
+
+    #[test]
+    fn test_each_thread_gets_its_own_generator() {
+        let generator = CharacterChainGenerator::builder()
+            .with_seed(1)
+            .remember_outputs(true)
+            .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+            .build();
+        let per_thread = PerThreadGenerator::new(move |i| generator.fork(i as u64));
+        let first = per_thread.generate_one();
+        // the same thread keeps its generator, and so its memory of past outputs
+        assert_ne!(per_thread.generate_one(), first);
+        assert_eq!(per_thread.threads(), 1);
+        std::thread::scope(|scope| {
+            for _thread in 0..4 {
+                scope.spawn(|| per_thread.generate_one());
+            }
+        });
+        assert_eq!(per_thread.threads(), 5);
+    }
+
+    #[test]
+    fn test_dropping_a_per_thread_generator_drops_each_threads_generator() {
+        /// A generator that counts how many of its kind have been dropped.
+        struct Counted(Arc<AtomicUsize>);
+        impl RandomTextGenerator for Counted {
+            fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+                Ok(String::from("bofur"))
+            }
+        }
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let counter = dropped.clone();
+        let per_thread = PerThreadGenerator::new(move |_| Counted(counter.clone()));
+        per_thread.generate_one();
+        drop(per_thread);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+
+        let counter = dropped.clone();
+        let per_thread = Arc::new(PerThreadGenerator::new(move |_| Counted(counter.clone())));
+        let (used, go_on) = (std::sync::mpsc::channel(), std::sync::mpsc::channel::<()>());
+        let worker = {
+            let per_thread = per_thread.clone();
+            std::thread::spawn(move || {
+                per_thread.generate_one();
+                drop(per_thread);
+                used.0.send(()).unwrap();
+                go_on.1.recv().unwrap();
+                // any other per-thread generator clears out the dropped one's
+                PerThreadGenerator::new(|_| Counted(Arc::default())).generate_one();
+            })
+        };
+        used.1.recv().unwrap();
+        drop(per_thread);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        go_on.0.send(()).unwrap();
+        worker.join().unwrap();
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_parallel_generation_is_reproducible() {
+        let generator = CharacterChainGenerator::builder()
+            .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+            .build();
+        let names = generate_in_parallel(10, 3, |i| generator.fork_substream(42, i as u64));
+        let names = names.unwrap();
+        assert_eq!(names.len(), 10);
+        // thread 0 made the first four names, from substream 0
+        let mut first = generator.fork_substream(42, 0);
+        let expected: Vec<String> = (0..4).map(|_| first.generate_one()).collect();
+        assert_eq!(names[..4], expected[..]);
+        assert_ne!(
+            generator.fork_substream(42, 1).generate_one(),
+            generator.fork_substream(42, 2).generate_one()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Separate statistics for the last letter (or cluster) of a word, for
+//! `.with_positional_tables()`.
+//!
+//! The model's contexts beginning with the start-of-word marker already give the first
+//! positions of a word statistics of their own, but nothing tells a letter that ends a word
+//! from one in the middle of it: the model picks the last letter as it would any other, and
+//! only then decides to end the word.  So alongside the model we count, for each context, the
+//! states that were the last of a word after it.  The weight of each transition in the model
+//! is then split between its final part (at most the count of times it ended a word) and its
+//! medial part (the rest), and the generator decides whether to end the word as it draws its
+//! last letter, from the final table, rather than after.
+use crate::model::{Chain, MarkovModel};
+use rand::{Rng, RngCore};
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+/// Counts the last state of a sample (which begins and ends with the end-of-word marker) as
+/// final after each of the contexts before it, up to `order` states long, with the given
+/// weight.  A negative weight takes the counts out again, removing any that fall to zero.
+pub(crate) fn count_finals<T: Eq + Hash + Clone + Ord>(
+    finals: &mut Chain<T>,
+    order: usize,
+    sample: &[T],
+    weight: f64,
+) {
+    // a sample with no states between its markers has no last state
+    if sample.len() < 3 {
+        return;
+    }
+    let position = sample.len() - 2;
+    let state = &sample[position];
+    for start in position.saturating_sub(order)..position {
+        let context = &sample[start..position];
+        if weight < 0.0 && !finals.contains_key(context) {
+            continue;
+        }
+        let transitions = finals.entry(context.to_vec()).or_default();
+        let count = transitions.entry(state.clone()).or_insert(0.0);
+        *count += weight;
+        if *count <= 0.0 {
+            transitions.remove(state);
+            if transitions.is_empty() {
+                finals.remove(context);
+            }
+        }
+    }
+}
+
+/// Learns more samples after training, as `MarkovModel::learned()` does: before each sample
+/// is counted, every count already there is multiplied by `1 - decay`.
+pub(crate) fn learn_finals<T: Eq + Hash + Clone + Ord>(
+    finals: &mut Chain<T>,
+    order: usize,
+    samples: &[Vec<T>],
+    decay: f64,
+) {
+    let retained = |samples: usize| (1.0 - decay).powi(samples as i32);
+    if decay > 0.0 {
+        let factor = retained(samples.len());
+        for count in finals.values_mut().flat_map(BTreeMap::values_mut) {
+            *count *= factor;
+        }
+    }
+    for (i, sample) in samples.iter().enumerate() {
+        count_finals(finals, order, sample, retained(samples.len() - 1 - i));
+    }
+}
+
+/// Draws the state to follow the given sequence, as `MarkovModel::random_next()` does, and
+/// whether it's the last of the word.  The draw is from the transitions of the same context as
+/// the model would use, each split into its final and medial parts; the end-of-word marker
+/// itself is only drawn if nothing else can follow the context.
+pub(crate) fn random_next<T: Eq + Hash + Clone + Ord>(
+    model: &MarkovModel<T>,
+    finals: &Chain<T>,
+    sequence: &[T],
+    end: &T,
+    rng: &mut dyn RngCore,
+) -> Option<(T, bool)> {
+    let (context, transitions) = model.best_context(sequence)?;
+    let finals = finals.get(context);
+    // each state's final and medial weights, in sorted order
+    let parts: Vec<(&T, f64, f64)> = transitions
+        .iter()
+        .filter(|(state, _)| *state != end)
+        .map(|(state, &weight)| {
+            let count = finals.and_then(|finals| finals.get(state)).copied();
+            let last = count.unwrap_or(0.0).min(weight);
+            (state, last, weight - last)
+        })
+        .collect();
+    let total: f64 = parts.iter().map(|(_, last, medial)| last + medial).sum();
+    if total <= 0.0 {
+        return Some((end.clone(), false));
+    }
+    let mut roll = rng.gen::<f64>() * total;
+    for &(state, last, medial) in &parts {
+        if roll < last {
+            return Some((state.clone(), true));
+        }
+        roll -= last;
+        if roll < medial {
+            return Some((state.clone(), false));
+        }
+        roll -= medial;
+    }
+    // only rounding error can bring us here
+    parts
+        .iter()
+        .rev()
+        .find(|(_, last, medial)| last + medial > 0.0)
+        .map(|&(state, last, _)| (state.clone(), last > 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multimarkov::MultiMarkov;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    fn samples(words: &[&str]) -> Vec<Vec<char>> {
+        words.iter().map(|word| word.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_finals_are_counted_and_uncounted() {
+        let mut finals = Chain::new();
+        for sample in samples(&["#ab#", "#cb#", "#a#", "##"]) {
+            count_finals(&mut finals, 2, &sample, 1.0);
+        }
+        assert_eq!(finals[&vec!['a']][&'b'], 1.0);
+        assert_eq!(finals[&vec!['#', 'c']][&'b'], 1.0);
+        assert_eq!(finals[&vec!['#']][&'a'], 1.0);
+        assert!(!finals.contains_key(&vec!['b']));
+        count_finals(&mut finals, 2, &samples(&["#ab#"])[0], -1.0);
+        assert!(!finals.contains_key(&vec!['a']));
+        assert!(!finals.contains_key(&vec!['#', 'a']));
+    }
+
+    #[test]
+    fn test_words_end_with_their_final_letters() {
+        // 'b' and 'c' both follow 'a', but only 'c' ever ends a word
+        let words = samples(&["#abac#", "#abac#", "#ac#", "#abc#"]);
+        let model: MarkovModel<char> = MultiMarkov::<char>::builder()
+            .with_order(1)
+            .without_prior()
+            .train(words.clone().into_iter())
+            .build()
+            .into();
+        let mut finals = Chain::new();
+        learn_finals(&mut finals, 1, &words, 0.0);
+        let mut rng = SmallRng::seed_from_u64(2);
+        for _i in 0..100 {
+            let (state, last) = random_next(&model, &finals, &['#', 'a'], &'#', &mut rng).unwrap();
+            assert!(!last || state == 'c');
+            assert_ne!(state, '#');
+        }
+    }
+}
@@ -0,0 +1,654 @@
+//! Ready-made transformations for use with the builders' `.with_postprocessor()`.
+use crate::clusterchain::builder::ClusterChainGeneratorBuilder;
+use crate::language::Language;
+use crate::script::Script;
+use is_vowel::IsRomanceVowel;
+use rand::{Rng, RngCore};
+use std::sync::Arc;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// A transformation applied to every generated name, after pattern filtering.  It is handed the
+/// generator's RNG, so that random decorations are as reproducible as the names themselves.
+pub(crate) type Postprocessor = Arc<dyn Fn(String, &mut dyn RngCore) -> String + Send + Sync>;
+
+/// Applies each postprocessor in turn, in the order they were added.
+pub(crate) fn apply_all(
+    postprocessors: &[Postprocessor],
+    name: String,
+    rng: &mut dyn RngCore,
+) -> String {
+    postprocessors
+        .iter()
+        .fold(name, |name, postprocess| postprocess(name, rng))
+}
+
+/// Picks one of the entries at random, in proportion to its weight.
+pub(crate) fn weighted_choice<'a>(
+    entries: &'a [(String, f64)],
+    rng: &mut dyn RngCore,
+) -> Option<&'a str> {
+    let sum_of_weights: f64 = entries.iter().map(|(_, weight)| weight).sum();
+    let mut randomroll = rng.gen::<f64>() * sum_of_weights;
+    for (entry, weight) in entries {
+        if randomroll > *weight {
+            randomroll -= weight;
+        } else {
+            return Some(entry);
+        }
+    }
+    None
+}
+
+/// Probabilistically attaches culturally-flavored prefixes and suffixes ("Mac", "von ", "son",
+/// "dottir") to generated names, so that surname styles can be layered on top of the Markov
+/// output.  Add it to a generator with the builders' `.with_affixes()`.
+///
+/// Each generated name gets a prefix with probability `prefix_probability`, and a suffix with
+/// probability `suffix_probability` (both 0.5 by default).  When it does, one is drawn from the
+/// list in proportion to the given weights.  Affixes are attached verbatim, so include any space
+/// or hyphen you want between affix and name.
+///
+/// ```
+/// use markov_namegen::{postprocess::Affixes, ClusterChainGenerator, RandomTextGenerator};
+/// let mut namegen = ClusterChainGenerator::builder()
+///     .with_affixes(
+///         Affixes::new()
+///             .with_suffix("son", 3.0)
+///             .with_suffix("dottir", 2.0)
+///             .with_suffix_probability(1.0),
+///     )
+///     .train(vec!["bjorn","gunnar","sigurd","ragnar"].into_iter())
+///     .build();
+/// let name = namegen.generate_one();
+/// assert!(name.ends_with("son") || name.ends_with("dottir"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Affixes {
+    prefixes: Vec<(String, f64)>,
+    suffixes: Vec<(String, f64)>,
+    prefix_probability: f64,
+    suffix_probability: f64,
+}
+
+impl Default for Affixes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Affixes {
+    /// Creates an empty set of affixes.
+    pub fn new() -> Self {
+        Affixes {
+            prefixes: Vec::new(),
+            suffixes: Vec::new(),
+            prefix_probability: 0.5,
+            suffix_probability: 0.5,
+        }
+    }
+    /// Adds a prefix with the given relative weight.
+    pub fn with_prefix(mut self, prefix: &str, weight: f64) -> Self {
+        assert!(weight > 0.0, "Affix weights must be greater than zero.");
+        self.prefixes.push((prefix.to_string(), weight));
+        self
+    }
+    /// Adds a suffix with the given relative weight.
+    pub fn with_suffix(mut self, suffix: &str, weight: f64) -> Self {
+        assert!(weight > 0.0, "Affix weights must be greater than zero.");
+        self.suffixes.push((suffix.to_string(), weight));
+        self
+    }
+    /// Sets the probability (from 0.0 to 1.0) that a name gets a prefix.
+    pub fn with_prefix_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.prefix_probability = probability;
+        self
+    }
+    /// Sets the probability (from 0.0 to 1.0) that a name gets a suffix.
+    pub fn with_suffix_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.suffix_probability = probability;
+        self
+    }
+
+    pub(crate) fn decorate(&self, mut name: String, rng: &mut dyn RngCore) -> String {
+        if rng.gen_bool(self.prefix_probability) {
+            if let Some(prefix) = weighted_choice(&self.prefixes, rng) {
+                name.insert_str(0, prefix);
+            }
+        }
+        if rng.gen_bool(self.suffix_probability) {
+            if let Some(suffix) = weighted_choice(&self.suffixes, rng) {
+                name.push_str(suffix);
+            }
+        }
+        name
+    }
+}
+
+/// Inserts apostrophes and hyphens into generated names for a fantasy flavor ("Kel'thar",
+/// "Ara-nel").  Add it to a generator with the builders' `.with_stylizer()`.
+///
+/// Marks are only placed at natural syllable breaks, found from the vowel and consonant clusters
+/// of the name, the way `ClusterChainGenerator` learns them: before a single consonant between
+/// vowels ("a-ra"), or after the first of several ("kel'thar").  At each
+/// such break, an apostrophe is inserted with probability `apostrophe_probability`, or else a
+/// hyphen with probability `hyphen_probability`, up to `max_marks` marks per name (by default,
+/// 0.2, 0.1, and 1).
+///
+/// Vowels are those of the stylizer's language, if set with `.with_language()`, or else of the
+/// `ClusterChainGenerator`'s language; other generators have theirs guessed from each word.
+///
+/// ```
+/// use markov_namegen::{postprocess::Stylizer, ClusterChainGenerator, RandomTextGenerator};
+/// let mut namegen = ClusterChainGenerator::builder()
+///     .with_stylizer(Stylizer::new().with_apostrophe_probability(0.3))
+///     .train(vec!["kelthar","aranel","ilyana","zaraketh"].into_iter())
+///     .build();
+/// println!("{}", namegen.generate_one());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stylizer {
+    apostrophe_probability: f64,
+    hyphen_probability: f64,
+    max_marks: usize,
+    language: Option<Language>,
+}
+
+impl Default for Stylizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stylizer {
+    /// Creates a stylizer with the default probabilities.
+    pub fn new() -> Self {
+        Stylizer {
+            apostrophe_probability: 0.2,
+            hyphen_probability: 0.1,
+            max_marks: 1,
+            language: None,
+        }
+    }
+    /// Sets the probability (from 0.0 to 1.0) of an apostrophe at each natural break.
+    pub fn with_apostrophe_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.apostrophe_probability = probability;
+        self
+    }
+    /// Sets the probability (from 0.0 to 1.0) of a hyphen at each natural break that didn't get
+    /// an apostrophe.
+    pub fn with_hyphen_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.hyphen_probability = probability;
+        self
+    }
+    /// Sets the maximum number of marks inserted into any one name.
+    pub fn with_max_marks(mut self, max_marks: usize) -> Self {
+        self.max_marks = max_marks;
+        self
+    }
+    /// Sets the language whose vowels decide where the syllable breaks are.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// Stylizes a name, falling back on the given language (the generator's, if known) when
+    /// the stylizer has none of its own.
+    pub(crate) fn stylize(
+        &self,
+        name: String,
+        rng: &mut dyn RngCore,
+        language: Option<Language>,
+    ) -> String {
+        let language = self.language.or(language);
+        let mut marks = 0;
+        let words: Vec<String> = name
+            .split(' ')
+            .map(|word| {
+                let language = language.unwrap_or_else(|| guess_language(word));
+                let syllables = ClusterChainGeneratorBuilder::syllabify(word.to_string(), |c| {
+                    language.is_vowel(c)
+                });
+                let mut stylized = String::new();
+                for (i, syllable) in syllables.iter().enumerate() {
+                    if i > 0 && marks < self.max_marks {
+                        if rng.gen_bool(self.apostrophe_probability) {
+                            stylized.push('\'');
+                            marks += 1;
+                        } else if rng.gen_bool(self.hyphen_probability) {
+                            stylized.push('-');
+                            marks += 1;
+                        }
+                    }
+                    stylized.push_str(syllable);
+                }
+                stylized
+            })
+            .collect();
+        words.join(" ")
+    }
+}
+
+/// Respells generated names with historical or dialectal variations (c↔k, i↔y, ph↔f,
+/// doubled consonants), so that a world's names can vary like real ones do.  Add it to a
+/// generator with the builders' `.with_spelling_variants()`.
+///
+/// Each name is respelled with probability `probability` (0.5 by default), by one of the
+/// configured substitutions at one place in the name.  Every possible respelling is scored by
+/// the generator's model, and those less likely than the original by more than a factor of
+/// `max_likelihood_drop` (100 by default) are rejected, so that variants stay plausible; one
+/// of the rest is drawn in proportion to its likelihood.  Substitutions apply to the name as
+/// the model generated it, before any other postprocessing, so they should be in lowercase
+/// unless case is kept with `.with_case_folding()`.
+///
+/// ```
+/// use markov_namegen::{postprocess::SpellingVariants, CharacterChainGenerator};
+/// use markov_namegen::RandomTextGenerator;
+/// let mut namegen = CharacterChainGenerator::builder()
+///     .with_spelling_variants(SpellingVariants::common().with_swap("v", "w"))
+///     .train(vec!["katrin", "vilhelm", "sofia", "kristoffer"].into_iter())
+///     .build();
+/// println!("{}", namegen.generate_one());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpellingVariants {
+    substitutions: Vec<(String, String)>,
+    consonant_doubling: bool,
+    probability: f64,
+    max_likelihood_drop: f64,
+}
+
+impl Default for SpellingVariants {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpellingVariants {
+    /// Creates an empty set of substitutions.
+    pub fn new() -> Self {
+        SpellingVariants {
+            substitutions: Vec::new(),
+            consonant_doubling: false,
+            probability: 0.5,
+            max_likelihood_drop: 100.0,
+        }
+    }
+    /// The common variations of European spelling: c↔k, i↔y, f↔ph, and doubling or
+    /// undoubling consonants.
+    pub fn common() -> Self {
+        Self::new()
+            .with_swap("c", "k")
+            .with_swap("i", "y")
+            .with_swap("f", "ph")
+            .with_consonant_doubling()
+    }
+    /// Adds a substitution of `to` for `from`, in one direction only.
+    pub fn with_substitution(mut self, from: &str, to: &str) -> Self {
+        assert!(!from.is_empty(), "A substitution must replace something.");
+        self.substitutions.push((from.to_string(), to.to_string()));
+        self
+    }
+    /// Adds substitutions of `b` for `a` and of `a` for `b`.
+    pub fn with_swap(self, a: &str, b: &str) -> Self {
+        self.with_substitution(a, b).with_substitution(b, a)
+    }
+    /// Allows a single consonant between two vowels to be doubled ("mila" → "milla"), and a
+    /// doubled one to be made single.  Vowels are those of the Latin alphabet.
+    pub fn with_consonant_doubling(mut self) -> Self {
+        self.consonant_doubling = true;
+        self
+    }
+    /// Sets the probability (from 0.0 to 1.0) that a name is respelled.
+    pub fn with_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.probability = probability;
+        self
+    }
+    /// Sets how many times less likely than the original, under the model, a respelling may
+    /// be.  1.0 only allows respellings at least as likely as the original.
+    pub fn with_max_likelihood_drop(mut self, factor: f64) -> Self {
+        assert!(
+            factor >= 1.0,
+            "The maximum likelihood drop must be at least 1.0."
+        );
+        self.max_likelihood_drop = factor;
+        self
+    }
+
+    /// Every respelling of the name by one substitution at one place.
+    pub(crate) fn variants(&self, name: &str) -> Vec<String> {
+        let mut variants = Vec::new();
+        for (from, to) in &self.substitutions {
+            for (i, _) in name.match_indices(from.as_str()) {
+                variants.push(format!("{}{}{}", &name[..i], to, &name[i + from.len()..]));
+            }
+        }
+        if self.consonant_doubling {
+            let chars: Vec<char> = name.chars().collect();
+            let vowel = |i: usize| chars.get(i).is_some_and(|c| c.is_romance_vowel());
+            for i in 1..chars.len().saturating_sub(1) {
+                let consonant = chars[i].is_alphabetic() && !vowel(i);
+                if consonant && vowel(i - 1) && vowel(i + 1) {
+                    let mut doubled = chars.clone();
+                    doubled.insert(i, chars[i]);
+                    variants.push(doubled.into_iter().collect());
+                } else if consonant && chars[i + 1] == chars[i] {
+                    let mut single = chars.clone();
+                    single.remove(i);
+                    variants.push(single.into_iter().collect());
+                }
+            }
+        }
+        variants.sort();
+        variants.dedup();
+        variants.retain(|variant| variant != name);
+        variants
+    }
+
+    /// Respells the name, with probability `probability`, by one of the variants that
+    /// `log_probability` doesn't rate too much less likely than the name itself.
+    pub(crate) fn vary(
+        &self,
+        name: String,
+        rng: &mut dyn RngCore,
+        log_probability: impl Fn(&str) -> f64,
+    ) -> String {
+        if !rng.gen_bool(self.probability) {
+            return name;
+        }
+        let threshold = log_probability(&name) - self.max_likelihood_drop.ln();
+        let plausible: Vec<(String, f64)> = self
+            .variants(&name)
+            .into_iter()
+            .filter_map(|variant| {
+                let score = log_probability(&variant);
+                (score.is_finite() && score >= threshold).then(|| (variant, score.exp()))
+            })
+            .collect();
+        match weighted_choice(&plausible, rng) {
+            Some(variant) => variant.to_string(),
+            None => name,
+        }
+    }
+}
+
+/// Returns a postprocessor that marks the syllable breaks in generated names ("fa·bia·na"), for
+/// text-to-speech pipelines and typesetting.  Syllables are found from the vowel and consonant
+/// clusters of the name, the way `ClusterChainGenerator` learns them, with the vowels of a
+/// language guessed from each word by `Language::detect()`.  Use `mark_syllables_in()` to set
+/// the language instead.
+///
+/// ```
+/// use markov_namegen::{postprocess, ClusterChainGenerator, RandomTextGenerator};
+/// let mut namegen = ClusterChainGenerator::builder()
+///     .without_prior()
+///     .with_postprocessor(postprocess::mark_syllables("·"))
+///     .train(vec!["fabiana"].into_iter())
+///     .build();
+/// assert_eq!(namegen.generate_one(), "fa·bia·na");
+/// ```
+pub fn mark_syllables(marker: &str) -> impl Fn(String) -> String + Send + Sync + 'static {
+    syllable_marker(marker, None)
+}
+
+/// Returns a postprocessor that marks the syllable breaks in generated names, like
+/// `mark_syllables()`, but with the vowels of the given language.
+///
+/// ```
+/// use markov_namegen::{postprocess, Language};
+/// let mark = postprocess::mark_syllables_in("·", Language::Welsh);
+/// assert_eq!(mark(String::from("gwynedd")), "gwy·nedd");
+/// ```
+pub fn mark_syllables_in(
+    marker: &str,
+    language: Language,
+) -> impl Fn(String) -> String + Send + Sync + 'static {
+    syllable_marker(marker, Some(language))
+}
+
+fn syllable_marker(
+    marker: &str,
+    language: Option<Language>,
+) -> impl Fn(String) -> String + Send + Sync + 'static {
+    let marker = marker.to_string();
+    move |name| {
+        name.split(' ')
+            .map(|word| {
+                let language = language.unwrap_or_else(|| guess_language(word));
+                ClusterChainGeneratorBuilder::syllabify(word.to_string(), |c| language.is_vowel(c))
+                    .join(&marker)
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+/// Guesses the language of a single word for its vowels, assuming the Romance vowels if the
+/// word has no letters to go by.
+fn guess_language(word: &str) -> Language {
+    Language::detect([word]).unwrap_or(Language::Romance)
+}
+
+/// Strips diacritics from a name (é→e, ñ→n) and transliterates letters that have no
+/// decomposition into ASCII (ø→o, æ→ae, ß→ss), for games whose fonts or save formats are
+/// ASCII-only even though the training corpus isn't.  Only letters of the Latin script lose
+/// their marks; letters of other scripts are left as they are, so "й" stays "й" rather than
+/// becoming "и".
+///
+/// ```
+/// use markov_namegen::{postprocess, CharacterChainGenerator, RandomTextGenerator};
+/// let mut namegen = CharacterChainGenerator::builder()
+///     .without_prior()
+///     .with_postprocessor(postprocess::strip_diacritics)
+///     .train(vec!["søren"].into_iter())
+///     .build();
+/// assert_eq!(namegen.generate_one(), "soren");
+/// ```
+pub fn strip_diacritics(name: String) -> String {
+    let mut stripped = String::with_capacity(name.len());
+    // whether the marks that follow belong to a Latin letter
+    let mut latin = false;
+    for c in name.nfd() {
+        if is_combining_mark(c) {
+            if !latin {
+                stripped.push(c);
+            }
+            continue;
+        }
+        latin = Script::is_latin(c);
+        match c {
+            'ø' => stripped.push('o'),
+            'Ø' => stripped.push('O'),
+            'æ' => stripped.push_str("ae"),
+            'Æ' => stripped.push_str("Ae"),
+            'œ' => stripped.push_str("oe"),
+            'Œ' => stripped.push_str("Oe"),
+            'ß' => stripped.push_str("ss"),
+            'þ' => stripped.push_str("th"),
+            'Þ' => stripped.push_str("Th"),
+            'ð' | 'đ' => stripped.push('d'),
+            'Ð' | 'Đ' => stripped.push('D'),
+            'ł' => stripped.push('l'),
+            'Ł' => stripped.push('L'),
+            'ħ' => stripped.push('h'),
+            'Ħ' => stripped.push('H'),
+            'ı' => stripped.push('i'),
+            _ => stripped.push(c),
+        }
+    }
+    stripped.nfc().collect()
+}
+
+/// Makes a name into a URL slug: lowercase, without diacritics (see `strip_diacritics()`),
+/// with apostrophes dropped and every other run of spaces and punctuation made into a single
+/// hyphen ("Jean-Luc O'Neil Marais" → "jean-luc-oneil-marais").  Letters of other scripts are
+/// kept, lowercased.  Add it to a generator with the builders' `.as_slug()`.
+///
+/// ```
+/// use markov_namegen::postprocess::slugify;
+/// assert_eq!(slugify(String::from("Jean-Luc Marais")), "jean-luc-marais");
+/// assert_eq!(slugify(String::from("  Ærø,  Kora! ")), "aero-kora");
+/// ```
+pub fn slugify(name: String) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in strip_diacritics(name).chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !matches!(c, '\'' | '’') && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    if slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+/// Capitalizes the first letter of every word of a name, where words are separated by spaces,
+/// hyphens or apostrophes ("jean-luc o'neil" → "Jean-Luc O'Neil").
+pub fn titlecase(name: String) -> String {
+    let mut titled = String::with_capacity(name.len());
+    let mut word_start = true;
+    for c in name.chars() {
+        if word_start {
+            titled.extend(c.to_uppercase());
+        } else {
+            titled.push(c);
+        }
+        word_start = c.is_whitespace() || c == '-' || c == '\'';
+    }
+    titled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn test_affixes() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let always = Affixes::new()
+            .with_prefix("von ", 1.0)
+            .with_prefix_probability(1.0)
+            .with_suffix("son", 1.0)
+            .with_suffix_probability(1.0);
+        assert_eq!(
+            always.decorate(String::from("olaf"), &mut rng),
+            "von olafson"
+        );
+        let never = always
+            .with_prefix_probability(0.0)
+            .with_suffix_probability(0.0);
+        assert_eq!(never.decorate(String::from("olaf"), &mut rng), "olaf");
+    }
+
+    #[test]
+    fn test_spelling_variants() {
+        let variants = SpellingVariants::common();
+        assert_eq!(
+            variants.variants("kamila"),
+            vec!["camila", "kamilla", "kammila", "kamyla"]
+        );
+        assert_eq!(variants.variants("anna"), vec!["ana"]);
+        let mut rng = SmallRng::seed_from_u64(5);
+        let always = variants.with_probability(1.0);
+        // of the variants of "kamila", only "camila" is likely enough
+        let score = |name: &str| match name {
+            "camila" => -1.0,
+            "kamila" => -5.0,
+            _ => -20.0,
+        };
+        assert_eq!(
+            always.vary(String::from("kamila"), &mut rng, score),
+            "camila"
+        );
+        let never = always.with_max_likelihood_drop(1.0);
+        assert_eq!(
+            never.vary(String::from("camila"), &mut rng, score),
+            "camila"
+        );
+    }
+
+    #[test]
+    fn test_stylizer_marks_only_natural_breaks() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let always = Stylizer::new()
+            .with_apostrophe_probability(1.0)
+            .with_max_marks(usize::MAX);
+        assert_eq!(
+            always.stylize(String::from("aranel"), &mut rng, None),
+            "a'ra'nel"
+        );
+        assert_eq!(
+            always.stylize(String::from("kelthar"), &mut rng, None),
+            "kel'thar"
+        );
+        let hyphens = Stylizer::new()
+            .with_apostrophe_probability(0.0)
+            .with_hyphen_probability(1.0);
+        assert_eq!(
+            hyphens.stylize(String::from("aranel"), &mut rng, None),
+            "a-ranel"
+        );
+    }
+
+    #[test]
+    fn test_mark_syllables() {
+        let mark = mark_syllables("-");
+        assert_eq!(mark(String::from("gaius julius")), "gaius ju-lius");
+        assert_eq!(mark(String::from("")), "");
+        assert_eq!(mark(String::from("наталья")), "на-тал-ья");
+        let welsh = mark_syllables_in("-", Language::Welsh);
+        assert_eq!(welsh(String::from("gwynedd")), "gwy-nedd");
+    }
+
+    #[test]
+    fn test_strip_diacritics() {
+        assert_eq!(strip_diacritics(String::from("Ærøskøbing")), "Aeroskobing");
+        assert_eq!(strip_diacritics(String::from("José Łódź")), "Jose Lodz");
+        assert_eq!(strip_diacritics(String::from("Straße")), "Strasse");
+        assert_eq!(strip_diacritics(String::from("Москва")), "Москва");
+        assert_eq!(strip_diacritics(String::from("Йёрн Jörn")), "Йёрн Jorn");
+        assert_eq!(strip_diacritics(String::from("Ἀθῆναι")), "Ἀθῆναι");
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(
+            slugify(String::from("Jean-Luc O'Neil Marais")),
+            "jean-luc-oneil-marais"
+        );
+        assert_eq!(slugify(String::from("Москва -- Сити")), "москва-сити");
+        assert_eq!(slugify(String::from("--")), "");
+    }
+
+    #[test]
+    fn test_titlecase() {
+        assert_eq!(
+            titlecase(String::from("jean-luc o'neil")),
+            "Jean-Luc O'Neil"
+        );
+        assert_eq!(titlecase(String::from("ærøskøbing")), "Ærøskøbing");
+    }
+}
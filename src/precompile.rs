@@ -0,0 +1,92 @@
+//! Precompiling models in a build script, so that release builds skip training entirely.
+//!
+//! In `build.rs`, train a generator from your corpus and write its model to `OUT_DIR`, and tell
+//! Cargo to do it again when the corpus changes:
+//!
+//! ```no_run
+//! use markov_namegen::{precompile, ClusterChainGenerator};
+//! let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//! precompile::rerun_if_changed("resources/romans.txt");
+//! precompile::write_model("resources/romans.txt", out_dir.join("romans.model"), |corpus| {
+//!     let namegen = ClusterChainGenerator::builder().train(corpus.lines()).try_build()?;
+//!     Ok(namegen.model_bytes())
+//! })
+//! .unwrap();
+//! ```
+//!
+//! Then embed the model in your binary and load it instead of training:
+//!
+//! ```ignore
+//! let namegen = ClusterChainGenerator::builder()
+//!     .with_precompiled_model(include_bytes!(concat!(env!("OUT_DIR"), "/romans.model")))
+//!     .build();
+//! ```
+use crate::error::NamegenError;
+use std::fs;
+use std::path::Path;
+
+/// Reads a corpus, trains a model from its text with `train` (which should return the
+/// trained generator's `model_bytes()`), and writes the model to `out`.
+pub fn write_model(
+    corpus: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+    train: impl FnOnce(&str) -> Result<Vec<u8>, NamegenError>,
+) -> Result<(), NamegenError> {
+    let (corpus, out) = (corpus.as_ref(), out.as_ref());
+    let text = fs::read_to_string(corpus).map_err(|error| {
+        NamegenError::InvalidConfig(format!("could not read '{}': {}", corpus.display(), error))
+    })?;
+    fs::write(out, train(&text)?).map_err(|error| {
+        NamegenError::InvalidConfig(format!("could not write '{}': {}", out.display(), error))
+    })
+}
+
+/// Tells Cargo to rerun the build script when the file at `path` changes, e.g. the corpus of a
+/// precompiled model.  This prints an instruction for Cargo to standard output, so only call it
+/// from a build script.
+pub fn rerun_if_changed(path: impl AsRef<Path>) {
+    println!("cargo:rerun-if-changed={}", path.as_ref().display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharacterChainGenerator, ClusterChainGenerator, RandomTextGenerator};
+
+    #[test]
+    fn test_precompiled_model_generates_the_same_names() {
+        let out = std::env::temp_dir().join("markov_namegen_test_romans.model");
+        write_model("resources/romans.txt", &out, |corpus| {
+            let namegen = ClusterChainGenerator::builder()
+                .train(corpus.lines())
+                .try_build()?;
+            Ok(namegen.model_bytes())
+        })
+        .unwrap();
+        let corpus = fs::read_to_string("resources/romans.txt").unwrap();
+        let mut trained = ClusterChainGenerator::builder()
+            .with_seed(5)
+            .train(corpus.lines())
+            .build();
+        let mut precompiled = ClusterChainGenerator::builder()
+            .with_seed(5)
+            .with_precompiled_model(&fs::read(&out).unwrap())
+            .build();
+        for _i in 0..10 {
+            assert_eq!(trained.generate_one(), precompiled.generate_one());
+        }
+    }
+
+    #[test]
+    fn test_model_of_the_wrong_kind_is_rejected() {
+        let bytes = ClusterChainGenerator::builder()
+            .train(vec!["julius", "marcus"].into_iter())
+            .build()
+            .model_bytes();
+        let result = CharacterChainGenerator::builder()
+            .with_precompiled_model(&bytes)
+            .try_build();
+        assert!(matches!(result, Err(NamegenError::InvalidConfig(_))));
+        assert!(write_model("resources/missing.txt", "unused.model", |_| Ok(vec![])).is_err());
+    }
+}
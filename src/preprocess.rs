@@ -0,0 +1,361 @@
+use crate::capitalization::CapitalizationModel;
+use crate::error::NamegenError;
+use crate::transliterate::Transliteration;
+use std::borrow::Cow;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// The character used internally to mark the beginning and end of every training sequence.
+pub(crate) const SENTINEL: char = '#';
+
+/// A private-use character that stands in for a literal `'#'` in escaped training data.
+pub(crate) const ESCAPED_SENTINEL: char = '\u{E000}';
+
+/// What to do with training samples containing the reserved `'#'` character (which the models
+/// use internally to mark the beginning and end of a word) or control characters such as tabs
+/// and carriage returns, which would otherwise silently corrupt the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReservedCharPolicy {
+    /// Remove `'#'` and control characters from the sample before training.  This is the default.
+    #[default]
+    Strip,
+    /// Keep `'#'` as an ordinary character that may appear in generated text.  Control
+    /// characters are still removed.
+    Escape,
+    /// Refuse the sample: it is left out of training, and `try_build()` will return a
+    /// `NamegenError::InvalidTrainingData`.
+    Error,
+}
+
+/// How training data is lowercased before training.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseFolding {
+    /// Lowercase using the standard, locale-independent Unicode mappings.  This is the default.
+    #[default]
+    Unicode,
+    /// Lowercase using the Turkish and Azerbaijani rules, in which `'I'` lowercases to the
+    /// dotless `'ı'` and `'İ'` lowercases to `'i'`.
+    Turkic,
+    /// Leave the training data's case untouched.
+    None,
+}
+
+impl CaseFolding {
+    /// Applies this case folding to one training sample.
+    pub(crate) fn fold(self, sample: &str) -> String {
+        match self {
+            CaseFolding::Unicode => sample.to_lowercase(),
+            CaseFolding::Turkic => {
+                let mut folded = String::with_capacity(sample.len());
+                let mut chars = sample.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        'İ' => folded.push('i'),
+                        // "I" followed by a combining dot above is a decomposed 'İ'
+                        'I' if chars.peek() == Some(&'\u{307}') => {
+                            chars.next();
+                            folded.push('i');
+                        }
+                        'I' => folded.push('ı'),
+                        _ => folded.extend(c.to_lowercase()),
+                    }
+                }
+                folded
+            }
+            CaseFolding::None => sample.to_string(),
+        }
+    }
+}
+
+/// What to do with training lines that contain more than one word, like "Gaius Julius Caesar".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MultiWordPolicy {
+    /// Train on the whole line, spaces included, so generated text may contain spaces too.
+    /// This is the default.
+    #[default]
+    Whole,
+    /// Train on each word as a separate sample.
+    Split,
+    /// Train only on the first word of each line.
+    FirstWord,
+    /// Train only on the last word of each line.
+    LastWord,
+}
+
+/// What to do with punctuation in training data, like the quotes, commas and footnote marks
+/// of a name list copied from a web page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PunctuationPolicy {
+    /// Train on punctuation like any other character.  This is the default.
+    #[default]
+    Keep,
+    /// Remove all punctuation before training.
+    Strip,
+    /// Remove punctuation, except apostrophes and hyphens between two letters, as in "O'Brien"
+    /// and "Jean-Luc".
+    KeepInWords,
+}
+
+impl PunctuationPolicy {
+    /// Applies this policy to one line of training data.
+    fn apply(self, line: &str) -> Cow<'_, str> {
+        if self == PunctuationPolicy::Keep || !line.chars().any(is_punctuation) {
+            return Cow::Borrowed(line);
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let between_letters = |i: usize| {
+            i > 0
+                && chars[i - 1].is_alphabetic()
+                && chars.get(i + 1).is_some_and(|c| c.is_alphabetic())
+        };
+        Cow::Owned(
+            chars
+                .iter()
+                .enumerate()
+                .filter(|&(i, &c)| {
+                    !is_punctuation(c)
+                        || (self == PunctuationPolicy::KeepInWords
+                            && matches!(c, '\'' | '’' | '-' | '‐')
+                            && between_letters(i))
+                })
+                .map(|(_, &c)| c)
+                .collect(),
+        )
+    }
+}
+
+/// Whether a character is punctuation: ASCII punctuation, or one of the common typographic
+/// quotes, dashes and marks.
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '‘' | '’'
+                | '“'
+                | '”'
+                | '«'
+                | '»'
+                | '‹'
+                | '›'
+                | '‐'
+                | '–'
+                | '—'
+                | '…'
+                | '¡'
+                | '¿'
+                | '·'
+                | '†'
+                | '‡'
+        )
+}
+
+/// The settings that turn a line of training data into training samples.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Preprocessor {
+    pub(crate) romanization: Option<Arc<Transliteration>>,
+    pub(crate) case_folding: CaseFolding,
+    pub(crate) reserved_char_policy: ReservedCharPolicy,
+    pub(crate) multi_word_policy: MultiWordPolicy,
+    pub(crate) trim: bool,
+    pub(crate) comment_marker: Option<char>,
+    pub(crate) punctuation_policy: PunctuationPolicy,
+}
+
+impl Preprocessor {
+    /// Turns one line of training data into zero or more training samples.
+    pub(crate) fn prepare(&self, line: &str) -> Result<Vec<String>, NamegenError> {
+        match self.clean(line) {
+            Some(line) => self.split(&line),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Trims, romanizes and strips the punctuation from one line of training data, if set to,
+    /// or returns `None` if the line should be skipped: a comment, or a line that the cleanup
+    /// left empty.
+    pub(crate) fn clean<'l>(&self, line: &'l str) -> Option<Cow<'l, str>> {
+        if self
+            .comment_marker
+            .is_some_and(|marker| line.trim_start().starts_with(marker))
+        {
+            return None;
+        }
+        let trimmed = if self.trim { line.trim() } else { line };
+        let cleaned = match &self.romanization {
+            Some(table) => Cow::Owned(
+                self.punctuation_policy
+                    .apply(&table.apply(trimmed))
+                    .into_owned(),
+            ),
+            None => self.punctuation_policy.apply(trimmed),
+        };
+        let cleaned_up = self.trim || self.punctuation_policy != PunctuationPolicy::Keep;
+        (!(cleaned_up && cleaned.is_empty())).then_some(cleaned)
+    }
+
+    /// Turns one cleaned line of training data into zero or more training samples.
+    fn split(&self, line: &str) -> Result<Vec<String>, NamegenError> {
+        let line = handle_reserved_chars(self.case_folding.fold(line), self.reserved_char_policy)?;
+        Ok(match self.multi_word_policy {
+            MultiWordPolicy::Whole => vec![line],
+            MultiWordPolicy::Split => line.split_whitespace().map(String::from).collect(),
+            MultiWordPolicy::FirstWord => {
+                line.split_whitespace().take(1).map(String::from).collect()
+            }
+            MultiWordPolicy::LastWord => line
+                .split_whitespace()
+                .last()
+                .map(String::from)
+                .into_iter()
+                .collect(),
+        })
+    }
+
+    /// Turns lines of training data into training samples, first learning their capitalization
+    /// if there's a model to learn it.  The first line rejected is recorded in `error`.
+    pub(crate) fn prepare_all(
+        &self,
+        lines: impl Iterator<Item = impl Deref<Target = str>>,
+        mut capitalization: Option<&mut CapitalizationModel>,
+        error: &mut Option<NamegenError>,
+    ) -> Vec<String> {
+        lines
+            // skip comments, trim, strip punctuation
+            .filter_map(|line| self.clean(&line).map(Cow::into_owned))
+            .inspect(|line| {
+                if let Some(capitalization) = capitalization.as_mut() {
+                    capitalization.observe(line);
+                }
+            }) // learn capitalization patterns before we lowercase the input
+            .flat_map(|line| {
+                self.split(&line).unwrap_or_else(|e| {
+                    error.get_or_insert(e);
+                    Vec::new()
+                })
+            }) // lowercase the input, handle reserved characters, and split it into words
+            .collect()
+    }
+}
+
+/// Applies the given policy to one training sample.
+pub(crate) fn handle_reserved_chars(
+    sample: String,
+    policy: ReservedCharPolicy,
+) -> Result<String, NamegenError> {
+    if !sample.chars().any(|c| c == SENTINEL || c.is_control()) {
+        return Ok(sample);
+    }
+    match policy {
+        ReservedCharPolicy::Strip => Ok(sample
+            .chars()
+            .filter(|c| *c != SENTINEL && !c.is_control())
+            .collect()),
+        ReservedCharPolicy::Escape => Ok(sample
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| if c == SENTINEL { ESCAPED_SENTINEL } else { c })
+            .collect()),
+        ReservedCharPolicy::Error => Err(NamegenError::InvalidTrainingData(format!(
+            "{:?} contains '#' or a control character",
+            sample
+        ))),
+    }
+}
+
+/// Turns an escaped sentinel back into a literal `'#'` character.
+pub(crate) fn unescape_char(c: char) -> char {
+    if c == ESCAPED_SENTINEL {
+        SENTINEL
+    } else {
+        c
+    }
+}
+
+/// Turns escaped sentinels in generated text back into literal `'#'` characters.
+pub(crate) fn unescape(text: String) -> String {
+    if text.contains(ESCAPED_SENTINEL) {
+        text.replace(ESCAPED_SENTINEL, "#")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_folding() {
+        assert_eq!(CaseFolding::Unicode.fold("IŞIK İzmir"), "işik i̇zmir");
+        assert_eq!(CaseFolding::Turkic.fold("IŞIK İzmir"), "ışık izmir");
+        assert_eq!(CaseFolding::Turkic.fold("I\u{307}stanbul"), "istanbul");
+        assert_eq!(CaseFolding::None.fold("McKinley"), "McKinley");
+    }
+
+    #[test]
+    fn test_multi_word_policies() {
+        let prepare = |multi_word_policy| {
+            Preprocessor {
+                multi_word_policy,
+                ..Default::default()
+            }
+            .prepare("Gaius Julius  Caesar")
+            .unwrap()
+        };
+        assert_eq!(
+            prepare(MultiWordPolicy::Whole),
+            vec!["gaius julius  caesar"]
+        );
+        assert_eq!(
+            prepare(MultiWordPolicy::Split),
+            vec!["gaius", "julius", "caesar"]
+        );
+        assert_eq!(prepare(MultiWordPolicy::FirstWord), vec!["gaius"]);
+        assert_eq!(prepare(MultiWordPolicy::LastWord), vec!["caesar"]);
+    }
+
+    #[test]
+    fn test_cleaning_raw_lines() {
+        let preprocessor = Preprocessor {
+            trim: true,
+            comment_marker: Some('#'),
+            punctuation_policy: PunctuationPolicy::KeepInWords,
+            ..Default::default()
+        };
+        assert_eq!(
+            preprocessor.clean("  O'Brien,\r").as_deref(),
+            Some("O'Brien")
+        );
+        assert_eq!(
+            preprocessor.clean("“Jean-Luc” (1)").as_deref(),
+            Some("Jean-Luc 1")
+        );
+        assert_eq!(preprocessor.clean("  # Irish surnames"), None);
+        assert_eq!(preprocessor.clean(" -- \r"), None);
+        let stripping = Preprocessor {
+            punctuation_policy: PunctuationPolicy::Strip,
+            ..Default::default()
+        };
+        assert_eq!(stripping.clean("O'Brien").as_deref(), Some("OBrien"));
+        assert_eq!(Preprocessor::default().clean(" ").as_deref(), Some(" "));
+    }
+
+    #[test]
+    fn test_reserved_char_policies() {
+        let sample = || String::from("no#1\tname\r");
+        assert_eq!(
+            handle_reserved_chars(sample(), ReservedCharPolicy::Strip),
+            Ok(String::from("no1name"))
+        );
+        let escaped = handle_reserved_chars(sample(), ReservedCharPolicy::Escape).unwrap();
+        assert!(!escaped.contains('#'));
+        assert_eq!(unescape(escaped), "no#1name");
+        assert!(handle_reserved_chars(sample(), ReservedCharPolicy::Error).is_err());
+        assert_eq!(
+            handle_reserved_chars(String::from("fine"), ReservedCharPolicy::Error),
+            Ok(String::from("fine"))
+        );
+    }
+}
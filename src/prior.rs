@@ -0,0 +1,49 @@
+/// How the model gives weight to transitions that never occur in the training data, so that
+/// the generator can produce sequences it has never seen.  See `.with_prior_policy()`.
+///
+/// Each observed transition has a weight of 1.0 per observation, so a weight here is a fraction
+/// of an observation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorPolicy {
+    /// Every unseen transition, after every context, gets the same weight.  This is what
+    /// `.with_prior()` sets.  The more states the model knows, the more prior weight each
+    /// context gets in total, which is why the cluster model, with its hundreds of clusters,
+    /// needs a much smaller prior than the character model.
+    Constant(f64),
+    /// The unseen transitions after each context share a total weight of this much per
+    /// different state observed after it, split evenly between them.  A context that has been
+    /// followed by many different states is likely to be followed by others, while one that
+    /// has only ever been followed by one is probably fixed; and since the weight is shared,
+    /// the total doesn't grow with the size of the alphabet, so the same value suits both
+    /// characters and clusters.
+    PerAlternative(f64),
+    /// Unseen transitions between a vowel and a consonant (in either order, or to or from the
+    /// start or end of a word) get the weight `alternating`, and those between two vowels or
+    /// two consonants get the weight `same`.  In the cluster model, two vowel clusters or two
+    /// consonant clusters can never be adjacent (they'd have been one cluster), so setting
+    /// `same` to zero keeps the prior from injecting impossible sequences.  In the character
+    /// model, where vowels are those of the Latin alphabet, it can be used to make unseen
+    /// consonant clusters rarer.
+    ByClass { alternating: f64, same: f64 },
+}
+
+impl PriorPolicy {
+    /// The weight of each unseen transition after a context that has been followed by
+    /// `alternatives` different states, out of the `states` the model knows, to a state of the
+    /// same class (vowel or consonant) as the end of the context or not.
+    pub(crate) fn weight(self, alternatives: usize, states: usize, same_class: bool) -> f64 {
+        match self {
+            PriorPolicy::Constant(weight) => weight,
+            PriorPolicy::PerAlternative(weight) => {
+                weight * alternatives as f64 / states.saturating_sub(alternatives).max(1) as f64
+            }
+            PriorPolicy::ByClass { alternating, same } => {
+                if same_class {
+                    same
+                } else {
+                    alternating
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::transliterate::Transliteration;
+
+/// A generated name together with an approximate pronunciation in the International Phonetic
+/// Alphabet, for voice-acting and text-to-speech use cases.  Get one from a generator's
+/// `generate_pronounced()`.
+///
+/// The pronunciation is produced by a grapheme-to-phoneme table, which you can configure with
+/// the builders' `.with_pronunciation()`; by default, `Transliteration::ipa()` is used.
+///
+/// ```
+/// use markov_namegen::{CharacterChainGenerator, Transliteration};
+/// let mut namegen = CharacterChainGenerator::builder()
+///     .without_prior()
+///     .with_pronunciation(Transliteration::ipa().with_mapping("c", "tʃ"))
+///     .train(vec!["ciao"].into_iter())
+///     .build();
+/// let name = namegen.generate_pronounced();
+/// assert_eq!(name.spelling, "ciao");
+/// assert_eq!(name.ipa, "tʃiao");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PronouncedName {
+    pub spelling: String,
+    pub ipa: String,
+}
+
+impl PronouncedName {
+    pub(crate) fn new(spelling: String, table: Option<&Transliteration>) -> Self {
+        let lowercased = spelling.to_lowercase();
+        let ipa = match table {
+            Some(table) => table.apply(&lowercased),
+            None => Transliteration::ipa().apply(&lowercased),
+        };
+        PronouncedName { spelling, ipa }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_pronunciation() {
+        let name = PronouncedName::new(String::from("Thessaly"), None);
+        assert_eq!(name.spelling, "Thessaly");
+        assert_eq!(name.ipa, "θesːali");
+    }
+}
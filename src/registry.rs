@@ -0,0 +1,462 @@
+//! Generators looked up by name at runtime, e.g. for data-driven games and mod systems.
+use crate::characterchain::generator::CharacterChainGenerator;
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::postprocess;
+use crate::preprocess::PunctuationPolicy;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+#[cfg(any(feature = "yaml", feature = "ron"))]
+use std::path::Path;
+
+/// A boxed generator of any kind, as stored in a `Registry`.
+pub type BoxedGenerator = Box<dyn RandomTextGenerator + Send>;
+
+/// A collection of generators of any kind, keyed by name ("dwarf_male", "elvish_place").
+///
+/// Fill it in code with `with_generator()`, or from configuration with `from_specs()`.
+///
+/// ```
+/// use markov_namegen::{CharacterChainGenerator, ClusterChainGenerator, Registry};
+/// let mut registry = Registry::new()
+///     .with_generator(
+///         "dwarf_male",
+///         CharacterChainGenerator::builder()
+///             .train(vec!["bofur", "bombur", "bifur"].into_iter())
+///             .build(),
+///     )
+///     .with_generator(
+///         "elvish_place",
+///         ClusterChainGenerator::builder()
+///             .train(vec!["rivendell", "lothlorien", "mirkwood"].into_iter())
+///             .build(),
+///     );
+/// let name = registry.generate("dwarf_male").unwrap();
+/// assert!(registry.generate("orc_male").is_err());
+/// ```
+#[derive(Default)]
+pub struct Registry {
+    generators: BTreeMap<String, BoxedGenerator>,
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a generator under the given key, replacing any already there.
+    pub fn with_generator(
+        mut self,
+        key: &str,
+        generator: impl RandomTextGenerator + Send + 'static,
+    ) -> Self {
+        self.insert(key, Box::new(generator));
+        self
+    }
+
+    /// Builds a registry from a set of specs, keyed by name, e.g. as deserialized from a
+    /// configuration file.  Returns the first error encountered.
+    pub fn from_specs<'s>(
+        specs: impl IntoIterator<Item = (&'s String, &'s GeneratorSpec)>,
+    ) -> Result<Self, NamegenError> {
+        let mut registry = Registry::new();
+        for (key, spec) in specs {
+            registry.insert(key, spec.build()?);
+        }
+        Ok(registry)
+    }
+
+    /// Loads generator specs from a YAML (`.yaml` or `.yml`, with the `yaml` feature) or RON
+    /// (`.ron`, with the `ron` feature) file, and builds them.  Corpus paths are relative to
+    /// the directory containing the file.
+    ///
+    /// ```yaml
+    /// elvish_place:
+    ///   type: clusterchain
+    ///   corpus: elves.txt
+    ///   order: 3
+    ///   prior: 0.0005
+    ///   pattern: "^.{4,10}$"
+    ///   postprocess: titlecase
+    /// ```
+    #[cfg(any(feature = "yaml", feature = "ron"))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NamegenError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| {
+            NamegenError::InvalidConfig(format!("could not read '{}': {}", path.display(), error))
+        })?;
+        let mut specs = match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => specs_from_yaml(&text)?,
+            #[cfg(feature = "ron")]
+            Some("ron") => specs_from_ron(&text)?,
+            _ => {
+                return Err(NamegenError::InvalidConfig(format!(
+                    "unsupported file type: '{}'",
+                    path.display()
+                )))
+            }
+        };
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        for spec in specs.values_mut() {
+            spec.corpus = directory.join(&spec.corpus).to_string_lossy().into_owned();
+        }
+        Registry::from_specs(&specs)
+    }
+
+    /// Adds a generator under the given key, returning the one it replaced, if any.
+    pub fn insert(&mut self, key: &str, generator: BoxedGenerator) -> Option<BoxedGenerator> {
+        self.generators.insert(key.to_string(), generator)
+    }
+
+    /// Removes and returns the generator under the given key.
+    pub fn remove(&mut self, key: &str) -> Option<BoxedGenerator> {
+        self.generators.remove(key)
+    }
+
+    /// The generator under the given key, if any.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut BoxedGenerator> {
+        self.generators.get_mut(key)
+    }
+
+    /// Generates a name with the generator under the given key.  Returns an error if there is
+    /// no such generator, or it can't produce a name.
+    pub fn generate(&mut self, key: &str) -> Result<String, NamegenError> {
+        self.get_mut(key)
+            .ok_or_else(|| NamegenError::UnknownGenerator(key.to_string()))?
+            .try_generate_one()
+    }
+
+    /// The keys of all registered generators, in sorted order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.generators.keys().map(String::as_str)
+    }
+
+    /// The number of registered generators.
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    /// Whether no generators are registered.
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+}
+
+/// Takes the generators out of the registry, with their keys, in sorted order.
+impl IntoIterator for Registry {
+    type Item = (String, BoxedGenerator);
+    type IntoIter = std::collections::btree_map::IntoIter<String, BoxedGenerator>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.generators.into_iter()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("keys", &self.generators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// The kind of generator described by a `GeneratorSpec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum GeneratorKind {
+    CharacterChain,
+    ClusterChain,
+}
+
+/// A ready-made postprocessor that a `GeneratorSpec` can name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NamedPostprocessor {
+    /// `postprocess::titlecase`
+    Titlecase,
+    /// Converts the name to uppercase.
+    Uppercase,
+    /// `postprocess::strip_diacritics`
+    StripDiacritics,
+}
+
+impl NamedPostprocessor {
+    /// Applies the postprocessor to a name.
+    pub fn apply(self, name: String) -> String {
+        match self {
+            NamedPostprocessor::Titlecase => postprocess::titlecase(name),
+            NamedPostprocessor::Uppercase => name.to_uppercase(),
+            NamedPostprocessor::StripDiacritics => postprocess::strip_diacritics(name),
+        }
+    }
+}
+
+/// A description of a generator to build: its kind, training corpus and settings.  With the
+/// `serde` feature enabled, specs can be deserialized from any format serde supports, e.g.:
+///
+/// ```json
+/// {
+///   "dwarf_male": { "type": "characterchain", "corpus": "dwarves.txt", "order": 2 },
+///   "elvish_place": { "type": "clusterchain", "corpus": "elves.txt", "prior": 0.0005 }
+/// }
+/// ```
+///
+/// and, with the `yaml` or `ron` features, loaded straight from YAML or RON files with
+/// `Registry::load()`.  Unset settings take the builders' defaults.  `trim`, `comment` and
+/// `punctuation` clean up a raw corpus as it's read; see `.trim_input()`, `.skip_comments()` and
+/// `.with_punctuation_policy()` on the builders.  `postprocess` may name a
+/// single postprocessor or a list of them, applied in order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeneratorSpec {
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub kind: GeneratorKind,
+    /// The path of the training corpus, with one name per line.
+    pub corpus: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub order: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prior: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pattern: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_attempts: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: Option<u64>,
+    /// Whether to trim whitespace from the corpus's lines and skip blank ones.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trim: bool,
+    /// A marker for comment lines in the corpus, to skip.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comment: Option<char>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub punctuation: PunctuationPolicy,
+    #[cfg_attr(feature = "serde", serde(default, deserialize_with = "one_or_many"))]
+    pub postprocess: Vec<NamedPostprocessor>,
+}
+
+/// Deserializes either a single value or a list of them.
+#[cfg(feature = "serde")]
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+    Ok(match serde::Deserialize::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+impl GeneratorSpec {
+    /// Creates a spec for a generator of the given kind, trained on the corpus at the given
+    /// path, with default settings.
+    pub fn new(kind: GeneratorKind, corpus: &str) -> Self {
+        GeneratorSpec {
+            kind,
+            corpus: corpus.to_string(),
+            order: None,
+            prior: None,
+            pattern: None,
+            max_attempts: None,
+            seed: None,
+            trim: false,
+            comment: None,
+            punctuation: PunctuationPolicy::Keep,
+            postprocess: Vec::new(),
+        }
+    }
+
+    /// Reads the corpus and trains the generator.
+    pub fn build(&self) -> Result<BoxedGenerator, NamegenError> {
+        let corpus = fs::read_to_string(&self.corpus).map_err(|error| {
+            NamegenError::InvalidConfig(format!("could not read '{}': {}", self.corpus, error))
+        })?;
+        // the two builders have the same settings, but no common trait
+        macro_rules! configure {
+            ($builder:expr) => {{
+                let mut builder = $builder;
+                if let Some(order) = self.order {
+                    builder = builder.with_order(order);
+                }
+                if let Some(prior) = self.prior {
+                    builder = builder.with_prior(prior);
+                }
+                if let Some(pattern) = &self.pattern {
+                    builder = builder.with_pattern(pattern);
+                }
+                if let Some(max_attempts) = self.max_attempts {
+                    builder = builder.with_max_attempts(max_attempts);
+                }
+                if let Some(seed) = self.seed {
+                    builder = builder.with_seed(seed);
+                }
+                if let Some(marker) = self.comment {
+                    builder = builder.skip_comments(marker);
+                }
+                builder = builder
+                    .trim_input(self.trim)
+                    .with_punctuation_policy(self.punctuation);
+                for &step in &self.postprocess {
+                    builder = builder.with_postprocessor(move |name| step.apply(name));
+                }
+                Box::new(builder.train(corpus.lines()).try_build()?)
+            }};
+        }
+        Ok(match self.kind {
+            GeneratorKind::CharacterChain => configure!(CharacterChainGenerator::builder()),
+            GeneratorKind::ClusterChain => configure!(ClusterChainGenerator::builder()),
+        })
+    }
+}
+
+/// Parses generator specs, keyed by name, from YAML.
+#[cfg(feature = "yaml")]
+pub fn specs_from_yaml(text: &str) -> Result<BTreeMap<String, GeneratorSpec>, NamegenError> {
+    serde_norway::from_str(text).map_err(|error| NamegenError::InvalidConfig(error.to_string()))
+}
+
+/// Parses generator specs, keyed by name, from RON.
+#[cfg(feature = "ron")]
+pub fn specs_from_ron(text: &str) -> Result<BTreeMap<String, GeneratorSpec>, NamegenError> {
+    ron::from_str(text).map_err(|error| NamegenError::InvalidConfig(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_registry_from_specs() {
+        let mut specs = HashMap::new();
+        let mut roman = GeneratorSpec::new(GeneratorKind::ClusterChain, "resources/romans.txt");
+        roman.seed = Some(1);
+        roman.pattern = Some(String::from("^.{4,8}$"));
+        specs.insert(String::from("roman"), roman);
+        specs.insert(
+            String::from("roman_chars"),
+            GeneratorSpec::new(GeneratorKind::CharacterChain, "resources/romans.txt"),
+        );
+        let mut registry = Registry::from_specs(&specs).unwrap();
+        assert_eq!(
+            registry.keys().collect::<Vec<_>>(),
+            vec!["roman", "roman_chars"]
+        );
+        let name = registry.generate("roman").unwrap();
+        assert!((4..=8).contains(&name.chars().count()));
+        assert_eq!(
+            registry.generate("greek"),
+            Err(NamegenError::UnknownGenerator(String::from("greek")))
+        );
+
+        specs.insert(
+            String::from("missing"),
+            GeneratorSpec::new(GeneratorKind::ClusterChain, "resources/missing.txt"),
+        );
+        assert!(matches!(
+            Registry::from_specs(&specs),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+
+        let mut impatient =
+            GeneratorSpec::new(GeneratorKind::CharacterChain, "resources/romans.txt");
+        impatient.max_attempts = Some(0);
+        assert!(matches!(
+            impatient.build(),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_specs_deserialize() {
+        let json = r#"{
+            "dwarf_male": { "type": "characterchain", "corpus": "dwarves.txt", "order": 2 },
+            "elvish_place": { "type": "clusterchain", "corpus": "elves.txt", "prior": 0.0005 }
+        }"#;
+        let specs: HashMap<String, GeneratorSpec> = serde_json::from_str(json).unwrap();
+        let mut expected = GeneratorSpec::new(GeneratorKind::CharacterChain, "dwarves.txt");
+        expected.order = Some(2);
+        assert_eq!(specs["dwarf_male"], expected);
+        assert_eq!(specs["elvish_place"].prior, Some(0.0005));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_specs_from_yaml() {
+        let yaml = "
+elvish_place:
+  type: clusterchain
+  corpus: elves.txt
+  order: 3
+  prior: 0.0005
+  pattern: \"^.{4,10}$\"
+  postprocess: titlecase
+dwarf_male:
+  type: characterchain
+  corpus: dwarves.txt
+  trim: true
+  comment: \";\"
+  punctuation: keep_in_words
+  postprocess: [strip_diacritics, uppercase]
+";
+        let specs = specs_from_yaml(yaml).unwrap();
+        assert_eq!(specs["elvish_place"].pattern.as_deref(), Some("^.{4,10}$"));
+        assert_eq!(
+            specs["elvish_place"].postprocess,
+            vec![NamedPostprocessor::Titlecase]
+        );
+        assert_eq!(
+            specs["dwarf_male"].postprocess,
+            vec![
+                NamedPostprocessor::StripDiacritics,
+                NamedPostprocessor::Uppercase
+            ]
+        );
+        assert!(specs["dwarf_male"].trim);
+        assert_eq!(specs["dwarf_male"].comment, Some(';'));
+        assert_eq!(
+            specs["dwarf_male"].punctuation,
+            PunctuationPolicy::KeepInWords
+        );
+        assert!(specs_from_yaml("elvish_place: {type: goblin}").is_err());
+        let impatient = specs_from_yaml(
+            "roman: {type: characterchain, corpus: resources/romans.txt, max_attempts: 0}",
+        )
+        .unwrap();
+        assert!(matches!(
+            Registry::from_specs(&impatient),
+            Err(NamegenError::InvalidConfig(_))
+        ));
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_load_ron_file() {
+        let directory = std::env::temp_dir().join("markov_namegen_test_load_ron_file");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("dwarves.txt"), "bofur\nbombur\nbifur\n").unwrap();
+        fs::write(
+            directory.join("names.ron"),
+            r##"{
+                "dwarf": (r#type: characterchain, corpus: "dwarves.txt", postprocess: titlecase),
+            }"##,
+        )
+        .unwrap();
+        let mut registry = Registry::load(directory.join("names.ron")).unwrap();
+        let name = registry.generate("dwarf").unwrap();
+        assert!(name.starts_with(char::is_uppercase));
+    }
+}
@@ -0,0 +1,237 @@
+//! Self-contained HTML reports on trained generators, for sharing the results of tuning a
+//! corpus with collaborators who'd rather not run any code.
+//!
+//! ```
+//! use markov_namegen::{report, ClusterChainGenerator};
+//! let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+//! let namegen = ClusterChainGenerator::builder()
+//!     .with_name("Romans")
+//!     .train(corpus.lines())
+//!     .build();
+//! let html = report::html(&namegen);
+//! assert!(html.contains("<h1>Romans</h1>"));
+//! // std::fs::write("romans.html", html).unwrap();
+//! ```
+use crate::interface::RandomTextGenerator;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How many of the most frequent transitions the report lists.
+const TOP_TRANSITIONS: usize = 25;
+/// How many sample names the report lists.
+const SAMPLES: usize = 30;
+/// How many names the length histogram is drawn from.
+const HISTOGRAM_SAMPLES: usize = 1000;
+
+/// A generator `html()` can report on: `CharacterChainGenerator` or `ClusterChainGenerator`.
+pub trait Reportable: RandomTextGenerator + sealed::Sealed {}
+
+pub(crate) mod sealed {
+    use super::TOP_TRANSITIONS;
+    use crate::interface::RandomTextGenerator;
+    use crate::model::MarkovModel;
+    use std::hash::Hash;
+
+    pub trait Sealed {
+        fn model_summary(&self) -> ModelSummary;
+        /// A copy of the generator with its own RNG, seeded with `seed`, that doesn't remember
+        /// its outputs, to draw samples from.
+        fn fork_for_report(&self, seed: u64) -> Box<dyn RandomTextGenerator>;
+    }
+
+    /// The statistics of a trained model that go into a report.
+    pub struct ModelSummary {
+        pub(crate) order: usize,
+        pub(crate) contexts: usize,
+        pub(crate) states: usize,
+        /// The most heavily-weighted transitions: context, state, weight and probability.
+        pub(crate) top_transitions: Vec<(String, String, f64, f64)>,
+    }
+
+    impl ModelSummary {
+        /// Summarizes a model, labelling its states (and sequences of them) with `label`.
+        pub(crate) fn new<T>(model: &MarkovModel<T>, label: impl Fn(&[T]) -> String) -> Self
+        where
+            T: Eq + Hash + Clone + Ord,
+        {
+            let mut transitions = Vec::new();
+            for (context, following) in model.contexts() {
+                let total: f64 = following.values().sum();
+                for (state, weight) in following {
+                    transitions.push((context.clone(), state, *weight, weight / total));
+                }
+            }
+            // ties are broken by context and state, so the table doesn't depend on hash order
+            transitions.sort_by(|a, b| {
+                b.2.total_cmp(&a.2)
+                    .then_with(|| a.0.cmp(&b.0))
+                    .then_with(|| a.1.cmp(b.1))
+            });
+            ModelSummary {
+                order: model.order,
+                contexts: model.context_count(),
+                states: model.known_states.len(),
+                top_transitions: transitions
+                    .into_iter()
+                    .take(TOP_TRANSITIONS)
+                    .map(|(context, state, weight, probability)| {
+                        (
+                            label(&context),
+                            label(std::slice::from_ref(state)),
+                            weight,
+                            probability,
+                        )
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+/// Renders a report on a trained generator as a single HTML page, with no scripts or external
+/// stylesheets: a summary of its settings and model, the model's most frequent transitions, a
+/// sample of the names it generates, and a histogram of their lengths.  The samples are drawn
+/// from a copy of the generator with a fixed seed, so the same generator always gives the same
+/// report and the generator itself is left untouched.
+pub fn html(generator: &impl Reportable) -> String {
+    let summary = generator.model_summary();
+    let mut sampler = generator.fork_for_report(0);
+    let names: Vec<String> = (0..HISTOGRAM_SAMPLES)
+        .filter_map(|_| sampler.try_generate_one().ok())
+        .collect();
+    let title = generator.name().unwrap_or(generator.kind());
+
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(page, "<title>{}</title>", escape(title));
+    page.push_str(STYLE);
+    page.push_str("</head>\n<body>\n");
+    let _ = writeln!(page, "<h1>{}</h1>", escape(title));
+    let _ = writeln!(
+        page,
+        "<p class=\"summary\">{}: {}</p>",
+        escape(generator.kind()),
+        escape(&generator.config_summary())
+    );
+
+    page.push_str("<h2>Model</h2>\n<table>\n");
+    let _ = writeln!(page, "<tr><th>Order</th><td>{}</td></tr>", summary.order);
+    let _ = writeln!(
+        page,
+        "<tr><th>Contexts</th><td>{}</td></tr>",
+        summary.contexts
+    );
+    let _ = writeln!(
+        page,
+        "<tr><th>Known states</th><td>{}</td></tr>",
+        summary.states
+    );
+    page.push_str("</table>\n");
+
+    page.push_str("<h2>Most frequent transitions</h2>\n<table>\n");
+    page.push_str("<tr><th>After</th><th>Comes</th><th>Weight</th><th>Probability</th></tr>\n");
+    for (context, state, weight, probability) in &summary.top_transitions {
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td>{}</td><td>{:.2}</td><td>{:.1}%</td></tr>",
+            escape(context),
+            escape(state),
+            weight,
+            probability * 100.0
+        );
+    }
+    page.push_str("</table>\n");
+
+    page.push_str("<h2>Sample names</h2>\n<ul class=\"samples\">\n");
+    for name in names.iter().take(SAMPLES) {
+        let _ = writeln!(page, "<li>{}</li>", escape(name));
+    }
+    page.push_str("</ul>\n");
+
+    let mut lengths: BTreeMap<usize, usize> = BTreeMap::new();
+    for name in &names {
+        *lengths.entry(name.chars().count()).or_default() += 1;
+    }
+    let tallest = lengths.values().copied().max().unwrap_or(1);
+    let _ = writeln!(
+        page,
+        "<h2>Name lengths</h2>\n<p>Of {} sample names.</p>\n<table class=\"histogram\">",
+        names.len()
+    );
+    for (length, count) in &lengths {
+        let _ = writeln!(
+            page,
+            "<tr><th>{}</th><td><div class=\"bar\" style=\"width: {:.1}%\"></div></td><td>{}</td></tr>",
+            length,
+            *count as f64 * 100.0 / tallest as f64,
+            count
+        );
+    }
+    page.push_str("</table>\n</body>\n</html>\n");
+    page
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { padding: 0.2em 0.8em; text-align: left; border-bottom: 1px solid #ddd; }
+.summary { color: #555; }
+.samples { columns: 3; }
+.histogram td:nth-child(2) { width: 30em; }
+.bar { background: #4a7ab5; height: 1em; }
+</style>
+";
+
+/// Escapes text for an HTML element or attribute.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CharacterChainGenerator;
+
+    #[test]
+    fn test_report_is_reproducible_and_escaped() {
+        let build = || {
+            CharacterChainGenerator::builder()
+                .with_name("<Dwarves>")
+                .train(vec!["dopey", "sneezy", "bashful", "sleepy", "happy"].into_iter())
+                .build()
+        };
+        let report = html(&build());
+        assert_eq!(report, html(&build()));
+        assert!(report.contains("<h1>&lt;Dwarves&gt;</h1>"));
+        assert!(report.contains("<tr><th>Order</th><td>3</td></tr>"));
+        assert_eq!(report.matches("<li>").count(), SAMPLES);
+        // the most frequent transition is 'y' at the end of a word, in four of the five names
+        assert!(report.contains("<tr><td>y</td><td>#</td><td>4.00</td>"));
+    }
+
+    #[test]
+    fn test_report_leaves_persistent_memory_alone() {
+        let path = std::env::temp_dir().join(format!(
+            "markov_namegen_report_seen_{}.txt",
+            std::process::id()
+        ));
+        let generator = CharacterChainGenerator::builder()
+            .remember_outputs_in(&path)
+            .train(vec!["dopey", "sneezy", "bashful", "sleepy", "happy"].into_iter())
+            .build();
+        html(&generator);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
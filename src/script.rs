@@ -0,0 +1,110 @@
+use is_vowel::IsRomanceVowel;
+
+/// The writing system of a training corpus, which decides which letters `ClusterChainGenerator`
+/// treats as vowels when it splits words into vowel and consonant clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Script {
+    /// The Latin alphabet.  Vowels are those of the Romance languages, with their accented
+    /// forms; 'y' and 'w' count as consonants.  This is the default.
+    #[default]
+    Latin,
+    /// The Cyrillic alphabet, as used for Russian, Ukrainian, Belarusian, Bulgarian and Serbian.
+    Cyrillic,
+    /// The modern Greek alphabet, including accented vowels.
+    Greek,
+}
+
+impl Script {
+    /// Whether the letter is a vowel in this script.
+    pub fn is_vowel(self, c: char) -> bool {
+        match self {
+            Script::Latin => c.is_romance_vowel(),
+            Script::Cyrillic => c.to_lowercase().all(|c| "аеёиоуыэюяіїєѣ".contains(c)),
+            Script::Greek => c.to_lowercase().all(|c| "αεηιουωάέήίόύώϊϋΐΰ".contains(c)),
+        }
+    }
+
+    /// The script of a single letter, if it's a letter of one of the supported scripts.
+    pub(crate) fn of(c: char) -> Option<Script> {
+        match c {
+            '\u{0400}'..='\u{052F}' => Some(Script::Cyrillic),
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Some(Script::Greek),
+            c if Script::is_latin(c) => Some(Script::Latin),
+            _ => None,
+        }
+    }
+
+    /// Whether a character is a letter of the Latin script: ASCII, the Latin-1 and Latin
+    /// Extended blocks, IPA and Latin Extended Additional, and fullwidth forms.
+    pub(crate) fn is_latin(c: char) -> bool {
+        c.is_alphabetic()
+            && matches!(c,
+                'a'..='z'
+                | 'A'..='Z'
+                | '\u{00AA}'
+                | '\u{00BA}'
+                | '\u{00C0}'..='\u{02AF}'
+                | '\u{1D00}'..='\u{1DBF}'
+                | '\u{1E00}'..='\u{1EFF}'
+                | '\u{2C60}'..='\u{2C7F}'
+                | '\u{A720}'..='\u{A7FF}'
+                | '\u{AB30}'..='\u{AB6F}'
+                | '\u{FF21}'..='\u{FF3A}'
+                | '\u{FF41}'..='\u{FF5A}')
+    }
+
+    /// Guesses the dominant script of some text, i.e. the script most of its letters belong
+    /// to.  Returns `None` if there are no letters of a supported script at all, e.g. for text
+    /// in Chinese or Arabic.
+    ///
+    /// ```
+    /// use markov_namegen::Script;
+    /// assert_eq!(Script::detect(["Иван", "Ольга"]), Some(Script::Cyrillic));
+    /// assert_eq!(Script::detect(["Νίκος", "Ελένη", "Alexis"]), Some(Script::Greek));
+    /// assert_eq!(Script::detect(["42"]), None);
+    /// assert_eq!(Script::detect(["李明", "محمد"]), None);
+    /// ```
+    pub fn detect<S: AsRef<str>>(samples: impl IntoIterator<Item = S>) -> Option<Script> {
+        let mut counts = [
+            (Script::Latin, 0),
+            (Script::Cyrillic, 0),
+            (Script::Greek, 0),
+        ];
+        for sample in samples {
+            for script in sample.as_ref().chars().filter_map(Script::of) {
+                if let Some((_, count)) = counts.iter_mut().find(|(s, _)| *s == script) {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .max_by_key(|(_, count)| *count)
+            .map(|(script, _)| script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vowels_by_script() {
+        assert!(Script::Latin.is_vowel('é'));
+        assert!(!Script::Latin.is_vowel('и'));
+        assert!(Script::Cyrillic.is_vowel('И'));
+        assert!(!Script::Cyrillic.is_vowel('в'));
+        assert!(Script::Greek.is_vowel('ώ'));
+        assert!(!Script::Greek.is_vowel('λ'));
+    }
+
+    #[test]
+    fn test_letters_of_other_scripts_have_no_script() {
+        assert_eq!(Script::of('ŵ'), Some(Script::Latin));
+        assert_eq!(Script::of('Ж'), Some(Script::Cyrillic));
+        assert_eq!(Script::of('李'), None);
+        assert_eq!(Script::of('م'), None);
+        assert_eq!(Script::of('א'), None);
+    }
+}
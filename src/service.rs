@@ -0,0 +1,329 @@
+//! A ready-made HTTP naming microservice, enabled by the `service` feature.
+//!
+//! Serve the generators in a `Registry`, one style per key, with axum:
+//!
+//! ```ignore
+//! use markov_namegen::{service::NameService, ClusterChainGenerator, Registry};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let roman = ClusterChainGenerator::builder()
+//!         .train(std::fs::read_to_string("romans.txt").unwrap().lines())
+//!         .build();
+//!     let service = NameService::new(Registry::new().with_generator("roman", roman));
+//!     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+//!     axum::serve(listener, service.router()).await.unwrap();
+//! }
+//! ```
+//!
+//! `GET /generate?count=10&seed=42&style=roman` then responds with
+//! `{"style":"roman","names":[...]}`.  All parameters are optional: `count` defaults to 1,
+//! `style` to the default style (the first key, unless set with `with_default_style()`), and
+//! without a `seed` the names are random.
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::registry::{BoxedGenerator, Registry};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use rand::random;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The generators of a `Registry`, served over HTTP by `router()`, each key a naming style.
+///
+/// A generator that can be forked cheaply (see `RandomTextGenerator::fork_boxed()`), like
+/// `ClusterChainGenerator` and `CharacterChainGenerator`, serves every request from a fresh
+/// fork, so requests never wait on one another, and a request with a `seed` always returns the
+/// same names.  Forks don't remember their outputs, so a generator's `.remember_outputs()`
+/// doesn't keep its names unique across requests.  Any other generator serves its requests one
+/// at a time, from the registered generator itself, and can't be asked for a `seed`; requests
+/// for other styles don't wait on it.
+///
+/// Generation runs on tokio's blocking thread pool, never on its workers, but a generator that
+/// rejects most of its candidates can still tie up a thread for a long time: set a
+/// `with_max_attempts()` on generators with filters.
+pub struct NameService {
+    styles: BTreeMap<String, Mutex<BoxedGenerator>>,
+    default_style: Option<String>,
+    max_count: usize,
+}
+
+/// The query parameters of `GET /generate`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GenerateParams {
+    pub count: Option<usize>,
+    pub seed: Option<u64>,
+    pub style: Option<String>,
+}
+
+/// The response to `GET /generate`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GenerateResponse {
+    pub style: String,
+    pub names: Vec<String>,
+}
+
+/// Why a request to the service failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    /// No style of that name is registered (or none at all).
+    UnknownStyle(String),
+    /// A `seed` was given for a style whose generator can't be forked.
+    Unseedable(String),
+    /// More names were requested than the service's maximum.
+    TooMany(usize),
+    /// The generator failed.
+    Generation(NamegenError),
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::UnknownStyle(style) => write!(f, "unknown style '{}'", style),
+            ServiceError::Unseedable(style) => write!(f, "style '{}' can't be seeded", style),
+            ServiceError::TooMany(max) => write!(f, "at most {} names may be requested", max),
+            ServiceError::Generation(error) => write!(f, "could not generate names: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl ServiceError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ServiceError::UnknownStyle(_) => StatusCode::NOT_FOUND,
+            ServiceError::Unseedable(_) => StatusCode::BAD_REQUEST,
+            ServiceError::TooMany(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Generation(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl NameService {
+    pub const DEFAULT_MAX_COUNT: usize = 100;
+
+    /// Creates a service with a style for each generator in the registry.
+    pub fn new(registry: Registry) -> Self {
+        NameService {
+            styles: registry
+                .into_iter()
+                .map(|(style, generator)| (style, Mutex::new(generator)))
+                .collect(),
+            default_style: None,
+            max_count: Self::DEFAULT_MAX_COUNT,
+        }
+    }
+
+    /// Sets the style used when a request names none; by default, the first key in sorted
+    /// order.
+    pub fn with_default_style(mut self, style: &str) -> Self {
+        self.default_style = Some(style.to_string());
+        self
+    }
+
+    /// Sets the largest `count` a single request may ask for (100 by default).
+    pub fn with_max_count(mut self, max_count: usize) -> Self {
+        self.max_count = max_count;
+        self
+    }
+
+    /// Answers a request to `/generate`, without going through HTTP.  This generates the
+    /// names on the calling thread, so in async code, call it with `spawn_blocking()`.
+    pub fn generate(&self, params: &GenerateParams) -> Result<GenerateResponse, ServiceError> {
+        let count = params.count.unwrap_or(1);
+        if count > self.max_count {
+            return Err(ServiceError::TooMany(self.max_count));
+        }
+        let style = match params.style.as_ref().or(self.default_style.as_ref()) {
+            Some(style) => style.clone(),
+            None => self.styles.keys().next().cloned().unwrap_or_default(),
+        };
+        let mut generator = self
+            .styles
+            .get(&style)
+            .ok_or_else(|| ServiceError::UnknownStyle(style.clone()))?
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let names = match generator.fork_boxed(params.seed.unwrap_or_else(random)) {
+            Some(mut fork) => {
+                drop(generator);
+                generate_names(&mut fork, count)
+            }
+            None if params.seed.is_some() => return Err(ServiceError::Unseedable(style)),
+            None => generate_names(&mut *generator, count),
+        }?;
+        Ok(GenerateResponse { style, names })
+    }
+
+    /// An axum router serving `GET /generate`.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/generate", get(generate_handler))
+            .with_state(Arc::new(self))
+    }
+}
+
+impl fmt::Debug for NameService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NameService")
+            .field("default_style", &self.default_style)
+            .field("max_count", &self.max_count)
+            .finish_non_exhaustive()
+    }
+}
+
+fn generate_names(
+    generator: &mut impl RandomTextGenerator,
+    count: usize,
+) -> Result<Vec<String>, ServiceError> {
+    let mut names = Vec::with_capacity(count);
+    generator
+        .try_generate_n_into(&mut names, count, false)
+        .map_err(ServiceError::Generation)?;
+    Ok(names)
+}
+
+async fn generate_handler(
+    State(service): State<Arc<NameService>>,
+    Query(params): Query<GenerateParams>,
+) -> Result<Json<GenerateResponse>, (StatusCode, String)> {
+    match tokio::task::spawn_blocking(move || service.generate(&params)).await {
+        Ok(response) => response
+            .map(Json)
+            .map_err(|error| (error.status(), error.to_string())),
+        Err(error) => Err((StatusCode::INTERNAL_SERVER_ERROR, error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharacterChainGenerator, ClusterChainGenerator};
+
+    /// A generator that can't be forked.
+    struct Counter(usize);
+
+    impl RandomTextGenerator for Counter {
+        fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+            self.0 += 1;
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn service() -> NameService {
+        let roman = ClusterChainGenerator::builder()
+            .train(vec!["julius", "marcus", "lucius", "tiberius"].into_iter())
+            .build();
+        let dwarf = CharacterChainGenerator::builder()
+            .train(vec!["bofur", "bombur", "bifur", "balin", "dwalin"].into_iter())
+            .build();
+        let registry = Registry::new()
+            .with_generator("roman", roman)
+            .with_generator("dwarf", dwarf)
+            .with_generator("counter", Counter(0));
+        NameService::new(registry)
+            .with_default_style("roman")
+            .with_max_count(10)
+    }
+
+    #[test]
+    fn test_seeded_requests_are_repeatable() {
+        let service = service();
+        let params = GenerateParams {
+            count: Some(5),
+            seed: Some(42),
+            style: Some(String::from("dwarf")),
+        };
+        let response = service.generate(&params).unwrap();
+        assert_eq!(response.style, "dwarf");
+        assert_eq!(response.names.len(), 5);
+        assert_eq!(service.generate(&params), Ok(response));
+        assert_eq!(
+            service.generate(&GenerateParams::default()).unwrap().style,
+            "roman"
+        );
+    }
+
+    #[test]
+    fn test_unforkable_generators_serve_requests_in_turn() {
+        let service = service();
+        let params = GenerateParams {
+            count: Some(2),
+            style: Some(String::from("counter")),
+            ..Default::default()
+        };
+        assert_eq!(service.generate(&params).unwrap().names, vec!["1", "2"]);
+        assert_eq!(service.generate(&params).unwrap().names, vec!["3", "4"]);
+        let seeded = GenerateParams {
+            seed: Some(42),
+            ..params
+        };
+        assert_eq!(
+            service.generate(&seeded),
+            Err(ServiceError::Unseedable(String::from("counter")))
+        );
+        let first_key = NameService::new(Registry::new().with_generator("counter", Counter(0)));
+        assert_eq!(
+            first_key
+                .generate(&GenerateParams::default())
+                .unwrap()
+                .style,
+            "counter"
+        );
+    }
+
+    #[test]
+    fn test_a_busy_unforkable_generator_blocks_only_its_own_style() {
+        let service = service();
+        let _busy = service.styles["counter"].lock().unwrap();
+        let params = GenerateParams {
+            style: Some(String::from("dwarf")),
+            ..Default::default()
+        };
+        assert_eq!(service.generate(&params).unwrap().names.len(), 1);
+    }
+
+    #[test]
+    fn test_bad_requests() {
+        let service = service();
+        let unknown = GenerateParams {
+            style: Some(String::from("elf")),
+            ..Default::default()
+        };
+        assert_eq!(
+            service.generate(&unknown),
+            Err(ServiceError::UnknownStyle(String::from("elf")))
+        );
+        let too_many = GenerateParams {
+            count: Some(11),
+            ..Default::default()
+        };
+        assert_eq!(service.generate(&too_many), Err(ServiceError::TooMany(10)));
+        let empty = NameService::new(Registry::new());
+        assert_eq!(
+            empty.generate(&GenerateParams::default()),
+            Err(ServiceError::UnknownStyle(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_handler_generates_off_the_async_workers() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let params = GenerateParams {
+            count: Some(3),
+            ..Default::default()
+        };
+        let Json(response) = runtime
+            .block_on(generate_handler(State(Arc::new(service())), Query(params)))
+            .unwrap();
+        assert_eq!(response.names.len(), 3);
+        let _router = service().router();
+    }
+}
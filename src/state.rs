@@ -0,0 +1,198 @@
+use crate::memory::{fnv1a, NameMemory};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The random number generator owned by a generator.  Our own (portable) RNG can be saved and
+/// restored; a custom RNG supplied by the user with `.with_rng()` is opaque to us.
+pub(crate) enum GeneratorRng {
+    Portable(Box<ChaCha8Rng>),
+    Custom(Box<dyn RngCore + Send + Sync>),
+}
+
+impl GeneratorRng {
+    pub(crate) fn from_entropy() -> Self {
+        GeneratorRng::Portable(Box::new(ChaCha8Rng::from_entropy()))
+    }
+
+    pub(crate) fn from_seed(seed: u64) -> Self {
+        GeneratorRng::Portable(Box::new(ChaCha8Rng::seed_from_u64(seed)))
+    }
+
+    /// An RNG determined entirely by a world seed and an entity's key: each key selects its
+    /// own independent ChaCha8 stream.
+    pub(crate) fn for_key(world_seed: u64, key: &str) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(world_seed);
+        rng.set_stream(fnv1a(key.as_bytes()));
+        GeneratorRng::Portable(Box::new(rng))
+    }
+
+    /// Substream `index` of a master seed: every index selects its own independent ChaCha8
+    /// stream, so that workers can split one seed between them without their draws overlapping.
+    pub(crate) fn substream(seed: u64, index: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(index);
+        GeneratorRng::Portable(Box::new(rng))
+    }
+
+    /// Capture the exact position of the RNG in its stream, if it is one we can capture.
+    pub(crate) fn save(&self) -> Option<GeneratorState> {
+        match self {
+            GeneratorRng::Portable(rng) => Some(GeneratorState {
+                seed: rng.get_seed(),
+                stream: rng.get_stream(),
+                word_pos: rng.get_word_pos(),
+                memory: None,
+            }),
+            GeneratorRng::Custom(_) => None,
+        }
+    }
+
+    pub(crate) fn restore(state: &GeneratorState) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(state.word_pos);
+        GeneratorRng::Portable(Box::new(rng))
+    }
+}
+
+impl RngCore for GeneratorRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            GeneratorRng::Portable(rng) => rng.next_u32(),
+            GeneratorRng::Custom(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            GeneratorRng::Portable(rng) => rng.next_u64(),
+            GeneratorRng::Custom(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            GeneratorRng::Portable(rng) => rng.fill_bytes(dest),
+            GeneratorRng::Custom(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            GeneratorRng::Portable(rng) => rng.try_fill_bytes(dest),
+            GeneratorRng::Custom(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+/// A snapshot of a generator's mutable state, taken with `save_state()` and handed back with
+/// `restore_state()`.  A restored generator will produce exactly the same future names as the
+/// original did from the moment the snapshot was taken, so a game can store this in its save
+/// files and resume world generation mid-stream.  The snapshot covers the RNG and, if the
+/// generator remembers its outputs, its memory of the names it has already produced.
+///
+/// The trained model itself is not part of the snapshot; restore it into a generator built
+/// with the same settings and training data.  Use `to_bytes()` and `from_bytes()` to persist it.
+///
+/// ```
+/// use markov_namegen::{CharacterChainGenerator, GeneratorState, RandomTextGenerator};
+/// let dwarf_names = vec!["dopey","sneezy","bashful","sleepy","happy","grumpy","doc"];
+/// let mut namegen = CharacterChainGenerator::builder()
+///     .with_seed(7)
+///     .train(dwarf_names.clone().into_iter())
+///     .build();
+/// namegen.generate_one();
+/// let saved = namegen.save_state().unwrap().to_bytes();
+/// let expected = namegen.generate_one();
+///
+/// // ...later, perhaps in another process...
+/// let mut resumed = CharacterChainGenerator::builder()
+///     .train(dwarf_names.into_iter())
+///     .build();
+/// resumed.restore_state(&GeneratorState::from_bytes(&saved).unwrap());
+/// assert_eq!(resumed.generate_one(), expected);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+    pub(crate) memory: Option<NameMemory>,
+}
+
+impl GeneratorState {
+    const RNG_ENCODED_LEN: usize = 32 + 8 + 16;
+
+    /// Encode the snapshot as a platform-independent byte string.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::RNG_ENCODED_LEN);
+        bytes.extend_from_slice(&self.seed);
+        bytes.extend_from_slice(&self.stream.to_le_bytes());
+        bytes.extend_from_slice(&self.word_pos.to_le_bytes());
+        if let Some(memory) = &self.memory {
+            memory.encode(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Decode a snapshot previously encoded with `to_bytes()`.  Returns `None` if the bytes
+    /// are not a valid snapshot.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::RNG_ENCODED_LEN {
+            return None;
+        }
+        let memory = match &bytes[Self::RNG_ENCODED_LEN..] {
+            [] => None,
+            encoded => Some(NameMemory::decode(encoded)?),
+        };
+        Some(GeneratorState {
+            seed: bytes[0..32].try_into().ok()?,
+            stream: u64::from_le_bytes(bytes[32..40].try_into().ok()?),
+            word_pos: u128::from_le_bytes(bytes[40..56].try_into().ok()?),
+            memory,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_survives_encoding() {
+        let mut rng = GeneratorRng::from_seed(99);
+        rng.next_u64();
+        let mut state = rng.save().unwrap();
+        assert_eq!(
+            GeneratorState::from_bytes(&state.to_bytes()),
+            Some(state.clone())
+        );
+        let mut memory = NameMemory::Exact(Default::default());
+        memory.insert("ærøskøbing");
+        state.memory = Some(memory);
+        assert_eq!(
+            GeneratorState::from_bytes(&state.to_bytes()),
+            Some(state.clone())
+        );
+        assert_eq!(GeneratorState::from_bytes(&[1, 2, 3]), None);
+        let mut truncated = state.to_bytes();
+        truncated.pop();
+        assert_eq!(GeneratorState::from_bytes(&truncated), None);
+    }
+
+    #[test]
+    fn test_restored_rng_continues_the_same_stream() {
+        let mut rng = GeneratorRng::from_seed(99);
+        rng.next_u32();
+        let mut restored = GeneratorRng::restore(&rng.save().unwrap());
+        for _i in 0..10 {
+            assert_eq!(rng.next_u64(), restored.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_custom_rng_cannot_be_saved() {
+        let rng = GeneratorRng::Custom(Box::new(rand::rngs::mock::StepRng::new(0, 1)));
+        assert!(rng.save().is_none());
+    }
+}
@@ -0,0 +1,563 @@
+//! How a trained model stores its contexts: the sequences of states it has seen, each with the
+//! states that followed it.  See `.with_context_storage()`.
+use crate::memory::splitmix64;
+use crate::model::Chain;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::ops::Index;
+
+/// How the model stores its contexts.  The choice doesn't change what the generator produces,
+/// only how much memory the model takes and how fast it is to look contexts up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextStorage {
+    /// Each context is a key in a hash map, a copy of every state in it.  Quick to look up, and
+    /// the default.
+    #[default]
+    HashMap,
+    /// The contexts are stored back to front in a trie keyed by token ids, so that contexts
+    /// sharing an ending share its nodes, and each state takes eight bytes rather than a copy
+    /// of the state.  This saves a great deal of memory for high-order models, and for the
+    /// cluster model, whose states are strings.  Looking up a context walks the trie from the
+    /// last state, and backing off to a shorter context costs nothing more.
+    Trie,
+    /// Each context is keyed by a 64-bit rolling hash of its states, computed from the last
+    /// state back, and stores only the id of its first state and the hash of the rest.  A
+    /// lookup checks both against the shorter context it has already found, so a collision
+    /// can't return the wrong context; the rare context that can't be checked that way (whose
+    /// hash collides with another's, say) is kept in a hash map like `HashMap`'s.  Contexts of
+    /// order 3 and up take a fraction of the memory, and are quicker to look up, since no
+    /// state is hashed or compared more than once.
+    Hashed,
+}
+
+/// The states that have followed a context, in sorted order, with their weights and the
+/// running total of the weights, for drawing one by binary search.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Transitions<T> {
+    states: Vec<T>,
+    weights: Vec<f64>,
+    totals: Vec<f64>,
+}
+
+impl<T: Clone + Ord> Transitions<T> {
+    pub(crate) fn new(transitions: &BTreeMap<T, f64>) -> Self {
+        let mut total = 0.0;
+        let mut totals = Vec::with_capacity(transitions.len());
+        for weight in transitions.values() {
+            total += weight;
+            totals.push(total);
+        }
+        Transitions {
+            states: transitions.keys().cloned().collect(),
+            weights: transitions.values().copied().collect(),
+            totals,
+        }
+    }
+
+    pub(crate) fn to_map(&self) -> BTreeMap<T, f64> {
+        self.iter()
+            .map(|(state, weight)| (state.clone(), *weight))
+            .collect()
+    }
+
+    fn position<Q: Ord + ?Sized>(&self, state: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+    {
+        self.states
+            .binary_search_by(|other| other.borrow().cmp(state))
+            .ok()
+    }
+
+    pub(crate) fn get<Q: Ord + ?Sized>(&self, state: &Q) -> Option<&f64>
+    where
+        T: Borrow<Q>,
+    {
+        self.position(state).map(|i| &self.weights[i])
+    }
+
+    pub(crate) fn keys(&self) -> std::slice::Iter<'_, T> {
+        self.states.iter()
+    }
+
+    pub(crate) fn values(&self) -> std::slice::Iter<'_, f64> {
+        self.weights.iter()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&T, &f64)> {
+        self.states.iter().zip(&self.weights)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// The sum of the weights.
+    pub(crate) fn total(&self) -> f64 {
+        self.totals.last().copied().unwrap_or(0.0)
+    }
+
+    /// The first state at which the running total reaches `r` times the sum of the weights, or
+    /// the last state with any weight if floating-point residue leaves the roll past the total.
+    pub(crate) fn draw(&self, r: f64) -> Option<&T> {
+        let roll = r * self.total();
+        let i = self.totals.partition_point(|total| *total < roll);
+        self.states.get(i).or_else(|| {
+            let last = self.weights.iter().rposition(|weight| *weight > 0.0)?;
+            Some(&self.states[last])
+        })
+    }
+}
+
+impl<T: Clone + Ord> Index<&T> for Transitions<T> {
+    type Output = f64;
+    fn index(&self, state: &T) -> &f64 {
+        self.get(state).expect("no transition to that state")
+    }
+}
+
+impl<'t, T: Clone + Ord> IntoIterator for &'t Transitions<T> {
+    type Item = (&'t T, &'t f64);
+    type IntoIter = std::iter::Zip<std::slice::Iter<'t, T>, std::slice::Iter<'t, f64>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.states.iter().zip(&self.weights)
+    }
+}
+
+/// The contexts of a model, stored as chosen with `ContextStorage`.
+pub(crate) enum Contexts<T> {
+    Map(HashMap<Vec<T>, Transitions<T>>),
+    Trie(ContextTrie<T>),
+    Hashed(HashedContexts<T>),
+}
+
+impl<T> Contexts<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    pub(crate) fn new(storage: ContextStorage, chain: Chain<T>) -> Self {
+        let contexts = chain
+            .into_iter()
+            .map(|(context, transitions)| (context, Transitions::new(&transitions)));
+        match storage {
+            ContextStorage::HashMap => Contexts::Map(contexts.collect()),
+            ContextStorage::Trie => Contexts::Trie(ContextTrie::new(contexts)),
+            ContextStorage::Hashed => Contexts::Hashed(HashedContexts::new(contexts)),
+        }
+    }
+
+    pub(crate) fn storage(&self) -> ContextStorage {
+        match self {
+            Contexts::Map(_) => ContextStorage::HashMap,
+            Contexts::Trie(_) => ContextStorage::Trie,
+            Contexts::Hashed(_) => ContextStorage::Hashed,
+        }
+    }
+
+    /// The longest tail-end subsequence of `sequence`, of at most `order` states, that is a
+    /// context, with its transitions.
+    pub(crate) fn best<'s>(
+        &self,
+        sequence: &'s [T],
+        order: usize,
+    ) -> Option<(&'s [T], &Transitions<T>)> {
+        let longest = order.min(sequence.len());
+        match self {
+            Contexts::Map(map) => (1..=longest).rev().find_map(|i| {
+                let context = &sequence[sequence.len() - i..];
+                map.get(context).map(|transitions| (context, transitions))
+            }),
+            Contexts::Trie(trie) => trie.best(&sequence[sequence.len() - longest..]),
+            Contexts::Hashed(hashed) => hashed.best(&sequence[sequence.len() - longest..]),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Contexts::Map(map) => map.len(),
+            Contexts::Trie(trie) => trie.len,
+            Contexts::Hashed(hashed) => hashed.entries.len() + hashed.overflow.len(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every context with its transitions, in no particular order.
+    pub(crate) fn iter(&self) -> Box<dyn Iterator<Item = (Vec<T>, &Transitions<T>)> + '_> {
+        match self {
+            Contexts::Map(map) => Box::new(
+                map.iter()
+                    .map(|(context, transitions)| (context.clone(), transitions)),
+            ),
+            Contexts::Trie(trie) => Box::new(trie.iter()),
+            Contexts::Hashed(hashed) => Box::new(hashed.iter()),
+        }
+    }
+
+    /// The contexts as a map, for changing them.
+    pub(crate) fn to_map(&self) -> Chain<T> {
+        self.iter()
+            .map(|(context, transitions)| (context, transitions.to_map()))
+            .collect()
+    }
+}
+
+/// The states that occur in contexts, in sorted order; a state's id is its index.
+struct TokenIds<T>(Vec<T>);
+
+impl<T: Ord + Clone> TokenIds<T> {
+    fn new(contexts: &[(Vec<T>, Transitions<T>)]) -> Self {
+        let mut tokens: Vec<T> = contexts
+            .iter()
+            .flat_map(|(context, _)| context.iter().cloned())
+            .collect();
+        tokens.sort();
+        tokens.dedup();
+        TokenIds(tokens)
+    }
+
+    fn id(&self, state: &T) -> Option<u32> {
+        self.0.binary_search(state).ok().map(|id| id as u32)
+    }
+
+    fn state(&self, id: u32) -> &T {
+        &self.0[id as usize]
+    }
+}
+
+/// A trie of contexts, read from the last state back, so that the nodes on the way to a
+/// context are its shorter tail-end subsequences: exactly the ones the model backs off to.
+/// States are replaced by ids, their indices in the sorted list of states that occur in
+/// contexts, so each edge takes eight bytes.
+pub(crate) struct ContextTrie<T> {
+    tokens: TokenIds<T>,
+    /// The root, the empty context, is the first node.
+    nodes: Vec<TrieNode<T>>,
+    len: usize,
+}
+
+struct TrieNode<T> {
+    /// Token ids and the indices of the nodes they lead to, sorted by id.
+    children: Vec<(u32, u32)>,
+    transitions: Option<Transitions<T>>,
+}
+
+impl<T> TrieNode<T> {
+    fn new() -> Self {
+        TrieNode {
+            children: Vec::new(),
+            transitions: None,
+        }
+    }
+}
+
+impl<T> ContextTrie<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    fn new(contexts: impl Iterator<Item = (Vec<T>, Transitions<T>)>) -> Self {
+        let contexts: Vec<_> = contexts.collect();
+        let mut trie = ContextTrie {
+            tokens: TokenIds::new(&contexts),
+            nodes: vec![TrieNode::new()],
+            len: contexts.len(),
+        };
+        for (context, transitions) in contexts {
+            let mut node = 0;
+            for state in context.iter().rev() {
+                let id = trie
+                    .tokens
+                    .id(state)
+                    .expect("every state in a context has an id");
+                node = match trie.child(node, id) {
+                    Ok(child) => child,
+                    Err(position) => {
+                        let child = trie.nodes.len() as u32;
+                        trie.nodes.push(TrieNode::new());
+                        trie.nodes[node].children.insert(position, (id, child));
+                        child as usize
+                    }
+                };
+            }
+            trie.nodes[node].transitions = Some(transitions);
+        }
+        trie.nodes.shrink_to_fit();
+        trie
+    }
+
+    /// The child of a node along the edge for a token id, or where that edge would go.
+    fn child(&self, node: usize, id: u32) -> Result<usize, usize> {
+        let children = &self.nodes[node].children;
+        children
+            .binary_search_by_key(&id, |(id, _)| *id)
+            .map(|i| children[i].1 as usize)
+    }
+
+    /// Follows a context from its last state back, as far as the trie goes, yielding the
+    /// nodes for ever longer tail-end subsequences of it.
+    fn walk<'s>(&'s self, context: &'s [T]) -> impl Iterator<Item = usize> + 's {
+        context.iter().rev().scan(0, move |node, state| {
+            *node = self.child(*node, self.tokens.id(state)?).ok()?;
+            Some(*node)
+        })
+    }
+
+    fn best<'s>(&self, sequence: &'s [T]) -> Option<(&'s [T], &Transitions<T>)> {
+        let (length, transitions) = self
+            .walk(sequence)
+            .enumerate()
+            .filter_map(|(i, node)| Some((i + 1, self.nodes[node].transitions.as_ref()?)))
+            .last()?;
+        Some((&sequence[sequence.len() - length..], transitions))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Vec<T>, &Transitions<T>)> {
+        let mut found = Vec::with_capacity(self.len);
+        let mut stack = vec![(0, Vec::new())];
+        while let Some((node, context)) = stack.pop() {
+            for &(id, child) in &self.nodes[node].children {
+                let mut longer = vec![self.tokens.state(id).clone()];
+                longer.extend_from_slice(&context);
+                stack.push((child as usize, longer));
+            }
+            if let Some(transitions) = &self.nodes[node].transitions {
+                found.push((context, transitions));
+            }
+        }
+        found.into_iter()
+    }
+}
+
+/// Contexts keyed by a rolling hash of their states' ids, from the last state back.  Every
+/// entry's parent, the context without its first state, is also an entry (or is empty), so an
+/// entry matches a sequence if its first state and its parent's hash match those of a
+/// tail-end subsequence whose parent has already been matched.  Contexts for which that isn't
+/// so overflow into a map keyed by the contexts themselves.
+pub(crate) struct HashedContexts<T> {
+    tokens: TokenIds<T>,
+    entries: HashMap<u64, HashedEntry<T>, BuildHasherDefault<PassThroughHasher>>,
+    overflow: HashMap<Vec<T>, Transitions<T>>,
+}
+
+struct HashedEntry<T> {
+    first: u32,
+    parent: u64,
+    transitions: Transitions<T>,
+}
+
+/// The hash of the empty context, that every rolling hash starts from.
+const EMPTY_CONTEXT: u64 = 0xcbf29ce484222325;
+
+/// The hash of a context, given that of the context without its first state and that state's
+/// id.
+fn roll(parent: u64, id: u32) -> u64 {
+    splitmix64(parent ^ id as u64)
+}
+
+impl<T> HashedContexts<T>
+where
+    T: Eq + Hash + Clone + Ord,
+{
+    fn new(contexts: impl Iterator<Item = (Vec<T>, Transitions<T>)>) -> Self {
+        let mut contexts: Vec<_> = contexts.collect();
+        // parents go in before the contexts that extend them
+        contexts.sort_by_key(|(context, _)| context.len());
+        let mut hashed = HashedContexts {
+            tokens: TokenIds::new(&contexts),
+            entries: HashMap::default(),
+            overflow: HashMap::new(),
+        };
+        for (context, transitions) in contexts {
+            let Some((first, rest)) = context.split_first() else {
+                hashed.overflow.insert(context, transitions);
+                continue;
+            };
+            let first = hashed
+                .tokens
+                .id(first)
+                .expect("every state in a context has an id");
+            let parent = match hashed.deepest(rest) {
+                _ if rest.is_empty() => Some(EMPTY_CONTEXT),
+                Some((depth, hash, _)) if depth == rest.len() => Some(hash),
+                _ => None,
+            };
+            let hash = parent.map(|parent| roll(parent, first));
+            match (parent, hash) {
+                (Some(parent), Some(hash))
+                    if hash != EMPTY_CONTEXT && !hashed.entries.contains_key(&hash) =>
+                {
+                    let entry = HashedEntry {
+                        first,
+                        parent,
+                        transitions,
+                    };
+                    hashed.entries.insert(hash, entry);
+                }
+                _ => {
+                    hashed.overflow.insert(context, transitions);
+                }
+            }
+        }
+        hashed.entries.shrink_to_fit();
+        hashed
+    }
+
+    /// Follows a sequence from its last state back, matching ever longer tail-end
+    /// subsequences to entries until one doesn't match, and returns the length, hash and entry
+    /// of the longest that did.
+    fn deepest(&self, sequence: &[T]) -> Option<(usize, u64, &HashedEntry<T>)> {
+        let mut deepest = None;
+        let mut parent = EMPTY_CONTEXT;
+        for (i, state) in sequence.iter().rev().enumerate() {
+            let Some(id) = self.tokens.id(state) else {
+                break;
+            };
+            let hash = roll(parent, id);
+            match self.entries.get(&hash) {
+                Some(entry) if entry.first == id && entry.parent == parent => {
+                    deepest = Some((i + 1, hash, entry));
+                    parent = hash;
+                }
+                _ => break,
+            }
+        }
+        deepest
+    }
+
+    fn best<'s>(&self, sequence: &'s [T]) -> Option<(&'s [T], &Transitions<T>)> {
+        let hashed = self
+            .deepest(sequence)
+            .map(|(depth, _, entry)| (depth, &entry.transitions));
+        let longest = match self.overflow.is_empty() {
+            true => 0,
+            false => sequence.len(),
+        };
+        let overflowed = (1..=longest).rev().find_map(|depth| {
+            let context = &sequence[sequence.len() - depth..];
+            self.overflow
+                .get(context)
+                .map(|transitions| (depth, transitions))
+        });
+        let (depth, transitions) = match (hashed, overflowed) {
+            (Some(hashed), Some(overflowed)) if overflowed.0 > hashed.0 => overflowed,
+            (Some(hashed), _) => hashed,
+            (None, overflowed) => overflowed?,
+        };
+        Some((&sequence[sequence.len() - depth..], transitions))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Vec<T>, &Transitions<T>)> {
+        let hashed = self.entries.values().map(|entry| {
+            let mut context = vec![self.tokens.state(entry.first).clone()];
+            let mut parent = entry.parent;
+            while parent != EMPTY_CONTEXT {
+                let ancestor = &self.entries[&parent];
+                context.push(self.tokens.state(ancestor.first).clone());
+                parent = ancestor.parent;
+            }
+            (context, &entry.transitions)
+        });
+        let overflowed = self
+            .overflow
+            .iter()
+            .map(|(context, transitions)| (context.clone(), transitions));
+        hashed.chain(overflowed)
+    }
+}
+
+/// A hasher for keys that are already well-mixed hashes.
+#[derive(Default)]
+struct PassThroughHasher(u64);
+
+impl Hasher for PassThroughHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 << 8) | *byte as u64;
+        }
+    }
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> HashMap<Vec<char>, BTreeMap<char, f64>> {
+        let mut chain = HashMap::new();
+        for (context, transitions) in [
+            ("#", vec![('a', 2.0), ('b', 1.0)]),
+            ("a", vec![('b', 1.0), ('#', 1.0)]),
+            ("b", vec![('a', 1.0)]),
+            ("#a", vec![('b', 1.0)]),
+            ("ba", vec![('#', 1.0)]),
+        ] {
+            chain.insert(context.chars().collect(), transitions.into_iter().collect());
+        }
+        chain
+    }
+
+    #[test]
+    fn test_transitions_draw_by_running_total() {
+        let mut weights = BTreeMap::new();
+        weights.insert('a', 1.0);
+        weights.insert('b', 0.0);
+        weights.insert('c', 3.0);
+        let transitions = Transitions::new(&weights);
+        assert_eq!(transitions.total(), 4.0);
+        assert_eq!(transitions.draw(0.0), Some(&'a'));
+        assert_eq!(transitions.draw(0.25), Some(&'a'));
+        assert_eq!(transitions.draw(0.26), Some(&'c'));
+        assert_eq!(transitions.draw(1.0), Some(&'c'));
+        assert_eq!(transitions.draw(1.0 + 1e-12), Some(&'c'));
+        assert_eq!(transitions[&'c'], 3.0);
+        assert_eq!(transitions.to_map(), weights);
+        assert_eq!(Transitions::<char>::new(&BTreeMap::new()).draw(0.5), None);
+    }
+
+    #[test]
+    fn test_storages_find_the_same_contexts() {
+        let map = Contexts::new(ContextStorage::HashMap, chain());
+        for storage in [ContextStorage::Trie, ContextStorage::Hashed] {
+            let contexts = Contexts::new(storage, chain());
+            assert_eq!(contexts.len(), 5);
+            assert_eq!(contexts.to_map(), chain());
+            for sequence in ["#", "#a", "#ab", "#aba", "xba", "xx", "", "a#a"] {
+                let sequence: Vec<char> = sequence.chars().collect();
+                for order in 1..=3 {
+                    assert_eq!(
+                        map.best(&sequence, order),
+                        contexts.best(&sequence, order),
+                        "{:?} {:?}",
+                        storage,
+                        sequence
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_unchained_contexts_overflow() {
+        // without "a", there's nothing to check "xa", "#a" or "ba" against
+        let mut chain = chain();
+        chain.remove(&vec!['a']);
+        chain.insert(vec!['x', 'a'], [('#', 1.0)].into_iter().collect());
+        let Contexts::Hashed(hashed) = Contexts::new(ContextStorage::Hashed, chain.clone()) else {
+            unreachable!()
+        };
+        assert_eq!(hashed.overflow.len(), 3);
+        let contexts = Contexts::Hashed(hashed);
+        assert_eq!(contexts.to_map(), chain);
+        let sequence = ['x', 'a'];
+        assert_eq!(contexts.best(&sequence, 2).unwrap().0, ['x', 'a']);
+        assert_eq!(contexts.best(&sequence[1..], 2), None);
+    }
+}
@@ -0,0 +1,254 @@
+//! Generated names as an asynchronous `Stream` (requires the `futures` feature), so that an
+//! async web handler can take names as it needs them, with backpressure, and a strict pattern
+//! that takes many re-rolls can't block the runtime.
+//!
+//! A stream tries a limited number of candidates each time it's polled; if none of them is
+//! accepted, it asks to be polled again and returns `Poll::Pending`, giving other tasks a turn
+//! before it re-rolls any more.  `NameStream` yields names one at a time, and `NameBatches`
+//! yields them in batches, to amortize the cost of waking the task (e.g. to send a page of
+//! names at a time from a tokio handler).
+//!
+//! ```
+//! use futures_util::StreamExt;
+//! use markov_namegen::stream::{NameBatches, NameStream};
+//! use markov_namegen::CharacterChainGenerator;
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let namegen = CharacterChainGenerator::builder()
+//!     .with_pattern("^.{6}$")
+//!     .train(vec!["julius", "augustus", "tiberius", "claudius"].into_iter())
+//!     .build();
+//! let names: Vec<String> = NameStream::new(namegen.fork(1)).take(10).collect().await;
+//! assert!(names.iter().all(|name| name.chars().count() == 6));
+//! let mut batches = NameBatches::new(namegen, 25);
+//! let batch = batches.next().await.unwrap();
+//! assert_eq!(batch.len(), 25);
+//! # });
+//! ```
+use crate::characterchain::generator::CharacterChainGenerator;
+use crate::clusterchain::generator::ClusterChainGenerator;
+use crate::error::NamegenError;
+use futures_core::Stream;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// How many candidates a stream tries each time it's polled, before yielding to other tasks.
+const ATTEMPTS_PER_POLL: usize = 64;
+
+/// A generator that can be made into a stream: `CharacterChainGenerator` or
+/// `ClusterChainGenerator`.
+pub trait Streamable: sealed::Sealed + Unpin {}
+
+pub(crate) mod sealed {
+    use crate::error::NamegenError;
+
+    pub trait Sealed {
+        /// Tries one candidate, returning it if it's accepted.  `attempts` counts this attempt
+        /// and those before it since the last name was accepted.
+        fn attempt_name(&mut self, attempts: usize) -> Result<Option<String>, NamegenError>;
+    }
+}
+
+impl sealed::Sealed for CharacterChainGenerator {
+    fn attempt_name(&mut self, attempts: usize) -> Result<Option<String>, NamegenError> {
+        Ok(self.attempt(attempts, None)?.map(|(_, record)| record.name))
+    }
+}
+
+impl Streamable for CharacterChainGenerator {}
+
+impl sealed::Sealed for ClusterChainGenerator {
+    fn attempt_name(&mut self, attempts: usize) -> Result<Option<String>, NamegenError> {
+        Ok(self.attempt(attempts, None)?.map(|(_, record)| record.name))
+    }
+}
+
+impl Streamable for ClusterChainGenerator {}
+
+/// A generator, and how far along it is in generating its next name.
+#[derive(Debug)]
+struct Attempts<G> {
+    generator: G,
+    attempts: usize,
+    error: Option<NamegenError>,
+}
+
+impl<G: Streamable> Attempts<G> {
+    fn new(generator: G) -> Self {
+        Attempts {
+            generator,
+            attempts: 0,
+            error: None,
+        }
+    }
+
+    /// Tries candidates until one is accepted or the budget for this poll runs out, in which
+    /// case the task is woken to be polled again.  Returns `Poll::Ready(None)` once the
+    /// generator has failed.
+    fn poll_name(&mut self, cx: &mut Context<'_>, budget: &mut usize) -> Poll<Option<String>> {
+        if self.error.is_some() {
+            return Poll::Ready(None);
+        }
+        while *budget > 0 {
+            *budget -= 1;
+            self.attempts += 1;
+            match self.generator.attempt_name(self.attempts) {
+                Ok(Some(name)) => {
+                    self.attempts = 0;
+                    return Poll::Ready(Some(name));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    self.error = Some(error);
+                    return Poll::Ready(None);
+                }
+            }
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// An endless stream of names from a generator.  The stream ends only if the generator fails,
+/// e.g. with `NamegenError::MaxAttemptsExceeded`; `error()` then says why.
+#[derive(Debug)]
+pub struct NameStream<G> {
+    attempts: Attempts<G>,
+}
+
+impl<G: Streamable> NameStream<G> {
+    /// Makes a stream of the generator's names.  Fork the generator first if you want to keep
+    /// using it elsewhere.
+    pub fn new(generator: G) -> Self {
+        NameStream {
+            attempts: Attempts::new(generator),
+        }
+    }
+
+    /// The error that ended the stream, if it has ended.
+    pub fn error(&self) -> Option<&NamegenError> {
+        self.attempts.error.as_ref()
+    }
+}
+
+impl<G: Streamable> Stream for NameStream<G> {
+    type Item = String;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        let mut budget = ATTEMPTS_PER_POLL;
+        self.get_mut().attempts.poll_name(cx, &mut budget)
+    }
+}
+
+/// An endless stream of batches of names from a generator, each of the same size.  If the
+/// generator fails, the names generated so far are yielded as a last, shorter batch, and
+/// `error()` says why the stream ended.
+#[derive(Debug)]
+pub struct NameBatches<G> {
+    attempts: Attempts<G>,
+    size: usize,
+    batch: Vec<String>,
+}
+
+impl<G: Streamable> NameBatches<G> {
+    /// Makes a stream of batches of `size` names.  Fork the generator first if you want to
+    /// keep using it elsewhere.
+    pub fn new(generator: G, size: usize) -> Self {
+        assert!(size > 0, "Batch size must be greater than zero.");
+        NameBatches {
+            attempts: Attempts::new(generator),
+            size,
+            batch: Vec::with_capacity(size),
+        }
+    }
+
+    /// The error that ended the stream, if it has ended.
+    pub fn error(&self) -> Option<&NamegenError> {
+        self.attempts.error.as_ref()
+    }
+}
+
+impl<G: Streamable> Stream for NameBatches<G> {
+    type Item = Vec<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<String>>> {
+        let this = self.get_mut();
+        let mut budget = ATTEMPTS_PER_POLL;
+        loop {
+            match this.attempts.poll_name(cx, &mut budget) {
+                Poll::Ready(Some(name)) => {
+                    this.batch.push(name);
+                    if this.batch.len() == this.size {
+                        let full = Vec::with_capacity(this.size);
+                        return Poll::Ready(Some(mem::replace(&mut this.batch, full)));
+                    }
+                }
+                Poll::Ready(None) if this.batch.is_empty() => return Poll::Ready(None),
+                Poll::Ready(None) => return Poll::Ready(Some(mem::take(&mut this.batch))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    /// Counts how many times the task asked to be polled again.
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_streams_yield_between_rerolls() {
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(Arc::clone(&counter));
+        let mut cx = Context::from_waker(&waker);
+        let namegen = CharacterChainGenerator::builder()
+            .with_pattern("^zzz$")
+            .with_max_attempts(100)
+            .train(vec!["dopey", "sneezy", "bashful"].into_iter())
+            .build();
+        let mut stream = NameStream::new(namegen);
+        // the first poll uses up its budget and yields; the second hits the max attempts
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+        assert_eq!(
+            stream.error(),
+            Some(&NamegenError::MaxAttemptsExceeded(100))
+        );
+    }
+
+    #[test]
+    fn test_batches_are_full_until_the_generator_fails() {
+        let waker = Waker::from(Arc::new(CountingWaker(AtomicUsize::new(0))));
+        let mut cx = Context::from_waker(&waker);
+        let namegen = ClusterChainGenerator::builder()
+            .with_pattern("^.{4,}$")
+            .remember_outputs(true)
+            .with_max_attempts(1000)
+            .train(vec!["dopey", "sneezy"].into_iter())
+            .build();
+        let mut batches = NameBatches::new(namegen, 3);
+        let mut sizes = Vec::new();
+        loop {
+            match Pin::new(&mut batches).poll_next(&mut cx) {
+                Poll::Ready(Some(batch)) => sizes.push(batch.len()),
+                Poll::Ready(None) => break,
+                Poll::Pending => {}
+            }
+        }
+        // the generator runs out of new names eventually
+        assert!(sizes[..sizes.len() - 1].iter().all(|size| *size == 3));
+        assert!(batches.error().is_some());
+    }
+}
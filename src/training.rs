@@ -0,0 +1,27 @@
+//! The states of a generator builder, which make misuse a compile error rather than a
+//! surprise.  A builder starts out `Untrained`, when settings that affect training (like
+//! `.with_order()` or `.with_case_folding()`) can be changed; `.train()` (or loading a
+//! precompiled model) makes it `Trained`, and only a trained builder can `.build()` a generator.
+//!
+//! ```compile_fail
+//! use markov_namegen::CharacterChainGenerator;
+//! // a generator that was never trained can't be built
+//! let namegen = CharacterChainGenerator::builder().build();
+//! ```
+//!
+//! ```compile_fail
+//! use markov_namegen::CharacterChainGenerator;
+//! // the order can't be changed once a model has been trained with another
+//! let namegen = CharacterChainGenerator::builder()
+//!     .train(vec!["julius", "augustus"].into_iter())
+//!     .with_order(2)
+//!     .build();
+//! ```
+
+/// The state of a builder that hasn't been trained yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Untrained;
+
+/// The state of a builder that has been trained, and can build a generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trained;
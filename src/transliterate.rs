@@ -0,0 +1,223 @@
+use crate::error::NamegenError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A table mapping letters (or short letter sequences) of one script to another, used to
+/// romanize generated names.  Because it is applied to the output, the same model trained on a
+/// Cyrillic or Greek corpus can emit either native-script names or romanized ones: use the
+/// builders' `.with_romanization()` to always romanize, or call `apply()` yourself on a name.
+/// To train on a romanized corpus instead, normalizing a corpus in the native script (or
+/// mixing romanization systems) to one spelling, use the builders'
+/// `.with_training_romanization()`.  Tables can be built in code, taken ready-made, or loaded
+/// from tab-separated files with `load()`.
+///
+/// Lookups are greedy, trying the longest source sequence first, so digraphs like Greek "ου"
+/// can be mapped as a unit.  Characters with no mapping are passed through unchanged.  Mappings
+/// are given in lowercase; an uppercase letter is romanized via its lowercase form, and the
+/// result capitalized.
+///
+/// ```
+/// use markov_namegen::Transliteration;
+/// let cyrillic = Transliteration::cyrillic();
+/// assert_eq!(cyrillic.apply("Щукин"), "Shchukin");
+/// assert_eq!(Transliteration::greek().apply("Σωκράτης"), "Sokratis");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Transliteration {
+    mappings: HashMap<String, String>,
+    longest_source: usize,
+}
+
+impl Transliteration {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mapping from a lowercase letter or letter sequence to its replacement.
+    pub fn with_mapping(mut self, from: &str, to: &str) -> Self {
+        self.longest_source = self.longest_source.max(from.chars().count());
+        self.mappings.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    /// A simple romanization of Russian, Ukrainian, and Belarusian Cyrillic, along the lines of
+    /// the BGN/PCGN system, without diacritics.
+    #[rustfmt::skip]
+    pub fn cyrillic() -> Self {
+        Self::from_pairs(&[
+            ("а", "a"), ("б", "b"), ("в", "v"), ("г", "g"), ("ґ", "g"), ("д", "d"),
+            ("е", "e"), ("є", "ye"), ("ё", "yo"), ("ж", "zh"), ("з", "z"), ("и", "i"),
+            ("і", "i"), ("ї", "yi"), ("й", "y"), ("к", "k"), ("л", "l"), ("м", "m"),
+            ("н", "n"), ("о", "o"), ("п", "p"), ("р", "r"), ("с", "s"), ("т", "t"),
+            ("у", "u"), ("ў", "w"), ("ф", "f"), ("х", "kh"), ("ц", "ts"), ("ч", "ch"),
+            ("ш", "sh"), ("щ", "shch"), ("ъ", ""), ("ы", "y"), ("ь", ""), ("э", "e"),
+            ("ю", "yu"), ("я", "ya"),
+        ])
+    }
+
+    /// The ISO 9 transliteration of Cyrillic, which gives every letter of the Russian,
+    /// Ukrainian, Belarusian, Serbian and Macedonian alphabets a letter of its own, with
+    /// diacritics, so that it can be reversed.
+    #[rustfmt::skip]
+    pub fn iso9() -> Self {
+        Self::from_pairs(&[
+            ("а", "a"), ("б", "b"), ("в", "v"), ("г", "g"), ("ґ", "g̀"), ("д", "d"),
+            ("ѓ", "ǵ"), ("е", "e"), ("ё", "ë"), ("є", "ê"), ("ж", "ž"), ("з", "z"),
+            ("ѕ", "ẑ"), ("и", "i"), ("і", "ì"), ("ї", "ï"), ("й", "j"), ("ј", "ǰ"),
+            ("к", "k"), ("л", "l"), ("љ", "l̂"), ("м", "m"), ("н", "n"), ("њ", "n̂"),
+            ("о", "o"), ("п", "p"), ("р", "r"), ("с", "s"), ("т", "t"), ("ќ", "ḱ"),
+            ("у", "u"), ("ў", "ǔ"), ("ф", "f"), ("х", "h"), ("ц", "c"), ("ч", "č"),
+            ("џ", "d̂"), ("ш", "š"), ("щ", "ŝ"), ("ъ", "ʺ"), ("ы", "y"), ("ь", "ʹ"),
+            ("э", "è"), ("ю", "û"), ("я", "â"),
+        ])
+    }
+
+    /// Reads a table from tab-separated text, one mapping per line: the source sequence, a
+    /// tab, and its replacement, which may be empty to drop the source.  Blank lines and lines
+    /// starting with `'#'` are skipped.  Returns a `NamegenError::InvalidConfig` error naming
+    /// the first line without a tab.
+    ///
+    /// ```
+    /// use markov_namegen::Transliteration;
+    /// let table = Transliteration::from_tsv("# Polish\nł\tw\nsz\tsh\n").unwrap();
+    /// assert_eq!(table.apply("Łaszcz"), "Washcz");
+    /// ```
+    pub fn from_tsv(text: &str) -> Result<Self, NamegenError> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .try_fold(Self::new(), |table, (number, line)| {
+                match line.trim_end_matches('\r').split_once('\t') {
+                    Some((from, to)) => Ok(table.with_mapping(&from.to_lowercase(), to)),
+                    None => Err(NamegenError::InvalidConfig(format!(
+                        "line {} of the transliteration table has no tab: {:?}",
+                        number + 1,
+                        line
+                    ))),
+                }
+            })
+    }
+
+    /// Reads a table from a tab-separated file, as with `from_tsv()`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NamegenError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| {
+            NamegenError::InvalidConfig(format!("could not read '{}': {}", path.display(), error))
+        })?;
+        Self::from_tsv(&text)
+    }
+
+    /// A simple romanization of modern Greek, along the lines of ELOT 743, without diacritics.
+    #[rustfmt::skip]
+    pub fn greek() -> Self {
+        Self::from_pairs(&[
+            ("α", "a"), ("ά", "a"), ("β", "v"), ("γ", "g"), ("δ", "d"), ("ε", "e"),
+            ("έ", "e"), ("ζ", "z"), ("η", "i"), ("ή", "i"), ("θ", "th"), ("ι", "i"),
+            ("ί", "i"), ("ϊ", "i"), ("ΐ", "i"), ("κ", "k"), ("λ", "l"), ("μ", "m"),
+            ("ν", "n"), ("ξ", "x"), ("ο", "o"), ("ό", "o"), ("π", "p"), ("ρ", "r"),
+            ("σ", "s"), ("ς", "s"), ("τ", "t"), ("υ", "y"), ("ύ", "y"), ("ϋ", "y"),
+            ("ΰ", "y"), ("φ", "f"), ("χ", "ch"), ("ψ", "ps"), ("ω", "o"), ("ώ", "o"),
+            ("ου", "ou"), ("ού", "ou"), ("γγ", "ng"),
+        ])
+    }
+
+    /// An approximate grapheme-to-phoneme table from Latin-script spelling to IPA, following
+    /// mostly Latin and Romance spelling conventions with a few common English digraphs.  It's
+    /// meant as a starting point for voice actors and text-to-speech, not as a dictionary;
+    /// build your own table for a particular language.
+    #[rustfmt::skip]
+    pub fn ipa() -> Self {
+        Self::from_pairs(&[
+            ("a", "a"), ("b", "b"), ("c", "k"), ("d", "d"), ("e", "e"), ("f", "f"),
+            ("g", "ɡ"), ("h", "h"), ("i", "i"), ("j", "j"), ("k", "k"), ("l", "l"),
+            ("m", "m"), ("n", "n"), ("o", "o"), ("p", "p"), ("q", "k"), ("r", "r"),
+            ("s", "s"), ("t", "t"), ("u", "u"), ("v", "v"), ("w", "w"), ("x", "ks"),
+            ("y", "i"), ("z", "z"), ("ae", "ai"), ("au", "au"), ("oe", "oi"),
+            ("ch", "tʃ"), ("sh", "ʃ"), ("th", "θ"), ("ph", "f"), ("qu", "kw"),
+            ("gu", "ɡw"), ("ng", "ŋ"), ("ll", "lː"), ("ss", "sː"), ("tt", "tː"),
+            ("ñ", "ɲ"), ("ç", "s"), ("á", "a"), ("é", "e"), ("í", "i"), ("ó", "o"),
+            ("ú", "u"),
+        ])
+    }
+
+    fn from_pairs(pairs: &[(&str, &str)]) -> Self {
+        pairs.iter().fold(Self::new(), |table, (from, to)| {
+            table.with_mapping(from, to)
+        })
+    }
+
+    /// Transliterates a name using this table.
+    pub fn apply(&self, name: &str) -> String {
+        let chars: Vec<char> = name.chars().collect();
+        let mut result = String::with_capacity(name.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = self.longest_source.min(chars.len() - i);
+            let found = (1..=longest).rev().find_map(|len| {
+                let source: String = chars[i..i + len].iter().collect();
+                self.mappings
+                    .get(&source.to_lowercase())
+                    .map(|target| (len, target, chars[i].is_uppercase()))
+            });
+            match found {
+                Some((len, target, capitalize)) => {
+                    if capitalize {
+                        let mut target_chars = target.chars();
+                        if let Some(first) = target_chars.next() {
+                            result.extend(first.to_uppercase());
+                        }
+                        result.extend(target_chars);
+                    } else {
+                        result.push_str(target);
+                    }
+                    i += len;
+                }
+                None => {
+                    result.push(chars[i]);
+                    i += 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_match_wins() {
+        let greek = Transliteration::greek();
+        assert_eq!(greek.apply("Αγγελούπολη"), "Angeloupoli");
+        assert_eq!(greek.apply("abc"), "abc");
+    }
+
+    #[test]
+    fn test_iso9_gives_every_letter_its_own() {
+        let iso9 = Transliteration::iso9();
+        assert_eq!(iso9.apply("Щукин"), "Ŝukin");
+        assert_eq!(iso9.apply("Љубљана"), "L̂ubl̂ana");
+        assert_eq!(iso9.apply("Хрущёв"), "Hruŝëv");
+    }
+
+    #[test]
+    fn test_tables_are_read_from_tsv() {
+        let table = Transliteration::from_tsv("#from\tto\r\nSZ\tsh\r\n\nż\tzh\nh\t\n").unwrap();
+        assert_eq!(table.apply("Szeżoh"), "Shezho");
+        assert!(matches!(
+            Transliteration::from_tsv("a\tb\nc d\n"),
+            Err(NamegenError::InvalidConfig(message)) if message.contains("line 2")
+        ));
+    }
+
+    #[test]
+    fn test_custom_table() {
+        let table = Transliteration::new()
+            .with_mapping("x", "ks")
+            .with_mapping("ch", "k");
+        assert_eq!(table.apply("Xochimilco"), "Ksokimilco");
+    }
+}
@@ -0,0 +1,266 @@
+//! Choosing a model's order and prior by grid search, instead of by folklore.
+//!
+//! `TuningGrid::search()` splits a corpus into a training set and a held-out set, trains a
+//! model for every combination of order and prior in the grid, and scores each by its
+//! perplexity on the held-out names: roughly, how many choices the model thinks it has at each
+//! step of a name it has never seen.  Lower is better.  The grid only searches the prior, not
+//! the interpolated smoothing of `.with_interpolation()`, so "no prior" stands in for "no
+//! smoothing" in the grid (unseen contexts still back off to shorter ones).
+//!
+//! ```
+//! use markov_namegen::{registry::GeneratorKind, tuning::TuningGrid, ClusterChainGenerator};
+//! let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+//! let report = TuningGrid::new()
+//!     .with_orders(1..=3)
+//!     .with_priors([0.0001, 0.001, 0.01])
+//!     .search(GeneratorKind::ClusterChain, corpus.lines())
+//!     .unwrap();
+//! println!("{}", report);
+//! let mut builder = ClusterChainGenerator::builder().with_order(report.best.order);
+//! if let Some(prior) = report.best.prior {
+//!     builder = builder.with_prior(prior);
+//! }
+//! let namegen = builder.train(corpus.lines()).build();
+//! ```
+use crate::clusterchain::builder::ClusterChainGeneratorBuilder;
+use crate::error::NamegenError;
+use crate::language::Language;
+use crate::model::MarkovModel;
+use crate::preprocess::Preprocessor;
+use crate::registry::GeneratorKind;
+use multimarkov::MultiMarkov;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Deref;
+
+/// The settings to try in a grid search, and how to split the corpus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningGrid {
+    orders: Vec<usize>,
+    priors: Vec<Option<f64>>,
+    holdout: f64,
+    seed: u64,
+}
+
+impl Default for TuningGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TuningGrid {
+    /// A grid of orders 1 to 5 and priors from none to 0.05, holding out a fifth of the corpus.
+    pub fn new() -> Self {
+        TuningGrid {
+            orders: (1..=5).collect(),
+            priors: vec![
+                None,
+                Some(0.0001),
+                Some(0.0005),
+                Some(0.001),
+                Some(0.005),
+                Some(0.01),
+                Some(0.05),
+            ],
+            holdout: 0.2,
+            seed: 0,
+        }
+    }
+    /// The orders to try.
+    pub fn with_orders(mut self, orders: impl IntoIterator<Item = usize>) -> Self {
+        self.orders = orders.into_iter().collect();
+        assert!(
+            self.orders.iter().all(|&order| order > 0),
+            "Order must be greater than zero."
+        );
+        self
+    }
+    /// The priors to try.  Use `with_priors_or_none()` to try models without a prior as well.
+    pub fn with_priors(self, priors: impl IntoIterator<Item = f64>) -> Self {
+        self.with_priors_or_none(priors.into_iter().map(Some))
+    }
+    /// The priors to try, where `None` means no prior at all.
+    pub fn with_priors_or_none(mut self, priors: impl IntoIterator<Item = Option<f64>>) -> Self {
+        self.priors = priors.into_iter().collect();
+        self
+    }
+    /// The fraction of the corpus to hold out for scoring, between 0 and 1.  The default is 0.2.
+    pub fn with_holdout(mut self, fraction: f64) -> Self {
+        assert!(
+            fraction > 0.0 && fraction < 1.0,
+            "The holdout must be between 0 and 1."
+        );
+        self.holdout = fraction;
+        self
+    }
+    /// Seeds the shuffle that splits the corpus, for a different (but still reproducible)
+    /// split.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Trains and scores a model of the given kind for every combination of order and prior.
+    /// Returns an error if the grid is empty, or the corpus is too small to hold some of it out.
+    pub fn search(
+        &self,
+        kind: GeneratorKind,
+        corpus: impl Iterator<Item = impl Deref<Target = str>>,
+    ) -> Result<TuningReport, NamegenError> {
+        let preprocessor = Preprocessor::default();
+        let mut words = Vec::new();
+        for line in corpus {
+            words.extend(preprocessor.prepare(&line)?);
+        }
+        words.shuffle(&mut ChaCha8Rng::seed_from_u64(self.seed));
+        let holdout = ((words.len() as f64 * self.holdout).round() as usize).max(1);
+        if holdout >= words.len() {
+            return Err(NamegenError::InvalidTrainingData(String::from(
+                "the corpus is too small to hold out any of it for scoring",
+            )));
+        }
+        let (test, train) = words.split_at(holdout);
+        let results = match kind {
+            GeneratorKind::CharacterChain => {
+                let tokenize = |word: &String| {
+                    let mut tokens: Vec<char> = word.chars().collect();
+                    tokens.insert(0, '#');
+                    tokens.push('#');
+                    tokens
+                };
+                self.score_all(train, test, tokenize)
+            }
+            GeneratorKind::ClusterChain => {
+                // split clusters the way the builder would detect them from the training set
+                let language = Language::detect(train).unwrap_or(Language::Romance);
+                let tokenize = |word: &String| {
+                    let mut tokens =
+                        ClusterChainGeneratorBuilder::clusterize_with(word.clone(), |c| {
+                            language.is_vowel(c)
+                        });
+                    tokens.insert(0, String::from("#"));
+                    tokens.push(String::from("#"));
+                    tokens
+                };
+                self.score_all(train, test, tokenize)
+            }
+        };
+        let best = results
+            .iter()
+            .min_by(|a, b| a.perplexity.total_cmp(&b.perplexity))
+            .cloned()
+            .ok_or_else(|| NamegenError::InvalidConfig(String::from("the grid is empty")))?;
+        Ok(TuningReport { best, results })
+    }
+
+    fn score_all<T>(
+        &self,
+        train: &[String],
+        test: &[String],
+        tokenize: impl Fn(&String) -> Vec<T>,
+    ) -> Vec<TuningResult>
+    where
+        T: Eq + Hash + Clone + Ord,
+    {
+        let train: Vec<Vec<T>> = train.iter().map(&tokenize).collect();
+        let test: Vec<Vec<T>> = test.iter().map(&tokenize).collect();
+        let mut results = Vec::with_capacity(self.orders.len() * self.priors.len());
+        for &order in &self.orders {
+            for &prior in &self.priors {
+                let builder = MultiMarkov::<T>::builder().with_order(order as i32);
+                let builder = match prior {
+                    Some(prior) => builder.with_prior(prior),
+                    None => builder.without_prior(),
+                };
+                let model: MarkovModel<T> = builder.train(train.iter().cloned()).build().into();
+                results.push(TuningResult {
+                    order,
+                    prior,
+                    perplexity: perplexity(&model, &test),
+                });
+            }
+        }
+        results
+    }
+}
+
+/// The perplexity of a model on some tokenized words: e raised to the average negative log
+/// probability of each transition.  Infinite if any transition is impossible under the model.
+fn perplexity<T: Eq + Hash + Clone + Ord>(model: &MarkovModel<T>, words: &[Vec<T>]) -> f64 {
+    let transitions: usize = words.iter().map(|word| word.len() - 1).sum();
+    let log_probability: f64 = words.iter().map(|word| model.log_probability(word)).sum();
+    (-log_probability / transitions as f64).exp()
+}
+
+/// The score of one combination of settings in a grid search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningResult {
+    pub order: usize,
+    /// `None` for a model without a prior.
+    pub prior: Option<f64>,
+    /// The perplexity of the model on the held-out names; lower is better.  Infinite if the
+    /// model can't produce some of them at all, as happens without a prior.
+    pub perplexity: f64,
+}
+
+/// The outcome of `TuningGrid::search()`: the best settings, and the scores of all of them in
+/// the order they were tried.  Displays as a table, with the best settings marked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningReport {
+    pub best: TuningResult,
+    pub results: Vec<TuningResult>,
+}
+
+impl fmt::Display for TuningReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  order  prior     perplexity")?;
+        for result in &self.results {
+            let marker = if *result == self.best { '*' } else { ' ' };
+            let prior = result
+                .prior
+                .map_or_else(|| String::from("none"), |prior| prior.to_string());
+            writeln!(
+                f,
+                "{} {:<6} {:<9} {:.4}",
+                marker, result.order, prior, result.perplexity
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_search_finds_the_best_settings() {
+        let corpus = std::fs::read_to_string("resources/romans.txt").unwrap();
+        let report = TuningGrid::new()
+            .with_orders(1..=3)
+            .with_priors_or_none([None, Some(0.001)])
+            .search(GeneratorKind::CharacterChain, corpus.lines())
+            .unwrap();
+        assert_eq!(report.results.len(), 6);
+        // without a prior, some held-out name is bound to be impossible
+        assert!(report
+            .results
+            .iter()
+            .all(|result| result.prior.is_some() || result.perplexity == f64::INFINITY));
+        assert_eq!(report.best.prior, Some(0.001));
+        assert!(report
+            .results
+            .iter()
+            .all(|result| result.perplexity >= report.best.perplexity));
+        assert_eq!(report.to_string().matches('*').count(), 1);
+    }
+
+    #[test]
+    fn test_tiny_corpus_is_rejected() {
+        let result = TuningGrid::new().search(GeneratorKind::ClusterChain, ["julius"].into_iter());
+        assert!(matches!(result, Err(NamegenError::InvalidTrainingData(_))));
+    }
+}
@@ -0,0 +1,258 @@
+//! Handle-style usernames ("velkora", "dark_wolf42"): a generated word, perhaps a second one,
+//! perhaps some digits, never the same one twice.
+//!
+//! ```
+//! use markov_namegen::username::UsernameGenerator;
+//! use markov_namegen::{CharacterChainGenerator, RandomTextGenerator};
+//! let words = CharacterChainGenerator::builder()
+//!     .train(vec!["kora", "vel", "dusk", "wolf", "raven"].into_iter())
+//!     .build();
+//! let mut usernames = UsernameGenerator::new(words)
+//!     .with_digits(0.5, 2)
+//!     .with_max_length(12)
+//!     .remember_outputs(true)
+//!     .with_seed(1);
+//! let username = usernames.generate_one();
+//! assert!(username.len() <= 12);
+//! ```
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::memory::NameMemory;
+use crate::postprocess::strip_diacritics;
+use crate::state::GeneratorRng;
+use rand::Rng;
+use std::collections::HashSet;
+use std::fmt;
+
+/// How many usernames the generator tries before giving up on finding one that is short
+/// enough and new.
+const MAX_ATTEMPTS: usize = 100;
+
+/// A generator of usernames, each a word from another generator, with a second word from
+/// another (or the same kind of) generator some of the time, and a few digits some of the
+/// time.  Words are lowercased, and stripped of diacritics and of anything but letters and
+/// digits, so that usernames are plain ASCII as long as the words are in the Latin script.
+///
+/// Usernames longer than the maximum length, or already generated when the generator
+/// remembers its outputs, are re-rolled, up to a hundred times.
+pub struct UsernameGenerator {
+    words: Box<dyn RandomTextGenerator + Send>,
+    second_words: Option<(Box<dyn RandomTextGenerator + Send>, f64)>,
+    digits: Option<(f64, usize)>,
+    separator: String,
+    max_length: Option<usize>,
+    memory: Option<NameMemory>,
+    rng: GeneratorRng,
+}
+
+impl UsernameGenerator {
+    /// Makes usernames from words generated by `words`.
+    pub fn new(words: impl RandomTextGenerator + Send + 'static) -> Self {
+        UsernameGenerator {
+            words: Box::new(words),
+            second_words: None,
+            digits: None,
+            separator: String::new(),
+            max_length: None,
+            memory: None,
+            rng: GeneratorRng::from_entropy(),
+        }
+    }
+    /// Adds a second word, from `words`, with the given probability from 0 to 1.
+    pub fn with_second_word(
+        mut self,
+        words: impl RandomTextGenerator + Send + 'static,
+        probability: f64,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        self.second_words = Some((Box::new(words), probability));
+        self
+    }
+    /// Adds one to `max_digits` random digits at the end, with the given probability from 0 to
+    /// 1.
+    pub fn with_digits(mut self, probability: f64, max_digits: usize) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Probability must be between 0.0 and 1.0."
+        );
+        assert!(max_digits > 0, "Max digits must be greater than zero.");
+        self.digits = Some((probability, max_digits));
+        self
+    }
+    /// Sets what goes between the two words, e.g. "_" or "."; by default, nothing.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+    /// Rejects usernames longer than this many characters.
+    pub fn with_max_length(mut self, length: usize) -> Self {
+        assert!(length > 0, "Max length must be greater than zero.");
+        self.max_length = Some(length);
+        self
+    }
+    /// Sets whether the generator remembers the usernames it returns, and re-rolls any
+    /// repeats, until `reset_memory()` is called.
+    pub fn remember_outputs(mut self, remember: bool) -> Self {
+        self.memory = remember.then(|| NameMemory::Exact(HashSet::new()));
+        self
+    }
+    /// Seeds the generator's own choices for reproducible output.  Seed the words' generators
+    /// too.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = GeneratorRng::from_seed(seed);
+        self
+    }
+
+    /// Forgets the usernames already generated, so that they may be produced again.
+    pub fn reset_memory(&mut self) {
+        if let Some(memory) = &mut self.memory {
+            memory.clear();
+        }
+    }
+
+    /// Puts together one username, which may be too long or a repeat.
+    fn candidate(&mut self) -> Result<String, NamegenError> {
+        let mut username = plain(self.words.try_generate_one()?);
+        if let Some((words, probability)) = &mut self.second_words {
+            if self.rng.gen_bool(*probability) {
+                let second = plain(words.try_generate_one()?);
+                username.push_str(&self.separator);
+                username.push_str(&second);
+            }
+        }
+        if let Some((probability, max_digits)) = self.digits {
+            if self.rng.gen_bool(probability) {
+                for _ in 0..self.rng.gen_range(1..=max_digits) {
+                    username.push(char::from(b'0' + self.rng.gen_range(0..10u8)));
+                }
+            }
+        }
+        Ok(username)
+    }
+}
+
+/// A word in lowercase, without diacritics, and without anything but letters and digits.
+fn plain(word: String) -> String {
+    strip_diacritics(word)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl fmt::Debug for UsernameGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UsernameGenerator")
+            .field("words", &self.words.kind())
+            .field("digits", &self.digits)
+            .field("separator", &self.separator)
+            .field("max_length", &self.max_length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RandomTextGenerator for UsernameGenerator {
+    /// Generates a username, or returns an error if a words' generator fails, or no username
+    /// short enough and new turns up in a hundred tries.
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        for _ in 0..MAX_ATTEMPTS {
+            let username = self.candidate()?;
+            if username.is_empty()
+                || self
+                    .max_length
+                    .is_some_and(|length| username.chars().count() > length)
+            {
+                continue;
+            }
+            if self
+                .memory
+                .as_mut()
+                .is_some_and(|memory| !memory.insert(&username))
+            {
+                continue;
+            }
+            return Ok(username);
+        }
+        Err(NamegenError::MaxAttemptsExceeded(MAX_ATTEMPTS))
+    }
+
+    fn kind(&self) -> &'static str {
+        "username"
+    }
+
+    /// Describes the parts, e.g. "characterchain words, second words, digits, remembers
+    /// outputs".
+    fn config_summary(&self) -> String {
+        let mut summary = format!("{} words", self.words.kind());
+        if self.second_words.is_some() {
+            summary.push_str(", second words");
+        }
+        if self.digits.is_some() {
+            summary.push_str(", digits");
+        }
+        if let Some(length) = self.max_length {
+            summary.push_str(&format!(", at most {} characters", length));
+        }
+        if self.memory.is_some() {
+            summary.push_str(", remembers outputs");
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CharacterChainGenerator;
+
+    fn words(corpus: Vec<&str>, seed: u64) -> CharacterChainGenerator {
+        CharacterChainGenerator::builder()
+            .with_seed(seed)
+            .without_prior()
+            .train(corpus.into_iter())
+            .build()
+    }
+
+    #[test]
+    fn test_usernames_are_put_together_from_parts() {
+        let mut usernames = UsernameGenerator::new(words(vec!["Dárk"], 1))
+            .with_second_word(words(vec!["wolf"], 2), 1.0)
+            .with_separator("_")
+            .with_digits(1.0, 3)
+            .with_seed(1);
+        assert_eq!(
+            usernames.config_summary(),
+            "characterchain words, second words, digits"
+        );
+        for _ in 0..20 {
+            let username = usernames.generate_one();
+            let digits = username.strip_prefix("dark_wolf").unwrap();
+            assert!((1..=3).contains(&digits.len()), "{}", username);
+            assert!(digits.chars().all(|c| c.is_ascii_digit()), "{}", username);
+        }
+    }
+
+    #[test]
+    fn test_usernames_are_short_and_unique() {
+        let mut usernames = UsernameGenerator::new(words(vec!["kora", "velkora"], 3))
+            .with_digits(0.5, 1)
+            .with_max_length(5)
+            .remember_outputs(true)
+            .with_seed(3);
+        let mut seen = HashSet::new();
+        for _ in 0..11 {
+            let username = usernames.generate_one();
+            assert!(username.starts_with("kora") && username.len() <= 5);
+            assert!(seen.insert(username));
+        }
+        assert_eq!(
+            usernames.try_generate_one(),
+            Err(NamegenError::MaxAttemptsExceeded(MAX_ATTEMPTS))
+        );
+        usernames.reset_memory();
+        assert!(usernames.try_generate_one().is_ok());
+    }
+}
@@ -0,0 +1,211 @@
+//! Checking a training corpus for entries that would quietly degrade a model, before training
+//! on it: stray digits and punctuation, entries far shorter or longer than the rest, entries
+//! mixing letters of different scripts, and duplicates, which count twice in training.
+//!
+//! ```
+//! use markov_namegen::validation::{validate_corpus, Problem};
+//! let corpus = ["Marcus", "Julia", "Iulia2", "marcus", "Μarcus"];
+//! let report = validate_corpus(corpus.iter().copied());
+//! assert_eq!(report.entries, 5);
+//! assert_eq!(report.issues[0].line, 3);
+//! assert_eq!(report.issues[0].problem, Problem::UnusualCharacters(vec!['2']));
+//! assert_eq!(report.issues[1].problem, Problem::Duplicate { first: 1 });
+//! assert_eq!(report.issues[2].problem, Problem::MixedScripts);
+//! println!("{}", report);
+//! ```
+use crate::script::Script;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+
+/// Entries longer than this many times the median length are reported as too long.
+const LONG_FACTOR: usize = 3;
+/// Entries shorter than this are reported as too short.
+const MIN_LENGTH: usize = 2;
+
+/// Something suspicious about an entry of a corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The entry is empty, or only whitespace.
+    Blank,
+    /// The entry has characters other than letters, spaces, apostrophes and hyphens, like
+    /// digits, punctuation or control characters.  These are listed, without repeats.
+    UnusualCharacters(Vec<char>),
+    /// The entry has fewer letters than any plausible name.
+    TooShort,
+    /// The entry is several times longer than the median entry, as when a whole sentence or
+    /// several names have ended up on one line.
+    TooLong,
+    /// The entry has letters from more than one script, like a Cyrillic or Greek lookalike of
+    /// a Latin letter.
+    MixedScripts,
+    /// The entry repeats an earlier one, ignoring case, so it will be trained on twice.
+    Duplicate {
+        /// The line number of the earlier entry.
+        first: usize,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Blank => write!(f, "is blank"),
+            Problem::UnusualCharacters(chars) => {
+                write!(f, "has unusual characters {:?}", chars)
+            }
+            Problem::TooShort => write!(f, "is too short"),
+            Problem::TooLong => write!(f, "is much longer than most entries"),
+            Problem::MixedScripts => write!(f, "mixes letters of different scripts"),
+            Problem::Duplicate { first } => write!(f, "duplicates line {}", first),
+        }
+    }
+}
+
+/// A suspicious entry found by `validate_corpus()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// The line number of the entry, counting from 1.
+    pub line: usize,
+    /// The entry, as it appears in the corpus.
+    pub entry: String,
+    pub problem: Problem,
+}
+
+/// The suspicious entries of a corpus, in the order of their lines; an entry with several
+/// problems is listed once for each.  Displays as a summary line followed by a line for each
+/// issue.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CorpusReport {
+    /// The number of entries checked.
+    pub entries: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl CorpusReport {
+    /// Whether no suspicious entries were found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} entries, {} issues", self.entries, self.issues.len())?;
+        for issue in &self.issues {
+            write!(
+                f,
+                "\n  line {}: {:?} {}",
+                issue.line, issue.entry, issue.problem
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every line of a corpus for problems that would degrade a model trained on it.
+/// Nothing is changed; fix the corpus, or clean it up as it's trained on with the builders'
+/// `.trim_input()`, `.skip_comments()` and `.with_punctuation_policy()`.
+pub fn validate_corpus(lines: impl Iterator<Item = impl Deref<Target = str>>) -> CorpusReport {
+    let lines: Vec<String> = lines.map(|line| line.to_string()).collect();
+    let mut lengths: Vec<usize> = lines
+        .iter()
+        .map(|line| line.trim().chars().count())
+        .filter(|length| *length > 0)
+        .collect();
+    lengths.sort_unstable();
+    let median = lengths.get(lengths.len() / 2).copied().unwrap_or(0);
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut report = CorpusReport {
+        entries: lines.len(),
+        issues: Vec::new(),
+    };
+    for (i, entry) in lines.into_iter().enumerate() {
+        let line = i + 1;
+        let trimmed = entry.trim();
+        let mut problems = Vec::new();
+        if trimmed.is_empty() {
+            problems.push(Problem::Blank);
+        } else {
+            let mut unusual: Vec<char> = Vec::new();
+            for c in entry.chars().filter(|c| !is_usual(*c)) {
+                if !unusual.contains(&c) {
+                    unusual.push(c);
+                }
+            }
+            if !unusual.is_empty() {
+                problems.push(Problem::UnusualCharacters(unusual));
+            }
+            let length = trimmed.chars().count();
+            if trimmed.chars().filter(|c| c.is_alphabetic()).count() < MIN_LENGTH {
+                problems.push(Problem::TooShort);
+            } else if length > median * LONG_FACTOR {
+                problems.push(Problem::TooLong);
+            }
+            let mut scripts = trimmed.chars().filter_map(Script::of);
+            if let Some(first) = scripts.next() {
+                if scripts.any(|script| script != first) {
+                    problems.push(Problem::MixedScripts);
+                }
+            }
+            match seen.get(&trimmed.to_lowercase()) {
+                Some(&first) => problems.push(Problem::Duplicate { first }),
+                None => {
+                    seen.insert(trimmed.to_lowercase(), line);
+                }
+            }
+        }
+        report
+            .issues
+            .extend(problems.into_iter().map(|problem| Issue {
+                line,
+                entry: entry.clone(),
+                problem,
+            }));
+    }
+    report
+}
+
+/// Whether a character belongs in a name: a letter, a combining accent, or a space, apostrophe
+/// or hyphen.
+fn is_usual(c: char) -> bool {
+    c.is_alphabetic() || ('\u{0300}'..='\u{036F}').contains(&c) || " '’-".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suspicious_entries_are_reported() {
+        let corpus = [
+            "Dopey",
+            "",
+            "Sneezy\r",
+            "X",
+            "Bashful Grumpy Sleepy Happy Doc",
+            "DOPEY",
+            "O'Brien",
+            "Jean-Luc",
+        ];
+        let report = validate_corpus(corpus.iter().copied());
+        let problems: Vec<(usize, &Problem)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.line, &issue.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (2, &Problem::Blank),
+                (3, &Problem::UnusualCharacters(vec!['\r'])),
+                (4, &Problem::TooShort),
+                (5, &Problem::TooLong),
+                (6, &Problem::Duplicate { first: 1 }),
+            ]
+        );
+        assert!(report
+            .to_string()
+            .starts_with("8 entries, 5 issues\n  line 2: \"\" is blank"));
+        assert!(validate_corpus(["Dopey", "Doc"].iter().copied()).is_clean());
+    }
+}
@@ -0,0 +1,207 @@
+//! Generators that retrain themselves when their corpus file changes (requires the `watch`
+//! feature).
+use crate::error::NamegenError;
+use crate::interface::RandomTextGenerator;
+use crate::memory::fnv1a;
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// A generator that watches its corpus file and, whenever the file changes, retrains and
+/// swaps in a new generator, so that a designer tweaking a name list sees the results without
+/// restarting the editor or server.
+///
+/// The file is polled in a background thread, which stops when the `WatchedGenerator` is
+/// dropped.  Retraining happens outside the lock, so generation is never blocked by it, and
+/// the new generator replaces the old one in a single step.  If the edited corpus can't be read
+/// or trained on (e.g. it's half-saved, or empty), the old generator is kept and a warning is
+/// logged.
+///
+/// ```
+/// use markov_namegen::{watch::WatchedGenerator, CharacterChainGenerator, RandomTextGenerator};
+/// let path = std::env::temp_dir().join("markov_namegen_doctest_romans.txt");
+/// std::fs::write(&path, "julius\nmarcus\nlucius\ntiberius\n").unwrap();
+/// let mut namegen = WatchedGenerator::watch(&path, |corpus| {
+///     CharacterChainGenerator::builder().train(corpus.lines()).try_build()
+/// })
+/// .unwrap();
+/// let name = namegen.generate_one();
+/// ```
+pub struct WatchedGenerator<G> {
+    current: Arc<Mutex<G>>,
+    reloads: Arc<AtomicUsize>,
+    // dropping the sender disconnects the channel, which stops the watcher thread
+    _stop: Sender<()>,
+}
+
+impl<G: Send + 'static> WatchedGenerator<G> {
+    /// How often `watch()` checks the corpus file for changes.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Reads the corpus file, builds a generator from its text with `build`, and starts
+    /// watching the file for changes.  Returns an error if the file can't be read or the first
+    /// generator can't be built.
+    pub fn watch<F>(path: impl AsRef<Path>, build: F) -> Result<Self, NamegenError>
+    where
+        F: Fn(&str) -> Result<G, NamegenError> + Send + 'static,
+    {
+        Self::watch_every(path, Self::DEFAULT_INTERVAL, build)
+    }
+
+    /// As `watch()`, but checks the file for changes every `interval`.
+    pub fn watch_every<F>(
+        path: impl AsRef<Path>,
+        interval: Duration,
+        build: F,
+    ) -> Result<Self, NamegenError>
+    where
+        F: Fn(&str) -> Result<G, NamegenError> + Send + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let corpus = read_corpus(&path)?;
+        let current = Arc::new(Mutex::new(build(&corpus)?));
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let watcher = Watcher {
+            path,
+            fingerprint: fnv1a(corpus.as_bytes()),
+            build,
+            current: Arc::clone(&current),
+            reloads: Arc::clone(&reloads),
+        };
+        thread::spawn(move || {
+            let mut watcher = watcher;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                watcher.poll();
+            }
+        });
+        Ok(WatchedGenerator {
+            current,
+            reloads,
+            _stop: stop,
+        })
+    }
+}
+
+impl<G> WatchedGenerator<G> {
+    /// How many times the generator has been retrained since it started watching.
+    pub fn reloads(&self) -> usize {
+        self.reloads.load(Ordering::Acquire)
+    }
+
+    /// Calls `f` with exclusive use of the current generator, e.g. to call methods not covered
+    /// by `RandomTextGenerator`.
+    pub fn with_generator<R>(&self, f: impl FnOnce(&mut G) -> R) -> R {
+        f(&mut lock(&self.current))
+    }
+}
+
+impl<G: RandomTextGenerator> RandomTextGenerator for WatchedGenerator<G> {
+    fn try_generate_one(&mut self) -> Result<String, NamegenError> {
+        self.with_generator(|generator| generator.try_generate_one())
+    }
+
+    fn kind(&self) -> &'static str {
+        self.with_generator(|generator| generator.kind())
+    }
+
+    fn config_summary(&self) -> String {
+        self.with_generator(|generator| generator.config_summary())
+    }
+}
+
+/// The state of the background thread.
+struct Watcher<G, F> {
+    path: PathBuf,
+    fingerprint: u64,
+    build: F,
+    current: Arc<Mutex<G>>,
+    reloads: Arc<AtomicUsize>,
+}
+
+impl<G, F> Watcher<G, F>
+where
+    F: Fn(&str) -> Result<G, NamegenError>,
+{
+    /// Retrains and swaps in the generator if the corpus has changed since we last looked.
+    fn poll(&mut self) {
+        // the file may briefly be missing while an editor saves it; try again next time
+        let Ok(corpus) = read_corpus(&self.path) else {
+            return;
+        };
+        let fingerprint = fnv1a(corpus.as_bytes());
+        if fingerprint == self.fingerprint {
+            return;
+        }
+        self.fingerprint = fingerprint;
+        match (self.build)(&corpus) {
+            Ok(generator) => {
+                *lock(&self.current) = generator;
+                self.reloads.fetch_add(1, Ordering::Release);
+                info!("retrained generator from '{}'", self.path.display());
+            }
+            Err(error) => warn!(
+                "keeping the old generator, could not retrain from '{}': {}",
+                self.path.display(),
+                error
+            ),
+        }
+    }
+}
+
+fn read_corpus(path: &Path) -> Result<String, NamegenError> {
+    fs::read_to_string(path).map_err(|error| {
+        NamegenError::InvalidConfig(format!("could not read '{}': {}", path.display(), error))
+    })
+}
+
+// a generator that panicked (e.g. in a postprocessor) is still usable
+fn lock<G>(mutex: &Mutex<G>) -> MutexGuard<'_, G> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharacterChainGenerator, ReservedCharPolicy};
+    use std::time::Instant;
+
+    fn wait_for_reloads(namegen: &WatchedGenerator<CharacterChainGenerator>, reloads: usize) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while namegen.reloads() < reloads {
+            assert!(Instant::now() < deadline, "the corpus was never reloaded");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_retrains_when_corpus_changes() {
+        let path = std::env::temp_dir().join("markov_namegen_test_watch.txt");
+        fs::write(&path, "abab\nbaba\naabb\n").unwrap();
+        let mut namegen =
+            WatchedGenerator::watch_every(&path, Duration::from_millis(5), |corpus| {
+                CharacterChainGenerator::builder()
+                    .without_prior()
+                    .with_reserved_char_policy(ReservedCharPolicy::Error)
+                    .train(corpus.lines())
+                    .try_build()
+            })
+            .unwrap();
+        assert!(namegen.generate_one().chars().all(|c| "ab".contains(c)));
+
+        fs::write(&path, "xyxy\nyxyx\nxxyy\n").unwrap();
+        wait_for_reloads(&namegen, 1);
+        assert!(namegen.generate_one().chars().all(|c| "xy".contains(c)));
+
+        // a corpus that can't be trained on leaves the old generator in place
+        fs::write(&path, "x#y\n").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(namegen.reloads(), 1);
+        assert!(namegen.generate_one().chars().all(|c| "xy".contains(c)));
+    }
+}